@@ -60,7 +60,7 @@ impl<V: Copy> GreedyQuadsBuffer<V> {
 ///
 /// All quads created will have the same "merge value" as defined by the [`MergeVoxel`] trait. The quads can be post-processed
 /// into meshes as the user sees fit.
-pub fn greedy_quads<T: Copy, S>(
+pub fn greedy_quads<T, S>(
     voxels: &[T],
     voxels_shape: &S,
     min: [u32; 3],
@@ -68,7 +68,7 @@ pub fn greedy_quads<T: Copy, S>(
     faces: &[OrientedBlockFace; 6],
     output: &mut GreedyQuadsBuffer<T>,
 ) where
-    T: MergeVoxel,
+    T: Copy + MergeVoxel,
     S: Shape<3, Coord = u32>,
 {
     greedy_quads_with_merge_strategy::<_, _, VoxelMerger<T>>(
@@ -82,7 +82,7 @@ pub fn greedy_quads<T: Copy, S>(
 }
 
 /// Run the greedy meshing algorithm with a custom quad merging strategy using the [`MergeStrategy`] trait.
-pub fn greedy_quads_with_merge_strategy<T: Copy, S, Merger>(
+pub fn greedy_quads_with_merge_strategy<T, S, Merger>(
     voxels: &[T],
     voxels_shape: &S,
     min: [u32; 3],
@@ -90,7 +90,7 @@ pub fn greedy_quads_with_merge_strategy<T: Copy, S, Merger>(
     faces: &[OrientedBlockFace; 6],
     output: &mut GreedyQuadsBuffer<T>,
 ) where
-    T: Voxel,
+    T: Copy + Voxel,
     S: Shape<3, Coord = u32>,
     Merger: MergeStrategy<Voxel = T>,
 {
@@ -115,7 +115,7 @@ pub fn greedy_quads_with_merge_strategy<T: Copy, S, Merger>(
     }
 }
 
-fn greedy_quads_for_face<T: Copy, S, Merger>(
+fn greedy_quads_for_face<T, S, Merger>(
     voxels: &[T],
     voxels_shape: &S,
     interior: Extent<UVec3>,
@@ -123,7 +123,7 @@ fn greedy_quads_for_face<T: Copy, S, Merger>(
     visited: &mut [bool],
     quads: &mut Vec<UnorientedQuad<T>>,
 ) where
-    T: Voxel,
+    T: Copy + Voxel,
     S: Shape<3, Coord = u32>,
     Merger: MergeStrategy<Voxel = T>,
 {
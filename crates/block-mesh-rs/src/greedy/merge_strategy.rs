@@ -110,6 +110,7 @@ where
 }
 
 impl<T> VoxelMerger<T> {
+    #[allow(clippy::too_many_arguments)]
     unsafe fn get_row_width(
         voxels: &[T],
         visited: &[bool],
@@ -95,6 +95,7 @@ mod simple;
 
 /// Describes how this voxel influences mesh generation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 pub enum VoxelVisibility {
     /// This voxel should not produce any geometry.
     Empty,
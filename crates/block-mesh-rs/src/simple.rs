@@ -10,7 +10,7 @@ use crate::{
 /// A fast and simple meshing algorithm that produces a single quad for every visible face of a block.
 ///
 /// This is faster than [`greedy_quads`](crate::greedy_quads) but it produces many more quads.
-pub fn visible_block_faces<T: Clone, S>(
+pub fn visible_block_faces<T, S>(
     voxels: &[T],
     voxels_shape: &S,
     min: [u32; 3],
@@ -18,7 +18,7 @@ pub fn visible_block_faces<T: Clone, S>(
     faces: &[OrientedBlockFace; 6],
     output: &mut UnitQuadBuffer<T>,
 ) where
-    T: Voxel,
+    T: Clone + Voxel,
     S: Shape<3, Coord = u32>,
 {
     visible_block_faces_with_voxel_view::<_, IdentityVoxel<T>, _>(
@@ -1,23 +1,138 @@
-use bevy::{
-    asset::{AssetPath, LoadContext},
-    prelude::*,
-};
+use bevy::prelude::*;
 use block_mesh::VoxelVisibility;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Clone, Debug, Asset, TypePath)]
+use crate::{turn_box, BlockModel, BlockProperty, BlockScript, BlockShape, BlockState};
+
+/// Brightest sky or block light level
+pub const MAX_LIGHT: u8 = 15;
+
+/// [Block::hardness] of blocks that don't set one
+pub const DEFAULT_HARDNESS: f32 = 1.0;
+
+/// Sounds played at a block, see [Block::sounds]
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct BlockSounds {
+    /// Played where the block is placed
+    pub place: Option<Handle<AudioSource>>,
+    /// Played where the block is broken
+    pub destroy: Option<Handle<AudioSource>>,
+}
+
+/// [BlockSounds] as asset paths in `.block` files
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SerializedBlockSounds {
+    #[serde(default)]
+    pub place: Option<String>,
+    #[serde(default)]
+    pub destroy: Option<String>,
+}
+
+/// Which pass of the chunk mesh a block is drawn in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, Reflect)]
+pub enum RenderLayer {
+    #[default]
+    Opaque,
+    /// Alpha blended, for glass, water and the like
+    Transparent,
+    /// Either fully opaque or fully see-through by the texture's alpha and drawn from both
+    /// sides, for foliage like grass tufts, flowers and saplings
+    Cutout,
+}
+
+/// Which colour of the biome a block's faces are multiplied by, so the same texture can be
+/// greener in one biome and browner in another
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, Reflect)]
+pub enum BiomeTint {
+    #[default]
+    None,
+    Grass,
+    Foliage,
+    Water,
+}
+
+/// Textures a block is lit with besides its colour, each laid out like the block's texture.
+/// Only blocks drawn with [BlockTextureMode::Atlas](crate::BlockTextureMode::Atlas) use them
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct BlockMaps {
+    /// Tangent space normals
+    pub normal: Option<Handle<Image>>,
+    /// Roughness in the green and metalness in the blue channel, like glTF
+    pub metallic_roughness: Option<Handle<Image>>,
+    /// Colour the block glows with regardless of light
+    pub emissive: Option<Handle<Image>>,
+}
+
+impl BlockMaps {
+    /// Every map that is set
+    pub fn iter(&self) -> impl Iterator<Item = &Handle<Image>> {
+        [&self.normal, &self.metallic_roughness, &self.emissive]
+            .into_iter()
+            .flatten()
+    }
+}
+
+/// Light given off by a glowing block
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct Emissive {
+    /// Tint of the block's glowing faces
+    pub color: Color,
+    /// Block light level given off, from 0 to [MAX_LIGHT]
+    pub strength: u8,
+}
+
+/// How a fluid block flows, see [Block::fluid]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Reflect)]
+pub struct Fluid {
+    /// Times per second the fluid flows on by a block
+    pub flow_rate: f32,
+    /// How many blocks the fluid flows sideways from a source before it runs out
+    pub spread: u8,
+}
+
+impl Fluid {
+    /// Level of a source block, flowing fluid is one level lower for every block it flowed
+    /// sideways
+    pub fn source_level(&self) -> u8 {
+        self.spread + 1
+    }
+
+    /// Height of the fluid's surface at `level`, from 0 to 1
+    pub fn height(&self, level: u8) -> f32 {
+        level.min(self.source_level()) as f32 / self.source_level() as f32
+    }
+}
+
+#[derive(Clone, Debug, Asset, Reflect)]
 pub struct VoxelBlock {
     name: String,
     texture: Option<Handle<Image>>,
     visibility: VoxelVisibility,
+    sounds: BlockSounds,
+    emissive: Option<Emissive>,
+    render_layer: RenderLayer,
+    hardness: f32,
+    gravity: bool,
+    fluid: Option<Fluid>,
+    ticks: bool,
+    script: Option<Handle<BlockScript>>,
+    properties: Vec<BlockProperty>,
+    shape: BlockShape,
+    model: Option<BlockModel>,
+    tint: BiomeTint,
+    maps: BlockMaps,
 }
 
-#[derive(Clone, Debug, Asset, TypePath)]
+#[derive(Clone, Debug, Asset, Reflect)]
 pub struct TileEntityBlock {
     mesh: Handle<Mesh>,
     name: String,
     texture: Handle<Image>,
+    hardness: f32,
+    sounds: BlockSounds,
+    ticks: bool,
+    script: Option<Handle<BlockScript>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -25,6 +140,70 @@ pub struct SerializedVoxelBlock {
     pub name: String,
     pub texture: Option<String>,
     pub visibility: VoxelVisibility,
+    /// See [Block::sounds]
+    #[serde(default)]
+    pub sounds: SerializedBlockSounds,
+    #[serde(default)]
+    pub emissive: Option<SerializedEmissive>,
+    #[serde(default)]
+    pub render_layer: RenderLayer,
+    /// See [Block::hardness]
+    #[serde(default = "default_hardness")]
+    pub hardness: f32,
+    /// See [Block::gravity]
+    #[serde(default)]
+    pub gravity: bool,
+    /// See [Block::fluid]
+    #[serde(default)]
+    pub fluid: Option<Fluid>,
+    /// See [Block::ticks]
+    #[serde(default)]
+    pub ticks: bool,
+    /// Asset path of the block's [BlockScript]
+    #[serde(default)]
+    pub script: Option<String>,
+    /// See [Block::properties]
+    #[serde(default)]
+    pub properties: Vec<BlockProperty>,
+    /// See [Block::shape]
+    #[serde(default)]
+    pub shape: BlockShape,
+    /// Path of the block's `.model` file, a file name is looked up in
+    /// [models_path](crate::BlockPluginSettings::models_path), see [Block::model]
+    #[serde(default)]
+    pub model: Option<String>,
+    /// See [Block::tint]
+    #[serde(default)]
+    pub tint: BiomeTint,
+    /// Paths of the block's [BlockMaps], looked up like its texture
+    #[serde(default)]
+    pub normal_map: Option<String>,
+    #[serde(default)]
+    pub metallic_roughness_map: Option<String>,
+    #[serde(default)]
+    pub emissive_map: Option<String>,
+}
+
+fn default_hardness() -> f32 {
+    DEFAULT_HARDNESS
+}
+
+/// [Emissive] as written in `.block` files
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct SerializedEmissive {
+    /// sRGB, from 0 to 1
+    pub color: [f32; 3],
+    pub strength: u8,
+}
+
+impl From<SerializedEmissive> for Emissive {
+    fn from(value: SerializedEmissive) -> Self {
+        let [red, green, blue] = value.color;
+        Self {
+            color: Color::rgb(red, green, blue),
+            strength: value.strength.min(MAX_LIGHT),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -32,6 +211,18 @@ pub struct SerializedTileEntityBlock {
     pub mesh: Option<String>,
     pub name: String,
     pub texture: Option<String>,
+    /// See [Block::hardness]
+    #[serde(default = "default_hardness")]
+    pub hardness: f32,
+    /// See [Block::sounds]
+    #[serde(default)]
+    pub sounds: SerializedBlockSounds,
+    /// See [Block::ticks]
+    #[serde(default)]
+    pub ticks: bool,
+    /// Asset path of the block's [BlockScript]
+    #[serde(default)]
+    pub script: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -41,7 +232,7 @@ pub enum SerializedBlock {
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Debug, Asset, TypePath)]
+#[derive(Clone, Debug, Asset, Reflect)]
 pub enum Block {
     Voxel(VoxelBlock),
     TileEntity(TileEntityBlock),
@@ -52,6 +243,19 @@ pub(crate) struct VoxelBlockBuilder {
     name: Option<String>,
     texture: Option<Handle<Image>>,
     visibility: Option<VoxelVisibility>,
+    sounds: BlockSounds,
+    emissive: Option<Emissive>,
+    render_layer: RenderLayer,
+    hardness: Option<f32>,
+    gravity: bool,
+    fluid: Option<Fluid>,
+    ticks: bool,
+    script: Option<Handle<BlockScript>>,
+    properties: Vec<BlockProperty>,
+    shape: BlockShape,
+    model: Option<BlockModel>,
+    tint: BiomeTint,
+    maps: BlockMaps,
 }
 
 #[derive(Default)]
@@ -59,6 +263,10 @@ pub(crate) struct TileEntityBlockBuilder {
     mesh: Option<Handle<Mesh>>,
     name: Option<String>,
     texture: Option<Handle<Image>>,
+    hardness: Option<f32>,
+    sounds: BlockSounds,
+    ticks: bool,
+    script: Option<Handle<BlockScript>>,
 }
 
 #[derive(Error, Debug)]
@@ -69,7 +277,7 @@ pub(crate) enum BlockBuilderError {
     UnsetNameForTileEntity,
     #[error("VoxelVisibility must be set")]
     UnsetVisbility,
-    #[error("Texture must be set to set VoxelVisbility to non None value")]
+    #[error("Texture or model must be set to set VoxelVisbility to non None value")]
     UnsetTextureForVoxel,
     #[error("Texture must be set on a TileEntity")]
     UnsetTextureForTileEntity,
@@ -86,6 +294,19 @@ impl Block {
             name: "Air".into(),
             texture: None,
             visibility: VoxelVisibility::Empty,
+            sounds: BlockSounds::default(),
+            emissive: None,
+            render_layer: RenderLayer::Opaque,
+            hardness: 0.0,
+            gravity: false,
+            fluid: None,
+            ticks: false,
+            script: None,
+            properties: Vec::new(),
+            shape: BlockShape::Full,
+            model: None,
+            tint: BiomeTint::None,
+            maps: BlockMaps::default(),
         })
     }
 
@@ -100,14 +321,22 @@ impl Block {
         }
     }
 
-    pub(crate) fn tile_entity_texture(&self) -> Option<Handle<Image>> {
+    /// Like [voxel_texture](Block::voxel_texture) without cloning the handle
+    pub(crate) fn voxel_texture_id(&self) -> Option<AssetId<Image>> {
+        match self {
+            Self::Voxel(block) => block.texture.as_ref().map(Handle::id),
+            _ => None,
+        }
+    }
+
+    pub fn tile_entity_texture(&self) -> Option<Handle<Image>> {
         match self {
             Self::TileEntity(block) => Some(block.texture.clone()),
             _ => None,
         }
     }
 
-    pub(crate) fn tile_entity_mesh(&self) -> Option<Handle<Mesh>> {
+    pub fn tile_entity_mesh(&self) -> Option<Handle<Mesh>> {
         match self {
             Self::TileEntity(block) => Some(block.mesh.clone()),
             _ => None,
@@ -121,6 +350,170 @@ impl Block {
         }
     }
 
+    pub fn emissive(&self) -> Option<Emissive> {
+        match self {
+            Self::Voxel(block) => block.emissive,
+            _ => None,
+        }
+    }
+
+    /// Block light level this block gives off, from 0 to [MAX_LIGHT]
+    pub fn light_emission(&self) -> u8 {
+        self.emissive().map_or(0, |emissive| emissive.strength)
+    }
+
+    pub fn render_layer(&self) -> RenderLayer {
+        match self {
+            Self::Voxel(block) => block.render_layer,
+            _ => RenderLayer::Opaque,
+        }
+    }
+
+    /// Seconds it takes to break the block by hand, `0.0` breaks it at once and a negative
+    /// hardness can't be broken at all
+    pub fn hardness(&self) -> f32 {
+        match self {
+            Self::Voxel(block) => block.hardness,
+            Self::TileEntity(block) => block.hardness,
+        }
+    }
+
+    /// Whether rays stop at the block, tile entities are hit like a full block
+    pub fn is_hit_by_rays(&self) -> bool {
+        !self.is_voxel() || self.get_voxel_visibility() != VoxelVisibility::Empty
+    }
+
+    /// Whether the block falls when the block beneath it is air, like sand
+    pub fn gravity(&self) -> bool {
+        match self {
+            Self::Voxel(block) => block.gravity,
+            _ => false,
+        }
+    }
+
+    /// How the block flows if it is a fluid, like water or lava. Placed fluid blocks are
+    /// sources, which flow into the air around them
+    pub fn fluid(&self) -> Option<Fluid> {
+        match self {
+            Self::Voxel(block) => block.fluid,
+            _ => None,
+        }
+    }
+
+    /// Whether the block receives random ticks, for blocks that change over time like crops
+    pub fn ticks(&self) -> bool {
+        match self {
+            Self::Voxel(block) => block.ticks,
+            Self::TileEntity(block) => block.ticks,
+        }
+    }
+
+    /// The WASM module giving the block its behaviour, see [BlockScript]
+    pub fn script(&self) -> Option<&Handle<BlockScript>> {
+        match self {
+            Self::Voxel(block) => block.script.as_ref(),
+            Self::TileEntity(block) => block.script.as_ref(),
+        }
+    }
+
+    /// What the [BlockState] of the block can vary in, e.g. which way it faces
+    pub fn properties(&self) -> &[BlockProperty] {
+        match self {
+            Self::Voxel(block) => &block.properties,
+            _ => &[],
+        }
+    }
+
+    /// Turns the block's default orientation into the one of `state`, for picking the face
+    /// of the texture each side shows
+    pub fn rotation(&self, state: BlockState) -> Quat {
+        let properties = self.properties();
+        let has = |property| properties.contains(&property);
+        if has(BlockProperty::Facing) || has(BlockProperty::HorizontalFacing) {
+            state.facing.rotation()
+        } else if has(BlockProperty::Axis) {
+            state.facing.axis_rotation()
+        } else {
+            Quat::IDENTITY
+        }
+    }
+
+    /// Geometry of the block, [BlockShape::Full] for tile entities, which bring their own mesh
+    pub fn shape(&self) -> BlockShape {
+        match self {
+            Self::Voxel(block) => block.shape,
+            _ => BlockShape::Full,
+        }
+    }
+
+    /// The boxes the block is made of in `state` as `(min, max)` corners within its `0..1`
+    /// cube, see [BlockShape::boxes]
+    pub fn boxes(&self, state: BlockState) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+        let rotation = self.rotation(state);
+        self.shape()
+            .boxes()
+            .iter()
+            .map(move |(min, max)| turn_box(rotation, *min, *max))
+    }
+
+    /// Geometry of the block described in data, drawn instead of its [shape](Block::shape)
+    pub fn model(&self) -> Option<&BlockModel> {
+        match self {
+            Self::Voxel(block) => block.model.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Colour of the biome the block's faces are tinted with where it stands
+    pub fn tint(&self) -> BiomeTint {
+        match self {
+            Self::Voxel(block) => block.tint,
+            _ => BiomeTint::None,
+        }
+    }
+
+    /// Normal, metallic-roughness and emissive maps of the block's texture
+    pub fn maps(&self) -> Option<&BlockMaps> {
+        match self {
+            Self::Voxel(block) => Some(&block.maps),
+            _ => None,
+        }
+    }
+
+    /// Whether the block is drawn as a whole cube, with the faces of its texture
+    pub fn is_full_cube(&self) -> bool {
+        self.shape() == BlockShape::Full && self.model().is_none()
+    }
+
+    /// Every texture the block is drawn with in chunks, once each
+    pub fn texture_ids(&self) -> Vec<AssetId<Image>> {
+        let mut ids: Vec<_> = self.voxel_texture_id().into_iter().collect();
+        for texture in self.model().into_iter().flat_map(BlockModel::textures) {
+            if !ids.contains(&texture.id()) {
+                ids.push(texture.id());
+            }
+        }
+        ids
+    }
+
+    /// Whether the block in `state` fills the whole side of its cube facing `side`. Models
+    /// never do
+    pub fn covers(&self, state: BlockState, side: IVec3) -> bool {
+        if self.model().is_some() {
+            return false;
+        }
+        let side = (self.rotation(state).inverse() * side.as_vec3())
+            .round()
+            .as_ivec3();
+        self.shape().covers(side)
+    }
+
+    /// Whether the block hides everything behind it from every side, an opaque full cube.
+    /// Only these block light and shade their neighbours
+    pub fn occludes(&self) -> bool {
+        self.get_voxel_visibility() == VoxelVisibility::Opaque && self.is_full_cube()
+    }
+
     pub(crate) fn get_name(&self) -> &str {
         match self {
             Self::TileEntity(block) => &block.name,
@@ -128,6 +521,14 @@ impl Block {
         }
     }
 
+    /// Sounds played where the block is placed and broken
+    pub fn sounds(&self) -> &BlockSounds {
+        match self {
+            Self::Voxel(block) => &block.sounds,
+            Self::TileEntity(block) => &block.sounds,
+        }
+    }
+
     pub(crate) fn get_mesh(&self) -> Option<Handle<Mesh>> {
         match self {
             Self::TileEntity(block) => Some(block.mesh.clone()),
@@ -154,7 +555,7 @@ impl VoxelBlockBuilder {
         let visibility = visibility.into();
         match &visibility {
             VoxelVisibility::Empty if self.texture.is_some() => {
-                return Err(BlockBuilderError::UnsetTextureForVoxel)
+                return Err(BlockBuilderError::VisbilityNoneTexture)
             }
             _ => self.visibility = Some(visibility),
         };
@@ -171,6 +572,75 @@ impl VoxelBlockBuilder {
         self
     }
 
+    pub(crate) fn sounds(&mut self, sounds: BlockSounds) -> &mut Self {
+        self.sounds = sounds;
+        self
+    }
+
+    pub(crate) fn emissive(&mut self, emissive: impl Into<Emissive>) -> &mut Self {
+        let emissive = emissive.into();
+        self.emissive = Some(Emissive {
+            strength: emissive.strength.min(MAX_LIGHT),
+            ..emissive
+        });
+        self
+    }
+
+    pub(crate) fn render_layer(&mut self, render_layer: RenderLayer) -> &mut Self {
+        self.render_layer = render_layer;
+        self
+    }
+
+    pub(crate) fn hardness(&mut self, hardness: f32) -> &mut Self {
+        self.hardness = Some(hardness);
+        self
+    }
+
+    pub(crate) fn gravity(&mut self, gravity: bool) -> &mut Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub(crate) fn fluid(&mut self, fluid: Fluid) -> &mut Self {
+        self.fluid = Some(fluid);
+        self
+    }
+
+    pub(crate) fn ticks(&mut self, ticks: bool) -> &mut Self {
+        self.ticks = ticks;
+        self
+    }
+
+    pub(crate) fn script(&mut self, script: Handle<BlockScript>) -> &mut Self {
+        self.script = Some(script);
+        self
+    }
+
+    pub(crate) fn properties(&mut self, properties: Vec<BlockProperty>) -> &mut Self {
+        self.properties = properties;
+        self
+    }
+
+    pub(crate) fn shape(&mut self, shape: BlockShape) -> &mut Self {
+        self.shape = shape;
+        self
+    }
+
+    pub(crate) fn model(&mut self, model: BlockModel) -> &mut Self {
+        self.model = Some(model);
+        self
+    }
+
+    pub(crate) fn tint(&mut self, tint: BiomeTint) -> &mut Self {
+        self.tint = tint;
+        self
+    }
+
+    pub(crate) fn maps(&mut self, maps: BlockMaps) -> &mut Self {
+        self.maps = maps;
+        self
+    }
+
     pub(crate) fn finish(self) -> Result<Block, BlockBuilderError> {
         let Some(name) = self.name else {
             return Err(BlockBuilderError::UnsetNameForVoxel);
@@ -178,11 +648,27 @@ impl VoxelBlockBuilder {
         let Some(visibility) = self.visibility else {
             return Err(BlockBuilderError::UnsetVisbility);
         };
+        if visibility != VoxelVisibility::Empty && self.texture.is_none() && self.model.is_none() {
+            return Err(BlockBuilderError::UnsetTextureForVoxel);
+        }
 
         Ok(Block::Voxel(VoxelBlock {
             name,
             texture: self.texture,
             visibility,
+            sounds: self.sounds,
+            emissive: self.emissive,
+            render_layer: self.render_layer,
+            hardness: self.hardness.unwrap_or(DEFAULT_HARDNESS),
+            gravity: self.gravity,
+            fluid: self.fluid,
+            ticks: self.ticks,
+            script: self.script,
+            properties: self.properties,
+            shape: self.shape,
+            model: self.model,
+            tint: self.tint,
+            maps: self.maps,
         }))
     }
 }
@@ -207,6 +693,26 @@ impl TileEntityBlockBuilder {
         self
     }
 
+    pub(crate) fn hardness(&mut self, hardness: f32) -> &mut Self {
+        self.hardness = Some(hardness);
+        self
+    }
+
+    pub(crate) fn sounds(&mut self, sounds: BlockSounds) -> &mut Self {
+        self.sounds = sounds;
+        self
+    }
+
+    pub(crate) fn ticks(&mut self, ticks: bool) -> &mut Self {
+        self.ticks = ticks;
+        self
+    }
+
+    pub(crate) fn script(&mut self, script: Handle<BlockScript>) -> &mut Self {
+        self.script = Some(script);
+        self
+    }
+
     pub(crate) fn finish(self) -> Result<Block, BlockBuilderError> {
         let Some(name) = self.name else {
             return Err(BlockBuilderError::UnsetNameForTileEntity);
@@ -224,6 +730,10 @@ impl TileEntityBlockBuilder {
             name,
             texture,
             mesh,
+            hardness: self.hardness.unwrap_or(DEFAULT_HARDNESS),
+            sounds: self.sounds,
+            ticks: self.ticks,
+            script: self.script,
         }))
     }
 }
@@ -5,16 +5,29 @@ use bevy::prelude::*;
 use definition::Block;
 use loader::BlockLoader;
 
+pub use model::*;
+use registry::build_block_registry;
+pub use registry::*;
+pub use script::*;
+pub use shape::*;
+pub use state::*;
 use texture_atlas::BlockInfoFolder;
 pub use texture_atlas::*;
 
-use cubizm_core::AppState;
+use cubizm_core::mods::ModPacks;
+use cubizm_core::{AppState, LoadingStateApp};
 
 pub mod definition;
 mod loader;
+mod model;
+mod registry;
+mod script;
+mod shape;
+mod state;
 pub mod texture_atlas;
 mod voxel;
 
+/// Steps of loading the blocks, scoped to [AppState::Setup]
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, States)]
 enum BlockLoadingState {
     #[default]
@@ -23,21 +36,64 @@ enum BlockLoadingState {
     Finished,
 }
 
-fn load_blocks(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// Where [BlockPlugin] looks for block assets, as asset paths. Mods use the same paths within
+/// their own folder
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct BlockPluginSettings {
+    /// Folder of `.block` files, all of which are loaded
+    pub info_path: String,
+    /// Folder `.block` files load their textures from when they only give a file name
+    pub textures_path: String,
+    /// Folder `.block` files load their [BlockModel]s from when they only give a file name
+    pub models_path: String,
+    /// Largest size of a page of the [BlockAtlas], textures that don't fit spill over onto
+    /// more pages
+    pub max_atlas_size: UVec2,
+    /// Empty texels between the textures in the [BlockAtlas]
+    pub atlas_padding: UVec2,
+    /// Texels each texture in the [BlockAtlas] is grown by on every side, repeating its edge
+    /// texels, so filtering and mipmaps don't bleed neighbouring textures into quad edges
+    pub atlas_extrusion: u32,
+}
+
+impl Default for BlockPluginSettings {
+    fn default() -> Self {
+        Self {
+            info_path: "blocks/info".to_string(),
+            textures_path: "blocks/textures".to_string(),
+            models_path: "blocks/models".to_string(),
+            max_atlas_size: UVec2::splat(2048),
+            atlas_padding: UVec2::ZERO,
+            atlas_extrusion: 0,
+        }
+    }
+}
+
+fn load_blocks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<BlockPluginSettings>,
+    mods: Option<Res<ModPacks>>,
+) {
+    let mod_folders = mods
+        .iter()
+        .flat_map(|mods| mods.iter())
+        .filter(|pack| pack.has_dir(&settings.info_path))
+        .map(|pack| asset_server.load_folder(pack.asset_path(&settings.info_path)))
+        .collect::<Vec<_>>();
     commands.insert_resource(BlockInfoFolder::new(
-        asset_server.load_folder("blocks/info"),
+        std::iter::once(asset_server.load_folder(settings.info_path.clone())).chain(mod_folders),
     ));
 }
 
 fn check_block(
     mut next_state: ResMut<NextState<BlockLoadingState>>,
     block_info_folder: Res<BlockInfoFolder>,
+    asset_server: Res<AssetServer>,
     mut events: EventReader<AssetEvent<LoadedFolder>>,
 ) {
-    for event in events.read() {
-        if event.is_loaded_with_dependencies(block_info_folder.clone_handle()) {
-            next_state.set(BlockLoadingState::Finished);
-        }
+    if events.read().count() > 0 && block_info_folder.is_loaded(&asset_server) {
+        next_state.set(BlockLoadingState::Finished);
     }
 }
 
@@ -45,25 +101,60 @@ fn move_to_loaded_block(mut next_state: ResMut<NextState<AppState>>) {
     next_state.set(AppState::BlocksLoaded);
 }
 
-fn begin_loading_blocks(mut next_state: ResMut<NextState<BlockLoadingState>>) {
-    next_state.set(BlockLoadingState::LoadBlockInfo);
+#[derive(Default)]
+pub struct BlockPlugin {
+    pub textures: BlockTextureMode,
+    pub sampling: BlockSampling,
+    pub settings: BlockPluginSettings,
+    /// Loads blocks without their textures and meshes and never builds the [BlockAtlas], for
+    /// running without a renderer, e.g. a dedicated server on `MinimalPlugins`
+    pub headless: bool,
 }
 
-pub struct BlockPlugin;
 impl Plugin for BlockPlugin {
     fn build(&self, app: &mut App) {
-        app.init_asset::<Block>()
-            .init_asset_loader::<BlockLoader>()
-            .init_state::<BlockLoadingState>()
-            .add_systems(OnEnter(AppState::Setup), begin_loading_blocks)
+        app.insert_resource(self.textures)
+            .insert_resource(self.sampling)
+            .insert_resource(self.settings.clone())
+            .init_asset::<Block>()
+            .register_asset_reflect::<Block>()
+            .register_type::<BlockTextureMode>()
+            .register_type::<BlockSampling>()
+            .register_type::<BlockAtlas>()
+            .register_asset_loader(BlockLoader::new(&self.settings, self.headless))
+            .init_asset::<BlockScript>()
+            .init_asset_loader::<BlockScriptLoader>()
+            .add_loading_state(AppState::Setup, BlockLoadingState::LoadBlockInfo)
+            .add_event::<BlockAtlasRebuilt>()
             .add_systems(OnEnter(BlockLoadingState::LoadBlockInfo), load_blocks)
             .add_systems(
                 Update,
                 check_block.run_if(in_state(BlockLoadingState::LoadBlockInfo)),
-            )
-            .add_systems(
+            );
+
+        if self.headless {
+            app.add_systems(
                 OnEnter(BlockLoadingState::Finished),
-                (texture_atlas::setup_texture_atlas, move_to_loaded_block),
+                (build_block_registry, move_to_loaded_block),
             );
+            return;
+        }
+        app.add_systems(
+            OnEnter(BlockLoadingState::Finished),
+            (
+                texture_atlas::setup_texture_atlas,
+                build_block_registry,
+                move_to_loaded_block,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                texture_atlas::reload_texture_atlas,
+                build_block_registry.run_if(on_event::<BlockAtlasRebuilt>()),
+            )
+                .chain()
+                .run_if(in_state(BlockLoadingState::Finished)),
+        );
     }
 }
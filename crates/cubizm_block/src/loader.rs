@@ -1,16 +1,98 @@
 use bevy::{
-    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext},
+    asset::{
+        io::Reader, ron, AssetLoader, AssetPath, AsyncReadExt, LoadContext, ReadAssetBytesError,
+    },
     prelude::*,
     utils::BoxedFuture,
 };
 use thiserror::Error;
 
-use crate::definition::{BlockBuilderError, TileEntityBlockBuilder, VoxelBlockBuilder};
+use crate::definition::{
+    BlockBuilderError, BlockMaps, BlockSounds, SerializedBlockSounds, TileEntityBlockBuilder,
+    VoxelBlockBuilder,
+};
+use crate::{BlockModel, BlockPluginSettings, SerializedBlockModel};
 
 use super::definition::{Block, SerializedBlock};
 
-#[derive(Default)]
-pub struct BlockLoader;
+pub struct BlockLoader {
+    /// See [BlockPluginSettings::textures_path](crate::BlockPluginSettings::textures_path)
+    textures_path: String,
+    /// See [BlockPluginSettings::models_path](crate::BlockPluginSettings::models_path)
+    models_path: String,
+    /// See [BlockPlugin::headless](crate::BlockPlugin::headless)
+    headless: bool,
+}
+
+impl BlockLoader {
+    pub(crate) fn new(settings: &BlockPluginSettings, headless: bool) -> Self {
+        Self {
+            textures_path: settings.textures_path.trim_end_matches('/').to_string(),
+            models_path: settings.models_path.trim_end_matches('/').to_string(),
+            headless,
+        }
+    }
+
+    /// Loads the texture, mesh or sounds of a block, or leaves it as a placeholder when
+    /// headless, where nothing can load, draw or play it
+    fn load_render_asset<A: Asset>(
+        &self,
+        path: AssetPath<'static>,
+        load_context: &mut LoadContext,
+    ) -> Handle<A> {
+        match self.headless {
+            true => Handle::default(),
+            false => load_context.load(path),
+        }
+    }
+
+    /// `path` as is if it is an asset path, otherwise the file of that name in
+    /// [textures_path](BlockLoader::textures_path), from the same source as the block
+    fn texture_path(&self, path: String, load_context: &LoadContext) -> AssetPath<'static> {
+        if path.contains('/') {
+            return path.into();
+        }
+        AssetPath::from(format!("{}/{path}", self.textures_path))
+            .with_source(load_context.asset_path().source().clone_owned())
+    }
+
+    fn load_sounds(
+        &self,
+        sounds: SerializedBlockSounds,
+        load_context: &mut LoadContext,
+    ) -> BlockSounds {
+        BlockSounds {
+            place: sounds
+                .place
+                .map(|path| self.load_render_asset(path.into(), load_context)),
+            destroy: sounds
+                .destroy
+                .map(|path| self.load_render_asset(path.into(), load_context)),
+        }
+    }
+
+    /// Reads the [BlockModel] at `path`, looked up like [texture_path](BlockLoader::texture_path)
+    /// in [models_path](BlockLoader::models_path), and loads its textures
+    async fn load_model(
+        &self,
+        path: String,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<BlockModel, BlockLoaderError> {
+        let path = match path.contains('/') {
+            true => AssetPath::from(path),
+            false => AssetPath::from(format!("{}/{path}", self.models_path))
+                .with_source(load_context.asset_path().source().clone_owned()),
+        };
+        let bytes = load_context.read_asset_bytes(path).await?;
+        let model: SerializedBlockModel = ron::de::from_bytes(&bytes)?;
+        model
+            .resolve(|texture| {
+                let path = self.texture_path(texture.to_string(), load_context);
+                self.load_render_asset::<Image>(path, load_context)
+            })
+            .map_err(BlockLoaderError::UnknownModelTexture)
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum BlockLoaderError {
@@ -22,6 +104,10 @@ pub enum BlockLoaderError {
     LoadDirectError(#[from] bevy::asset::LoadDirectError),
     #[error(transparent)]
     BlockBuilderError(#[from] BlockBuilderError),
+    #[error(transparent)]
+    ReadAssetBytesError(#[from] ReadAssetBytesError),
+    #[error("Model uses the texture {0} without defining it")]
+    UnknownModelTexture(String),
 }
 
 impl AssetLoader for BlockLoader {
@@ -41,12 +127,23 @@ impl AssetLoader for BlockLoader {
             let ron: SerializedBlock = ron::de::from_bytes(&bytes)?;
             match ron {
                 SerializedBlock::SerializedTileEntity(tile_entity) => {
-                    let mesh = tile_entity.mesh.map(|path| load_context.load(path));
+                    let mesh = tile_entity
+                        .mesh
+                        .map(|path| self.load_render_asset::<Mesh>(path.into(), load_context));
 
-                    let texture = tile_entity.texture.map(|path| load_context.load(path));
+                    let texture = tile_entity.texture.map(|path| {
+                        let path = self.texture_path(path, load_context);
+                        self.load_render_asset::<Image>(path, load_context)
+                    });
 
                     let mut block = TileEntityBlockBuilder::new();
                     block.name(&tile_entity.name);
+                    block.hardness(tile_entity.hardness);
+                    block.sounds(self.load_sounds(tile_entity.sounds, load_context));
+                    block.ticks(tile_entity.ticks);
+                    if let Some(script) = tile_entity.script {
+                        block.script(load_context.load(script));
+                    }
 
                     if let Some(mesh) = mesh {
                         block.mesh(mesh);
@@ -59,11 +156,41 @@ impl AssetLoader for BlockLoader {
                     Ok(block.finish()?)
                 }
                 SerializedBlock::SerializedVoxel(voxel) => {
-                    let texture = voxel.texture.map(|path| load_context.load::<Image>(path));
+                    let mut load_texture = |path| {
+                        let path = self.texture_path(path, load_context);
+                        self.load_render_asset::<Image>(path, load_context)
+                    };
+                    let texture = voxel.texture.map(&mut load_texture);
+                    let maps = BlockMaps {
+                        normal: voxel.normal_map.map(&mut load_texture),
+                        metallic_roughness: voxel.metallic_roughness_map.map(&mut load_texture),
+                        emissive: voxel.emissive_map.map(&mut load_texture),
+                    };
 
                     let mut block = VoxelBlockBuilder::new();
                     block.name(&voxel.name);
                     block.visibility(voxel.visibility)?;
+                    block.sounds(self.load_sounds(voxel.sounds, load_context));
+                    if let Some(emissive) = voxel.emissive {
+                        block.emissive(emissive);
+                    }
+                    block.render_layer(voxel.render_layer);
+                    block.hardness(voxel.hardness);
+                    block.gravity(voxel.gravity);
+                    block.ticks(voxel.ticks);
+                    if let Some(script) = voxel.script {
+                        block.script(load_context.load(script));
+                    }
+                    block.properties(voxel.properties);
+                    block.shape(voxel.shape);
+                    block.tint(voxel.tint);
+                    block.maps(maps);
+                    if let Some(model) = voxel.model {
+                        block.model(self.load_model(model, load_context).await?);
+                    }
+                    if let Some(fluid) = voxel.fluid {
+                        block.fluid(fluid);
+                    }
                     if let Some(texture) = texture {
                         block.texture(texture);
                     }
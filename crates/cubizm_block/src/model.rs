@@ -0,0 +1,134 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::Facing;
+
+/// Geometry of a block described in data, a list of boxes with their own textures, for
+/// blocks like fences and torches. Read from `.model` files, see
+/// [Block::model](crate::definition::Block::model)
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct BlockModel {
+    pub elements: Vec<ModelElement>,
+}
+
+/// A box of a [BlockModel]
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct ModelElement {
+    /// Corner within the block's `0..1` cube, before the block is turned
+    pub from: Vec3,
+    pub to: Vec3,
+    /// The faces drawn, sides without one are left open
+    pub faces: Vec<(Facing, ModelFace)>,
+}
+
+/// A face of a [ModelElement]
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct ModelFace {
+    pub texture: Handle<Image>,
+    /// Part of the texture stretched over the face as `min..max`, from 0 to 1
+    pub uv: Rect,
+    /// Left out when the neighbour on this side hides it, for faces on the side of the block
+    pub cull: bool,
+}
+
+impl BlockModel {
+    /// Every texture the model is drawn with, once each
+    pub fn textures(&self) -> impl Iterator<Item = &Handle<Image>> {
+        let mut seen = Vec::new();
+        self.elements
+            .iter()
+            .flat_map(|element| element.faces.iter())
+            .map(|(_, face)| &face.texture)
+            .filter(move |texture| {
+                let new = !seen.contains(&texture.id());
+                seen.push(texture.id());
+                new
+            })
+    }
+}
+
+impl ModelElement {
+    pub fn face(&self, side: Facing) -> Option<&ModelFace> {
+        self.faces
+            .iter()
+            .find(|(facing, _)| *facing == side)
+            .map(|(_, face)| face)
+    }
+}
+
+/// [BlockModel] as written in `.model` files, in sixteenths of a block like Minecraft models
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedBlockModel {
+    /// Texture of each name faces use, file names are looked up like the textures of blocks
+    pub textures: HashMap<String, String>,
+    pub elements: Vec<SerializedModelElement>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedModelElement {
+    /// From `(0, 0, 0)` to `(16, 16, 16)`
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+    pub faces: HashMap<Facing, SerializedModelFace>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedModelFace {
+    /// Name of the texture in [SerializedBlockModel::textures]
+    pub texture: String,
+    /// `[min_u, min_v, max_u, max_v]` from 0 to 16, the whole texture by default
+    #[serde(default = "full_uv")]
+    pub uv: [f32; 4],
+    /// See [ModelFace::cull]
+    #[serde(default)]
+    pub cull: bool,
+}
+
+fn full_uv() -> [f32; 4] {
+    [0., 0., 16., 16.]
+}
+
+impl SerializedBlockModel {
+    /// Turns sixteenths into blocks and looks up the textures with `texture`. Fails with the
+    /// name of the texture if a face uses one the model doesn't have
+    pub(crate) fn resolve(
+        self,
+        mut texture: impl FnMut(&str) -> Handle<Image>,
+    ) -> Result<BlockModel, String> {
+        let textures = self
+            .textures
+            .into_iter()
+            .map(|(name, path)| (name, texture(&path)))
+            .collect::<HashMap<_, _>>();
+        let elements = self
+            .elements
+            .into_iter()
+            .map(|mut element| {
+                let faces = Facing::ALL
+                    .into_iter()
+                    .filter_map(|side| Some((side, element.faces.remove(&side)?)))
+                    .map(|(side, face)| {
+                        let texture = textures.get(&face.texture).cloned().ok_or(face.texture)?;
+                        let [min_u, min_v, max_u, max_v] = face.uv.map(|uv| uv / 16.);
+                        Ok((
+                            side,
+                            ModelFace {
+                                texture,
+                                uv: Rect::new(min_u, min_v, max_u, max_v),
+                                cull: face.cull,
+                            },
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                let from = Vec3::from_array(element.from) / 16.;
+                let to = Vec3::from_array(element.to) / 16.;
+                Ok(ModelElement {
+                    from: from.min(to),
+                    to: from.max(to),
+                    faces,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(BlockModel { elements })
+    }
+}
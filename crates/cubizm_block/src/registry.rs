@@ -0,0 +1,117 @@
+use bevy::{
+    asset::{io::AssetSourceId, LoadedFolder},
+    prelude::*,
+    utils::HashMap,
+};
+
+use cubizm_core::mods::ModPacks;
+
+use crate::definition::Block;
+use crate::texture_atlas::BlockInfoFolder;
+
+/// Namespace of the blocks in the base `assets` folder, blocks from mods use the mod's name
+pub const BASE_NAMESPACE: &str = "cubizm";
+
+/// The registry name of the block called `name` in `namespace`, e.g. `"cubizm:dirt"` for the
+/// base block `"Dirt"`
+pub fn block_key(namespace: &str, name: &str) -> String {
+    let name = name
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_lowercase();
+    format!("{namespace}:{name}")
+}
+
+/// Every loaded [Block] by its namespaced name, see [block_key]
+#[derive(Resource, Debug, Default)]
+pub struct BlockRegistry {
+    blocks: HashMap<String, Handle<Block>>,
+}
+
+impl BlockRegistry {
+    pub fn get(&self, name: &str) -> Option<&Handle<Block>> {
+        self.blocks.get(name)
+    }
+
+    /// Like [get](BlockRegistry::get), with names without a namespace taken as base blocks,
+    /// e.g. `dirt` for `cubizm:dirt`
+    pub fn find(&self, name: &str) -> Option<&Handle<Block>> {
+        match name.contains(':') {
+            true => self.get(name),
+            false => self.get(&format!("{BASE_NAMESPACE}:{name}")),
+        }
+    }
+
+    /// The name `block` is registered as
+    pub fn name(&self, block: &Handle<Block>) -> Option<&str> {
+        self.iter()
+            .find(|(_, handle)| *handle == block)
+            .map(|(name, _)| name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.blocks.contains_key(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Handle<Block>)> {
+        self.blocks
+            .iter()
+            .map(|(name, handle)| (name.as_str(), handle))
+    }
+}
+
+pub(crate) fn build_block_registry(
+    mut commands: Commands,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    block_info_handles: Res<BlockInfoFolder>,
+    blocks: Res<Assets<Block>>,
+    mods: Option<Res<ModPacks>>,
+) {
+    let mut registry = BlockRegistry::default();
+    let mut overrides = Vec::new();
+    for handle in block_info_handles
+        .iter()
+        .filter_map(|handle| loaded_folders.get(handle))
+        .flat_map(|folder| folder.handles.iter())
+    {
+        let handle = handle.clone().typed_unchecked::<Block>();
+        let Some(block) = blocks.get(&handle) else {
+            continue;
+        };
+        let namespace = match handle.path().map(|path| path.source()) {
+            Some(AssetSourceId::Name(name)) => name.to_string(),
+            _ => BASE_NAMESPACE.to_string(),
+        };
+        if let Some(pack) = mods.as_ref().and_then(|mods| mods.get(&namespace)) {
+            overrides.extend(
+                pack.manifest
+                    .overrides
+                    .iter()
+                    .map(|overridden| (block_key(overridden, block.get_name()), handle.clone())),
+            );
+        }
+        let key = block_key(&namespace, block.get_name());
+        if let Some(existing) = registry.blocks.get(&key) {
+            warn!(
+                "{:?} and {:?} are both registered as {key}, keeping the first",
+                existing.path(),
+                handle.path()
+            );
+            continue;
+        }
+        registry.blocks.insert(key, handle);
+    }
+    // Applied once every block is registered, in the load order of the packs so later packs
+    // win
+    for (key, handle) in overrides {
+        match registry.blocks.get_mut(&key) {
+            Some(existing) => {
+                info!("{key} overridden by {:?}", handle.path());
+                *existing = handle;
+            }
+            None => warn!("{:?} overrides {key}, which does not exist", handle.path()),
+        }
+    }
+    commands.insert_resource(registry);
+}
@@ -0,0 +1,39 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+
+/// A WASM module giving a block its behaviour, referenced by `script` in `.block` files. It is
+/// only run with the game's `scripting` feature
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct BlockScript {
+    /// The module as compiled WASM
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct BlockScriptLoader;
+
+impl AssetLoader for BlockScriptLoader {
+    type Asset = BlockScript;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _: &'a Self::Settings,
+        _: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(BlockScript { bytes })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["wasm"]
+    }
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Geometry of a voxel block within its `0..1` cube, see
+/// [Block::shape](crate::definition::Block::shape)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, Reflect)]
+pub enum BlockShape {
+    #[default]
+    Full,
+    /// The lower half of the block
+    BottomSlab,
+    /// The upper half of the block
+    TopSlab,
+    /// A bottom slab with a step on its back half, the `-z` one, so the low front faces the
+    /// player when placed with
+    /// [BlockProperty::HorizontalFacing](crate::BlockProperty::HorizontalFacing)
+    Stairs,
+    /// Two quads crossing diagonally through the block, showing the `+z` face of its texture,
+    /// for foliage. Usually in [RenderLayer::Cutout](crate::definition::RenderLayer::Cutout),
+    /// it never hides its neighbours
+    Cross,
+}
+
+/// Turns the box from `min` to `max` within the `0..1` cube about the cube's centre, by quarter
+/// turns like [Facing::rotation](crate::Facing::rotation). Returns the turned `(min, max)`
+pub fn turn_box(rotation: Quat, min: Vec3, max: Vec3) -> (Vec3, Vec3) {
+    // Turned corners land on sixteenths again, rounding drops the float error
+    let turn = |corner: Vec3| (rotation * (corner - 0.5) * 16.).round() / 16. + 0.5;
+    let (min, max) = (turn(min), turn(max));
+    (min.min(max), min.max(max))
+}
+
+impl BlockShape {
+    /// The boxes the shape is made of as `(min, max)` corners, before the block is turned
+    pub fn boxes(self) -> &'static [(Vec3, Vec3)] {
+        const FULL: [(Vec3, Vec3); 1] = [(Vec3::ZERO, Vec3::ONE)];
+        const BOTTOM_SLAB: [(Vec3, Vec3); 1] = [(Vec3::ZERO, Vec3::new(1., 0.5, 1.))];
+        const TOP_SLAB: [(Vec3, Vec3); 1] = [(Vec3::new(0., 0.5, 0.), Vec3::ONE)];
+        const STAIRS: [(Vec3, Vec3); 2] = [
+            (Vec3::ZERO, Vec3::new(1., 0.5, 1.)),
+            (Vec3::new(0., 0.5, 0.), Vec3::new(1., 1., 0.5)),
+        ];
+        match self {
+            BlockShape::Full => &FULL,
+            BlockShape::BottomSlab => &BOTTOM_SLAB,
+            BlockShape::TopSlab => &TOP_SLAB,
+            BlockShape::Stairs => &STAIRS,
+            BlockShape::Cross => &[],
+        }
+    }
+
+    /// Whether the shape fills the whole side of its block facing `side`, before the block is
+    /// turned, hiding the face of the neighbour there
+    pub fn covers(self, side: IVec3) -> bool {
+        match self {
+            BlockShape::Full => true,
+            BlockShape::BottomSlab => side == IVec3::NEG_Y,
+            BlockShape::TopSlab => side == IVec3::Y,
+            BlockShape::Stairs => side == IVec3::NEG_Y || side == IVec3::NEG_Z,
+            BlockShape::Cross => false,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Property a block's [BlockState] can vary in, see
+/// [Block::properties](crate::definition::Block::properties)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Reflect)]
+pub enum BlockProperty {
+    /// Front faces the player on any side, like a dispenser
+    Facing,
+    /// Front faces the player on one of the four sides around, like a furnace or stairs
+    HorizontalFacing,
+    /// Ends point along the axis of the face the block was placed against, like a log
+    Axis,
+    /// Open or closed, like a door or trapdoor
+    Open,
+}
+
+/// One of the six sides of a block. The front of a block, the `+z` face of its texture,
+/// faces [PosZ](Facing::PosZ) unless its state turns it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, Reflect)]
+pub enum Facing {
+    PosX,
+    NegX,
+    PosY,
+    NegY,
+    #[default]
+    PosZ,
+    NegZ,
+}
+
+impl Facing {
+    pub const ALL: [Facing; 6] = [
+        Facing::PosX,
+        Facing::NegX,
+        Facing::PosY,
+        Facing::NegY,
+        Facing::PosZ,
+        Facing::NegZ,
+    ];
+
+    pub fn normal(self) -> IVec3 {
+        match self {
+            Facing::PosX => IVec3::X,
+            Facing::NegX => IVec3::NEG_X,
+            Facing::PosY => IVec3::Y,
+            Facing::NegY => IVec3::NEG_Y,
+            Facing::PosZ => IVec3::Z,
+            Facing::NegZ => IVec3::NEG_Z,
+        }
+    }
+
+    /// The side `normal` points to, `None` unless it is a unit vector along an axis
+    pub fn from_normal(normal: IVec3) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|facing| facing.normal() == normal)
+    }
+
+    /// The side `direction` points to the most, `None` for zero
+    pub fn from_direction(direction: Vec3) -> Option<Self> {
+        let abs = direction.abs();
+        if abs.max_element() == 0.0 {
+            return None;
+        }
+        let normal = if abs.x >= abs.y && abs.x >= abs.z {
+            IVec3::X * direction.x.signum() as i32
+        } else if abs.y >= abs.z {
+            IVec3::Y * direction.y.signum() as i32
+        } else {
+            IVec3::Z * direction.z.signum() as i32
+        };
+        Self::from_normal(normal)
+    }
+
+    /// Turns `+z` to face this side, around `y` for the sides around and `x` for up and down
+    pub fn rotation(self) -> Quat {
+        match self {
+            Facing::PosZ => Quat::IDENTITY,
+            Facing::PosX => Quat::from_rotation_y(FRAC_PI_2),
+            Facing::NegZ => Quat::from_rotation_y(PI),
+            Facing::NegX => Quat::from_rotation_y(-FRAC_PI_2),
+            Facing::PosY => Quat::from_rotation_x(-FRAC_PI_2),
+            Facing::NegY => Quat::from_rotation_x(FRAC_PI_2),
+        }
+    }
+
+    /// Turns `y` to lie along the axis of this side
+    pub fn axis_rotation(self) -> Quat {
+        match self {
+            Facing::PosX | Facing::NegX => Quat::from_rotation_z(-FRAC_PI_2),
+            Facing::PosY | Facing::NegY => Quat::IDENTITY,
+            Facing::PosZ | Facing::NegZ => Quat::from_rotation_x(FRAC_PI_2),
+        }
+    }
+
+    fn index(self) -> u8 {
+        Self::ALL
+            .iter()
+            .position(|facing| *facing == self)
+            .unwrap_or_default() as u8
+    }
+}
+
+/// The variant of a block at one position, stored by the chunk next to the block. Only the
+/// parts matching the block's [properties](crate::definition::Block::properties) mean anything
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize, Reflect)]
+pub struct BlockState {
+    /// Side the front faces for [BlockProperty::Facing] and [BlockProperty::HorizontalFacing],
+    /// the axis for [BlockProperty::Axis]
+    #[serde(default)]
+    pub facing: Facing,
+    #[serde(default)]
+    pub open: bool,
+}
+
+impl BlockState {
+    /// The state of a block with `properties` placed against the face with `normal` by a
+    /// player looking along `look`. Blocks face the player and lie along the face's axis
+    pub fn placed(properties: &[BlockProperty], normal: IVec3, look: Vec3) -> Self {
+        let mut state = Self::default();
+        for property in properties {
+            match property {
+                BlockProperty::Facing => {
+                    state.facing = Facing::from_direction(-look).unwrap_or_default();
+                }
+                BlockProperty::HorizontalFacing => {
+                    state.facing =
+                        Facing::from_direction(-look * Vec3::new(1., 0., 1.)).unwrap_or_default();
+                }
+                BlockProperty::Axis => {
+                    state.facing = Facing::from_normal(normal).unwrap_or(Facing::PosY);
+                }
+                BlockProperty::Open => {}
+            }
+        }
+        state
+    }
+
+    /// Packs the state into a byte: the facing in the low three bits, then whether it is open
+    pub fn to_bits(self) -> u8 {
+        self.facing.index() | ((self.open as u8) << 3)
+    }
+
+    /// The state packed by [to_bits](BlockState::to_bits), `None` for bytes it never returns
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        if bits >> 4 != 0 {
+            return None;
+        }
+        Some(Self {
+            facing: *Facing::ALL.get((bits & 0b111) as usize)?,
+            open: bits & 0b1000 != 0,
+        })
+    }
+}
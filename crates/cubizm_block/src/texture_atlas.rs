@@ -1,23 +1,193 @@
-use bevy::{asset::LoadedFolder, prelude::*, render::texture::ImageSampler};
+use bevy::{
+    asset::LoadedFolder,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{ImageFilterMode, ImageSampler, ImageSamplerDescriptor},
+    },
+    utils::{HashMap, HashSet},
+};
+use image::imageops::FilterType;
 
-use crate::definition::Block;
+use crate::definition::{Block, BlockMaps};
+use crate::BlockPluginSettings;
 
+/// The base `blocks/info` folder followed by the one of every mod that ships blocks
 #[derive(Resource, Default)]
-pub(crate) struct BlockInfoFolder(Handle<LoadedFolder>);
+pub(crate) struct BlockInfoFolder(Vec<Handle<LoadedFolder>>);
 
-#[derive(Resource)]
+/// How block textures are packed for rendering chunks
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Resource)]
+pub enum BlockTextureMode {
+    /// Stitched into a single atlas image
+    #[default]
+    Atlas,
+    /// One layer per texture in a 2D texture array, so textures cannot bleed into each other.
+    /// Textures are scaled to the size of the largest one
+    Array,
+}
+
+/// Drawn in place of block textures that failed to load, a magenta and black checker. Always
+/// in the [BlockAtlas]
+pub const MISSING_TEXTURE: Handle<Image> =
+    Handle::weak_from_u128(0x4d15_51e9_c7a2_4b0f_8e36_95d1_0bf4_7c28);
+
+/// The image of [MISSING_TEXTURE], laid out like block textures as a column of six faces
+pub fn missing_texture_image() -> Image {
+    const FACE: u32 = 16;
+    let data = (0..FACE * 6)
+        .flat_map(|y| (0..FACE).map(move |x| (x / (FACE / 2) + y / (FACE / 2)).is_multiple_of(2)))
+        .flat_map(|magenta| match magenta {
+            true => [255, 0, 255, 255],
+            false => [0, 0, 0, 255],
+        })
+        .collect();
+    Image::new(
+        Extent3d {
+            width: FACE,
+            height: FACE * 6,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// How chunks sample block textures, see [BlockPlugin::sampling](crate::BlockPlugin::sampling)
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Resource)]
+pub struct BlockSampling {
+    /// Blends neighbouring texels instead of keeping the pixel look
+    pub linear: bool,
+    /// Generates mipmaps and blends between them, so distant terrain doesn't shimmer. Each
+    /// texture is downsampled on its own, pair with
+    /// [atlas_extrusion](BlockPluginSettings::atlas_extrusion) to keep edges from bleeding
+    pub mipmaps: bool,
+    /// Samples taken for anisotropic filtering, 1 turns it off. Only used when `linear`
+    /// and `mipmaps` are both on, wgpu allows it with nothing else
+    pub anisotropy: u16,
+}
+
+impl Default for BlockSampling {
+    /// Nearest texels with mipmaps
+    fn default() -> Self {
+        Self {
+            linear: false,
+            mipmaps: true,
+            anisotropy: 1,
+        }
+    }
+}
+
+impl BlockSampling {
+    pub fn sampler(&self) -> ImageSampler {
+        let filter = match self.linear {
+            true => ImageFilterMode::Linear,
+            false => ImageFilterMode::Nearest,
+        };
+        let mipmap_filter = match self.mipmaps {
+            true => ImageFilterMode::Linear,
+            false => ImageFilterMode::Nearest,
+        };
+        let anisotropy_clamp = match self.linear && self.mipmaps {
+            true => self.anisotropy.max(1),
+            false => 1,
+        };
+        ImageSampler::Descriptor(ImageSamplerDescriptor {
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter,
+            anisotropy_clamp,
+            ..ImageSamplerDescriptor::nearest()
+        })
+    }
+}
+
+/// The [TextureAtlasLayout] of a [BlockAtlas] along with the index of every texture in it.
+/// Bevy only keeps those for layouts built by a single [TextureAtlasBuilder], which the
+/// pages are not merged back into
+#[derive(Debug, Clone, Reflect)]
+pub struct BlockAtlasLayout {
+    layout: TextureAtlasLayout,
+    indices: HashMap<AssetId<Image>, usize>,
+}
+
+impl BlockAtlasLayout {
+    pub fn new_empty(size: Vec2) -> Self {
+        Self {
+            layout: TextureAtlasLayout::new_empty(size),
+            indices: HashMap::new(),
+        }
+    }
+
+    /// Adds the rect of `texture`, returning its index
+    pub fn add_texture(&mut self, texture: AssetId<Image>, rect: Rect) -> usize {
+        let index = self.layout.add_texture(rect);
+        self.indices.insert(texture, index);
+        index
+    }
+
+    pub fn get_texture_index(&self, texture: impl Into<AssetId<Image>>) -> Option<usize> {
+        self.indices.get(&texture.into()).copied()
+    }
+}
+
+impl std::ops::Deref for BlockAtlasLayout {
+    type Target = TextureAtlasLayout;
+
+    fn deref(&self) -> &TextureAtlasLayout {
+        &self.layout
+    }
+}
+
+/// Sent once the [BlockAtlas] was rebuilt after a block or block texture changed on disk
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BlockAtlasRebuilt;
+
+/// The block textures stitched into atlas pages. Textures that don't fit into one page of
+/// [max_atlas_size](BlockPluginSettings::max_atlas_size) spill over onto more pages, which
+/// share the layout: textures on page `n` sit `n` page heights down, see [atlas_page]
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
 pub struct BlockAtlas {
+    /// The first page
     image: Handle<Image>,
-    texture_atlas_layout: TextureAtlasLayout,
+    texture_atlas_layout: BlockAtlasLayout,
+    /// Every page, all of the size of the layout, `image` first
+    pages: Vec<Handle<Image>>,
+    /// The pages as the layers of a texture array, only when there is more than one
+    page_array: Option<Handle<Image>>,
+    /// Layer `i` holds the texture at index `i` of the atlas layout
+    array: Option<Handle<Image>>,
+    /// Atlases of the blocks' [BlockMaps], laid out like `image`. Only built for
+    /// [BlockTextureMode::Atlas] and when a block has the map
+    maps: BlockMaps,
 }
 
 impl BlockInfoFolder {
-    pub(crate) fn new(handle: impl Into<Handle<LoadedFolder>>) -> Self {
-        Self(handle.into())
+    pub(crate) fn new(handles: impl IntoIterator<Item = Handle<LoadedFolder>>) -> Self {
+        Self(handles.into_iter().collect())
     }
 
-    pub(crate) fn clone_handle(&self) -> Handle<LoadedFolder> {
-        Handle::clone(&self.0)
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Handle<LoadedFolder>> {
+        self.0.iter()
+    }
+
+    pub(crate) fn is_loaded(&self, asset_server: &AssetServer) -> bool {
+        self.0
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+    }
+}
+
+/// An atlas without textures, needed to reflect [BlockAtlas] as a resource
+impl Default for BlockAtlas {
+    fn default() -> Self {
+        Self::new(Handle::default(), BlockAtlasLayout::new_empty(Vec2::ZERO))
     }
 }
 
@@ -25,11 +195,16 @@ impl BlockInfoFolder {
 impl BlockAtlas {
     pub(crate) fn new(
         image: impl Into<Handle<Image>>,
-        texture_atlas_layout: impl Into<TextureAtlasLayout>,
+        texture_atlas_layout: BlockAtlasLayout,
     ) -> Self {
+        let image = image.into();
         Self {
-            image: image.into(),
-            texture_atlas_layout: texture_atlas_layout.into(),
+            pages: vec![image.clone()],
+            image,
+            texture_atlas_layout,
+            page_array: None,
+            array: None,
+            maps: BlockMaps::default(),
         }
     }
 
@@ -37,43 +212,384 @@ impl BlockAtlas {
         Handle::clone(&self.image)
     }
 
-    pub fn get_texture_atlas_layout(&self) -> &TextureAtlasLayout {
+    pub fn texture_mode(&self) -> BlockTextureMode {
+        match self.array {
+            Some(_) => BlockTextureMode::Array,
+            None => BlockTextureMode::Atlas,
+        }
+    }
+
+    /// Every page of the atlas, the first one being [clone_image](BlockAtlas::clone_image)
+    pub fn pages(&self) -> &[Handle<Image>] {
+        &self.pages
+    }
+
+    /// The pages as layers of a texture array, `None` while everything fits on one page. Chunk
+    /// meshes then hold the page of each vertex in `UV_1` like [BlockTextureMode::Array]
+    pub fn clone_page_array(&self) -> Option<Handle<Image>> {
+        self.page_array.clone()
+    }
+
+    /// The page the texture at `index` of the atlas layout is on
+    pub fn texture_page(&self, index: usize) -> usize {
+        atlas_page(&self.texture_atlas_layout, index).0
+    }
+
+    /// The atlas layout with the rects of the textures on `page` moved onto the page, for
+    /// drawing them from its image
+    pub fn page_layout(&self, page: usize) -> TextureAtlasLayout {
+        let layout = &self.texture_atlas_layout;
+        let offset = Vec2::new(0., page as f32 * layout.size.y);
+        let mut page_layout = TextureAtlasLayout::new_empty(layout.size);
+        for rect in &layout.textures {
+            page_layout.add_texture(Rect::from_corners(rect.min - offset, rect.max - offset));
+        }
+        page_layout
+    }
+
+    /// The texture array of [BlockTextureMode::Array], indexed like the atlas layout
+    pub fn clone_array_image(&self) -> Option<Handle<Image>> {
+        self.array.clone()
+    }
+
+    /// The normal, metallic-roughness and emissive atlases, each texel lining up with the
+    /// atlas image
+    pub fn maps(&self) -> &BlockMaps {
+        &self.maps
+    }
+
+    pub fn get_texture_atlas_layout(&self) -> &BlockAtlasLayout {
         &self.texture_atlas_layout
     }
+
+    /// Index in the atlas layout of the texture of `block`, or of [MISSING_TEXTURE] if it isn't
+    /// in the atlas. `None` for blocks without one
+    pub fn texture_index(&self, block: &Block) -> Option<usize> {
+        let texture = block.voxel_texture_id()?;
+        self.texture_atlas_layout
+            .get_texture_index(texture)
+            .or_else(|| {
+                self.texture_atlas_layout
+                    .get_texture_index(&MISSING_TEXTURE)
+            })
+    }
+}
+
+/// The page of the [BlockAtlas] the texture at `index` of `layout` is on, along with its rect
+/// within that page
+pub fn atlas_page(layout: &TextureAtlasLayout, index: usize) -> (usize, Rect) {
+    let rect = layout.textures[index];
+    let page = match layout.size.y > 0. {
+        true => (rect.min.y / layout.size.y).floor(),
+        false => 0.,
+    };
+    let offset = Vec2::new(0., page * layout.size.y);
+    (
+        page as usize,
+        Rect::from_corners(rect.min - offset, rect.max - offset),
+    )
+}
+
+/// How many pages the [BlockAtlas] with `layout` has
+pub fn atlas_pages(layout: &TextureAtlasLayout) -> usize {
+    (0..layout.len())
+        .map(|index| atlas_page(layout, index).0 + 1)
+        .max()
+        .unwrap_or(1)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn setup_texture_atlas(
     loaded_folders: Res<Assets<LoadedFolder>>,
     block_info_handles: Res<BlockInfoFolder>,
     mut textures: ResMut<Assets<Image>>,
     blocks: Res<Assets<Block>>,
+    mode: Res<BlockTextureMode>,
+    settings: Res<BlockPluginSettings>,
+    sampling: Res<BlockSampling>,
     mut commands: Commands,
 ) {
-    let loaded_folder = loaded_folders.get(&block_info_handles.0).unwrap();
-    let (texture_atlas_linear, linear_texture) = create_texture_atlas(
-        loaded_folder,
-        None,
-        Some(ImageSampler::nearest()),
+    commands.insert_resource(build_block_atlas(
+        &loaded_folders,
+        &block_info_handles,
         &mut textures,
+        blocks,
+        *mode,
+        &settings,
+        *sampling,
+    ));
+}
+
+/// Rebuilds the [BlockAtlas] whenever a loaded block, or the texture of one, is modified,
+/// for hot reloading with bevy's `file_watcher`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn reload_texture_atlas(
+    mut block_events: EventReader<AssetEvent<Block>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    block_info_handles: Res<BlockInfoFolder>,
+    mut textures: ResMut<Assets<Image>>,
+    blocks: Res<Assets<Block>>,
+    mode: Res<BlockTextureMode>,
+    settings: Res<BlockPluginSettings>,
+    sampling: Res<BlockSampling>,
+    mut commands: Commands,
+    mut rebuilt: EventWriter<BlockAtlasRebuilt>,
+) {
+    let blocks_modified = block_events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Modified { .. }));
+    // The atlas and array images are modified by building them, only block textures count
+    let textures_modified = image_events.read().any(|event| match event {
+        AssetEvent::Modified { id } | AssetEvent::LoadedWithDependencies { id } => {
+            blocks.iter().any(|(_, block)| {
+                block.texture_ids().contains(id)
+                    || block
+                        .maps()
+                        .is_some_and(|maps| maps.iter().any(|map| map.id() == *id))
+            })
+        }
+        _ => false,
+    });
+    if !blocks_modified && !textures_modified {
+        return;
+    }
+
+    info!("Block assets changed, rebuilding the block atlas");
+    commands.insert_resource(build_block_atlas(
+        &loaded_folders,
+        &block_info_handles,
+        &mut textures,
+        blocks,
+        *mode,
+        &settings,
+        *sampling,
+    ));
+    rebuilt.send(BlockAtlasRebuilt);
+}
+
+fn build_block_atlas(
+    loaded_folders: &Assets<LoadedFolder>,
+    block_info_handles: &BlockInfoFolder,
+    textures: &mut ResMut<Assets<Image>>,
+    blocks: Res<Assets<Block>>,
+    mode: BlockTextureMode,
+    settings: &BlockPluginSettings,
+    sampling: BlockSampling,
+) -> BlockAtlas {
+    let loaded_folders = block_info_handles
+        .0
+        .iter()
+        .map(|handle| loaded_folders.get(handle).unwrap())
+        .collect::<Vec<_>>();
+    let (texture_atlas_linear, pages) = create_texture_atlas(
+        loaded_folders.iter().copied(),
+        settings.atlas_padding,
+        settings.atlas_extrusion,
+        settings.max_atlas_size,
+        sampling,
+        textures,
         Res::clone(&blocks),
     );
-    commands.insert_resource(BlockAtlas {
+    let page_array = match (mode, pages.len()) {
+        (BlockTextureMode::Atlas, 2..) => stack_atlas_pages(&pages, textures),
+        _ => None,
+    };
+    let folder_blocks = loaded_folders
+        .iter()
+        .flat_map(|folder| folder.handles.iter())
+        .filter_map(|handle| blocks.get(handle.id().typed_unchecked::<Block>()))
+        .collect::<Vec<_>>();
+    let (array, maps) = match mode {
+        // The maps line up with a single page
+        BlockTextureMode::Atlas if page_array.is_some() => (None, BlockMaps::default()),
+        BlockTextureMode::Atlas => {
+            let mut map_atlas = |map: fn(&BlockMaps) -> &Option<Handle<Image>>, fill, format| {
+                create_map_atlas(
+                    &texture_atlas_linear,
+                    folder_blocks.iter().filter_map(|block| {
+                        Some((block.voxel_texture_id()?, map(block.maps()?).as_ref()?))
+                    }),
+                    fill,
+                    format,
+                    sampling,
+                    textures,
+                )
+            };
+            // Blocks without a map get a flat normal, the default roughness and no glow
+            let maps = BlockMaps {
+                normal: map_atlas(
+                    |maps| &maps.normal,
+                    [128, 128, 255, 255],
+                    TextureFormat::Rgba8Unorm,
+                ),
+                metallic_roughness: map_atlas(
+                    |maps| &maps.metallic_roughness,
+                    [0, 128, 0, 255],
+                    TextureFormat::Rgba8Unorm,
+                ),
+                emissive: map_atlas(
+                    |maps| &maps.emissive,
+                    [0, 0, 0, 255],
+                    TextureFormat::Rgba8UnormSrgb,
+                ),
+            };
+            (None, maps)
+        }
+        BlockTextureMode::Array => {
+            let texture_ids = folder_blocks.iter().flat_map(|block| block.texture_ids());
+            let array =
+                create_texture_array(&texture_atlas_linear, texture_ids, sampling, textures);
+            (array, BlockMaps::default())
+        }
+    };
+    BlockAtlas {
         texture_atlas_layout: texture_atlas_linear,
-        image: linear_texture,
-    });
+        image: pages[0].clone(),
+        pages,
+        page_array,
+        array,
+        maps,
+    }
+}
+
+/// An image the size of the atlas of `layout` with each `(texture, map)` of `maps` stretched
+/// over the rect of the texture, so the map lines up with the atlas. Texels of textures
+/// without a map are `fill`. `None` if there are no maps. Textures shared by blocks with
+/// different maps get the map of the last block
+pub(crate) fn create_map_atlas<'a>(
+    layout: &BlockAtlasLayout,
+    maps: impl IntoIterator<Item = (AssetId<Image>, &'a Handle<Image>)>,
+    fill: [u8; 4],
+    format: TextureFormat,
+    sampling: BlockSampling,
+    textures: &mut Assets<Image>,
+) -> Option<Handle<Image>> {
+    let size = layout.size.as_uvec2();
+    let mut data = fill.repeat((size.x * size.y) as usize);
+    let mut any = false;
+    for (texture, map) in maps {
+        let Some(index) = layout.get_texture_index(texture) else {
+            continue;
+        };
+        let Some(image) = textures
+            .get(map)
+            .and_then(|image| image.clone().try_into_dynamic().ok())
+        else {
+            warn!("{:?} could not be read as a block map", map.path());
+            continue;
+        };
+        let rect = layout.textures[index];
+        let (min, rect_size) = (rect.min.as_uvec2(), rect.size().as_uvec2());
+        let image = image
+            .resize_exact(rect_size.x, rect_size.y, FilterType::Nearest)
+            .to_rgba8();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let offset = (((min.y + y) * size.x + min.x + x) * 4) as usize;
+            data[offset..offset + 4].copy_from_slice(&pixel.0);
+        }
+        any = true;
+    }
+    if !any {
+        return None;
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    if sampling.mipmaps {
+        add_mipmaps(&mut image, &layout.textures);
+    }
+    image.sampler = sampling.sampler();
+    Some(textures.add(image))
 }
 
-pub(crate) fn create_texture_atlas(
-    folder: &LoadedFolder,
-    padding: Option<UVec2>,
-    sampling: Option<ImageSampler>,
+/// Stacks `texture_ids` into a texture array, each at its index in `layout`
+pub(crate) fn create_texture_array(
+    layout: &BlockAtlasLayout,
+    texture_ids: impl IntoIterator<Item = AssetId<Image>>,
+    sampling: BlockSampling,
+    textures: &mut Assets<Image>,
+) -> Option<Handle<Image>> {
+    let mut ids = vec![None; layout.len()];
+    for id in texture_ids {
+        if let Some(index) = layout.get_texture_index(id) {
+            ids[index] = Some(id);
+        }
+    }
+    let layers = ids
+        .into_iter()
+        .map(|id| {
+            let image = textures.get(id?)?;
+            image
+                .convert(TextureFormat::Rgba8UnormSrgb)?
+                .try_into_dynamic()
+                .ok()
+        })
+        .collect::<Option<Vec<_>>>();
+    let Some(layers) = layers.filter(|layers| !layers.is_empty()) else {
+        warn!("Block textures could not be packed into a texture array");
+        return None;
+    };
+
+    let width = layers.iter().map(|layer| layer.width()).max()?;
+    let height = layers.iter().map(|layer| layer.height()).max()?;
+    let mut data = Vec::new();
+    for layer in layers.iter() {
+        let layer = match layer.width() == width && layer.height() == height {
+            true => layer.to_rgba8(),
+            false => layer
+                .resize_exact(width, height, FilterType::Nearest)
+                .to_rgba8(),
+        };
+        data.extend_from_slice(&layer);
+    }
+    let mut array = Image::new(
+        Extent3d {
+            width,
+            height: height * layers.len() as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    array.reinterpret_stacked_2d_as_array(layers.len() as u32);
+    if sampling.mipmaps {
+        let layer = Rect::new(0., 0., width as f32, height as f32);
+        add_mipmaps(&mut array, &[layer]);
+    }
+    array.sampler = sampling.sampler();
+    Some(textures.add(array))
+}
+
+/// Stitches the textures of the blocks in `folders` into atlas pages of at most `max_size`,
+/// starting a new page whenever the textures left don't fit. Each texture is grown by
+/// `extrusion` texels repeating its edges, the rects in the layout leave those out. Returns the
+/// layout shared by the pages, see [atlas_page], and the pages, all of the size of the layout
+pub(crate) fn create_texture_atlas<'a>(
+    folders: impl IntoIterator<Item = &'a LoadedFolder>,
+    padding: UVec2,
+    extrusion: u32,
+    max_size: UVec2,
+    sampling: BlockSampling,
     textures: &mut ResMut<Assets<Image>>,
     blocks: Res<Assets<Block>>,
-) -> (TextureAtlasLayout, Handle<Image>) {
-    // Build a texture atlas using the individual sprites
-    let mut texture_atlas_builder =
-        TextureAtlasBuilder::default().padding(padding.unwrap_or_default());
-    for handle in folder.handles.iter() {
+) -> (BlockAtlasLayout, Vec<Handle<Image>>) {
+    // Blocks fall back to it for textures that aren't in the atlas
+    textures.insert(&MISSING_TEXTURE, missing_texture_image());
+    let mut ids = vec![MISSING_TEXTURE.id()];
+    let mut added = HashSet::new();
+    added.insert(MISSING_TEXTURE.id());
+    for handle in folders.into_iter().flat_map(|folder| folder.handles.iter()) {
         let block_id = handle.id().typed_unchecked::<Block>();
         let Some(block) = blocks.get(block_id) else {
             warn!(
@@ -87,28 +603,264 @@ pub(crate) fn create_texture_atlas(
             continue;
         }
 
-        let Some(texture_handle) = block.voxel_texture() else {
-            continue;
-        };
+        // Blocks and models can share textures
+        for id in block.texture_ids() {
+            if !added.insert(id) {
+                continue;
+            }
+            if textures.get(id).is_none() {
+                warn!(
+                    "{id:?} of {:?} did not resolve to an `Image` asset, it is drawn with the \
+                     missing texture",
+                    handle.path()
+                );
+                continue;
+            }
+            ids.push(id);
+        }
+    }
+    let extruded = match extrusion {
+        0 => HashMap::new(),
+        _ => ids
+            .iter()
+            .filter_map(|id| Some((*id, extrude_image(textures.get(*id)?, extrusion)?)))
+            .collect(),
+    };
 
-        let id = texture_handle.id();
+    // Fill each page with as many of the remaining textures as fit, fewer after every miss
+    let mut pages = Vec::new();
+    let mut remaining = ids.as_slice();
+    while !remaining.is_empty() {
+        let mut count = remaining.len();
+        loop {
+            let mut texture_atlas_builder = TextureAtlasBuilder::default()
+                .padding(padding)
+                .max_size(max_size.as_vec2());
+            for id in &remaining[..count] {
+                let texture = extruded.get(id).or_else(|| textures.get(*id)).unwrap();
+                texture_atlas_builder.add_texture(Some(*id), texture);
+            }
+            match texture_atlas_builder.finish() {
+                Ok(page) => {
+                    pages.push(page);
+                    remaining = &remaining[count..];
+                    break;
+                }
+                Err(_) if count > 1 => count = count * 3 / 4,
+                Err(err) => {
+                    warn!(
+                        "{:?} does not fit into a block atlas page: {err}",
+                        remaining[0]
+                    );
+                    remaining = &remaining[1..];
+                    break;
+                }
+            }
+        }
+    }
 
-        let Some(texture) = textures.get(id) else {
-            warn!(
-                "{:?} did not resolve to an `Image` asset.",
-                texture_handle.path().unwrap()
+    // Pages are padded to the size of the largest one, so they share the layout
+    let size = pages
+        .iter()
+        .fold(Vec2::ZERO, |size, (layout, _)| size.max(layout.size));
+    let mut texture_atlas_layout = BlockAtlasLayout::new_empty(size);
+    let mut page_images = Vec::new();
+    for (page, (layout, image)) in pages.into_iter().enumerate() {
+        let offset = Vec2::new(0., page as f32 * size.y);
+        for id in &ids {
+            let Some(index) = layout.get_texture_index(*id) else {
+                continue;
+            };
+            let rect = layout.textures[index];
+            let inset = match extruded.contains_key(id) {
+                true => Vec2::splat(extrusion as f32),
+                false => Vec2::ZERO,
+            };
+            texture_atlas_layout.add_texture(
+                *id,
+                Rect::from_corners(rect.min + offset + inset, rect.max + offset - inset),
             );
-            continue;
-        };
+        }
+        let mut image = pad_image(image, size.as_uvec2());
+        if sampling.mipmaps {
+            add_mipmaps(&mut image, &layout.textures);
+        }
+        image.sampler = sampling.sampler();
+        page_images.push(textures.add(image));
+    }
+    if page_images.is_empty() {
+        page_images.push(textures.add(Image {
+            sampler: sampling.sampler(),
+            ..default()
+        }));
+    }
+
+    (texture_atlas_layout, page_images)
+}
+
+/// `image` grown by `extrusion` texels on every side, each a copy of the nearest edge texel, so
+/// filtering near the edge of its rect in the atlas doesn't pick up its neighbours
+fn extrude_image(image: &Image, extrusion: u32) -> Option<Image> {
+    let image = image.convert(TextureFormat::Rgba8UnormSrgb)?;
+    let size = image.size();
+    if size.x == 0 || size.y == 0 {
+        return None;
+    }
+    let extruded = size + UVec2::splat(extrusion * 2);
+    let mut data = Vec::with_capacity((extruded.x * extruded.y * 4) as usize);
+    for y in 0..extruded.y {
+        let source_y = y.saturating_sub(extrusion).min(size.y - 1);
+        for x in 0..extruded.x {
+            let source_x = x.saturating_sub(extrusion).min(size.x - 1);
+            let offset = ((source_y * size.x + source_x) * 4) as usize;
+            data.extend_from_slice(&image.data[offset..offset + 4]);
+        }
+    }
+    let mut extruded = Image::new(
+        Extent3d {
+            width: extruded.x,
+            height: extruded.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        image.asset_usage,
+    );
+    extruded.sampler = image.sampler;
+    Some(extruded)
+}
 
-        texture_atlas_builder.add_texture(Some(id), texture);
+/// `image` with transparent texels added to its right and bottom up to `size`
+fn pad_image(image: Image, size: UVec2) -> Image {
+    let current = image.size();
+    if current == size {
+        return image;
     }
+    let texel = image
+        .texture_descriptor
+        .format
+        .block_copy_size(None)
+        .unwrap_or(4) as usize;
+    let mut data = vec![0; (size.x * size.y) as usize * texel];
+    let row = current.x as usize * texel;
+    for (y, source) in image.data.chunks_exact(row).enumerate() {
+        let start = y * size.x as usize * texel;
+        data[start..start + row].copy_from_slice(source);
+    }
+    Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        image.texture_descriptor.format,
+        image.asset_usage,
+    )
+}
 
-    let (texture_atlas_layout, texture) = texture_atlas_builder.finish().unwrap();
-    let texture = textures.add(texture);
-    // Update the sampling settings of the texture atlas
-    let image = textures.get_mut(&texture).unwrap();
-    image.sampler = sampling.unwrap_or_default();
+/// Stacks the atlas `pages`, all of the same size, into the layers of a texture array
+fn stack_atlas_pages(
+    pages: &[Handle<Image>],
+    textures: &mut Assets<Image>,
+) -> Option<Handle<Image>> {
+    let first = textures.get(&pages[0])?;
+    let (size, format, sampler, mip_levels) = (
+        first.size(),
+        first.texture_descriptor.format,
+        first.sampler.clone(),
+        first.texture_descriptor.mip_level_count,
+    );
+    let level_size =
+        (size.x * size.y) as usize * format.block_copy_size(None).unwrap_or(4) as usize;
+    let (mut data, mut mips) = (Vec::new(), Vec::new());
+    for page in pages {
+        let page = &textures.get(page)?.data;
+        data.extend_from_slice(&page[..level_size]);
+        mips.extend_from_slice(page);
+    }
+    let mut array = Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y * pages.len() as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    array.reinterpret_stacked_2d_as_array(pages.len() as u32);
+    // Every layer is followed by its mips
+    if mip_levels > 1 {
+        array.data = mips;
+        array.texture_descriptor.mip_level_count = mip_levels;
+    }
+    array.sampler = sampler;
+    Some(textures.add(array))
+}
+
+/// Adds mipmaps to every layer of the `Rgba8` `image`, down to where the smallest of `tiles`
+/// is a texel wide. Each texel averages the texels it covers within its tile, so the textures
+/// in an atlas don't blend into each other. Texels outside the tiles are left transparent
+fn add_mipmaps(image: &mut Image, tiles: &[Rect]) {
+    let size = image.size();
+    let smallest = tiles
+        .iter()
+        .map(|tile| tile.size().min_element() as u32)
+        .min()
+        .unwrap_or(0)
+        .min(size.min_element());
+    if smallest < 2 {
+        return;
+    }
+    let levels = smallest.ilog2() + 1;
+    let layer_size = (size.x * size.y * 4) as usize;
+    let mut data = Vec::with_capacity(image.data.len() * 4 / 3);
+    for layer in image.data.chunks_exact(layer_size) {
+        data.extend_from_slice(layer);
+        for level in 1..levels {
+            data.extend(downsample_tiles(layer, size, tiles, level));
+        }
+    }
+    image.data = data;
+    image.texture_descriptor.mip_level_count = levels;
+}
 
-    (texture_atlas_layout, texture)
+/// Mip `level` of the `Rgba8` texels of `data`, the size of which is `size`, see [add_mipmaps]
+fn downsample_tiles(data: &[u8], size: UVec2, tiles: &[Rect], level: u32) -> Vec<u8> {
+    let level_size = (size >> level).max(UVec2::ONE);
+    let mut mip = vec![0; (level_size.x * level_size.y * 4) as usize];
+    for tile in tiles {
+        let (min, max) = (tile.min.as_uvec2(), tile.max.as_uvec2().min(size));
+        let step = 1 << level;
+        let level_min = min >> level;
+        let level_max = ((max + step - 1) >> level).min(level_size);
+        for y in level_min.y..level_max.y {
+            for x in level_min.x..level_max.x {
+                let from = (UVec2::new(x, y) << level).max(min);
+                let to = ((UVec2::new(x, y) + 1) << level).min(max);
+                let (mut sum, mut count) = ([0u32; 4], 0);
+                for source_y in from.y..to.y {
+                    for source_x in from.x..to.x {
+                        let offset = ((source_y * size.x + source_x) * 4) as usize;
+                        for (channel, value) in sum.iter_mut().zip(&data[offset..offset + 4]) {
+                            *channel += *value as u32;
+                        }
+                        count += 1;
+                    }
+                }
+                if count == 0 {
+                    continue;
+                }
+                let offset = ((y * level_size.x + x) * 4) as usize;
+                for (texel, channel) in mip[offset..offset + 4].iter_mut().zip(sum) {
+                    *texel = (channel / count) as u8;
+                }
+            }
+        }
+    }
+    mip
 }
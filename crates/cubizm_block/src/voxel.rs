@@ -1,6 +1,7 @@
-use block_mesh::{Voxel, VoxelVisibility};
+use bevy::prelude::*;
+use block_mesh::{MergeVoxel, Voxel, VoxelVisibility};
 
-use crate::definition::Block;
+use crate::definition::{Block, RenderLayer};
 
 impl Voxel for Block {
     fn get_visibility(&self) -> VoxelVisibility {
@@ -14,6 +15,26 @@ impl Voxel for &Block {
     }
 }
 
+/// Faces merge when they show the same texture and are exposed to the same kind of neighbour
+impl MergeVoxel for &Block {
+    /// Faces only merge when they look the same: texture, [RenderLayer] and glow
+    type MergeValue = (Option<AssetId<Image>>, RenderLayer, Option<([u8; 4], u8)>);
+    type MergeValueFacingNeighbour = VoxelVisibility;
+
+    fn merge_value(&self) -> Self::MergeValue {
+        (
+            self.voxel_texture_id(),
+            self.render_layer(),
+            self.emissive()
+                .map(|emissive| (emissive.color.as_rgba_u8(), emissive.strength)),
+        )
+    }
+
+    fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+        self.get_visibility()
+    }
+}
+
 impl Voxel for Box<Block> {
     fn get_visibility(&self) -> VoxelVisibility {
         Block::get_voxel_visibility(self)
@@ -0,0 +1,330 @@
+use std::collections::HashMap as StdHashMap;
+use std::io::Read;
+use std::path::Path;
+
+use bevy::{
+    asset::ron,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use block_mesh::ndshape::ConstShape;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{chunk_file_name, ChunkShape, SerializedChunk};
+
+/// Bytes of the location and timestamp tables at the start of a region file
+const REGION_HEADER: usize = 8192;
+/// Region files are made of sectors of this many bytes
+const SECTOR: usize = 4096;
+
+/// How Minecraft blocks turn into cubizm blocks when importing a region with
+/// [import_region], loaded from a RON file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AnvilMapping {
+    /// Asset path of the cubizm block for each namespaced Minecraft block name, e.g.
+    /// `"minecraft:stone": "blocks/info/stone.block"`. Block states are ignored
+    pub blocks: StdHashMap<String, String>,
+    /// Asset path of the block Minecraft blocks missing from `blocks` become
+    pub fallback: String,
+    /// Asset path of air, sections holding only air are not imported
+    pub air: String,
+}
+
+impl AnvilMapping {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AnvilError> {
+        Ok(ron::de::from_bytes(&std::fs::read(path)?)?)
+    }
+}
+
+/// The chunks read from a region file
+#[derive(Debug, Clone, Default)]
+pub struct RegionImport {
+    /// One for every 16 blocks high section of a Minecraft chunk
+    pub chunks: Vec<SerializedChunk>,
+    /// Minecraft blocks that were not in [AnvilMapping::blocks]
+    pub unmapped: HashSet<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum AnvilError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+    #[error(transparent)]
+    SaveChunk(#[from] crate::SaveChunkError),
+    #[error("Region file ended early")]
+    UnexpectedEof,
+    #[error("Unknown chunk compression {0}")]
+    UnknownCompression(u8),
+    #[error("Unknown NBT tag {0}")]
+    UnknownTag(u8),
+    #[error("Chunk is missing {0}")]
+    MissingField(&'static str),
+}
+
+/// The parts of the NBT format the importer reads, other tags are skipped
+#[derive(Debug, Clone)]
+enum Nbt {
+    /// Any integer tag
+    Integer(i64),
+    String(String),
+    List(Vec<Nbt>),
+    Compound(HashMap<String, Nbt>),
+    LongArray(Vec<i64>),
+    Skipped,
+}
+
+impl Nbt {
+    fn get(&self, name: &str) -> Option<&Nbt> {
+        match self {
+            Self::Compound(fields) => fields.get(name),
+            _ => None,
+        }
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Reads big endian NBT
+struct NbtReader<'a>(&'a [u8]);
+
+impl NbtReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], AnvilError> {
+        let (bytes, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(AnvilError::UnexpectedEof)?;
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn length(&mut self) -> Result<usize, AnvilError> {
+        Ok(i32::from_be_bytes(self.take()?).max(0) as usize)
+    }
+
+    fn string(&mut self) -> Result<String, AnvilError> {
+        let len = u16::from_be_bytes(self.take()?) as usize;
+        if self.0.len() < len {
+            return Err(AnvilError::UnexpectedEof);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        // Java's modified UTF-8 only differs for characters block names don't use
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// The named root tag of a chunk
+    fn root(&mut self) -> Result<Nbt, AnvilError> {
+        let [tag] = self.take()?;
+        self.string()?;
+        self.payload(tag)
+    }
+
+    /// Drops `len` bytes
+    fn skip(&mut self, len: usize) -> Result<Nbt, AnvilError> {
+        if self.0.len() < len {
+            return Err(AnvilError::UnexpectedEof);
+        }
+        self.0 = &self.0[len..];
+        Ok(Nbt::Skipped)
+    }
+
+    fn payload(&mut self, tag: u8) -> Result<Nbt, AnvilError> {
+        Ok(match tag {
+            1 => Nbt::Integer(i8::from_be_bytes(self.take()?) as i64),
+            2 => Nbt::Integer(i16::from_be_bytes(self.take()?) as i64),
+            3 => Nbt::Integer(i32::from_be_bytes(self.take()?) as i64),
+            4 => Nbt::Integer(i64::from_be_bytes(self.take()?)),
+            5 => self.skip(4)?,
+            6 => self.skip(8)?,
+            7 => {
+                let len = self.length()?;
+                self.skip(len)?
+            }
+            8 => Nbt::String(self.string()?),
+            9 => {
+                let [tag] = self.take()?;
+                Nbt::List(
+                    (0..self.length()?)
+                        .map(|_| self.payload(tag))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            10 => {
+                let mut fields = HashMap::new();
+                loop {
+                    let [tag] = self.take()?;
+                    if tag == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    fields.insert(name, self.payload(tag)?);
+                }
+                Nbt::Compound(fields)
+            }
+            11 => {
+                let len = self.length()?;
+                self.skip(len * 4)?
+            }
+            12 => Nbt::LongArray(
+                (0..self.length()?)
+                    .map(|_| Ok(i64::from_be_bytes(self.take()?)))
+                    .collect::<Result<_, AnvilError>>()?,
+            ),
+            tag => return Err(AnvilError::UnknownTag(tag)),
+        })
+    }
+}
+
+/// Reads every chunk of the `.mca` region file `bytes`. Supports chunks saved by Minecraft
+/// 1.16 and later, whose block states don't span several longs
+pub fn read_region(bytes: &[u8], mapping: &AnvilMapping) -> Result<RegionImport, AnvilError> {
+    let header = bytes
+        .get(..REGION_HEADER)
+        .ok_or(AnvilError::UnexpectedEof)?;
+    let mut import = RegionImport::default();
+    for location in header[..SECTOR].chunks_exact(4) {
+        let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize;
+        // Chunks that were never generated have no sectors
+        if offset == 0 || location[3] == 0 {
+            continue;
+        }
+        let data = bytes
+            .get(offset * SECTOR..)
+            .ok_or(AnvilError::UnexpectedEof)?;
+        let mut reader = NbtReader(data);
+        let length = u32::from_be_bytes(reader.take()?) as usize;
+        let [compression] = reader.take()?;
+        let compressed = reader
+            .0
+            .get(..length.saturating_sub(1))
+            .ok_or(AnvilError::UnexpectedEof)?;
+        let mut nbt = Vec::new();
+        match compression {
+            1 => GzDecoder::new(compressed).read_to_end(&mut nbt)?,
+            2 => ZlibDecoder::new(compressed).read_to_end(&mut nbt)?,
+            3 => {
+                nbt.extend_from_slice(compressed);
+                nbt.len()
+            }
+            compression => return Err(AnvilError::UnknownCompression(compression)),
+        };
+        read_chunk(&NbtReader(&nbt).root()?, mapping, &mut import)?;
+    }
+    Ok(import)
+}
+
+/// Adds the sections of the chunk `root` to `import`
+fn read_chunk(
+    root: &Nbt,
+    mapping: &AnvilMapping,
+    import: &mut RegionImport,
+) -> Result<(), AnvilError> {
+    // Before 1.18 everything is in a `Level` compound and sections hold their blocks directly
+    let level = root.get("Level").unwrap_or(root);
+    let position = |name: &'static str| {
+        level
+            .get(name)
+            .and_then(Nbt::as_i64)
+            .ok_or(AnvilError::MissingField(name))
+    };
+    let (x, z) = (position("xPos")? as i32, position("zPos")? as i32);
+    let Some(Nbt::List(sections)) = level.get("sections").or_else(|| level.get("Sections")) else {
+        return Err(AnvilError::MissingField("sections"));
+    };
+    for section in sections {
+        let y = section
+            .get("Y")
+            .and_then(Nbt::as_i64)
+            .ok_or(AnvilError::MissingField("Y"))? as i32;
+        let states = section.get("block_states").unwrap_or(section);
+        let Some(Nbt::List(palette)) = states.get("palette").or_else(|| states.get("Palette"))
+        else {
+            continue;
+        };
+        let palette: Vec<&str> = palette
+            .iter()
+            .map(|entry| match entry.get("Name") {
+                Some(Nbt::String(name)) => name.as_str(),
+                _ => "minecraft:air",
+            })
+            .collect();
+        let data = match states.get("data").or_else(|| states.get("BlockStates")) {
+            Some(Nbt::LongArray(data)) => data.as_slice(),
+            _ => &[],
+        };
+        // Indices are packed into longs with at least 4 bits, a single entry palette has none
+        let bits = (usize::BITS - (palette.len().saturating_sub(1)).leading_zeros()).max(4);
+        let per_long = 64 / bits as usize;
+        let entry = |index: usize| -> usize {
+            let Some(long) = data.get(index / per_long) else {
+                return 0;
+            };
+            let shift = (index % per_long) as u32 * bits;
+            ((*long as u64 >> shift) & ((1u64 << bits) - 1)) as usize
+        };
+
+        let mut chunk = SerializedChunk {
+            blocks: vec![mapping.air.clone(); ChunkShape::SIZE as usize],
+            position: IVec3::new(x, y, z),
+            states: Vec::new(),
+            tile_data: default(),
+            entities: Vec::new(),
+        };
+        let mut empty = true;
+        // Sections are as large as chunks, with blocks ordered by y, then z, then x
+        for index in 0..ChunkShape::SIZE as usize {
+            let name = palette
+                .get(entry(index))
+                .copied()
+                .unwrap_or("minecraft:air");
+            let block = match mapping.blocks.get(name) {
+                Some(block) => block,
+                None => {
+                    import.unmapped.insert(name.to_string());
+                    &mapping.fallback
+                }
+            };
+            if *block == mapping.air {
+                continue;
+            }
+            empty = false;
+            let (x, y, z) = (index % 16, index / 256, index / 16 % 16);
+            let local = [x as u32, y as u32, z as u32];
+            chunk.blocks[ChunkShape::linearize(local) as usize] = block.clone();
+        }
+        if !empty {
+            import.chunks.push(chunk);
+        }
+    }
+    Ok(())
+}
+
+/// Imports the `.mca` region file at `region` as `.chunkb` files in `directory`, named like the
+/// [WorldSaver](crate::WorldSaver) names them. Returns what was imported
+pub fn import_region(
+    region: impl AsRef<Path>,
+    mapping: &AnvilMapping,
+    directory: impl AsRef<Path>,
+) -> Result<RegionImport, AnvilError> {
+    let import = read_region(&std::fs::read(region)?, mapping)?;
+    let directory = directory.as_ref();
+    std::fs::create_dir_all(directory)?;
+    for chunk in import.chunks.iter() {
+        std::fs::write(
+            directory.join(chunk_file_name(chunk.position)),
+            chunk.to_binary().map_err(crate::SaveChunkError::from)?,
+        )?;
+    }
+    Ok(import)
+}
@@ -0,0 +1,287 @@
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext},
+    ecs::system::SystemParam,
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use cubizm_block::definition::BiomeTint;
+use cubizm_core::chunk_to_world;
+
+use crate::{hash_unit, value_noise, ActiveWorld, WorldManifest, CHUNK_SIZE};
+
+/// How many blocks around a column [BiomeTints] blend the biome colours over
+pub const TINT_BLEND_RADIUS: i32 = 2;
+
+/// How the terrain of a region of the world is generated, picked per block column by the
+/// [BiomeMap]. Loaded from `.biome` files
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Biome {
+    pub name: String,
+    /// Registry name of the top block of every column, see [block_key](cubizm_block::block_key)
+    pub surface: String,
+    /// Registry name of the blocks below the surface
+    pub filler: String,
+    pub height: HeightCurve,
+    /// Tint of the biome's grass, see [BiomeTint]
+    pub tint: Color,
+    pub foliage_tint: Color,
+    pub water_tint: Color,
+    /// Blocks scattered on top of the surface
+    pub decorations: Vec<Decoration>,
+}
+
+/// World height of the surface of a [Biome], `base` plus up to `amplitude` blocks of noise
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct HeightCurve {
+    pub base: i32,
+    pub amplitude: f32,
+    /// Width in blocks of the hills
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Decoration {
+    /// Registry name of the block
+    pub block: String,
+    /// Chance of the block standing on a surface block, from 0 to 1
+    pub chance: f32,
+}
+
+/// [Biome] as written in `.biome` files
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedBiome {
+    pub name: String,
+    pub surface: String,
+    pub filler: String,
+    pub height: HeightCurve,
+    /// sRGB, from 0 to 1
+    #[serde(default = "white")]
+    pub tint: [f32; 3],
+    /// The grass `tint` if left out
+    #[serde(default)]
+    pub foliage_tint: Option<[f32; 3]>,
+    #[serde(default = "white")]
+    pub water_tint: [f32; 3],
+    #[serde(default)]
+    pub decorations: Vec<Decoration>,
+}
+
+fn white() -> [f32; 3] {
+    [1.0; 3]
+}
+
+impl Biome {
+    /// The colour blocks with `tint` are multiplied by in this biome
+    pub fn tint_color(&self, tint: BiomeTint) -> Color {
+        match tint {
+            BiomeTint::None => Color::WHITE,
+            BiomeTint::Grass => self.tint,
+            BiomeTint::Foliage => self.foliage_tint,
+            BiomeTint::Water => self.water_tint,
+        }
+    }
+}
+
+impl From<SerializedBiome> for Biome {
+    fn from(value: SerializedBiome) -> Self {
+        let color = |[red, green, blue]: [f32; 3]| Color::rgb(red, green, blue);
+        Self {
+            name: value.name,
+            surface: value.surface,
+            filler: value.filler,
+            height: value.height,
+            tint: color(value.tint),
+            foliage_tint: color(value.foliage_tint.unwrap_or(value.tint)),
+            water_tint: color(value.water_tint),
+            decorations: value.decorations,
+        }
+    }
+}
+
+/// The biomes of the [ActiveWorld](crate::ActiveWorld), in the order of its
+/// [WorldGenerator::biomes](crate::WorldGenerator::biomes) followed by those of mods in load
+/// order
+#[derive(Resource, Debug, Default)]
+pub struct WorldBiomes(pub Vec<Handle<Biome>>);
+
+/// Finds the [Biome] of block columns of the [ActiveWorld], as its generator picked them.
+/// Finds none without [ChunksPlugin](crate::ChunksPlugin)
+#[derive(SystemParam)]
+pub struct BiomeLookup<'w> {
+    world: Option<Res<'w, ActiveWorld>>,
+    manifests: Option<Res<'w, Assets<WorldManifest>>>,
+    biomes: Option<Res<'w, WorldBiomes>>,
+    biome_assets: Option<Res<'w, Assets<Biome>>>,
+}
+
+impl BiomeLookup<'_> {
+    /// The biome of the block column at world `column`, `None` before a world is loaded or if
+    /// its generator has no biomes
+    pub fn biome(&self, column: IVec2) -> Option<&Biome> {
+        let manifest = self.manifests.as_ref()?.get(&self.world.as_ref()?.0)?;
+        manifest
+            .generator
+            .kind
+            .biome_map(
+                manifest.seed,
+                &self.biomes.as_ref()?.0,
+                self.biome_assets.as_ref()?,
+            )
+            .biome(column)
+    }
+}
+
+/// Picks the [Biome] of every block column from 2D noise over the world
+#[derive(Debug, Clone)]
+pub struct BiomeMap<'a> {
+    seed: u64,
+    /// Width in blocks of the features of the map
+    scale: f32,
+    biomes: Vec<&'a Biome>,
+}
+
+impl<'a> BiomeMap<'a> {
+    pub fn new(seed: u64, scale: f32, biomes: Vec<&'a Biome>) -> Self {
+        Self {
+            seed,
+            scale: scale.max(1.0),
+            biomes,
+        }
+    }
+
+    /// The biome of the block column at world `column`, `None` without biomes
+    pub fn biome(&self, column: IVec2) -> Option<&'a Biome> {
+        let index = biome_index(self.seed, self.scale, self.biomes.len(), column);
+        self.biomes.get(index).copied()
+    }
+
+    /// World height of the surface block of the column at world `column` in `biome`
+    pub fn height(&self, biome: &Biome, column: IVec2) -> i32 {
+        let curve = biome.height;
+        // Hills use other noise than the map, so they don't follow the biome borders
+        let noise = value_noise(
+            self.seed.wrapping_add(1),
+            column.as_vec2() / curve.scale.max(1.0),
+        );
+        curve.base + (noise * curve.amplitude).round() as i32
+    }
+
+    /// Whether the decoration `index` of the column at world `column` is placed
+    pub fn decorated(&self, column: IVec2, index: usize, chance: f32) -> bool {
+        hash_unit((self.seed, column.x, column.y, index)) < chance
+    }
+}
+
+/// Index of the biome of the column at world `column` out of `count`, by the noise of the
+/// [BiomeMap]
+fn biome_index(seed: u64, scale: f32, count: usize, column: IVec2) -> usize {
+    let noise = value_noise(seed, column.as_vec2() / scale);
+    ((noise * count as f32) as usize).min(count.saturating_sub(1))
+}
+
+/// The [BiomeTint] colours of the [BiomeMap] of the world, owned so chunks can be tinted
+/// while they are meshed. Colours are blended over [TINT_BLEND_RADIUS] blocks around each
+/// column, so they change smoothly between biomes. See
+/// [Chunks::set_biome_tints](crate::Chunks::set_biome_tints)
+#[derive(Debug, Clone, Default)]
+pub struct BiomeTints {
+    seed: u64,
+    scale: f32,
+    /// Linear grass, foliage and water colour of every biome of the map, in its order
+    colors: Vec<[Vec3; 3]>,
+}
+
+impl BiomeTints {
+    pub fn new(map: &BiomeMap) -> Self {
+        let linear = |color: Color| Vec3::from_slice(&color.as_linear_rgba_f32()[..3]);
+        Self {
+            seed: map.seed,
+            scale: map.scale,
+            colors: map
+                .biomes
+                .iter()
+                .map(|biome| {
+                    [BiomeTint::Grass, BiomeTint::Foliage, BiomeTint::Water]
+                        .map(|tint| linear(biome.tint_color(tint)))
+                })
+                .collect(),
+        }
+    }
+
+    /// Blended sRGB grass, foliage and water colours of the columns of the chunk at `chunk`
+    /// and one column around it, indexed by `x + z * (CHUNK_SIZE + 2)` from the column at
+    /// `-1` of the chunk. Empty without biomes, which leaves chunks untinted
+    pub fn padded(&self, chunk: IVec3) -> Vec<[[u8; 3]; 3]> {
+        if self.colors.is_empty() {
+            return Vec::new();
+        }
+        let padded = CHUNK_SIZE as i32 + 2;
+        let width = padded + 2 * TINT_BLEND_RADIUS;
+        let origin = chunk_to_world(chunk).xz() - IVec2::splat(1 + TINT_BLEND_RADIUS);
+        let biomes = (0..width * width)
+            .map(|index| {
+                let column = origin + IVec2::new(index % width, index / width);
+                biome_index(self.seed, self.scale, self.colors.len(), column)
+            })
+            .collect::<Vec<_>>();
+        let samples = ((2 * TINT_BLEND_RADIUS + 1) * (2 * TINT_BLEND_RADIUS + 1)) as f32;
+        let mut tints = Vec::with_capacity((padded * padded) as usize);
+        for z in 0..padded {
+            for x in 0..padded {
+                let mut sum = [Vec3::ZERO; 3];
+                for dz in 0..=2 * TINT_BLEND_RADIUS {
+                    for dx in 0..=2 * TINT_BLEND_RADIUS {
+                        let biome = biomes[((x + dx) + (z + dz) * width) as usize];
+                        for (sum, color) in sum.iter_mut().zip(self.colors[biome]) {
+                            *sum += color;
+                        }
+                    }
+                }
+                tints.push(sum.map(|sum| {
+                    let [red, green, blue, _] =
+                        Color::rgb_linear_from_array(sum / samples).as_rgba_u8();
+                    [red, green, blue]
+                }));
+            }
+        }
+        tints
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BiomeLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+pub struct BiomeLoader;
+
+impl AssetLoader for BiomeLoader {
+    type Asset = Biome;
+    type Settings = ();
+    type Error = BiomeLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let ron: SerializedBiome = ron::de::from_bytes(&bytes)?;
+            Ok(ron.into())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["biome"]
+    }
+}
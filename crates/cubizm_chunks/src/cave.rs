@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+use block_mesh::ndshape::ConstShape;
+use serde::{Deserialize, Serialize};
+
+use cubizm_block::{block_key, BlockRegistry, BASE_NAMESPACE};
+use cubizm_core::local_to_world;
+
+use crate::{value_noise_3d, Chunk, ChunkShape};
+
+/// Carves caves and overhangs out of the generated terrain, wherever 3D noise is above a
+/// threshold
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CaveSettings {
+    /// Noise features per block, lower for wider caves
+    pub frequency: f32,
+    /// From 0 to 1, blocks where the noise is above it are carved out. Lower for more caves
+    pub threshold: f32,
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        Self {
+            frequency: 0.06,
+            threshold: 0.75,
+        }
+    }
+}
+
+impl CaveSettings {
+    /// Replaces the carved blocks of `chunk` with air, the same ones for the same `seed`
+    pub fn carve(&self, chunk: &mut Chunk, seed: u64, registry: &BlockRegistry) {
+        let Some(air) = registry.get(&block_key(BASE_NAMESPACE, "Air")) else {
+            return;
+        };
+        // Caves use other noise than the biome map and the hills
+        let seed = seed.wrapping_add(2);
+        for index in 0..ChunkShape::SIZE {
+            let local = UVec3::from_array(ChunkShape::delinearize(index));
+            let position = local_to_world(chunk.position, local).as_vec3();
+            if value_noise_3d(seed, position * self.frequency) > self.threshold {
+                chunk.blocks[index as usize] = air.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use bevy::prelude::*;
+use block_mesh::ndshape::ConstShape;
+use thiserror::Error;
+
+use cubizm_block::BlockState;
+
+use crate::{ChunkShape, PaddedChunkShape, SavedEntity, SerializedChunk, TileEntityData};
+
+/// Start of every `.chunkb` file
+const MAGIC: &[u8; 4] = b"CBZC";
+const FORMAT_VERSION: u8 = 3;
+/// Versions before block states and tile entity data, still read
+const LEGACY_VERSIONS: [u8; 2] = [1, 2];
+
+#[derive(Debug, Error)]
+pub enum BinaryChunkError {
+    #[error("Not a binary chunk file")]
+    InvalidMagic,
+    #[error("Unsupported binary chunk version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Binary chunk ended early")]
+    UnexpectedEof,
+    #[error("Block path, tile entity data or saved entity is not valid UTF-8")]
+    InvalidPath(#[from] std::string::FromUtf8Error),
+    #[error("Palette index {0} is out of range for a palette of {1} blocks")]
+    InvalidPaletteIndex(u16, usize),
+    #[error("Binary chunk holds {0} blocks, expected {1}")]
+    WrongBlockCount(usize, usize),
+    #[error("Chunk uses more than {} different blocks", u16::MAX)]
+    PaletteTooLarge,
+    #[error("Tile entity data at index {0} is outside the chunk")]
+    InvalidTileIndex(u32),
+    #[error("Invalid block state {0:#010b}")]
+    InvalidState(u8),
+}
+
+struct ByteReader<'a>(&'a [u8]);
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], BinaryChunkError> {
+        let (bytes, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(BinaryChunkError::UnexpectedEof)?;
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, BinaryChunkError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, BinaryChunkError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, BinaryChunkError> {
+        self.take().map(i32::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Result<f32, BinaryChunkError> {
+        self.take().map(f32::from_le_bytes)
+    }
+
+    fn string(&mut self) -> Result<String, BinaryChunkError> {
+        let len = self.u16()? as usize;
+        self.string_of(len)
+    }
+
+    fn long_string(&mut self) -> Result<String, BinaryChunkError> {
+        let len = self.u32()? as usize;
+        self.string_of(len)
+    }
+
+    fn string_of(&mut self, len: usize) -> Result<String, BinaryChunkError> {
+        if self.0.len() < len {
+            return Err(BinaryChunkError::UnexpectedEof);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+}
+
+/// Compact binary encoding of a [SerializedChunk], stored in `.chunkb` files.
+///
+/// All integers are little endian:
+/// - `CBZC` and a version byte
+/// - the chunk position as three `i32`
+/// - a `u16` palette length followed by each block as its path, a `u16` length and UTF-8
+///   bytes, and its [BlockState] packed by [BlockState::to_bits]. Version 1 and 2 leave out
+///   the state
+/// - a `u32` run count followed by each run as a `u16` palette index and a `u16` length,
+///   covering the blocks in [ChunkShape] order
+/// - a `u32` count of blocks with [TileEntityData] followed by each
+///   as its `u32` index in [ChunkShape] order and its RON as a `u32` length and UTF-8 bytes.
+///   Left out by version 1
+/// - a `u32` count of [SavedEntity]s followed by each as its kind like a block path, its
+///   position as three `f32` and its data as a `u32` length and UTF-8 bytes
+impl SerializedChunk {
+    pub fn to_binary(&self) -> Result<Vec<u8>, BinaryChunkError> {
+        let mut palette: Vec<(&str, BlockState)> = Vec::new();
+        let mut runs: Vec<(u16, u16)> = Vec::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            let entry = (
+                block.as_str(),
+                self.states.get(index).copied().unwrap_or_default(),
+            );
+            let index = match palette.iter().position(|pair| *pair == entry) {
+                Some(index) => index,
+                None => {
+                    palette.push(entry);
+                    palette.len() - 1
+                }
+            };
+            let index = u16::try_from(index).map_err(|_| BinaryChunkError::PaletteTooLarge)?;
+            match runs.last_mut() {
+                Some((run_index, length)) if *run_index == index && *length < u16::MAX => {
+                    *length += 1
+                }
+                _ => runs.push((index, 1)),
+            }
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        for coordinate in self.position.to_array() {
+            bytes.extend_from_slice(&coordinate.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+        for (path, state) in palette {
+            bytes.extend_from_slice(&(path.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(path.as_bytes());
+            bytes.push(state.to_bits());
+        }
+        bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for (index, length) in runs {
+            bytes.extend_from_slice(&index.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(self.tile_data.len() as u32).to_le_bytes());
+        for (local, ron) in self.tile_data.iter() {
+            bytes.extend_from_slice(&ChunkShape::linearize(local.to_array()).to_le_bytes());
+            bytes.extend_from_slice(&(ron.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(ron.as_bytes());
+        }
+        bytes.extend_from_slice(&(self.entities.len() as u32).to_le_bytes());
+        for entity in &self.entities {
+            bytes.extend_from_slice(&(entity.kind.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(entity.kind.as_bytes());
+            for coordinate in entity.position.to_array() {
+                bytes.extend_from_slice(&coordinate.to_le_bytes());
+            }
+            bytes.extend_from_slice(&(entity.data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(entity.data.as_bytes());
+        }
+        Ok(bytes)
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, BinaryChunkError> {
+        let mut reader = ByteReader(bytes);
+        if &reader.take::<4>()? != MAGIC {
+            return Err(BinaryChunkError::InvalidMagic);
+        }
+        let [version] = reader.take()?;
+        if version != FORMAT_VERSION && !LEGACY_VERSIONS.contains(&version) {
+            return Err(BinaryChunkError::UnsupportedVersion(version));
+        }
+        let position = IVec3::new(reader.i32()?, reader.i32()?, reader.i32()?);
+
+        let palette = (0..reader.u16()?)
+            .map(|_| {
+                let path = reader.string()?;
+                if version < 3 {
+                    return Ok((path, BlockState::default()));
+                }
+                let [bits] = reader.take()?;
+                let state =
+                    BlockState::from_bits(bits).ok_or(BinaryChunkError::InvalidState(bits))?;
+                Ok((path, state))
+            })
+            .collect::<Result<Vec<_>, BinaryChunkError>>()?;
+        // Legacy versions may still hold the padding of neighbouring blocks
+        let max_blocks = if version < 3 {
+            PaddedChunkShape::SIZE
+        } else {
+            ChunkShape::SIZE
+        } as usize;
+        let mut blocks = Vec::with_capacity(ChunkShape::SIZE as usize);
+        let mut states = Vec::with_capacity(ChunkShape::SIZE as usize);
+        for _ in 0..reader.u32()? {
+            let index = reader.u16()?;
+            let length = reader.u16()?;
+            let (path, state) = palette
+                .get(index as usize)
+                .ok_or(BinaryChunkError::InvalidPaletteIndex(index, palette.len()))?;
+            // Checked before extending so a corrupt run count can't allocate without bound
+            let total = blocks.len() + length as usize;
+            if total > max_blocks {
+                return Err(BinaryChunkError::WrongBlockCount(
+                    total,
+                    ChunkShape::SIZE as usize,
+                ));
+            }
+            blocks.extend(std::iter::repeat_n(path.clone(), length as usize));
+            states.extend(std::iter::repeat_n(*state, length as usize));
+        }
+        // Chunks only store states once a block has one
+        if states.iter().all(|state| *state == BlockState::default()) {
+            states.clear();
+        }
+        let mut tile_data = TileEntityData::default();
+        if version >= 2 {
+            for _ in 0..reader.u32()? {
+                let index = reader.u32()?;
+                if index >= ChunkShape::SIZE {
+                    return Err(BinaryChunkError::InvalidTileIndex(index));
+                }
+                let local = UVec3::from_array(ChunkShape::delinearize(index));
+                tile_data.insert_raw(local, reader.long_string()?);
+            }
+        }
+        let entities = (0..reader.u32()?)
+            .map(|_| {
+                Ok(SavedEntity {
+                    kind: reader.string()?,
+                    position: Vec3::new(reader.f32()?, reader.f32()?, reader.f32()?),
+                    data: reader.long_string()?,
+                })
+            })
+            .collect::<Result<Vec<_>, BinaryChunkError>>()?;
+        let chunk = Self {
+            blocks,
+            position,
+            states,
+            tile_data,
+            entities,
+        }
+        .without_legacy_padding();
+        if chunk.blocks.len() != ChunkShape::SIZE as usize {
+            return Err(BinaryChunkError::WrongBlockCount(
+                chunk.blocks.len(),
+                ChunkShape::SIZE as usize,
+            ));
+        }
+        Ok(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk() -> SerializedChunk {
+        let mut chunk = SerializedChunk {
+            position: IVec3::new(-3, 1, 7),
+            ..default()
+        };
+        for block in chunk.blocks.iter_mut().skip(100).take(300) {
+            *block = "blocks/stone.block".to_string();
+        }
+        chunk.blocks[ChunkShape::SIZE as usize - 1] = "blocks/dirt.block".to_string();
+        chunk
+    }
+
+    /// Header of a version 3 chunk with a single block palette, followed by its runs
+    fn header(runs: &[u16]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&[0; 12]);
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        let path = "blocks/info/air.block";
+        bytes.extend_from_slice(&(path.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(path.as_bytes());
+        bytes.push(BlockState::default().to_bits());
+        bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for length in runs {
+            bytes.extend_from_slice(&0u16.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+        }
+        // No tile entity data and no entities
+        bytes.extend_from_slice(&[0; 8]);
+        bytes
+    }
+
+    #[test]
+    fn round_trips() {
+        let chunk = chunk();
+        let read = SerializedChunk::from_binary(&chunk.to_binary().unwrap()).unwrap();
+        assert_eq!(read.position, chunk.position);
+        assert_eq!(read.blocks, chunk.blocks);
+        assert!(read.states.is_empty());
+    }
+
+    #[test]
+    fn round_trips_saved_entities() {
+        let mut chunk = chunk();
+        chunk.entities.push(SavedEntity {
+            kind: "falling_block".to_string(),
+            position: Vec3::new(-40.5, 3.25, 112.),
+            data: "(block:\"blocks/stone.block\",velocity:-2.5)".to_string(),
+        });
+        let read = SerializedChunk::from_binary(&chunk.to_binary().unwrap()).unwrap();
+        assert_eq!(read.entities, chunk.entities);
+    }
+
+    #[test]
+    fn reads_exactly_one_chunk_of_runs() {
+        let size = ChunkShape::SIZE as u16;
+        let read = SerializedChunk::from_binary(&header(&[size - 8, 8])).unwrap();
+        assert_eq!(read.blocks.len(), ChunkShape::SIZE as usize);
+    }
+
+    #[test]
+    fn rejects_runs_past_the_chunk() {
+        let result = SerializedChunk::from_binary(&header(&[u16::MAX; 1024]));
+        assert!(matches!(
+            result,
+            Err(BinaryChunkError::WrongBlockCount(total, _)) if total > ChunkShape::SIZE as usize
+        ));
+        assert!(matches!(
+            SerializedChunk::from_binary(&header(&[ChunkShape::SIZE as u16, 1])),
+            Err(BinaryChunkError::WrongBlockCount(..))
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_chunks() {
+        let bytes = chunk().to_binary().unwrap();
+        assert!(matches!(
+            SerializedChunk::from_binary(&bytes[..bytes.len() - 3]),
+            Err(BinaryChunkError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            SerializedChunk::from_binary(b"RIFF"),
+            Err(BinaryChunkError::InvalidMagic)
+        ));
+    }
+}
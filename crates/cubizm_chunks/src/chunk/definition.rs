@@ -1,20 +1,115 @@
 use bevy::{
     prelude::*,
     render::{
-        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues},
         render_asset::RenderAssetUsages,
+        render_resource::VertexFormat,
     },
+    utils::{FixedState, HashMap},
 };
 use block_mesh::{
+    greedy_quads,
     ndshape::{ConstShape, ConstShape3u32},
-    visible_block_faces, UnitQuadBuffer, RIGHT_HANDED_Y_UP_CONFIG,
+    visible_block_faces, GreedyQuadsBuffer, MergeVoxel, UnitQuadBuffer, UnorientedQuad, Voxel,
+    VoxelVisibility, RIGHT_HANDED_Y_UP_CONFIG,
 };
 use serde::{Deserialize, Serialize};
+use std::hash::BuildHasher;
+use thiserror::Error;
 
-use cubizm_block::definition::Block;
+use crate::TileEntityData;
 
-pub const CHUNK_SIZE: u32 = 16;
-pub type ChunkShape = ConstShape3u32<{ CHUNK_SIZE + 2 }, { CHUNK_SIZE + 2 }, { CHUNK_SIZE + 2 }>;
+use cubizm_block::{
+    atlas_page, atlas_pages,
+    definition::{BiomeTint, Block, Fluid, RenderLayer, MAX_LIGHT},
+    turn_box, BlockAtlasLayout, BlockModel, BlockShape, BlockState, BlockTextureMode, Facing,
+    MISSING_TEXTURE,
+};
+
+use crate::SavedEntity;
+
+pub use cubizm_core::CHUNK_SIZE;
+/// Layout of the blocks stored in a [Chunk]
+pub type ChunkShape = ConstShape3u32<CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE>;
+/// Layout of a chunk as it is meshed, with one layer of blocks of its neighbours on every side,
+/// see [ChunkSnapshot]
+pub type PaddedChunkShape =
+    ConstShape3u32<{ CHUNK_SIZE + 2 }, { CHUNK_SIZE + 2 }, { CHUNK_SIZE + 2 }>;
+
+/// Sky and block light in front of each chunk face vertex, from 0 to 1
+pub const ATTRIBUTE_LIGHT: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Light", 0x6c69_6768, VertexFormat::Float32x2);
+
+/// Ambient occlusion at each chunk face vertex from the blocks around it, from 0 in a corner
+/// to 1 in the open
+pub const ATTRIBUTE_OCCLUSION: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Occlusion", 0x616f_6363, VertexFormat::Float32);
+
+/// Light given off by the face at each chunk face vertex, the linear colour of the block's
+/// [Emissive](cubizm_block::definition::Emissive) scaled by its strength
+pub const ATTRIBUTE_EMISSIVE: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Emissive", 0x656d_6974, VertexFormat::Float32x3);
+
+/// Linear colour the face is multiplied by at each chunk face vertex, the biome's colour for
+/// blocks with a [BiomeTint] and white for the rest
+pub const ATTRIBUTE_TINT: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Tint", 0x7469_6e74, VertexFormat::Float32x3);
+
+/// Everything the [ChunkMaterial](crate::ChunkMaterial) reads of each vertex of a
+/// [ChunkVertexFormat::Packed] mesh, in four words: the position in 1/32 blocks with 10 bits
+/// per axis, then its `UV_0` as 4.7 fixed point `u` and `v` with 12 bits each, the normal id,
+/// texture face and occlusion, then the [ATTRIBUTE_TINT] as 8 bit channels and the texture
+/// layer, then the [ATTRIBUTE_EMISSIVE] as 8 bit channels and the sky and block light
+pub const ATTRIBUTE_PACKED: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Packed", 0x7061_636b, VertexFormat::Uint32x4);
+
+/// How the vertices of chunk meshes are laid out
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Resource)]
+pub enum ChunkVertexFormat {
+    /// A float attribute for everything the [ChunkMaterial](crate::ChunkMaterial) reads
+    #[default]
+    Standard,
+    /// Only [ATTRIBUTE_PACKED], a sixth of the size of [Standard](ChunkVertexFormat::Standard),
+    /// drawn without normal maps. Only packs with [BlockTextureMode::Array], and meshes with a
+    /// face it can't describe, like model faces or more than 256 texture layers, fall back to
+    /// the standard layout. Packed meshes are left out of bevy's normal and deferred prepasses
+    Packed,
+}
+
+/// How chunk faces are turned into quads
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Resource)]
+pub enum MeshingMode {
+    /// One quad per visible block face
+    #[default]
+    Simple,
+    /// Coplanar faces with the same texture are merged into larger quads, trading meshing time
+    /// for fewer vertices
+    Greedy,
+}
+
+/// Resolution a chunk is meshed at, coarser for distant chunks, see [ChunkSnapshot::downsampled]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum ChunkLod {
+    #[default]
+    Full,
+    /// Cells of 2x2x2 blocks
+    Half,
+    /// Cells of 4x4x4 blocks
+    Quarter,
+}
+
+impl ChunkLod {
+    /// Edge length in blocks of the cells merged into a single block
+    pub fn scale(self) -> u32 {
+        match self {
+            Self::Full => 1,
+            Self::Half => 2,
+            Self::Quarter => 4,
+        }
+    }
+}
 
 #[derive(Clone, Copy)]
 pub enum ChunkFace {
@@ -26,6 +121,29 @@ pub enum ChunkFace {
     Right,
 }
 
+impl ChunkFace {
+    pub const ALL: [ChunkFace; 6] = [
+        ChunkFace::Front,
+        ChunkFace::Back,
+        ChunkFace::Top,
+        ChunkFace::Bottom,
+        ChunkFace::Left,
+        ChunkFace::Right,
+    ];
+
+    /// Position of the neighbouring chunk on this face, relative to the chunk
+    pub fn offset(self) -> IVec3 {
+        match self {
+            ChunkFace::Front => IVec3::NEG_Z,
+            ChunkFace::Back => IVec3::Z,
+            ChunkFace::Top => IVec3::Y,
+            ChunkFace::Bottom => IVec3::NEG_Y,
+            ChunkFace::Left => IVec3::NEG_X,
+            ChunkFace::Right => IVec3::X,
+        }
+    }
+}
+
 pub trait Opposite {
     fn opposite(&self) -> Self;
 }
@@ -43,249 +161,1197 @@ impl Opposite for ChunkFace {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SerializedChunk {
     pub blocks: Vec<String>,
     pub position: IVec3,
+    /// The state of every block, left out if they all have the default one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub states: Vec<BlockState>,
+    #[serde(default, skip_serializing_if = "TileEntityData::is_empty")]
+    pub tile_data: TileEntityData,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entities: Vec<SavedEntity>,
 }
 
 /// Internal representation of a chunk. This does not contain the final [Mesh],
 /// see [ChunkEntity] instead if a mesh is needed
-#[derive(Asset, TypePath, Clone, Debug)]
+#[derive(Asset, Reflect, Clone, Debug)]
 pub struct Chunk {
     pub blocks: Vec<Handle<Block>>,
     pub position: IVec3,
+    /// The [BlockState] of every block, empty until one has a state other than the default,
+    /// see [state](Chunk::state)
+    pub states: Vec<BlockState>,
+    #[reflect(ignore)]
+    pub tile_data: TileEntityData,
+    /// Entities standing in the chunk when it was saved, taken out once they are spawned, see
+    /// [PersistentEntities](crate::PersistentEntities)
+    #[reflect(ignore)]
+    pub entities: Vec<SavedEntity>,
 }
 
 impl Default for SerializedChunk {
     fn default() -> Self {
         Self {
-            blocks: std::iter::repeat("blocks/info/air.block".to_string())
-                .take(ChunkShape::SIZE as usize)
-                .collect(),
+            blocks: std::iter::repeat_n(
+                "blocks/info/air.block".to_string(),
+                ChunkShape::SIZE as usize,
+            )
+            .collect(),
             position: IVec3::new(0, 0, 0),
+            states: Vec::new(),
+            tile_data: TileEntityData::default(),
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl SerializedChunk {
+    /// Drops the layer of neighbouring blocks chunks were saved with before they stopped
+    /// storing it, keeping the blocks of [ChunkShape]. Other chunks are returned as they are
+    pub fn without_legacy_padding(self) -> Self {
+        if self.blocks.len() != PaddedChunkShape::SIZE as usize {
+            return self;
+        }
+        let mut padded = self.blocks;
+        let blocks = (0..ChunkShape::SIZE)
+            .map(|index| {
+                let padded_index =
+                    PaddedChunkShape::linearize(ChunkShape::delinearize(index).map(|v| v + 1));
+                std::mem::take(&mut padded[padded_index as usize])
+            })
+            .collect();
+        Self {
+            blocks,
+            position: self.position,
+            states: self.states,
+            tile_data: self.tile_data,
+            entities: self.entities,
         }
     }
 }
 
 impl Chunk {
-    pub fn get_own_face_indicies(
-        face: ChunkFace,
-    ) -> [u32; { (CHUNK_SIZE + 2) * (CHUNK_SIZE + 2) } as usize] {
-        let mut indicies = [0; { (CHUNK_SIZE + 2) * (CHUNK_SIZE + 2) } as usize];
-        let mut index = 0;
-        match face {
-            ChunkFace::Front => {
-                for x in 0..CHUNK_SIZE + 2 {
-                    for y in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([x, y, 1]);
-                        index += 1;
-                    }
-                }
-            }
-            ChunkFace::Back => {
-                for x in 0..CHUNK_SIZE + 2 {
-                    for y in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([x, y, CHUNK_SIZE]);
-                        index += 1;
-                    }
-                }
-            }
-            ChunkFace::Top => {
-                for x in 0..CHUNK_SIZE + 2 {
-                    for z in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([x, CHUNK_SIZE, z]);
-                        index += 1;
-                    }
-                }
+    /// The [BlockState] of the block at `index` in [ChunkShape]
+    pub fn state(&self, index: usize) -> BlockState {
+        self.states.get(index).copied().unwrap_or_default()
+    }
+
+    pub fn set_state(&mut self, index: usize, state: BlockState) {
+        if self.states.is_empty() {
+            if state == BlockState::default() {
+                return;
             }
-            ChunkFace::Bottom => {
-                for x in 0..CHUNK_SIZE + 2 {
-                    for z in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([x, 1, z]);
-                        index += 1;
+            self.states.resize(self.blocks.len(), BlockState::default());
+        }
+        self.states[index] = state;
+    }
+
+    /// Copies the blocks this chunk uses out of `blocks_server`, so it can be meshed
+    /// off the main thread. `padding` gives the block of a neighbour at each position of
+    /// [PaddedChunkShape] outside this chunk, `None` is meshed as air. Neighbours are meshed
+    /// in their default state, they only hide or show faces. Fails if a block isn't loaded
+    pub fn snapshot(
+        &self,
+        blocks_server: &Assets<Block>,
+        padding: impl Fn(UVec3) -> Option<AssetId<Block>>,
+    ) -> Result<ChunkSnapshot, MeshGenError> {
+        let mut palette = Vec::new();
+        let mut palette_ids = Vec::new();
+        let mut palette_states = Vec::new();
+        let mut palette_indices = HashMap::new();
+        let voxels = (0..PaddedChunkShape::SIZE)
+            .map(|index| {
+                let padded = UVec3::from_array(PaddedChunkShape::delinearize(index));
+                let inside =
+                    padded.cmpge(UVec3::ONE).all() && padded.cmple(UVec3::splat(CHUNK_SIZE)).all();
+                let (id, state) = match inside {
+                    true => {
+                        let index =
+                            ChunkShape::linearize((padded - UVec3::ONE).to_array()) as usize;
+                        (Some(self.blocks[index].id()), self.state(index))
                     }
+                    false => (padding(padded), BlockState::default()),
+                };
+                if let Some(palette_index) = palette_indices.get(&(id, state)) {
+                    return Ok(*palette_index);
                 }
-            }
-            ChunkFace::Right => {
-                for y in 0..CHUNK_SIZE + 2 {
-                    for z in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([CHUNK_SIZE, y, z]);
-                        index += 1;
+                palette.push(match id {
+                    Some(id) => blocks_server
+                        .get(id)
+                        .ok_or(MeshGenError::MissingBlock(id))?
+                        .clone(),
+                    None => Block::default(),
+                });
+                palette_ids.push(id);
+                palette_states.push(state);
+                let palette_index = (palette.len() - 1) as u16;
+                palette_indices.insert((id, state), palette_index);
+                Ok(palette_index)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ChunkSnapshot {
+            palette,
+            palette_ids,
+            palette_states,
+            voxels,
+            light: vec![MAX_LIGHT << 4; PaddedChunkShape::SIZE as usize],
+            fluid_levels: vec![0; PaddedChunkShape::SIZE as usize],
+            tints: Vec::new(),
+        })
+    }
+
+    /// Meshes this chunk on its own, as if it was surrounded by air
+    pub fn gen_geometry(
+        &self,
+        texture_atlas: &BlockAtlasLayout,
+        blocks_server: Res<Assets<Block>>,
+        meshing: MeshingMode,
+        textures: BlockTextureMode,
+        format: ChunkVertexFormat,
+    ) -> Result<ChunkMeshes, MeshGenError> {
+        self.snapshot(&blocks_server, |_| None)?.gen_geometry(
+            texture_atlas,
+            meshing,
+            textures,
+            format,
+        )
+    }
+}
+
+/// A block of a [ChunkSnapshot] with its state, faces of differently turned blocks don't merge
+#[derive(Clone, Copy)]
+struct StatedBlock<'a> {
+    block: &'a Block,
+    state: BlockState,
+}
+
+impl Voxel for StatedBlock<'_> {
+    /// Blocks of other shapes and models are meshed on their own, see
+    /// [ChunkSnapshot::shaped_faces]
+    fn get_visibility(&self) -> VoxelVisibility {
+        match self.block.is_full_cube() {
+            true => Voxel::get_visibility(&self.block),
+            false => VoxelVisibility::Empty,
+        }
+    }
+}
+
+impl<'a> MergeVoxel for StatedBlock<'a> {
+    type MergeValue = (<&'a Block as MergeVoxel>::MergeValue, BlockState);
+    type MergeValueFacingNeighbour = VoxelVisibility;
+
+    fn merge_value(&self) -> Self::MergeValue {
+        (self.block.merge_value(), self.state)
+    }
+
+    fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+        self.block.merge_value_facing_neighbour()
+    }
+}
+
+/// The blocks of a [Chunk] as owned data, see [Chunk::snapshot]
+#[derive(Clone, Debug)]
+pub struct ChunkSnapshot {
+    palette: Vec<Block>,
+    /// The block asset each `palette` entry was copied from, `None` for air outside the
+    /// loaded chunks
+    palette_ids: Vec<Option<AssetId<Block>>>,
+    /// The state of the block of each `palette` entry
+    palette_states: Vec<BlockState>,
+    /// Index into `palette` for every voxel of [PaddedChunkShape]
+    voxels: Vec<u16>,
+    /// Sky light in the high and block light in the low four bits for every voxel of
+    /// [PaddedChunkShape], full sunlight everywhere unless set with [with_light](ChunkSnapshot::with_light)
+    light: Vec<u8>,
+    /// Level of the flowing fluid for every voxel of [PaddedChunkShape], `0` for sources and
+    /// other blocks, see [FluidLevels](crate::FluidLevels)
+    fluid_levels: Vec<u8>,
+    /// sRGB grass, foliage and water colour of every column of [PaddedChunkShape], see
+    /// [BiomeTints::padded](crate::BiomeTints::padded). Empty leaves every block untinted
+    tints: Vec<[[u8; 3]; 3]>,
+}
+
+impl ChunkSnapshot {
+    pub(crate) fn with_light(mut self, light: Vec<u8>) -> Self {
+        self.light = light;
+        self
+    }
+
+    pub(crate) fn with_fluid_levels(mut self, fluid_levels: Vec<u8>) -> Self {
+        self.fluid_levels = fluid_levels;
+        self
+    }
+
+    pub(crate) fn with_tints(mut self, tints: Vec<[[u8; 3]; 3]>) -> Self {
+        self.tints = tints;
+        self
+    }
+
+    /// Hash of everything [gen_geometry](ChunkSnapshot::gen_geometry) reads, snapshots with
+    /// the same hash are meshed the same way. Only valid as long as the blocks themselves
+    /// and the texture atlas stay the same
+    pub(crate) fn content_hash(
+        &self,
+        meshing: MeshingMode,
+        textures: BlockTextureMode,
+        format: ChunkVertexFormat,
+    ) -> u64 {
+        FixedState.hash_one((
+            &self.palette_ids,
+            &self.palette_states,
+            &self.voxels,
+            &self.light,
+            &self.fluid_levels,
+            &self.tints,
+            meshing,
+            textures,
+            format,
+        ))
+    }
+
+    /// Fills every cell of `lod`'s scale with the block most of the cell is made of, so
+    /// [MeshingMode::Greedy] merges each cell into a few large faces. The padding shared with
+    /// the neighbours, the light and the fluid levels are left as they are
+    pub(crate) fn downsampled(mut self, lod: ChunkLod) -> Self {
+        let scale = lod.scale();
+        if scale == 1 {
+            return self;
+        }
+        let cell_starts = || (1..=CHUNK_SIZE).step_by(scale as usize);
+        let mut cell = Vec::with_capacity((scale * scale * scale) as usize);
+        let mut counts: Vec<(u16, u32)> = Vec::new();
+        for x in cell_starts() {
+            for y in cell_starts() {
+                for z in cell_starts() {
+                    cell.clear();
+                    counts.clear();
+                    for dx in 0..scale {
+                        for dy in 0..scale {
+                            for dz in 0..scale {
+                                let index = PaddedChunkShape::linearize([x + dx, y + dy, z + dz]);
+                                let voxel = self.voxels[index as usize];
+                                match counts.iter_mut().find(|(block, _)| *block == voxel) {
+                                    Some((_, count)) => *count += 1,
+                                    None => counts.push((voxel, 1)),
+                                }
+                                cell.push(index as usize);
+                            }
+                        }
                     }
-                }
-            }
-            ChunkFace::Left => {
-                for y in 0..CHUNK_SIZE + 2 {
-                    for z in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([1, y, z]);
-                        index += 1;
+                    // Ties go to the block found first, keeping the result deterministic
+                    let majority = counts
+                        .iter()
+                        .rev()
+                        .max_by_key(|(_, count)| *count)
+                        .map(|(block, _)| *block)
+                        .unwrap_or_default();
+                    for index in cell.iter() {
+                        self.voxels[*index] = majority;
                     }
                 }
             }
+        }
+        self
+    }
+
+    /// The block in front of a face at each of its vertices, along with the direction on
+    /// the face pointing away from the face at that vertex
+    fn quad_corners(positions: &[[f32; 3]; 4], normal: IVec3) -> [(IVec3, IVec3); 4] {
+        let first = IVec3::from_array(positions[0].map(|v| v.floor() as i32));
+        let (min, max) = positions[1..]
+            .iter()
+            .fold((first, first), |(min, max), position| {
+                let position = IVec3::from_array(position.map(|v| v.floor() as i32));
+                (min.min(position), max.max(position))
+            });
+        let axis = match normal {
+            IVec3 { x: 0, y: 0, .. } => 2,
+            IVec3 { x: 0, .. } => 1,
+            _ => 0,
         };
-        indicies
-    }
-
-    pub fn get_other_face_indicies(
-        face: ChunkFace,
-    ) -> [u32; { (CHUNK_SIZE + 2) * (CHUNK_SIZE + 2) } as usize] {
-        let mut indicies = [0; { (CHUNK_SIZE + 2) * (CHUNK_SIZE + 2) } as usize];
-        let mut index = 0;
-        match face {
-            ChunkFace::Front => {
-                for x in 0..CHUNK_SIZE + 2 {
-                    for y in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([x, y, 0]);
-                        index += 1;
-                    }
-                }
-            }
-            ChunkFace::Back => {
-                for x in 0..CHUNK_SIZE + 2 {
-                    for y in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([x, y, CHUNK_SIZE + 1]);
-                        index += 1;
-                    }
-                }
-            }
-            ChunkFace::Top => {
-                for x in 0..CHUNK_SIZE + 2 {
-                    for z in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([x, CHUNK_SIZE + 1, z]);
-                        index += 1;
-                    }
-                }
+        positions.map(|position| {
+            let corner = IVec3::from_array(position.map(|v| v.floor() as i32));
+            // Vertices on the far edges belong to the last block the face covers
+            let mut block = corner.min((max - IVec3::ONE).max(min));
+            // The face sits on the positive side of its block for positive normals
+            block[axis] = min[axis] + normal[axis].min(0);
+            let mut outwards = IVec3::select(corner.cmpeq(block), IVec3::NEG_ONE, IVec3::ONE);
+            outwards[axis] = 0;
+            (block, outwards)
+        })
+    }
+
+    /// Texture coordinates of a face of a turned block, the corners projected onto the turned
+    /// axes of the face of the texture it shows. The shader only keeps the fraction, so the
+    /// offset doesn't matter
+    fn projected_tex_coords(
+        positions: &[[f32; 3]; 4],
+        face_axes: &[(IVec3, Vec3, Vec3)],
+        local_normal: IVec3,
+        rotation: Quat,
+    ) -> [[f32; 2]; 4] {
+        let (_, u, v) = face_axes
+            .iter()
+            .find(|(normal, _, _)| *normal == local_normal)
+            .copied()
+            .unwrap_or((local_normal, Vec3::X, Vec3::Y));
+        let (u, v) = (rotation * u, rotation * v);
+        positions.map(|position| {
+            let position = Vec3::from_array(position);
+            [position.dot(u), position.dot(v)]
+        })
+    }
+
+    fn emissive_color(block: &Block) -> [f32; 3] {
+        block.emissive().map_or([0.; 3], |emissive| {
+            let [red, green, blue, _] = emissive.color.as_linear_rgba_f32();
+            let strength = emissive.strength as f32 / MAX_LIGHT as f32;
+            [red * strength, green * strength, blue * strength]
+        })
+    }
+
+    /// Linear colour of `block`'s [BiomeTint] at a vertex at `position`, blended between the
+    /// four columns around it so it is continuous across blocks and chunks. White for blocks
+    /// without a tint and chunks without biomes
+    fn tint_at(&self, block: &Block, position: [f32; 3]) -> [f32; 3] {
+        let channel = match block.tint() {
+            BiomeTint::None => return [1.; 3],
+            BiomeTint::Grass => 0,
+            BiomeTint::Foliage => 1,
+            BiomeTint::Water => 2,
+        };
+        if self.tints.is_empty() {
+            return [1.; 3];
+        }
+        let padded = CHUNK_SIZE as i32 + 2;
+        // Each column's colour sits at its centre
+        let sample = Vec2::new(position[0], position[2]) - 0.5;
+        let base = sample.floor();
+        let weight = sample - base;
+        let corners = [
+            (IVec2::ZERO, (1. - weight.x) * (1. - weight.y)),
+            (IVec2::X, weight.x * (1. - weight.y)),
+            (IVec2::Y, (1. - weight.x) * weight.y),
+            (IVec2::ONE, weight.x * weight.y),
+        ];
+        let color = corners
+            .into_iter()
+            .fold(Vec3::ZERO, |color, (offset, weight)| {
+                let column =
+                    (base.as_ivec2() + offset).clamp(IVec2::ZERO, IVec2::splat(padded - 1));
+                let [red, green, blue] =
+                    self.tints[(column.x + column.y * padded) as usize][channel];
+                let [red, green, blue, _] = Color::rgb_u8(red, green, blue).as_linear_rgba_f32();
+                color + Vec3::new(red, green, blue) * weight
+            });
+        color.to_array()
+    }
+
+    /// Whether the block at `position` hides the face of `voxel` lying against its side
+    /// facing `side`, by the same rules the mesher uses for full blocks
+    fn hides(&self, position: IVec3, side: IVec3, voxel: StatedBlock) -> bool {
+        let Some(index) = Self::voxel_index(position) else {
+            return false;
+        };
+        let entry = self.voxels[index] as usize;
+        let neighbour = &self.palette[entry];
+        if !neighbour.is_voxel() || !neighbour.covers(self.palette_states[entry], side) {
+            return false;
+        }
+        match Voxel::get_visibility(&neighbour) {
+            VoxelVisibility::Empty => false,
+            VoxelVisibility::Translucent => {
+                Voxel::get_visibility(&voxel.block) == VoxelVisibility::Translucent
             }
-            ChunkFace::Bottom => {
-                for x in 0..CHUNK_SIZE + 2 {
-                    for z in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([x, 0, z]);
-                        index += 1;
-                    }
+            VoxelVisibility::Opaque => true,
+        }
+    }
+
+    /// The corners of the face with `normal` of the box from `min` to `max` within the block at
+    /// `position`, counter-clockwise seen from the front, and whether it lies on the side of
+    /// the block
+    fn box_face(position: IVec3, min: Vec3, max: Vec3, normal: IVec3) -> ([[f32; 3]; 4], bool) {
+        let axis = (0..3).find(|axis| normal[*axis] != 0).unwrap_or_default();
+        let positive = normal[axis] > 0;
+        let plane = if positive { max[axis] } else { min[axis] };
+        let (u, v) = match positive {
+            true => ((axis + 1) % 3, (axis + 2) % 3),
+            false => ((axis + 2) % 3, (axis + 1) % 3),
+        };
+        let corner = |at_u: f32, at_v: f32| {
+            let mut corner = Vec3::ZERO;
+            corner[axis] = plane;
+            corner[u] = at_u;
+            corner[v] = at_v;
+            (position.as_vec3() + corner).to_array()
+        };
+        let positions = [
+            corner(min[u], min[v]),
+            corner(max[u], min[v]),
+            corner(max[u], max[v]),
+            corner(min[u], max[v]),
+        ];
+        (positions, plane == if positive { 1. } else { 0. })
+    }
+
+    /// Adds a face of a shaped or model block without ambient occlusion, lit by the block in
+    /// front of it
+    fn push_box_face(
+        &self,
+        buffers: &mut MeshBuffers,
+        positions: [[f32; 3]; 4],
+        normal: IVec3,
+        front: IVec3,
+        block: &Block,
+    ) {
+        let start = buffers.positions.len() as u32;
+        buffers
+            .indices
+            .extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+        buffers.positions.extend_from_slice(&positions);
+        buffers.normals.extend([normal.as_vec3().to_array(); 4]);
+        buffers.light.extend([self.light_at(front); 4]);
+        buffers.occlusion.extend([1.; 4]);
+        buffers.emissive.extend([Self::emissive_color(block); 4]);
+        buffers
+            .tint
+            .extend(positions.map(|position| self.tint_at(block, position)));
+    }
+
+    /// Meshes a block that isn't a full cube box by box, see [Block::boxes], leaving out the
+    /// faces hidden by the neighbour they lie against or by another box of the block. Full
+    /// neighbours still draw their faces against it. Blocks with a [BlockModel] are meshed
+    /// from it instead
+    fn shaped_faces(
+        &self,
+        buffers: &mut MeshBuffers,
+        position: IVec3,
+        voxel: StatedBlock,
+        face_axes: &[(IVec3, Vec3, Vec3)],
+        texture_atlas: &BlockAtlasLayout,
+        textures: BlockTextureMode,
+    ) -> Result<(), MeshGenError> {
+        if let Some(model) = voxel.block.model() {
+            return self.model_faces(
+                buffers,
+                position,
+                voxel,
+                model,
+                face_axes,
+                texture_atlas,
+                textures,
+            );
+        }
+        let Some(texture) = voxel.block.voxel_texture() else {
+            return Ok(());
+        };
+        if voxel.block.shape() == BlockShape::Cross {
+            return self.cross_faces(
+                buffers,
+                position,
+                voxel.block,
+                &texture,
+                texture_atlas,
+                textures,
+            );
+        }
+        let rotation = voxel.block.rotation(voxel.state);
+        let boxes = voxel.block.boxes(voxel.state).collect::<Vec<_>>();
+        for (min, max) in boxes.iter() {
+            for normal in Facing::ALL.map(Facing::normal) {
+                let (positions, on_side) = Self::box_face(position, *min, *max, normal);
+                let hidden = match on_side {
+                    true => self.hides(position + normal, -normal, voxel),
+                    // Faces inside the block are hidden by a box lying against them
+                    false => boxes.iter().any(|(other_min, other_max)| {
+                        let axis = (0..3).find(|axis| normal[*axis] != 0).unwrap_or_default();
+                        let (plane, other_plane) = match normal[axis] > 0 {
+                            true => (max[axis], other_min[axis]),
+                            false => (min[axis], other_max[axis]),
+                        };
+                        other_plane == plane
+                            && (0..3).filter(|other| *other != axis).all(|other| {
+                                other_min[other] <= min[other] && other_max[other] >= max[other]
+                            })
+                    }),
+                };
+                if hidden {
+                    continue;
                 }
+                let front = if on_side { position + normal } else { position };
+                self.push_box_face(buffers, positions, normal, front, voxel.block);
+                let local_normal = (rotation.inverse() * normal.as_vec3()).round().as_ivec3();
+                buffers.push_texture_rect(texture_atlas, textures, &texture, local_normal)?;
+                buffers.tex_coords.extend(Self::projected_tex_coords(
+                    &positions,
+                    face_axes,
+                    local_normal,
+                    rotation,
+                ));
             }
-            ChunkFace::Right => {
-                for y in 0..CHUNK_SIZE + 2 {
-                    for z in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([CHUNK_SIZE + 1, y, z]);
-                        index += 1;
-                    }
+        }
+        Ok(())
+    }
+
+    /// Meshes a block with a [BlockModel] element by element, leaving out the faces marked to
+    /// cull when the neighbour they lie against hides them. Each face stretches its part of
+    /// its texture over the whole face
+    #[allow(clippy::too_many_arguments)]
+    fn model_faces(
+        &self,
+        buffers: &mut MeshBuffers,
+        position: IVec3,
+        voxel: StatedBlock,
+        model: &BlockModel,
+        face_axes: &[(IVec3, Vec3, Vec3)],
+        texture_atlas: &BlockAtlasLayout,
+        textures: BlockTextureMode,
+    ) -> Result<(), MeshGenError> {
+        let rotation = voxel.block.rotation(voxel.state);
+        for element in model.elements.iter() {
+            let (min, max) = turn_box(rotation, element.from, element.to);
+            for normal in Facing::ALL.map(Facing::normal) {
+                let local_normal = (rotation.inverse() * normal.as_vec3()).round().as_ivec3();
+                let Some(face) =
+                    Facing::from_normal(local_normal).and_then(|side| element.face(side))
+                else {
+                    continue;
+                };
+                let (positions, on_side) = Self::box_face(position, min, max, normal);
+                if face.cull && on_side && self.hides(position + normal, -normal, voxel) {
+                    continue;
                 }
+                let front = if on_side { position + normal } else { position };
+                self.push_box_face(buffers, positions, normal, front, voxel.block);
+                buffers.push_texture_region(texture_atlas, textures, &face.texture, face.uv)?;
+                // Stretch the face's part of the texture over it, turned like the block
+                let tex_coords =
+                    Self::projected_tex_coords(&positions, face_axes, local_normal, rotation);
+                let [min_uv, max_uv] = tex_coords.iter().fold(
+                    [Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)],
+                    |[min, max], uv| [min.min(Vec2::from(*uv)), max.max(Vec2::from(*uv))],
+                );
+                let span = (max_uv - min_uv).max(Vec2::splat(f32::EPSILON));
+                buffers
+                    .tex_coords
+                    .extend(tex_coords.map(|uv| ((Vec2::from(uv) - min_uv) / span).to_array()));
             }
-            ChunkFace::Left => {
-                for y in 0..CHUNK_SIZE + 2 {
-                    for z in 0..CHUNK_SIZE + 2 {
-                        indicies[index] = ChunkShape::linearize([0, y, z]);
-                        index += 1;
-                    }
-                }
+        }
+        Ok(())
+    }
+
+    /// Meshes a [BlockShape::Cross] block as two diagonal quads, never hidden by neighbours.
+    /// The material draws them from both sides
+    fn cross_faces(
+        &self,
+        buffers: &mut MeshBuffers,
+        position: IVec3,
+        block: &Block,
+        texture: &Handle<Image>,
+        texture_atlas: &BlockAtlasLayout,
+        textures: BlockTextureMode,
+    ) -> Result<(), MeshGenError> {
+        let origin = position.as_vec3();
+        for (from, to) in [(Vec3::ZERO, Vec3::new(1., 0., 1.)), (Vec3::X, Vec3::Z)] {
+            let top = Vec3::Y;
+            let positions =
+                [from, to, to + top, from + top].map(|corner| (origin + corner).to_array());
+            let normal = (to - from).cross(top).normalize();
+            let start = buffers.positions.len() as u32;
+            buffers
+                .indices
+                .extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+            buffers.positions.extend_from_slice(&positions);
+            buffers.normals.extend([normal.to_array(); 4]);
+            buffers.light.extend([self.light_at(position); 4]);
+            buffers.occlusion.extend([1.; 4]);
+            buffers.emissive.extend([Self::emissive_color(block); 4]);
+            buffers
+                .tint
+                .extend(positions.map(|position| self.tint_at(block, position)));
+            buffers.push_texture_rect(texture_atlas, textures, texture, IVec3::Z)?;
+            // The top of the texture at the top of the block
+            buffers
+                .tex_coords
+                .extend_from_slice(&[[0., 1.], [1., 1.], [1., 0.], [0., 0.]]);
+        }
+        Ok(())
+    }
+
+    fn voxel_index(position: IVec3) -> Option<usize> {
+        let max = IVec3::splat(CHUNK_SIZE as i32 + 1);
+        (position.cmpge(IVec3::ZERO).all() && position.cmple(max).all())
+            .then(|| PaddedChunkShape::linearize(position.as_uvec3().to_array()) as usize)
+    }
+
+    fn is_opaque(&self, position: IVec3) -> bool {
+        Self::voxel_index(position)
+            .is_some_and(|index| self.palette[self.voxels[index] as usize].occludes())
+    }
+
+    /// Sky and block light of `block`, from 0 to 1
+    fn light_at(&self, block: IVec3) -> [f32; 2] {
+        let packed = Self::voxel_index(block).map_or(MAX_LIGHT << 4, |index| self.light[index]);
+        [
+            (packed >> 4) as f32 / MAX_LIGHT as f32,
+            (packed & 0xf) as f32 / MAX_LIGHT as f32,
+        ]
+    }
+
+    /// Lowers the vertices along the top edge of a fluid's faces to the height of its surface,
+    /// unless the same fluid is above. `corners` are the blocks in front of each vertex, see
+    /// [quad_corners](ChunkSnapshot::quad_corners)
+    fn lower_fluid_surface(
+        &self,
+        positions: &mut [[f32; 3]; 4],
+        corners: &[(IVec3, IVec3); 4],
+        normal: IVec3,
+        fluid: Fluid,
+    ) {
+        for (position, (front, _)) in positions.iter_mut().zip(corners) {
+            let block = *front - normal;
+            let (Some(index), Some(above)) = (
+                Self::voxel_index(block),
+                Self::voxel_index(block + IVec3::Y),
+            ) else {
+                continue;
+            };
+            if position[1] < (block.y + 1) as f32 || self.voxels[above] == self.voxels[index] {
+                continue;
             }
+            let level = match self.fluid_levels[index] {
+                0 => fluid.source_level(),
+                level => level,
+            };
+            position[1] -= 1. - fluid.height(level);
+        }
+    }
+
+    /// Ambient occlusion of a vertex from the opaque blocks beside and diagonal to the
+    /// `block` in front of it, from 0 when surrounded to 3 when open
+    fn occlusion_at(&self, block: IVec3, outwards: IVec3) -> u8 {
+        let mut sides = [IVec3::X, IVec3::Y, IVec3::Z]
+            .into_iter()
+            .map(|axis| axis * outwards)
+            .filter(|side| *side != IVec3::ZERO);
+        let (Some(first), Some(second)) = (sides.next(), sides.next()) else {
+            return 3;
         };
-        indicies
+        let first = self.is_opaque(block + first);
+        let second = self.is_opaque(block + second);
+        if first && second {
+            return 0;
+        }
+        3 - first as u8 - second as u8 - self.is_opaque(block + outwards) as u8
     }
 
+    /// Meshes the visible faces of the chunk, split by the [RenderLayer] of their blocks.
+    /// `UV_0` holds the position on the face in blocks and `COLOR` the rect of the face's
+    /// texture as `(min, size)`, the [ChunkMaterial](crate::ChunkMaterial) repeats the texture
+    /// once per block from them. With [BlockTextureMode::Array] the rect is within the texture's
+    /// layer, which is stored in the x of `UV_1`. [ATTRIBUTE_LIGHT] holds the light in front
+    /// of the face, [ATTRIBUTE_OCCLUSION] how enclosed each corner is, [ATTRIBUTE_EMISSIVE]
+    /// how much the face glows and [ATTRIBUTE_TINT] the biome colour it is multiplied by.
+    /// `TANGENT` runs along the `UV_0` x of each face, for the normal map of the atlas.
+    /// Merged faces of [MeshingMode::Greedy] only blend the tint between their corners.
+    /// With [ChunkVertexFormat::Packed] everything goes into [ATTRIBUTE_PACKED] instead.
+    /// Textures missing from the atlas are drawn with [MISSING_TEXTURE] while it is there
     pub fn gen_geometry(
         &self,
-        texture_atlas: &TextureAtlasLayout,
-        blocks_server: Res<Assets<Block>>,
-    ) -> Mesh {
+        texture_atlas: &BlockAtlasLayout,
+        meshing: MeshingMode,
+        textures: BlockTextureMode,
+        format: ChunkVertexFormat,
+    ) -> Result<ChunkMeshes, MeshGenError> {
         let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
 
-        let mut buffer = UnitQuadBuffer::new();
         let blocks = self
-            .blocks
+            .voxels
             .iter()
-            .map(|handle| {
-                blocks_server
-                    .get(handle)
-                    .expect("Got an Id for an Asset that does not exist")
+            .map(|index| StatedBlock {
+                block: &self.palette[*index as usize],
+                state: self.palette_states[*index as usize],
             })
             .collect::<Vec<_>>();
-        visible_block_faces(
-            &blocks,
-            &ChunkShape {},
-            [0; 3],
-            [CHUNK_SIZE + 1; 3],
-            &RIGHT_HANDED_Y_UP_CONFIG.faces,
-            &mut buffer,
-        );
-        let num_indices = buffer.num_quads() * 6;
-        let num_vertices = buffer.num_quads() * 4;
-        let mut indices = Vec::with_capacity(num_indices);
-        let mut positions = Vec::with_capacity(num_vertices);
-        let mut normals = Vec::with_capacity(num_vertices);
-        let mut tex_coords = Vec::with_capacity(num_vertices);
-
-        for (group, face) in buffer.groups.into_iter().zip(faces.into_iter()) {
+        let groups: [Vec<UnorientedQuad<StatedBlock>>; 6] = match meshing {
+            MeshingMode::Simple => {
+                let mut buffer = UnitQuadBuffer::new();
+                visible_block_faces(
+                    &blocks,
+                    &PaddedChunkShape {},
+                    [0; 3],
+                    [CHUNK_SIZE + 1; 3],
+                    &faces,
+                    &mut buffer,
+                );
+                buffer
+                    .groups
+                    .map(|group| group.into_iter().map(UnorientedQuad::from).collect())
+            }
+            MeshingMode::Greedy => {
+                let mut buffer = GreedyQuadsBuffer::new(blocks.len());
+                greedy_quads(
+                    &blocks,
+                    &PaddedChunkShape {},
+                    [0; 3],
+                    [CHUNK_SIZE + 1; 3],
+                    &faces,
+                    &mut buffer,
+                );
+                buffer.quads.groups
+            }
+        };
+
+        // The texture axes of every face, from a unit quad laid out like the texture
+        // coordinates below, for turning them along with turned blocks
+        let face_axes = faces.map(|face| {
+            let unit = UnorientedQuad {
+                minimum: [0; 3],
+                width: 1,
+                height: 1,
+                voxel: (),
+            };
+            let [first, second, third, _] =
+                face.quad_mesh_positions(&unit, 1.0).map(Vec3::from_array);
+            (
+                IVec3::from_array(face.signed_normal().to_array()),
+                first - second,
+                first - third,
+            )
+        });
+
+        let buffers = || MeshBuffers {
+            paged: atlas_pages(texture_atlas) > 1,
+            ..default()
+        };
+        let mut opaque = buffers();
+        let mut transparent = buffers();
+        let mut cutout = buffers();
+
+        for (group, face) in groups.into_iter().zip(faces) {
+            let normal = IVec3::from_array(face.signed_normal().to_array());
             for quad in group.into_iter() {
-                if !&quad.voxel.is_voxel() {
+                let voxel = quad.voxel.block;
+                if !voxel.is_voxel() {
                     continue;
                 };
-                indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
-                normals.extend_from_slice(&face.quad_mesh_normals());
-                let texture = &quad
-                    .voxel
+                // Turned blocks show the face of their texture that turned to this side
+                let rotation = voxel.rotation(quad.voxel.state);
+                let local_normal = (rotation.inverse() * normal.as_vec3()).round().as_ivec3();
+                let buffers = match voxel.render_layer() {
+                    RenderLayer::Opaque => &mut opaque,
+                    RenderLayer::Transparent => &mut transparent,
+                    RenderLayer::Cutout => &mut cutout,
+                };
+                buffers.normals.extend_from_slice(&face.quad_mesh_normals());
+                let texture = &voxel
                     .voxel_texture()
-                    .expect("Voxel is marked as opaque but no texture was found");
-                positions.extend_from_slice(&face.quad_mesh_positions(&quad.into(), 1.0));
-
-                let index = texture_atlas
-                    .get_texture_index(texture)
-                    .expect("image hasn't been loaded into texture atlas");
-
-                let rect = texture_atlas.textures[index];
-                let width = rect.width() / texture_atlas.size[0];
-                let height = rect.height() / texture_atlas.size[1];
-                let start_pos: [f32; 2] = (rect.min / texture_atlas.size).into();
-
-                fn calculate_face_uv(
-                    face_no: f32,
-                    width: f32,
-                    height: f32,
-                    start_pos: [f32; 2],
-                ) -> [[f32; 2]; 4] {
-                    let (start_pos_x, start_pos_y) = start_pos.into();
-                    let base_face: [[f32; 2]; 4] =
-                        [[1., 0.], [0., 0.], [1., -1. / 6.], [0., -1. / 6.]];
-
-                    base_face
-                        .map(|mut x| {
-                            x[1] += face_no * 1. / 6.;
-                            x
-                        })
-                        .map(|xy| [xy[0] * width, xy[1] * height])
-                        .map(|xy| [xy[0] + start_pos_x, xy[1] + start_pos_y])
+                    .ok_or(MeshGenError::MissingTexture(UVec3::from(quad.minimum)))?;
+                let mut quad_positions = face.quad_mesh_positions(&quad, 1.0);
+                let corners = Self::quad_corners(&quad_positions, normal);
+                if let Some(fluid) = voxel.fluid() {
+                    self.lower_fluid_surface(&mut quad_positions, &corners, normal, fluid);
+                }
+                let corner_occlusion =
+                    corners.map(|(block, outwards)| self.occlusion_at(block, outwards));
+                let start = buffers.positions.len() as u32;
+                let mut quad_indices = face.quad_mesh_indices(start);
+                // Split along the brighter diagonal, so a single dark corner does not shade
+                // the whole face
+                if corner_occlusion[0] + corner_occlusion[3]
+                    > corner_occlusion[1] + corner_occlusion[2]
+                {
+                    let diagonal = [(2, 3); 3].into_iter().chain([(1, 0); 3]);
+                    for (index, (from, to)) in quad_indices.iter_mut().zip(diagonal) {
+                        if *index == start + from {
+                            *index = start + to;
+                        }
+                    }
                 }
+                buffers.indices.extend_from_slice(&quad_indices);
+                buffers.positions.extend_from_slice(&quad_positions);
+                buffers
+                    .light
+                    .extend(corners.map(|(block, _)| self.light_at(block)));
+                buffers
+                    .occlusion
+                    .extend(corner_occlusion.map(|occlusion| occlusion as f32 / 3.));
+                buffers.emissive.extend([Self::emissive_color(voxel); 4]);
+                buffers
+                    .tint
+                    .extend(quad_positions.map(|position| self.tint_at(voxel, position)));
+                buffers.push_texture_rect(texture_atlas, textures, texture, local_normal)?;
 
-                let face_tex_coords: [[f32; 2]; 4] = match face.signed_normal().into() {
-                    (1, 0, 0) => calculate_face_uv(1., width, height, start_pos),
-                    (0, 1, 0) => calculate_face_uv(2., width, height, start_pos),
-                    (0, 0, 1) => calculate_face_uv(3., width, height, start_pos),
-                    (-1, 0, 0) => calculate_face_uv(4., width, height, start_pos),
-                    (0, -1, 0) => calculate_face_uv(5., width, height, start_pos),
-                    (0, 0, -1) => calculate_face_uv(6., width, height, start_pos),
-                    _ => calculate_face_uv(1., width, height, start_pos),
-                };
-                tex_coords.extend_from_slice(&face_tex_coords);
+                if rotation == Quat::IDENTITY {
+                    let (width, height) = (quad.width as f32, quad.height as f32);
+                    buffers.tex_coords.extend_from_slice(&[
+                        [width, height],
+                        [0., height],
+                        [width, 0.],
+                        [0., 0.],
+                    ]);
+                } else {
+                    buffers.tex_coords.extend(Self::projected_tex_coords(
+                        &quad_positions,
+                        &face_axes,
+                        local_normal,
+                        rotation,
+                    ));
+                }
             }
         }
-        Mesh::new(
+
+        for (index, voxel) in blocks.iter().enumerate() {
+            let position = UVec3::from_array(PaddedChunkShape::delinearize(index as u32));
+            let inside =
+                position.cmpge(UVec3::ONE).all() && position.cmple(UVec3::splat(CHUNK_SIZE)).all();
+            if !inside
+                || !voxel.block.is_voxel()
+                || voxel.block.is_full_cube()
+                || Voxel::get_visibility(&voxel.block) == VoxelVisibility::Empty
+            {
+                continue;
+            }
+            let buffers = match voxel.block.render_layer() {
+                RenderLayer::Opaque => &mut opaque,
+                RenderLayer::Transparent => &mut transparent,
+                RenderLayer::Cutout => &mut cutout,
+            };
+            self.shaped_faces(
+                buffers,
+                position.as_ivec3(),
+                *voxel,
+                &face_axes,
+                texture_atlas,
+                textures,
+            )?;
+        }
+        Ok(ChunkMeshes {
+            opaque: opaque.into_mesh(textures, format),
+            transparent: transparent.into_mesh(textures, format),
+            cutout: cutout.into_mesh(textures, format),
+        })
+    }
+}
+
+/// Why [ChunkSnapshot::gen_geometry] couldn't mesh a chunk
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum MeshGenError {
+    #[error("Block {0:?} of the chunk is not loaded")]
+    MissingBlock(AssetId<Block>),
+    #[error("Voxel block at {0} of the padded chunk has no texture")]
+    MissingTexture(UVec3),
+    #[error("Texture {0:?} is not in the block atlas, nor is the missing texture")]
+    AtlasMiss(AssetId<Image>),
+}
+
+/// The meshes of a chunk, see [ChunkSnapshot::gen_geometry]
+#[derive(Debug, Clone)]
+pub struct ChunkMeshes {
+    /// Blocks in [RenderLayer::Opaque]
+    pub opaque: Mesh,
+    /// Blocks in [RenderLayer::Transparent], drawn with alpha blending
+    pub transparent: Mesh,
+    /// Blocks in [RenderLayer::Cutout], drawn with alpha masking from both sides
+    pub cutout: Mesh,
+}
+
+/// Vertex attributes of one of the [ChunkMeshes] while it is being built
+#[derive(Default)]
+struct MeshBuffers {
+    indices: Vec<u32>,
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    tex_coords: Vec<[f32; 2]>,
+    texture_rects: Vec<[f32; 4]>,
+    texture_layers: Vec<[f32; 2]>,
+    light: Vec<[f32; 2]>,
+    occlusion: Vec<f32>,
+    emissive: Vec<[f32; 3]>,
+    tint: Vec<[f32; 3]>,
+    /// Which sixth of its texture each face shows, see
+    /// [push_texture_rect](MeshBuffers::push_texture_rect), `None` for faces showing another part
+    texture_faces: Vec<Option<u8>>,
+    /// Whether the [BlockAtlas](cubizm_block::BlockAtlas) has several pages, the page of each
+    /// vertex then goes into `texture_layers` in [BlockTextureMode::Atlas] too
+    paged: bool,
+}
+
+impl MeshBuffers {
+    /// Adds the rect of the face of `texture` facing `local_normal` for the four vertices of a
+    /// face, where [BlockTextureMode::Array] also adds the layer
+    fn push_texture_rect(
+        &mut self,
+        texture_atlas: &BlockAtlasLayout,
+        textures: BlockTextureMode,
+        texture: &Handle<Image>,
+        local_normal: IVec3,
+    ) -> Result<(), MeshGenError> {
+        // Each block texture is a column of six faces, top to bottom:
+        // +x, +y, +z, -x, -y, -z
+        let face_no = match local_normal.into() {
+            (1, 0, 0) => 0,
+            (0, 1, 0) => 1,
+            (0, 0, 1) => 2,
+            (-1, 0, 0) => 3,
+            (0, -1, 0) => 4,
+            (0, 0, -1) => 5,
+            _ => 0,
+        };
+        let region = Rect::new(0., face_no as f32 / 6., 1., (face_no + 1) as f32 / 6.);
+        self.push_texture_region(texture_atlas, textures, texture, region)?;
+        if let Some(last) = self.texture_faces.last_mut() {
+            *last = Some(face_no);
+        }
+        Ok(())
+    }
+
+    /// Adds the rect of `region` of `texture`, from 0 to 1 within the texture, for the four
+    /// vertices of a face
+    fn push_texture_region(
+        &mut self,
+        texture_atlas: &BlockAtlasLayout,
+        textures: BlockTextureMode,
+        texture: &Handle<Image>,
+        region: Rect,
+    ) -> Result<(), MeshGenError> {
+        let index = match texture_atlas.get_texture_index(texture) {
+            Some(index) => index,
+            None => {
+                warn_once!(
+                    "{:?} isn't in the block atlas, drawing the missing texture instead",
+                    texture.path()
+                );
+                texture_atlas
+                    .get_texture_index(&MISSING_TEXTURE)
+                    .ok_or(MeshGenError::AtlasMiss(texture.id()))?
+            }
+        };
+
+        let (min, size) = match textures {
+            BlockTextureMode::Atlas => {
+                let (page, rect) = atlas_page(texture_atlas, index);
+                if self.paged {
+                    self.texture_layers.extend([[page as f32, 0.]; 4]);
+                }
+                (
+                    rect.min / texture_atlas.size,
+                    rect.size() / texture_atlas.size,
+                )
+            }
+            BlockTextureMode::Array => {
+                self.texture_layers.extend([[index as f32, 0.]; 4]);
+                (Vec2::ZERO, Vec2::ONE)
+            }
+        };
+        let (min, size) = (min + region.min * size, region.size() * size);
+        self.texture_rects
+            .extend([[min.x, min.y, size.x, size.y]; 4]);
+        self.texture_faces.push(None);
+        Ok(())
+    }
+
+    /// The [ATTRIBUTE_PACKED] of every vertex, `None` if a face can't be packed. The second
+    /// word holds the normal id in bits 24-26, see [packed_normal_id], the texture face in
+    /// 27-29 and the occlusion in 30-31
+    fn pack(&self) -> Option<Vec<[u32; 4]>> {
+        let mut packed = Vec::with_capacity(self.positions.len());
+        for (face, texture_face) in self.texture_faces.iter().enumerate() {
+            let texture_face = (*texture_face)? as u32;
+            let vertices = face * 4..face * 4 + 4;
+            // Only the fraction of UVs matters, shift them to start at 0 so they fit
+            let offset = self.tex_coords[vertices.clone()]
+                .iter()
+                .fold(Vec2::splat(f32::MAX), |min, uv| min.min(Vec2::from(*uv)))
+                .floor();
+            for vertex in vertices {
+                let normal = packed_normal_id(self.normals[vertex])?;
+                let layer = self.texture_layers.get(vertex)?[0] as u32;
+                let position = (Vec3::from(self.positions[vertex]) * 32.).round();
+                let uv = ((Vec2::from(self.tex_coords[vertex]) - offset) * 128.).round();
+                if layer >= 1 << 8
+                    || position.min_element() < 0.
+                    || position.max_element() >= (1 << 10) as f32
+                    || uv.max_element() >= (1 << 12) as f32
+                {
+                    return None;
+                }
+                let (position, uv) = (position.as_uvec3(), uv.as_uvec2());
+                let occlusion = (self.occlusion[vertex] * 3.).round() as u32;
+                let [sky, block] =
+                    self.light[vertex].map(|light| (light * MAX_LIGHT as f32).round() as u32);
+                packed.push([
+                    position.x | (position.y << 10) | (position.z << 20),
+                    uv.x | (uv.y << 12) | (normal << 24) | (texture_face << 27) | (occlusion << 30),
+                    pack_unorm8(self.tint[vertex]) | (layer << 24),
+                    pack_unorm8(self.emissive[vertex]) | (sky << 24) | (block << 28),
+                ]);
+            }
+        }
+        Some(packed)
+    }
+
+    /// Tangent of every face along its `UV_0` x, with the sign of the bitangent along its y in
+    /// `w`. Every face is four vertices
+    fn tangents(&self) -> Vec<[f32; 4]> {
+        let faces = self
+            .positions
+            .chunks_exact(4)
+            .zip(self.normals.chunks_exact(4))
+            .zip(self.tex_coords.chunks_exact(4));
+        faces
+            .flat_map(|((positions, normals), tex_coords)| {
+                let [first, second, third] = [0, 1, 2].map(|i| Vec3::from_array(positions[i]));
+                let [first_uv, second_uv, third_uv] =
+                    [0, 1, 2].map(|i| Vec2::from_array(tex_coords[i]));
+                let normal = Vec3::from_array(normals[0]);
+                let (edge, other_edge) = (second - first, third - first);
+                let (delta, other_delta) = (second_uv - first_uv, third_uv - first_uv);
+                let det = delta.x * other_delta.y - other_delta.x * delta.y;
+                let tangent = (edge * other_delta.y - other_edge * delta.y) / det;
+                let bitangent = (other_edge * delta.x - edge * other_delta.x) / det;
+                // Faces whose texture doesn't vary along them get any tangent
+                let tangent = (tangent - normal * normal.dot(tangent))
+                    .try_normalize()
+                    .unwrap_or_else(|| normal.any_orthonormal_vector());
+                let sign = match normal.cross(tangent).dot(bitangent) < 0. {
+                    true => -1.,
+                    false => 1.,
+                };
+                [tangent.extend(sign).to_array(); 4]
+            })
+            .collect()
+    }
+
+    fn into_mesh(self, textures: BlockTextureMode, format: ChunkVertexFormat) -> Mesh {
+        let packed = match (format, textures) {
+            (ChunkVertexFormat::Packed, BlockTextureMode::Array) => self.pack(),
+            _ => None,
+        };
+        if let Some(packed) = packed {
+            return Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::default(),
+            )
+            .with_inserted_attribute(ATTRIBUTE_PACKED, VertexAttributeValues::Uint32x4(packed))
+            .with_inserted_indices(Indices::U32(self.indices));
+        }
+        let tangents = self.tangents();
+        let mesh = Mesh::new(
             PrimitiveTopology::TriangleList,
             RenderAssetUsages::default(),
         )
         .with_inserted_attribute(
             Mesh::ATTRIBUTE_POSITION,
-            VertexAttributeValues::Float32x3(positions),
+            VertexAttributeValues::Float32x3(self.positions),
         )
         .with_inserted_attribute(
             Mesh::ATTRIBUTE_NORMAL,
-            VertexAttributeValues::Float32x3(normals),
+            VertexAttributeValues::Float32x3(self.normals),
         )
         .with_inserted_attribute(
             Mesh::ATTRIBUTE_UV_0,
-            VertexAttributeValues::Float32x2(tex_coords),
+            VertexAttributeValues::Float32x2(self.tex_coords),
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_COLOR,
+            VertexAttributeValues::Float32x4(self.texture_rects),
+        )
+        .with_inserted_attribute(
+            ATTRIBUTE_LIGHT,
+            VertexAttributeValues::Float32x2(self.light),
+        )
+        .with_inserted_attribute(
+            ATTRIBUTE_OCCLUSION,
+            VertexAttributeValues::Float32(self.occlusion),
+        )
+        .with_inserted_attribute(
+            ATTRIBUTE_EMISSIVE,
+            VertexAttributeValues::Float32x3(self.emissive),
+        )
+        .with_inserted_attribute(ATTRIBUTE_TINT, VertexAttributeValues::Float32x3(self.tint))
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_TANGENT,
+            VertexAttributeValues::Float32x4(tangents),
         )
-        .with_inserted_indices(Indices::U32(indices.clone()))
+        .with_inserted_indices(Indices::U32(self.indices));
+        match (textures, self.paged) {
+            (BlockTextureMode::Atlas, false) => mesh,
+            _ => mesh.with_inserted_attribute(
+                Mesh::ATTRIBUTE_UV_1,
+                VertexAttributeValues::Float32x2(self.texture_layers),
+            ),
+        }
+    }
+}
+
+/// A colour with channels from 0 to 1 as 8 bits each, red in the lowest
+fn pack_unorm8(color: [f32; 3]) -> u32 {
+    color
+        .into_iter()
+        .enumerate()
+        .fold(0, |packed, (channel, value)| {
+            packed | (((value.clamp(0., 1.) * 255.).round() as u32) << (channel * 8))
+        })
+}
+
+/// Id of `normal` in [ATTRIBUTE_PACKED], the six sides in the order of [Facing::ALL] and then
+/// the two diagonals of [BlockShape::Cross]. `None` for other normals
+fn packed_normal_id(normal: [f32; 3]) -> Option<u32> {
+    let normal = Vec3::from_array(normal);
+    let diagonals = [Vec3::new(-1., 0., 1.), Vec3::new(-1., 0., -1.)].map(Vec3::normalize);
+    Facing::ALL
+        .map(|side| side.normal().as_vec3())
+        .into_iter()
+        .chain(diagonals)
+        .position(|id_normal| id_normal.abs_diff_eq(normal, 1e-4))
+        .map(|id| id as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A single upward face of the block at `(1, 2, 3)` with the given tint and emissive
+    fn face(tint: [f32; 3], emissive: [f32; 3]) -> MeshBuffers {
+        MeshBuffers {
+            indices: vec![0, 1, 2, 0, 2, 3],
+            positions: vec![[1., 3., 3.], [2., 3., 3.], [2., 3., 4.], [1., 3., 4.]],
+            normals: vec![[0., 1., 0.]; 4],
+            tex_coords: vec![[1., 1.], [0., 1.], [1., 0.], [0., 0.]],
+            texture_rects: vec![[0., 0., 1., 1.]; 4],
+            texture_layers: vec![[5., 0.]; 4],
+            light: vec![[1., 0.]; 4],
+            occlusion: vec![1.; 4],
+            emissive: vec![emissive; 4],
+            tint: vec![tint; 4],
+            texture_faces: vec![Some(2)],
+            paged: false,
+        }
+    }
+
+    #[test]
+    fn packs_tinted_and_glowing_faces() {
+        let packed = face([0.2, 0.8, 0.4], [1., 0.5, 0.]).pack().unwrap();
+        let [position, uv, tint, emissive] = packed[2];
+        assert_eq!(position, 64 | (96 << 10) | (128 << 20));
+        assert_eq!(uv & 0xfff, 128);
+        assert_eq!((uv >> 24) & 7, packed_normal_id([0., 1., 0.]).unwrap());
+        assert_eq!((uv >> 27) & 7, 2);
+        assert_eq!(uv >> 30, 3);
+        assert_eq!(tint, 51 | (204 << 8) | (102 << 16) | (5 << 24));
+        assert_eq!(emissive, 255 | (128 << 8) | (15 << 24));
+    }
+
+    #[test]
+    fn leaves_unpackable_faces_to_the_standard_layout() {
+        let mut far = face([1.; 3], [0.; 3]);
+        far.positions[0] = [40., 3., 3.];
+        assert!(far.pack().is_none());
+        let mut layered = face([1.; 3], [0.; 3]);
+        layered.texture_layers = vec![[300., 0.]; 4];
+        assert!(layered.pack().is_none());
     }
 }
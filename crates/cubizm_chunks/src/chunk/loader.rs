@@ -6,7 +6,7 @@ use bevy::{
 use block_mesh::ndshape::ConstShape;
 use thiserror::Error;
 
-use crate::{Chunk, ChunkShape, SerializedChunk};
+use crate::{BinaryChunkError, Chunk, ChunkShape, SerializedChunk};
 use cubizm_block::definition::Block;
 
 #[derive(Debug, Error)]
@@ -17,6 +17,30 @@ pub enum ChunkLoaderError {
     RonSpannedError(#[from] ron::error::SpannedError),
     #[error(transparent)]
     LoadDirectError(#[from] bevy::asset::LoadDirectError),
+    #[error(transparent)]
+    BinaryChunkError(#[from] BinaryChunkError),
+    #[error("Chunk holds {0} blocks, expected {1}")]
+    WrongBlockCount(usize, usize),
+    #[error("Chunk holds {0} block states, expected none or {1}")]
+    WrongStateCount(usize, usize),
+}
+
+/// Loads the blocks a [SerializedChunk] refers to
+fn load_serialized_chunk(serialized: SerializedChunk, load_context: &mut LoadContext) -> Chunk {
+    let mut blocks: Vec<Handle<Block>> = Vec::with_capacity(ChunkShape::SIZE as usize);
+
+    for block in serialized.blocks.iter() {
+        let loaded = load_context.load(block);
+
+        blocks.push(loaded);
+    }
+    Chunk {
+        blocks,
+        position: serialized.position,
+        states: serialized.states,
+        tile_data: serialized.tile_data,
+        entities: serialized.entities,
+    }
 }
 
 #[derive(Default)]
@@ -36,18 +60,20 @@ impl AssetLoader for ChunkLoader {
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
-            let ron: SerializedChunk = ron::de::from_bytes(&bytes)?;
-            let mut blocks: Vec<Handle<Block>> = Vec::with_capacity(ChunkShape::SIZE as usize);
-
-            for block in ron.blocks.iter() {
-                let loaded = load_context.load(block);
-
-                blocks.push(loaded);
+            let ron = ron::de::from_bytes::<SerializedChunk>(&bytes)?.without_legacy_padding();
+            if ron.blocks.len() != ChunkShape::SIZE as usize {
+                return Err(ChunkLoaderError::WrongBlockCount(
+                    ron.blocks.len(),
+                    ChunkShape::SIZE as usize,
+                ));
+            }
+            if !ron.states.is_empty() && ron.states.len() != ChunkShape::SIZE as usize {
+                return Err(ChunkLoaderError::WrongStateCount(
+                    ron.states.len(),
+                    ChunkShape::SIZE as usize,
+                ));
             }
-            Ok(Chunk {
-                blocks,
-                position: ron.position,
-            })
+            Ok(load_serialized_chunk(ron, load_context))
         })
     }
 
@@ -55,3 +81,31 @@ impl AssetLoader for ChunkLoader {
         &["chunk"]
     }
 }
+
+/// Loads chunks in the binary format, see [SerializedChunk::to_binary]
+#[derive(Default)]
+pub struct BinaryChunkLoader;
+
+impl AssetLoader for BinaryChunkLoader {
+    type Asset = Chunk;
+    type Settings = ();
+    type Error = ChunkLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let serialized = SerializedChunk::from_binary(&bytes)?;
+            Ok(load_serialized_chunk(serialized, load_context))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["chunkb"]
+    }
+}
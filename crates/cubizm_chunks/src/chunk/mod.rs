@@ -1,5 +1,9 @@
+pub use binary::*;
 pub use definition::*;
 pub use loader::*;
+pub use tile_data::*;
 
+mod binary;
 mod definition;
 mod loader;
+mod tile_data;
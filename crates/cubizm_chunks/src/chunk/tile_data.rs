@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+
+use bevy::{asset::ron, prelude::*};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::ChunkError;
+
+#[derive(Debug, Error)]
+pub enum TileDataError {
+    #[error(transparent)]
+    Chunk(#[from] ChunkError),
+    #[error(transparent)]
+    Serialize(#[from] ron::Error),
+    #[error(transparent)]
+    Deserialize(#[from] ron::error::SpannedError),
+}
+
+/// State of the individual blocks of a [Chunk](crate::Chunk) beyond which block they are,
+/// like the items in a chest or the text of a sign. Stored as RON by position within the
+/// chunk, so blocks can keep any serde type and chunks save it without knowing about them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TileEntityData(BTreeMap<[u32; 3], String>);
+
+impl TileEntityData {
+    /// The data of the block at `local`, `None` if it has none
+    pub fn get<T: DeserializeOwned>(&self, local: UVec3) -> Result<Option<T>, TileDataError> {
+        self.0
+            .get(&local.to_array())
+            .map(|data| ron::de::from_str(data))
+            .transpose()
+            .map_err(TileDataError::from)
+    }
+
+    pub fn set<T: Serialize>(&mut self, local: UVec3, data: &T) -> Result<(), TileDataError> {
+        self.0.insert(local.to_array(), ron::ser::to_string(data)?);
+        Ok(())
+    }
+
+    /// Returns whether the block at `local` had data
+    pub fn remove(&mut self, local: UVec3) -> bool {
+        self.0.remove(&local.to_array()).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The data of every block that has some as RON, ordered by position
+    pub fn iter(&self) -> impl Iterator<Item = (UVec3, &str)> {
+        self.0
+            .iter()
+            .map(|(local, ron)| (UVec3::from_array(*local), ron.as_str()))
+    }
+
+    pub(crate) fn insert_raw(&mut self, local: UVec3, ron: String) {
+        self.0.insert(local.to_array(), ron);
+    }
+}
@@ -0,0 +1,155 @@
+#import bevy_pbr::{
+    mesh_functions,
+    view_transformations::position_world_to_clip,
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT,
+}
+#import cubizm_chunks::packed_vertex
+
+@group(2) @binding(100) var array_texture: texture_2d_array<f32>;
+@group(2) @binding(101) var array_sampler: sampler;
+
+// How much each light level below the brightest darkens a face
+const LIGHT_FALLOFF: f32 = 0.8;
+// Brightness of a fully occluded vertex
+const OCCLUDED_BRIGHTNESS: f32 = 0.4;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+#ifdef PACKED_VERTEX
+    @location(12) packed: vec4<u32>,
+#else
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_UVS_B
+    @location(3) uv_b: vec2<f32>,
+#endif
+#ifdef VERTEX_TANGENTS
+    @location(4) tangent: vec4<f32>,
+#endif
+    @location(5) color: vec4<f32>,
+    @location(8) light: vec2<f32>,
+    @location(9) occlusion: f32,
+    @location(10) emissive: vec3<f32>,
+    @location(11) tint: vec3<f32>,
+#endif
+};
+
+// The standard `VertexOutput` with the light in front of the face, the occlusion, the
+// emissive and the biome tint added
+struct ChunkVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_UVS_B
+    @location(3) uv_b: vec2<f32>,
+#endif
+#ifdef VERTEX_TANGENTS
+    @location(4) world_tangent: vec4<f32>,
+#endif
+    @location(5) color: vec4<f32>,
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    @location(6) @interpolate(flat) instance_index: u32,
+#endif
+    @location(7) light: vec2<f32>,
+    @location(8) occlusion: f32,
+    @location(9) emissive: vec3<f32>,
+    @location(10) tint: vec3<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> ChunkVertexOutput {
+    var out: ChunkVertexOutput;
+    let model = mesh_functions::get_model_matrix(vertex.instance_index);
+#ifdef PACKED_VERTEX
+    // Packed meshes only use texture arrays and have no tangents
+    let packed = vertex.packed;
+    let position = packed_vertex::position(packed);
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(packed_vertex::normal(packed), vertex.instance_index);
+    out.uv = packed_vertex::uv(packed);
+    out.uv_b = vec2<f32>(packed_vertex::texture_layer(packed), 0.0);
+    out.color = vec4<f32>(0.0, packed_vertex::texture_face(packed) / 6.0, 1.0, 1.0 / 6.0);
+    out.light = packed_vertex::light(packed);
+    out.occlusion = packed_vertex::occlusion(packed);
+    out.emissive = packed_vertex::emissive(packed);
+    out.tint = packed_vertex::tint(packed);
+#else
+    let position = vertex.position;
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(vertex.normal, vertex.instance_index);
+    out.uv = vertex.uv;
+#ifdef VERTEX_UVS_B
+    out.uv_b = vertex.uv_b;
+#endif
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = mesh_functions::mesh_tangent_local_to_world(model, vertex.tangent, vertex.instance_index);
+#endif
+    out.color = vertex.color;
+    out.light = vertex.light;
+    out.occlusion = vertex.occlusion;
+    out.emissive = vertex.emissive;
+    out.tint = vertex.tint;
+#endif
+    out.world_position = mesh_functions::mesh_position_local_to_world(model, vec4<f32>(position, 1.0));
+    out.position = position_world_to_clip(out.world_position.xyz);
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+    return out;
+}
+
+@fragment
+fn fragment(
+    in: ChunkVertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var tiled: VertexOutput;
+    tiled.position = in.position;
+    tiled.world_position = in.world_position;
+    tiled.world_normal = in.world_normal;
+#ifdef VERTEX_UVS
+    // uv is the position on the face in blocks, color the texture rect of the face as (min, size)
+    tiled.uv = in.color.xy + fract(in.uv) * in.color.zw;
+#endif
+#ifdef VERTEX_UVS_B
+    tiled.uv_b = in.uv_b;
+#endif
+#ifdef VERTEX_TANGENTS
+    tiled.world_tangent = in.world_tangent;
+#endif
+#ifdef VERTEX_COLORS
+    tiled.color = vec4<f32>(1.0);
+#endif
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    tiled.instance_index = in.instance_index;
+#endif
+
+    var pbr_input = pbr_input_from_standard_material(tiled, is_front);
+#ifdef VERTEX_UVS_B
+    // Texture array meshes store the layer of the face's texture in uv_b
+    let layer = i32(round(in.uv_b.x));
+    pbr_input.material.base_color *= textureSample(array_texture, array_sampler, tiled.uv, layer);
+#endif
+    // Tinted blocks take the colour of their biome
+    pbr_input.material.base_color = vec4<f32>(pbr_input.material.base_color.rgb * in.tint, pbr_input.material.base_color.a);
+    // Glowing faces shine with their texture tinted by the emissive colour, unaffected by light
+    let glow = pbr_input.material.base_color.rgb * in.emissive;
+    pbr_input.material.emissive = vec4<f32>(pbr_input.material.emissive.rgb + glow, pbr_input.material.emissive.a);
+    // Sky and block light levels, whichever is brighter
+    let level = max(in.light.x, in.light.y) * 15.0;
+    let brightness = pow(LIGHT_FALLOFF, 15.0 - level) * mix(OCCLUDED_BRIGHTNESS, 1.0, in.occlusion);
+    pbr_input.material.base_color = vec4<f32>(pbr_input.material.base_color.rgb * brightness, pbr_input.material.base_color.a);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    var out: FragmentOutput;
+    if (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u {
+        out.color = apply_pbr_lighting(pbr_input);
+    } else {
+        out.color = vec4<f32>(pbr_input.material.base_color.rgb + glow, pbr_input.material.base_color.a);
+    }
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
@@ -0,0 +1,35 @@
+#import bevy_pbr::{
+    mesh_functions,
+    prepass_io::VertexOutput,
+}
+#import cubizm_chunks::packed_vertex
+
+// Depth and shadow prepass of packed chunk meshes, which have no float position for bevy's
+// prepass shader to read
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(12) packed: vec4<u32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let model = mesh_functions::get_model_matrix(vertex.instance_index);
+    let position = vec4<f32>(packed_vertex::position(vertex.packed), 1.0);
+    out.position = mesh_functions::mesh_position_local_to_clip(model, position);
+#ifdef DEPTH_CLAMP_ORTHO
+    out.clip_position_unclamped = out.position;
+    out.position.z = min(out.position.z, 1.0);
+#endif
+    out.world_position = mesh_functions::mesh_position_local_to_world(model, position);
+#ifdef MOTION_VECTOR_PREPASS
+    out.previous_world_position = mesh_functions::mesh_position_local_to_world(
+        mesh_functions::get_previous_model_matrix(vertex.instance_index),
+        position
+    );
+#endif
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+    return out;
+}
@@ -1,25 +1,173 @@
-use crate::ChunkShape;
-use crate::Opposite;
-use crate::{Chunk, ChunkFace};
-use bevy::{prelude::*, utils::HashMap};
-use block_mesh::ndshape::ConstShape;
-use cubizm_block::{definition::Block, texture_atlas::BlockAtlas};
-use std::ops::Add;
+use crate::material::use_block_atlas;
+use crate::{
+    AtlasTiling, BiomeTints, Chunk, ChunkFace, ChunkLod, ChunkMaterial, ChunkMeshes,
+    ChunkOccupancy, ChunkVertexFormat, FluidLevels, LightEngine, LightProperties, MeshGenError,
+    MeshingMode, OccupancyMap, TileDataError,
+};
+use crate::{ChunkShape, CHUNK_SIZE};
+use bevy::{
+    prelude::*,
+    render::{primitives::Aabb, render_resource::Face},
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+    utils::{HashMap, HashSet, Instant},
+};
+use block_mesh::{ndshape::ConstShape, Voxel, VoxelVisibility};
+use cubizm_block::{
+    definition::Block, texture_atlas::BlockAtlas, BlockAtlasLayout, BlockState, BlockTextureMode,
+};
+use cubizm_core::{chunk_to_world, local_to_world, world_to_chunk, world_to_local};
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
 use thiserror::Error;
 
 /// The chunk representation of the world
-#[derive(Resource, Default)]
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
 pub struct Chunks {
     pub chunks: HashMap<IVec3, ChunkEntity>,
+    #[reflect(ignore)]
+    occupancy: OccupancyMap,
+    #[reflect(ignore)]
+    mesh_tasks: MeshTasks,
+    /// Used by every chunk without its own [ChunkEntity::meshing]
+    meshing: MeshingMode,
+    vertex_format: ChunkVertexFormat,
+    /// Chunks modified since they were last saved
+    dirty: HashSet<IVec3>,
+    /// How the [BlockAtlas] the chunks were inserted with packs its textures
+    textures: BlockTextureMode,
+    /// Shared by every chunk, created with the first chunk
+    #[reflect(ignore)]
+    materials: Option<ChunkMaterials>,
+    #[reflect(ignore)]
+    light: LightEngine,
+    #[reflect(ignore)]
+    fluids: FluidLevels,
+    /// Colours of the tinted blocks, untinted without biomes
+    #[reflect(ignore)]
+    biome_tints: BiomeTints,
+    /// Lifecycle events waiting to be sent, see [ChunkLoaded]
+    #[reflect(ignore)]
+    lifecycle: Vec<ChunkLifecycle>,
+}
+
+/// A chunk loaded, meshed or unloaded since the events were last sent
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ChunkLifecycle {
+    Loaded(IVec3),
+    Meshed(IVec3),
+    Unloaded(IVec3),
+}
+
+/// Chunks waiting to be meshed, the meshes being generated on the [AsyncComputeTaskPool] and
+/// the finished meshes, shared by every chunk whose [ChunkSnapshot] hashes the same
+#[derive(Default)]
+struct MeshTasks {
+    /// Dispatched nearest to the camera first by [Chunks::dispatch_mesh_tasks]
+    pending: HashSet<IVec3>,
+    /// Keyed by chunk position, along with the content hash the meshes are for
+    running: HashMap<IVec3, (u64, Task<Result<ChunkMeshes, MeshGenError>>)>,
+    /// Chunks whose content hash was already in `cache` when dispatched
+    cached: HashMap<IVec3, u64>,
+    cache: HashMap<u64, CachedMeshes>,
+    /// Nothing is ever meshed, see [Chunks::headless]
+    headless: bool,
+}
+
+/// Meshes in the [MeshTasks] cache and how many chunks show them
+struct CachedMeshes {
+    handles: ChunkMeshHandles,
+    users: usize,
+}
+
+impl MeshTasks {
+    /// Remeshes the chunk at `position` once its turn comes, from its data at that time. Does
+    /// nothing if chunks are not meshed
+    fn queue(&mut self, position: IVec3) {
+        if !self.headless {
+            self.pending.insert(position);
+        }
+    }
+
+    /// Forgets every cached mesh and drops the meshes being generated, for when the hashes no
+    /// longer describe the meshes, e.g. after the blocks were reloaded
+    fn clear_cache(&mut self) {
+        self.running.clear();
+        self.cached.clear();
+        self.cache.clear();
+    }
+
+    /// Stops meshing the chunk at `position` and lets go of the cached meshes it showed
+    fn forget(&mut self, position: IVec3, mesh_key: Option<u64>) {
+        self.pending.remove(&position);
+        self.running.remove(&position);
+        self.cached.remove(&position);
+        if let Some(cached) = mesh_key.and_then(|key| self.cache.get_mut(&key)) {
+            cached.users -= 1;
+        }
+        self.cache.retain(|_, cached| cached.users > 0);
+    }
+}
+
+/// How much chunk meshing [ChunksPlugin](crate::ChunksPlugin) starts per frame, so many chunks
+/// changing at once are remeshed over several frames instead of stalling one. Chunks nearest
+/// to the active camera go first
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemeshBudget {
+    /// Most chunks to start meshing in a frame
+    pub max_chunks: usize,
+    /// Stop starting chunks once this much of the frame was spent preparing them
+    pub max_time: Duration,
+}
+
+impl Default for RemeshBudget {
+    fn default() -> Self {
+        Self {
+            max_chunks: 32,
+            max_time: Duration::from_millis(4),
+        }
+    }
+}
+
+/// The [Mesh] handles of the three [ChunkMeshes] of a chunk
+#[derive(Debug, Clone)]
+struct ChunkMeshHandles {
+    opaque: Handle<Mesh>,
+    transparent: Handle<Mesh>,
+    cutout: Handle<Mesh>,
+}
+
+/// The [ChunkMaterial]s of the opaque, transparent and cutout blocks of every chunk
+#[derive(Debug, Clone)]
+struct ChunkMaterials {
+    opaque: Handle<ChunkMaterial>,
+    transparent: Handle<ChunkMaterial>,
+    cutout: Handle<ChunkMaterial>,
 }
 
 /// Stores the [Chunk] data and its [Mesh], use the [Chunks] resource to access.
-#[derive(Debug)]
+#[derive(Debug, Reflect)]
 pub struct ChunkEntity {
-    #[allow(unused)]
-    entity: Entity,
+    pub entity: Entity,
     pub chunk: Handle<Chunk>,
+    /// Blocks in [RenderLayer::Opaque](cubizm_block::definition::RenderLayer::Opaque), shared
+    /// with every chunk meshed from the same content
     pub mesh_handle: Handle<Mesh>,
+    /// Child of [entity](ChunkEntity::entity) drawing the transparent blocks with alpha blending
+    pub transparent_entity: Entity,
+    /// Blocks in [RenderLayer::Transparent](cubizm_block::definition::RenderLayer::Transparent)
+    pub transparent_mesh_handle: Handle<Mesh>,
+    /// Child of [entity](ChunkEntity::entity) drawing the cutout blocks with alpha masking
+    pub cutout_entity: Entity,
+    /// Blocks in [RenderLayer::Cutout](cubizm_block::definition::RenderLayer::Cutout)
+    pub cutout_mesh_handle: Handle<Mesh>,
+    /// Overrides the [MeshingMode] of [Chunks] for this chunk,
+    /// see [set_chunk_meshing_mode](Chunks::set_chunk_meshing_mode)
+    pub meshing: Option<MeshingMode>,
+    /// See [set_chunk_lod](Chunks::set_chunk_lod)
+    pub lod: ChunkLod,
+    /// Content hash of the cached meshes the chunk shows, `None` until it was first meshed
+    mesh_key: Option<u64>,
 }
 
 impl From<&mut ChunkEntity> for AssetId<Chunk> {
@@ -45,6 +193,34 @@ impl From<ChunkEntity> for Handle<Chunk> {
     }
 }
 
+/// Sent by [Chunks::set_block] for every block it replaces
+#[derive(Event, Debug, Clone)]
+pub struct BlockChanged {
+    pub world_pos: IVec3,
+    pub old: Handle<Block>,
+    pub new: Handle<Block>,
+}
+
+/// Sent when a chunk at the chunk position was added to [Chunks], before it is meshed
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkLoaded(pub IVec3);
+
+/// Sent when a chunk at the chunk position shows new meshes, after it loaded and whenever its
+/// blocks or light changed. Never sent for [headless](Chunks::headless) chunks
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkMeshed(pub IVec3);
+
+/// Sent when a chunk at the chunk position was removed with [Chunks::remove_chunk]
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkUnloaded(pub IVec3);
+
+/// Sent when a player uses a block, e.g. to open a chest or a door
+#[derive(Event, Debug, Clone)]
+pub struct BlockInteract {
+    pub position: IVec3,
+    pub block: Handle<Block>,
+}
+
 #[derive(Debug, Error)]
 pub enum ChunkError {
     #[error("Chunk could not be found")]
@@ -57,34 +233,323 @@ impl Chunks {
         Self::default()
     }
 
-    /// Grabs the neighbouring chunk by a given [direction](ChunkFace)
-    fn get_neighbouring_chunk_mut(
+    /// Which blocks of the loaded chunks rays hit, see [OccupancyMap::ray_steps]
+    pub fn occupancy(&self) -> &OccupancyMap {
+        &self.occupancy
+    }
+
+    /// Stops meshing the chunks, dropping the meshes they show and those being generated, for
+    /// renderers drawing the chunk data directly. Chunks inserted later are not meshed either
+    pub fn stop_meshing(&mut self, meshes: &mut Assets<Mesh>) {
+        self.mesh_tasks.headless = true;
+        self.mesh_tasks.pending.clear();
+        self.mesh_tasks.clear_cache();
+        for chunk_entity in self.chunks.values_mut() {
+            chunk_entity.mesh_key = None;
+            meshes.remove(&chunk_entity.mesh_handle);
+            meshes.remove(&chunk_entity.transparent_mesh_handle);
+            meshes.remove(&chunk_entity.cutout_mesh_handle);
+        }
+    }
+
+    pub fn with_meshing_mode(meshing: MeshingMode) -> Self {
+        Self {
+            meshing,
+            ..default()
+        }
+    }
+
+    /// Tints the blocks with a [BiomeTint](cubizm_block::definition::BiomeTint) by the biomes
+    /// of `tints` from now on, chunks meshed before keep their colours until remeshed
+    pub fn set_biome_tints(&mut self, tints: BiomeTints) {
+        self.biome_tints = tints;
+    }
+
+    /// Chunks that only hold the world data and are never meshed or drawn, insert them with
+    /// [insert_chunk_data](Chunks::insert_chunk_data)
+    pub fn headless() -> Self {
+        Self {
+            mesh_tasks: MeshTasks {
+                headless: true,
+                ..default()
+            },
+            ..default()
+        }
+    }
+
+    pub fn meshing_mode(&self) -> MeshingMode {
+        self.meshing
+    }
+
+    /// Marks the chunk at `position` as modified so the [WorldSaver](crate::WorldSaver) writes
+    /// it back, done by [set_block](Chunks::set_block)
+    pub fn mark_dirty(&mut self, position: IVec3) {
+        self.dirty.insert(position);
+    }
+
+    pub fn is_dirty(&self, position: IVec3) -> bool {
+        self.dirty.contains(&position)
+    }
+
+    pub fn fluids(&self) -> &FluidLevels {
+        &self.fluids
+    }
+
+    pub(crate) fn fluids_mut(&mut self) -> &mut FluidLevels {
+        &mut self.fluids
+    }
+
+    /// Sets the level of the flowing fluid at world `position`, `None` to make it a source,
+    /// and remeshes its chunk if the level changed
+    pub fn set_fluid_level(&mut self, position: IVec3, level: Option<u8>) {
+        if self.fluids.set(position, level) {
+            // Chunks that aren't loaded have nothing to remesh
+            let _ = self.regenerate_chunk_at(world_to_chunk(position));
+        }
+    }
+
+    pub(crate) fn take_dirty(&mut self) -> Vec<IVec3> {
+        self.dirty.drain().collect()
+    }
+
+    /// Changes the [MeshingMode] of every chunk without an override and remeshes them
+    pub fn set_meshing_mode(&mut self, meshing: MeshingMode) {
+        if meshing == self.meshing {
+            return;
+        }
+        self.meshing = meshing;
+        for (position, chunk_entity) in self.chunks.iter() {
+            if chunk_entity.meshing.is_none() {
+                self.mesh_tasks.queue(*position);
+            }
+        }
+    }
+
+    pub fn vertex_format(&self) -> ChunkVertexFormat {
+        self.vertex_format
+    }
+
+    /// Changes the [ChunkVertexFormat] of the chunk meshes and remeshes every chunk
+    pub fn set_vertex_format(&mut self, format: ChunkVertexFormat) {
+        if format == self.vertex_format {
+            return;
+        }
+        self.vertex_format = format;
+        for position in self.chunks.keys() {
+            self.mesh_tasks.queue(*position);
+        }
+    }
+
+    /// Overrides the [MeshingMode] of the chunk at `position`, or makes it follow the one of
+    /// [Chunks] again with `None`, and remeshes it
+    pub fn set_chunk_meshing_mode(
         &mut self,
         position: IVec3,
-        neighbour: ChunkFace,
-    ) -> Option<&mut ChunkEntity> {
-        // front position = negative z
-        let front_position = position.add(IVec3::from_array([0, 0, -1]));
-        // back position = positive z
-        let back_position = position.add(IVec3::from_array([0, 0, 1]));
-        // top position = positive y
-        let top_position = position.add(IVec3::from_array([0, 1, 0]));
-        // bottom position = negative y
-        let bottom_position = position.add(IVec3::from_array([0, -1, 0]));
-        // right position = positive x
-        let right_position = position.add(IVec3::from_array([1, 0, 0]));
-        // left position = negative x
-        let left_position = position.add(IVec3::from_array([-1, 0, 0]));
-        match neighbour {
-            ChunkFace::Top => self.chunks.get_mut(&top_position),
-            ChunkFace::Bottom => self.chunks.get_mut(&bottom_position),
-            ChunkFace::Front => self.chunks.get_mut(&front_position),
-            ChunkFace::Back => self.chunks.get_mut(&back_position),
-            ChunkFace::Right => self.chunks.get_mut(&right_position),
-            ChunkFace::Left => self.chunks.get_mut(&left_position),
+        meshing: Option<MeshingMode>,
+    ) -> Result<(), ChunkError> {
+        let chunk_entity = self
+            .chunks
+            .get_mut(&position)
+            .ok_or(ChunkError::ChunkNotFound)?;
+        chunk_entity.meshing = meshing;
+        self.mesh_tasks.queue(position);
+        Ok(())
+    }
+
+    /// Changes the [ChunkLod] of the chunk at `position` and remeshes it if it differs
+    pub fn set_chunk_lod(&mut self, position: IVec3, lod: ChunkLod) -> Result<(), ChunkError> {
+        let chunk_entity = self
+            .chunks
+            .get_mut(&position)
+            .ok_or(ChunkError::ChunkNotFound)?;
+        if chunk_entity.lod == lod {
+            return Ok(());
+        }
+        chunk_entity.lod = lod;
+        self.mesh_tasks.queue(position);
+        Ok(())
+    }
+
+    /// Whether the mesh of the chunk at `position` is waiting for its turn or still being
+    /// generated
+    pub fn is_meshing(&self, position: IVec3) -> bool {
+        self.mesh_tasks.pending.contains(&position)
+            || self.mesh_tasks.running.contains_key(&position)
+            || self.mesh_tasks.cached.contains_key(&position)
+    }
+
+    /// Starts meshing the queued chunks nearest to `camera_chunk` within `budget`, replacing
+    /// any older meshes still being generated for them. Chunks whose content was meshed before
+    /// reuse the cached meshes instead. Chunks below [ChunkLod::Full] are always meshed with
+    /// [MeshingMode::Greedy]
+    pub(crate) fn dispatch_mesh_tasks(
+        &mut self,
+        budget: &RemeshBudget,
+        camera_chunk: IVec3,
+        texture_atlas_layout: &BlockAtlasLayout,
+        chunks: &Assets<Chunk>,
+        blocks: &Assets<Block>,
+    ) {
+        if self.mesh_tasks.pending.is_empty() {
+            return;
+        }
+        let start = Instant::now();
+        let mut queued = self.mesh_tasks.pending.iter().copied().collect::<Vec<_>>();
+        queued.sort_by_key(|position| (*position - camera_chunk).length_squared());
+
+        for (dispatched, position) in queued.into_iter().enumerate() {
+            if dispatched >= budget.max_chunks || start.elapsed() >= budget.max_time {
+                break;
+            }
+            self.mesh_tasks.pending.remove(&position);
+            let Some(chunk_entity) = self.chunks.get(&position) else {
+                continue;
+            };
+            let Some(chunk) = chunks.get(&chunk_entity.chunk) else {
+                continue;
+            };
+            let lod = chunk_entity.lod;
+            let meshing = match lod {
+                ChunkLod::Full => chunk_entity.meshing.unwrap_or(self.meshing),
+                _ => MeshingMode::Greedy,
+            };
+            // The border is sampled from the neighbours as they are now, chunks only store
+            // their own blocks
+            let origin = chunk_to_world(position);
+            let chunk_entities = &self.chunks;
+            let snapshot = chunk.snapshot(blocks, |padded| {
+                let (neighbour, index) = Self::block_index(origin + padded.as_ivec3());
+                let neighbour = chunks.get(&chunk_entities.get(&neighbour)?.chunk)?;
+                Some(neighbour.blocks[index as usize].id())
+            });
+            let snapshot = match snapshot {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    warn!("Skipped meshing chunk {position}: {err}");
+                    continue;
+                }
+            };
+            let snapshot = snapshot
+                .with_light(self.light.padded_light(position))
+                .with_fluid_levels(self.fluids.padded_levels(position))
+                .with_tints(self.biome_tints.padded(position))
+                .downsampled(lod);
+            let (textures, format) = (self.textures, self.vertex_format);
+            let key = snapshot.content_hash(meshing, textures, format);
+            if chunk_entity.mesh_key == Some(key) {
+                self.mesh_tasks.running.remove(&position);
+                self.mesh_tasks.cached.remove(&position);
+                continue;
+            }
+            if self.mesh_tasks.cache.contains_key(&key) {
+                self.mesh_tasks.running.remove(&position);
+                self.mesh_tasks.cached.insert(position, key);
+                continue;
+            }
+            let texture_atlas_layout = texture_atlas_layout.clone();
+            let task = AsyncComputeTaskPool::get().spawn(async move {
+                snapshot.gen_geometry(&texture_atlas_layout, meshing, textures, format)
+            });
+            self.mesh_tasks.cached.remove(&position);
+            self.mesh_tasks.running.insert(position, (key, task));
         }
     }
 
+    /// Caches finished meshes and points every chunk meshed since the last poll at the cached
+    /// meshes for its content. Cached meshes no chunk shows anymore are dropped
+    pub(crate) fn poll_mesh_tasks(&mut self, commands: &mut Commands, meshes: &mut Assets<Mesh>) {
+        let mesh_tasks = &mut self.mesh_tasks;
+        let mut finished: Vec<(IVec3, u64)> = mesh_tasks.cached.drain().collect();
+        mesh_tasks.running.retain(|position, (key, task)| {
+            let Some(chunk_meshes) = block_on(poll_once(task)) else {
+                return true;
+            };
+            // The chunk keeps showing its previous meshes
+            let chunk_meshes = match chunk_meshes {
+                Ok(chunk_meshes) => chunk_meshes,
+                Err(err) => {
+                    warn!("Failed to mesh chunk {position}: {err}");
+                    return false;
+                }
+            };
+            // A chunk with the same content may have finished first
+            mesh_tasks
+                .cache
+                .entry(*key)
+                .or_insert_with(|| CachedMeshes {
+                    handles: ChunkMeshHandles {
+                        opaque: meshes.add(chunk_meshes.opaque),
+                        transparent: meshes.add(chunk_meshes.transparent),
+                        cutout: meshes.add(chunk_meshes.cutout),
+                    },
+                    users: 0,
+                });
+            finished.push((*position, *key));
+            false
+        });
+
+        for (position, key) in finished {
+            let Some(chunk_entity) = self.chunks.get_mut(&position) else {
+                continue;
+            };
+            if chunk_entity.mesh_key == Some(key) {
+                continue;
+            }
+            let Some(cached) = self.mesh_tasks.cache.get_mut(&key) else {
+                continue;
+            };
+            cached.users += 1;
+            let handles = cached.handles.clone();
+            if let Some(old) = chunk_entity.mesh_key.replace(key) {
+                if let Some(old) = self.mesh_tasks.cache.get_mut(&old) {
+                    old.users -= 1;
+                }
+            }
+            commands
+                .entity(chunk_entity.entity)
+                .insert(handles.opaque.clone());
+            commands
+                .entity(chunk_entity.transparent_entity)
+                .insert(handles.transparent.clone());
+            commands
+                .entity(chunk_entity.cutout_entity)
+                .insert(handles.cutout.clone());
+            chunk_entity.mesh_handle = handles.opaque;
+            chunk_entity.transparent_mesh_handle = handles.transparent;
+            chunk_entity.cutout_mesh_handle = handles.cutout;
+            self.lifecycle.push(ChunkLifecycle::Meshed(position));
+        }
+        self.mesh_tasks.cache.retain(|_, cached| cached.users > 0);
+    }
+
+    /// Removes the chunk at `position` and despawns its entities. Its neighbours keep their
+    /// meshes and the light it spread into them, and changes not saved yet are lost. Returns
+    /// the handle of its [Chunk], the data is dropped along with it
+    pub fn remove_chunk(
+        &mut self,
+        position: IVec3,
+        commands: &mut Commands,
+    ) -> Result<Handle<Chunk>, ChunkError> {
+        let chunk_entity = self
+            .chunks
+            .remove(&position)
+            .ok_or(ChunkError::ChunkNotFound)?;
+        // The transparent and cutout entities are its children
+        commands.entity(chunk_entity.entity).despawn_recursive();
+        self.mesh_tasks.forget(position, chunk_entity.mesh_key);
+        self.light.remove_chunk(position);
+        self.fluids.remove_chunk(position);
+        self.occupancy.remove_chunk(position);
+        self.dirty.remove(&position);
+        self.lifecycle.push(ChunkLifecycle::Unloaded(position));
+        Ok(chunk_entity.chunk)
+    }
+
+    /// The lifecycle events since the last call, see [ChunkLoaded]
+    pub(crate) fn take_lifecycle(&mut self) -> Vec<ChunkLifecycle> {
+        std::mem::take(&mut self.lifecycle)
+    }
+
     /// Inserts a [Chunk] at a given [position](IVec3), does NOT update neighbours
     /// use [insert_chunk_and_regenerate](Chunks::insert_chunk_and_regenerate) to update neighbours on insertion or
     /// manually call [regenerate_chunk_at](Chunks::regenerate_chunk_at) to update neighbours
@@ -96,183 +561,249 @@ impl Chunks {
         texture_atlas: Res<BlockAtlas>,
         commands: &mut Commands,
         meshes: &mut ResMut<Assets<Mesh>>,
-        materials: &mut ResMut<Assets<StandardMaterial>>,
+        materials: &mut ResMut<Assets<ChunkMaterial>>,
         chunks: &mut ResMut<Assets<Chunk>>,
         blocks: Res<Assets<Block>>,
     ) {
-        let mesh = chunk.gen_geometry(texture_atlas.get_texture_atlas_layout(), blocks);
+        self.occupancy
+            .insert_chunk(position, ChunkOccupancy::new(&chunk, &blocks));
+        self.textures = texture_atlas.texture_mode();
+        // The meshes stay empty until their task finishes, queued once the chunk is lit
+        let mesh_handle = meshes.reserve_handle();
+        let transparent_mesh_handle = meshes.reserve_handle();
+        let cutout_mesh_handle = meshes.reserve_handle();
         let chunk_handle = chunks.add(chunk);
-        let mesh_handle = meshes.add(mesh);
+        // Every chunk draws with the same atlas, sharing the materials lets bevy batch them
+        let shared = self.materials.get_or_insert_with(|| {
+            let mut material = |alpha_mode, double_sided: bool| {
+                let mut material = ChunkMaterial {
+                    base: StandardMaterial {
+                        alpha_mode,
+                        double_sided,
+                        cull_mode: (!double_sided).then_some(Face::Back),
+                        ..default()
+                    },
+                    extension: AtlasTiling::default(),
+                };
+                use_block_atlas(&mut material, &texture_atlas);
+                materials.add(material)
+            };
+            ChunkMaterials {
+                opaque: material(AlphaMode::Opaque, false),
+                transparent: material(AlphaMode::Blend, false),
+                cutout: material(AlphaMode::Mask(0.5), true),
+            }
+        });
 
+        // Packed meshes have no positions for bevy to compute their bounds from, every mesh
+        // gets those of the padded chunk instead
+        let bounds = Aabb::from_min_max(Vec3::ZERO, Vec3::splat(CHUNK_SIZE as f32 + 2.));
+        // Transparent blocks are a child drawn in bevy's transparent pass, it keeps the
+        // chunk material so tiling and baked light match the opaque blocks
+        let transparent_entity = commands
+            .spawn((
+                MaterialMeshBundle {
+                    mesh: transparent_mesh_handle.clone(),
+                    material: shared.transparent.clone(),
+                    ..default()
+                },
+                bounds,
+            ))
+            .id();
+        let cutout_entity = commands
+            .spawn((
+                MaterialMeshBundle {
+                    mesh: cutout_mesh_handle.clone(),
+                    material: shared.cutout.clone(),
+                    ..default()
+                },
+                bounds,
+            ))
+            .id();
         let entity = commands
-            .spawn(PbrBundle {
-                transform: Transform::from_xyz(
-                    position.x as f32 * 16.,
-                    position.y as f32 * 16.,
-                    position.z as f32 * 16.,
-                ),
-                mesh: mesh_handle.clone(),
-                material: materials.add(StandardMaterial {
-                    base_color_texture: Some(texture_atlas.clone_image()),
+            .spawn((
+                MaterialMeshBundle {
+                    transform: Transform::from_translation(chunk_to_world(position).as_vec3()),
+                    mesh: mesh_handle.clone(),
+                    material: shared.opaque.clone(),
                     ..default()
-                }),
-                ..default()
-            })
+                },
+                bounds,
+            ))
+            .push_children(&[transparent_entity, cutout_entity])
             .id();
         let chunk_entity = ChunkEntity {
             entity,
             chunk: chunk_handle,
             mesh_handle,
+            transparent_entity,
+            transparent_mesh_handle,
+            cutout_entity,
+            cutout_mesh_handle,
+            meshing: None,
+            lod: ChunkLod::Full,
+            mesh_key: None,
         };
-
-        self.chunks.insert(position, chunk_entity);
+        self.insert_chunk_entity(position, chunk_entity, chunks, &blocks);
     }
 
-    /// Regenerate a chunk and its neighbours
-    pub fn regenerate_chunk_at(
+    /// Inserts a [Chunk] at a given [position](IVec3) without meshes or materials, for
+    /// [headless](Chunks::headless) chunks. Its entity only has a [TransformBundle], and the
+    /// transparent and cutout entities and mesh handles are placeholders
+    pub fn insert_chunk_data(
         &mut self,
+        chunk: Chunk,
         position: IVec3,
-        meshes: &mut ResMut<Assets<Mesh>>,
-        texture_atlas_layout: &TextureAtlasLayout,
+        commands: &mut Commands,
         chunks: &mut ResMut<Assets<Chunk>>,
         blocks: Res<Assets<Block>>,
-    ) -> Result<(), ChunkError> {
-        let own_entity = self
-            .chunks
-            .get_mut(&position)
-            .ok_or(ChunkError::ChunkNotFound)?;
+    ) {
+        let entity = commands
+            .spawn(TransformBundle::from_transform(
+                Transform::from_translation(chunk_to_world(position).as_vec3()),
+            ))
+            .id();
+        let chunk_entity = ChunkEntity {
+            entity,
+            chunk: chunks.add(chunk),
+            mesh_handle: Handle::default(),
+            transparent_entity: Entity::PLACEHOLDER,
+            transparent_mesh_handle: Handle::default(),
+            cutout_entity: Entity::PLACEHOLDER,
+            cutout_mesh_handle: Handle::default(),
+            meshing: None,
+            lod: ChunkLod::Full,
+            mesh_key: None,
+        };
+        self.insert_chunk_entity(position, chunk_entity, chunks, &blocks);
+    }
 
-        let own_handle = &mut own_entity.chunk.clone();
-        let mut own = chunks.get(own_handle.to_owned()).unwrap().to_owned();
-        let handle = own_entity.mesh_handle.clone();
-        fn create_and_update_geometry(
-            other_chunk: &mut Chunk,
-            chunk: &mut Chunk,
-            meshes: &mut ResMut<Assets<Mesh>>,
-            texture_atlas_layout: &TextureAtlasLayout,
-            chunk_face: ChunkFace,
-            mesh_handle: Handle<Mesh>,
-            blocks: Res<Assets<Block>>,
-        ) {
-            let chunk_own_indicies = Chunk::get_own_face_indicies(chunk_face);
-            let chunk_other_indicies = Chunk::get_other_face_indicies(chunk_face);
-            let other_chunk_own_indicies = Chunk::get_own_face_indicies(chunk_face.opposite());
-            let other_chunk_other_indicies = Chunk::get_other_face_indicies(chunk_face.opposite());
-            for ((chunk_own, chunk_other), (front_own, front_other)) in
-                chunk_own_indicies.iter().zip(chunk_other_indicies).zip(
-                    other_chunk_own_indicies
-                        .iter()
-                        .zip(other_chunk_other_indicies),
-                )
-            {
-                chunk.blocks[chunk_other as usize] =
-                    other_chunk.blocks[*front_own as usize].clone();
-                other_chunk.blocks[front_other as usize] =
-                    chunk.blocks[*chunk_own as usize].clone();
-                let other_chunk_geometry =
-                    other_chunk.gen_geometry(texture_atlas_layout, Res::clone(&blocks));
-                meshes.insert(mesh_handle.clone(), other_chunk_geometry);
-            }
-        }
+    /// Stores `chunk_entity` and lights it
+    fn insert_chunk_entity(
+        &mut self,
+        position: IVec3,
+        chunk_entity: ChunkEntity,
+        chunks: &Assets<Chunk>,
+        blocks: &Assets<Block>,
+    ) {
+        self.chunks.insert(position, chunk_entity);
+        self.lifecycle.push(ChunkLifecycle::Loaded(position));
+        let relit = self.light.insert_chunk(
+            position,
+            Self::light_properties(&self.chunks, chunks, blocks),
+        );
+        self.remesh_relit(relit);
+    }
 
-        if let Some(front) = self.get_neighbouring_chunk_mut(position, ChunkFace::Front) {
-            let mesh_handle = front.mesh_handle.clone();
-            let front = chunks.get_mut(front.chunk.clone()).unwrap();
-            create_and_update_geometry(
-                front,
-                &mut own,
-                meshes,
-                texture_atlas_layout,
-                ChunkFace::Front,
-                mesh_handle,
-                Res::clone(&blocks),
-            );
-        }
-        if let Some(back) = self.get_neighbouring_chunk_mut(position, ChunkFace::Back) {
-            let mesh_handle = back.mesh_handle.clone();
-            let back = chunks.get_mut(back.chunk.clone()).unwrap();
-            create_and_update_geometry(
-                back,
-                &mut own,
-                meshes,
-                texture_atlas_layout,
-                ChunkFace::Back,
-                mesh_handle,
-                Res::clone(&blocks),
-            );
-        }
-        if let Some(top) = self.get_neighbouring_chunk_mut(position, ChunkFace::Top) {
-            let mesh_handle = top.mesh_handle.clone();
-            let top = chunks.get_mut(top.chunk.clone()).unwrap();
-            create_and_update_geometry(
-                top,
-                &mut own,
-                meshes,
-                texture_atlas_layout,
-                ChunkFace::Top,
-                mesh_handle,
-                Res::clone(&blocks),
-            );
+    /// Sky and block light of the loaded chunks
+    pub fn light(&self) -> &LightEngine {
+        &self.light
+    }
+
+    /// Looks up the [LightProperties] of blocks by world position for the [LightEngine]
+    fn light_properties<'a>(
+        chunk_entities: &'a HashMap<IVec3, ChunkEntity>,
+        chunks: &'a Assets<Chunk>,
+        blocks: &'a Assets<Block>,
+    ) -> impl Fn(IVec3) -> Option<LightProperties> + 'a {
+        move |position| {
+            let (chunk_coords, index) = Self::block_index(position);
+            let chunk = chunks.get(&chunk_entities.get(&chunk_coords)?.chunk)?;
+            blocks
+                .get(&chunk.blocks[index as usize])
+                .map(LightProperties::from)
         }
-        if let Some(bottom) = self.get_neighbouring_chunk_mut(position, ChunkFace::Bottom) {
-            let mesh_handle = bottom.mesh_handle.clone();
-            let bottom = chunks.get_mut(bottom.chunk.clone()).unwrap();
-            create_and_update_geometry(
-                bottom,
-                &mut own,
-                meshes,
-                texture_atlas_layout,
-                ChunkFace::Bottom,
-                mesh_handle,
-                Res::clone(&blocks),
-            );
+    }
+
+    /// Remeshes every chunk in `relit` and the chunks next to them, which show the light at
+    /// their shared border
+    fn remesh_relit(&mut self, relit: HashSet<IVec3>) {
+        let affected: HashSet<IVec3> = relit
+            .into_iter()
+            .flat_map(|position| {
+                [
+                    IVec3::ZERO,
+                    IVec3::X,
+                    IVec3::NEG_X,
+                    IVec3::Y,
+                    IVec3::NEG_Y,
+                    IVec3::Z,
+                    IVec3::NEG_Z,
+                ]
+                .map(|offset| position + offset)
+            })
+            .collect();
+        for position in affected {
+            if self.chunks.contains_key(&position) {
+                self.mesh_tasks.queue(position);
+            }
         }
-        if let Some(right) = self.get_neighbouring_chunk_mut(position, ChunkFace::Right) {
-            let mesh_handle = right.mesh_handle.clone();
-            let right = chunks.get_mut(right.chunk.clone()).unwrap();
-            create_and_update_geometry(
-                right,
-                &mut own,
-                meshes,
-                texture_atlas_layout,
-                ChunkFace::Right,
-                mesh_handle,
-                Res::clone(&blocks),
-            );
+    }
+
+    /// Relights and remeshes every chunk against `texture_atlas` and tracks again which blocks
+    /// rays hit, for when the blocks themselves changed, e.g. after [BlockAtlasRebuilt](cubizm_block::texture_atlas::BlockAtlasRebuilt)
+    pub fn reload_blocks(
+        &mut self,
+        texture_atlas: &BlockAtlas,
+        chunks: &Assets<Chunk>,
+        blocks: &Assets<Block>,
+    ) {
+        self.textures = texture_atlas.texture_mode();
+        self.mesh_tasks.clear_cache();
+        for chunk_entity in self.chunks.values_mut() {
+            chunk_entity.mesh_key = None;
         }
-        if let Some(left) = self.get_neighbouring_chunk_mut(position, ChunkFace::Left) {
-            let mesh_handle = left.mesh_handle.clone();
-            let left = chunks.get_mut(left.chunk.clone()).unwrap();
-            create_and_update_geometry(
-                left,
-                &mut own,
-                meshes,
-                texture_atlas_layout,
-                ChunkFace::Left,
-                mesh_handle,
-                Res::clone(&blocks),
+        self.light = LightEngine::default();
+        let positions: HashSet<IVec3> = self.chunks.keys().copied().collect();
+        for position in positions.iter() {
+            if let Some(chunk) = chunks.get(&self.chunks[position].chunk) {
+                self.occupancy
+                    .insert_chunk(*position, ChunkOccupancy::new(chunk, blocks));
+            }
+            self.light.insert_chunk(
+                *position,
+                Self::light_properties(&self.chunks, chunks, blocks),
             );
         }
+        self.remesh_relit(positions);
+    }
 
-        meshes.insert(
-            handle.clone(),
-            own.gen_geometry(texture_atlas_layout, Res::clone(&blocks)),
+    /// Updates the light around the block at world `position` after it changed and remeshes
+    /// the chunks whose light changed
+    pub(crate) fn relight_block(
+        &mut self,
+        position: IVec3,
+        chunks: &Assets<Chunk>,
+        blocks: &Assets<Block>,
+    ) {
+        let relit = self.light.update_block(
+            position,
+            Self::light_properties(&self.chunks, chunks, blocks),
         );
+        self.remesh_relit(relit);
+    }
 
-        let own_entity = self
-            .chunks
-            .get_mut(&position)
-            .ok_or(ChunkError::ChunkNotFound)?;
-        chunks.insert(own_handle.to_owned(), own);
-        own_handle.clone_into(&mut own_entity.chunk);
-        own_entity.mesh_handle = handle;
+    /// Remeshes a chunk and its loaded neighbours, which mesh the blocks along their shared
+    /// borders. The new meshes are generated in the background and replace the old ones once
+    /// ready
+    pub fn regenerate_chunk_at(&mut self, position: IVec3) -> Result<(), ChunkError> {
+        if !self.chunks.contains_key(&position) {
+            return Err(ChunkError::ChunkNotFound);
+        }
+        self.mesh_tasks.queue(position);
+        for face in ChunkFace::ALL {
+            let neighbour = position + face.offset();
+            if self.chunks.contains_key(&neighbour) {
+                self.mesh_tasks.queue(neighbour);
+            }
+        }
         Ok(())
     }
 
     /// Insert a [Chunk] and regenerate neighbours.
     /// Essentially it will regenerate meshes for all adjacent chunks
-    /// This is needed as block face is only meshed if it exposed to air, and upon insertion a chunk
-    /// assumes it is surrounded by air and will be updated by its neighbours
+    /// This is needed as block face is only meshed if it exposed to air, and the neighbours
+    /// meshed their border with the new chunk as air before it was inserted
     #[allow(clippy::too_many_arguments)]
     pub fn insert_chunk_and_regenerate(
         &mut self,
@@ -280,7 +811,7 @@ impl Chunks {
         position: IVec3,
         commands: &mut Commands,
         meshes: &mut ResMut<Assets<Mesh>>,
-        materials: &mut ResMut<Assets<StandardMaterial>>,
+        materials: &mut ResMut<Assets<ChunkMaterial>>,
         texture_atlas: Res<BlockAtlas>,
         chunks: &mut ResMut<Assets<Chunk>>,
         blocks: Res<Assets<Block>>,
@@ -296,39 +827,321 @@ impl Chunks {
             Res::clone(&blocks),
         );
 
-        self.regenerate_chunk_at(
-            position,
-            meshes,
-            texture_atlas.get_texture_atlas_layout(),
-            chunks,
-            blocks,
+        self.regenerate_chunk_at(position).unwrap();
+    }
+
+    /// The chunk holding the block at world `position` and the block's index in [ChunkShape],
+    /// see [world_to_chunk]
+    pub(crate) fn block_index(position: IVec3) -> (IVec3, u32) {
+        (
+            world_to_chunk(position),
+            ChunkShape::linearize(world_to_local(position).to_array()),
         )
-        .unwrap();
     }
 
+    /// World position of the highest loaded block in the world `column` that isn't air, `None`
+    /// if there is none
+    pub fn highest_block(
+        &self,
+        column: IVec2,
+        chunks: &Assets<Chunk>,
+        blocks: &Assets<Block>,
+    ) -> Option<IVec3> {
+        let chunk_column = world_to_chunk(column.extend(0).xzy()).xz();
+        let mut loaded = self
+            .chunks
+            .iter()
+            .filter(|(position, _)| position.xz() == chunk_column)
+            .collect::<Vec<_>>();
+        loaded.sort_by_key(|(position, _)| std::cmp::Reverse(position.y));
+        loaded.into_iter().find_map(|(position, chunk_entity)| {
+            let chunk = chunks.get(&chunk_entity.chunk)?;
+            column_in_chunk(*position, column).find_map(|block| {
+                let (_, index) = Self::block_index(block);
+                let definition = blocks.get(&chunk.blocks[index as usize])?;
+                (definition.get_visibility() != VoxelVisibility::Empty).then_some(block)
+            })
+        })
+    }
+
+    /// The block at world `position`
+    pub fn get_block(
+        &self,
+        position: IVec3,
+        chunks: &Assets<Chunk>,
+    ) -> Result<Handle<Block>, ChunkError> {
+        let (chunk_coords, index) = Self::block_index(position);
+        let chunk = self
+            .chunks
+            .get(&chunk_coords)
+            .and_then(|chunk_entity| chunks.get(&chunk_entity.chunk))
+            .ok_or(ChunkError::ChunkNotFound)?;
+        Ok(chunk.blocks[index as usize].clone())
+    }
+
+    /// Every loaded block from `min` to `max` inclusive with its world position.
+    /// Each chunk is only looked up once
+    pub fn get_blocks_in_aabb(
+        &self,
+        min: IVec3,
+        max: IVec3,
+        chunks: &Assets<Chunk>,
+    ) -> Vec<(IVec3, Handle<Block>)> {
+        let (min, max) = (min.min(max), min.max(max));
+        let (min_chunk, max_chunk) = (world_to_chunk(min), world_to_chunk(max));
+        let mut found = Vec::new();
+        for chunk_x in min_chunk.x..=max_chunk.x {
+            for chunk_y in min_chunk.y..=max_chunk.y {
+                for chunk_z in min_chunk.z..=max_chunk.z {
+                    let chunk_coords = IVec3::new(chunk_x, chunk_y, chunk_z);
+                    let Some(chunk) = self
+                        .chunks
+                        .get(&chunk_coords)
+                        .and_then(|chunk_entity| chunks.get(&chunk_entity.chunk))
+                    else {
+                        continue;
+                    };
+                    let origin = chunk_to_world(chunk_coords);
+                    let chunk_min = (origin + IVec3::ONE).max(min);
+                    let chunk_max = (origin + IVec3::splat(CHUNK_SIZE as i32)).min(max);
+                    for x in chunk_min.x..=chunk_max.x {
+                        for y in chunk_min.y..=chunk_max.y {
+                            for z in chunk_min.z..=chunk_max.z {
+                                let position = IVec3::new(x, y, z);
+                                let local = world_to_local(position);
+                                let index = ChunkShape::linearize(local.to_array());
+                                found.push((position, chunk.blocks[index as usize].clone()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Replaces the block at world `position` and sends a [BlockChanged] for it.
+    /// The chunk is remeshed once at the end of the frame, however many of its blocks changed
     pub fn set_block(
         &mut self,
         position: IVec3,
         block: Handle<Block>,
-        blocks: Res<Assets<Block>>,
-        meshes: &mut ResMut<Assets<Mesh>>,
-        texture_atlas_layout: &TextureAtlasLayout,
-        chunks: &mut ResMut<Assets<Chunk>>,
+        chunks: &mut Assets<Chunk>,
+        events: &mut EventWriter<BlockChanged>,
+    ) -> Result<(), ChunkError> {
+        let (chunk_coords, index) = Self::block_index(position);
+        let chunk = self
+            .chunks
+            .get_mut(&chunk_coords)
+            .ok_or(ChunkError::ChunkNotFound)?;
+        let chunk = chunks
+            .get_mut(&chunk.chunk)
+            .ok_or(ChunkError::ChunkNotFound)?;
+        let old = std::mem::replace(&mut chunk.blocks[index as usize], block.clone());
+        // The state and data belonged to the block that was there
+        if old != block {
+            chunk.set_state(index as usize, BlockState::default());
+            chunk.tile_data.remove(world_to_local(position));
+        }
+        self.occupancy.set(position, true);
+        self.mark_dirty(chunk_coords);
+        events.send(BlockChanged {
+            world_pos: position,
+            old,
+            new: block,
+        });
+        Ok(())
+    }
+
+    /// Updates whether rays hit the block at world `position` after it changed. Until then
+    /// [set_block](Chunks::set_block) marks it occupied, so raycasts never skip it
+    pub(crate) fn update_occupancy(
+        &mut self,
+        position: IVec3,
+        chunks: &Assets<Chunk>,
+        blocks: &Assets<Block>,
+    ) {
+        let hit = self
+            .get_block(position, chunks)
+            .is_ok_and(|block| blocks.get(&block).is_none_or(Block::is_hit_by_rays));
+        self.occupancy.set(position, hit);
+    }
+
+    /// Replaces many blocks at once, touching each chunk's data once and sending a
+    /// [BlockChanged] only for blocks that actually change. Edits are applied in order, those in
+    /// chunks that are not loaded are skipped. Every changed chunk is remeshed once at the end
+    /// of the frame, like with [set_block](Chunks::set_block). Returns how many blocks changed
+    pub fn set_blocks(
+        &mut self,
+        edits: impl IntoIterator<Item = (IVec3, Handle<Block>)>,
+        chunks: &mut Assets<Chunk>,
+        events: &mut EventWriter<BlockChanged>,
+    ) -> usize {
+        let mut by_chunk: HashMap<IVec3, Vec<(u32, IVec3, Handle<Block>)>> = HashMap::new();
+        for (position, block) in edits {
+            let (chunk_coords, index) = Self::block_index(position);
+            by_chunk
+                .entry(chunk_coords)
+                .or_default()
+                .push((index, position, block));
+        }
+        let mut changed = 0;
+        for (chunk_coords, edits) in by_chunk {
+            let Some(chunk) = self
+                .chunks
+                .get(&chunk_coords)
+                .and_then(|chunk_entity| chunks.get_mut(&chunk_entity.chunk))
+            else {
+                continue;
+            };
+            let mut chunk_changed = false;
+            for (index, position, block) in edits {
+                let current = &mut chunk.blocks[index as usize];
+                if *current == block {
+                    continue;
+                }
+                let old = std::mem::replace(current, block.clone());
+                chunk.set_state(index as usize, BlockState::default());
+                chunk.tile_data.remove(world_to_local(position));
+                self.occupancy.set(position, true);
+                events.send(BlockChanged {
+                    world_pos: position,
+                    old,
+                    new: block,
+                });
+                chunk_changed = true;
+                changed += 1;
+            }
+            if chunk_changed {
+                self.mark_dirty(chunk_coords);
+            }
+        }
+        changed
+    }
+
+    /// The [BlockState] of the block at world `position`
+    pub fn get_block_state(
+        &self,
+        position: IVec3,
+        chunks: &Assets<Chunk>,
+    ) -> Result<BlockState, ChunkError> {
+        let (chunk_coords, index) = Self::block_index(position);
+        let chunk = self
+            .chunks
+            .get(&chunk_coords)
+            .and_then(|chunk_entity| chunks.get(&chunk_entity.chunk))
+            .ok_or(ChunkError::ChunkNotFound)?;
+        Ok(chunk.state(index as usize))
+    }
+
+    /// Changes the [BlockState] of the block at world `position` and remeshes its chunk. The
+    /// state is reset whenever the block is replaced
+    pub fn set_block_state(
+        &mut self,
+        position: IVec3,
+        state: BlockState,
+        chunks: &mut Assets<Chunk>,
     ) -> Result<(), ChunkError> {
-        let chunk_coords = position / 16;
-        let relative_coords = position - chunk_coords * 16;
+        let (chunk_coords, index) = Self::block_index(position);
         let chunk = self
             .chunks
-            .get_mut(&(position / 16))
+            .get(&chunk_coords)
+            .and_then(|chunk_entity| chunks.get_mut(&chunk_entity.chunk))
             .ok_or(ChunkError::ChunkNotFound)?;
-        let index = ChunkShape::linearize([
-            relative_coords.x as u32,
-            relative_coords.y as u32,
-            relative_coords.z as u32,
-        ]);
-        let chunk = chunks.get_mut(chunk.chunk.clone()).unwrap();
-        chunk.blocks[index as usize] = block;
-        self.regenerate_chunk_at(chunk_coords, meshes, texture_atlas_layout, chunks, blocks)?;
+        if chunk.state(index as usize) == state {
+            return Ok(());
+        }
+        chunk.set_state(index as usize, state);
+        self.mark_dirty(chunk_coords);
+        self.regenerate_chunk_at(chunk_coords)
+    }
+
+    /// The [TileEntityData](crate::TileEntityData) of the block at world `position` read as `T`, `None` if the block
+    /// has none
+    pub fn tile_data<T: DeserializeOwned>(
+        &self,
+        position: IVec3,
+        chunks: &Assets<Chunk>,
+    ) -> Result<Option<T>, TileDataError> {
+        let chunk = self
+            .chunks
+            .get(&world_to_chunk(position))
+            .and_then(|chunk_entity| chunks.get(&chunk_entity.chunk))
+            .ok_or(ChunkError::ChunkNotFound)?;
+        chunk.tile_data.get(world_to_local(position))
+    }
+
+    /// Stores `data` for the block at world `position`, replacing what it had. The data is
+    /// saved with the chunk and dropped once the block is replaced
+    pub fn set_tile_data<T: Serialize>(
+        &mut self,
+        position: IVec3,
+        data: &T,
+        chunks: &mut Assets<Chunk>,
+    ) -> Result<(), TileDataError> {
+        let chunk_coords = world_to_chunk(position);
+        let chunk = self
+            .chunks
+            .get(&chunk_coords)
+            .and_then(|chunk_entity| chunks.get_mut(&chunk_entity.chunk))
+            .ok_or(ChunkError::ChunkNotFound)?;
+        chunk.tile_data.set(world_to_local(position), data)?;
+        self.mark_dirty(chunk_coords);
         Ok(())
     }
+
+    /// Drops the [TileEntityData](crate::TileEntityData) of the block at world `position`. Returns whether it had any
+    pub fn remove_tile_data(
+        &mut self,
+        position: IVec3,
+        chunks: &mut Assets<Chunk>,
+    ) -> Result<bool, ChunkError> {
+        let chunk_coords = world_to_chunk(position);
+        let chunk = self
+            .chunks
+            .get(&chunk_coords)
+            .and_then(|chunk_entity| chunks.get_mut(&chunk_entity.chunk))
+            .ok_or(ChunkError::ChunkNotFound)?;
+        let removed = chunk.tile_data.remove(world_to_local(position));
+        if removed {
+            self.mark_dirty(chunk_coords);
+        }
+        Ok(removed)
+    }
+}
+
+/// World positions of the blocks of the world `column` inside the chunk at `chunk`, top down
+fn column_in_chunk(chunk: IVec3, column: IVec2) -> impl Iterator<Item = IVec3> {
+    let local = world_to_local(column.extend(0).xzy());
+    (0..CHUNK_SIZE)
+        .rev()
+        .map(move |y| local_to_world(chunk, UVec3::new(local.x, y, local.z)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_stays_inside_the_chunk() {
+        for chunk in [IVec3::ZERO, IVec3::new(-1, -1, 2), IVec3::new(3, 4, -5)] {
+            let column = chunk_to_world(chunk).xz() + IVec2::new(1, CHUNK_SIZE as i32);
+            let blocks = column_in_chunk(chunk, column).collect::<Vec<_>>();
+            assert_eq!(blocks.len(), CHUNK_SIZE as usize);
+            assert!(blocks.iter().all(|block| world_to_chunk(*block) == chunk));
+            assert!(blocks.iter().all(|block| block.xz() == column));
+            assert!(blocks.windows(2).all(|pair| pair[0].y == pair[1].y + 1));
+        }
+    }
+
+    #[test]
+    fn finds_single_block_at_the_top_of_a_chunk() {
+        for chunk in [IVec3::ZERO, IVec3::new(0, -1, 0), IVec3::new(-2, 3, 1)] {
+            let top = local_to_world(chunk, UVec3::new(4, CHUNK_SIZE - 1, 9));
+            assert_eq!(top.y, chunk_to_world(chunk).y + CHUNK_SIZE as i32);
+            let found = column_in_chunk(chunk, top.xz()).find(|block| *block == top);
+            assert_eq!(found, Some(top));
+            assert_eq!(column_in_chunk(chunk, top.xz()).next(), Some(top));
+        }
+    }
 }
@@ -1,22 +1,48 @@
 use std::fmt::Debug;
 
-use bevy::asset::{Handle, LoadedFolder};
+use bevy::asset::{Handle, LoadState, LoadedFolder, UntypedAssetId};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::utils::HashSet;
 
-use crate::chunk::Chunk;
+use crate::chunk::{Chunk, ChunkLod, ChunkVertexFormat, MeshingMode};
+use crate::diagnostics::ChunkDiagnosticsPlugin;
+use crate::fluid::FluidPlugin;
+use crate::gizmos::{draw_chunk_gizmos, ChunkGizmos};
+use crate::impostor::{build_impostors, cull_impostors, Impostors};
+use crate::material::{use_block_atlas, ChunkMaterial, ChunkMaterialPlugin};
+use crate::occlusion::{update_chunk_connectivity, update_visible_chunks, CaveCulling};
+use crate::persistence::EntityPersistencePlugin;
+use crate::population::PopulationPlugin;
+use crate::save::WorldSaverPlugin;
+use crate::streaming::ChunkStreamingPlugin;
+use crate::tick::BlockTickPlugin;
+use crate::tile_entity::TileEntityPlugin;
+use crate::worlds::{switch_world, SwitchWorld};
+use crate::{
+    ActiveWorld, Biome, BiomeLoader, BiomeTints, ExportWorldMap, FarTerrainDistance, Schematic,
+    SchematicLoader, StructurePass, WorldBiomes, WorldGeneration, WorldHeightmap, WorldId,
+    WorldManifest, WorldManifestLoader, WorldSaver, Worlds,
+};
 use cubizm_block::definition::Block;
-use cubizm_block::texture_atlas::BlockAtlas;
+use cubizm_block::texture_atlas::{BlockAtlas, BlockAtlasRebuilt};
+use cubizm_block::BlockRegistry;
 
-use cubizm_core::AppState;
+use cubizm_core::mods::ModPacks;
+use cubizm_core::{point_to_block, point_to_chunk, world_to_chunk, AppState, LoadingStateApp};
 
-use definition::*;
+pub use cubizm_core::RenderDistance;
+pub use definition::*;
 
 mod definition;
 
+/// Steps of loading the world, scoped to [AppState::BlocksLoaded]. Run again by
+/// [SwitchWorld] for worlds that never loaded
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, States)]
-enum ChunkLoadingState {
+pub(crate) enum ChunkLoadingState {
     #[default]
     Pending,
+    LoadManifest,
     LoadChunks,
     Finished,
 }
@@ -24,47 +50,275 @@ enum ChunkLoadingState {
 #[derive(Resource, Default)]
 pub struct ChunksFolder(Handle<LoadedFolder>);
 
-fn load_chunks(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(ChunksFolder(asset_server.load_folder("world/chunks")));
+/// Distance in chunks from the active camera beyond which chunks are meshed at a lower
+/// [ChunkLod], `None` to keep them at the higher one
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkLodDistances {
+    pub half: Option<u32>,
+    pub quarter: Option<u32>,
+}
+
+impl Default for ChunkLodDistances {
+    fn default() -> Self {
+        Self {
+            half: Some(3),
+            quarter: Some(6),
+        }
+    }
+}
+
+impl ChunkLodDistances {
+    /// The [ChunkLod] of a chunk `distance` chunks away from the camera
+    pub fn lod(&self, distance: u32) -> ChunkLod {
+        let beyond = |limit: Option<u32>| limit.is_some_and(|limit| distance > limit);
+        if beyond(self.quarter) {
+            ChunkLod::Quarter
+        } else if beyond(self.half) {
+            ChunkLod::Half
+        } else {
+            ChunkLod::Full
+        }
+    }
+}
+
+/// Where [ChunksPlugin] loads the world from
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct ChunksPluginSettings {
+    /// Asset path of the [WorldManifest] of [WorldId::default], whose chunk directory is
+    /// also where the [WorldSaver] writes chunks back to
+    pub world_path: String,
+    /// Folder of `.biome` files in every mod, which worlds generated from biomes pick from
+    /// along with their own
+    pub biomes_path: String,
+    /// Folder of `.population` files in the base assets and every mod, see
+    /// [PopulationRule](crate::PopulationRule)
+    pub population_path: String,
+    /// Worlds besides the one at [world_path](ChunksPluginSettings::world_path), which is
+    /// [WorldId::default], by id and asset path of their manifest. See [Worlds]
+    pub worlds: Vec<(WorldId, String)>,
+}
+
+impl Default for ChunksPluginSettings {
+    fn default() -> Self {
+        Self {
+            world_path: "world/default.world.ron".to_string(),
+            biomes_path: "biomes".to_string(),
+            population_path: "population".to_string(),
+            worlds: Vec::new(),
+        }
+    }
+}
+
+fn load_world_manifest(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut worlds: ResMut<Worlds>,
+) {
+    commands.insert_resource(ActiveWorld(worlds.load_active_manifest(&asset_server)));
+}
+
+fn check_world_manifest(
+    mut next_state: ResMut<NextState<ChunkLoadingState>>,
+    world: Res<ActiveWorld>,
+    asset_server: Res<AssetServer>,
+) {
+    match asset_server.load_state(&world.0) {
+        LoadState::Loaded => next_state.set(ChunkLoadingState::LoadChunks),
+        LoadState::Failed => {
+            error!("World manifest {:?} failed to load", world.0.path());
+            next_state.set(ChunkLoadingState::Pending);
+        }
+        _ => {}
+    }
+}
+
+fn load_chunks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    world: Res<ActiveWorld>,
+    manifests: Res<Assets<WorldManifest>>,
+    settings: Res<ChunksPluginSettings>,
+    mods: Option<Res<ModPacks>>,
+    mut saver: ResMut<WorldSaver>,
+) {
+    let manifest = manifests.get(&world.0).unwrap();
+    info!("Loading world {}", manifest.name);
+    saver.set_chunk_directory(&manifest.chunk_directory);
+    commands.insert_resource(ChunksFolder(
+        asset_server.load_folder(manifest.chunk_directory.clone()),
+    ));
+    commands.insert_resource(StructurePass::new(
+        manifest
+            .generator
+            .structures
+            .iter()
+            .map(|rule| asset_server.load(rule.schematic.clone()))
+            .collect(),
+    ));
+    let biomes = manifest.generator.kind.biomes();
+    // Worlds that don't generate biomes don't get those of mods either
+    let mod_biomes = mods
+        .iter()
+        .filter(|_| !biomes.is_empty())
+        .flat_map(|mods| mods.iter())
+        .flat_map(|pack| pack.files(&settings.biomes_path, "biome"));
+    commands.insert_resource(WorldBiomes(
+        biomes
+            .iter()
+            .cloned()
+            .chain(mod_biomes)
+            .map(|path| asset_server.load(path))
+            .collect(),
+    ));
+    commands.insert_resource(WorldHeightmap(
+        manifest
+            .generator
+            .kind
+            .heightmap()
+            .map(|path| asset_server.load(path.to_string())),
+    ));
 }
 
 fn check_chunk(
     mut next_state: ResMut<NextState<ChunkLoadingState>>,
-    block_info_folder: Res<ChunksFolder>,
-    mut events: EventReader<AssetEvent<LoadedFolder>>,
+    chunks_folder: Res<ChunksFolder>,
+    structures: Res<StructurePass>,
+    biomes: Res<WorldBiomes>,
+    heightmap: Res<WorldHeightmap>,
+    asset_server: Res<AssetServer>,
 ) {
-    for event in events.read() {
-        if event.is_loaded_with_dependencies(&block_info_folder.0) {
-            next_state.set(ChunkLoadingState::Finished);
+    // Polled rather than waiting for the folder's event, as a world switched back to may
+    // still be loaded. A world that was never saved has no chunk directory yet, it is
+    // generated instead. Structures, biomes and heightmaps whose file failed to load are left
+    // out
+    let done = |handle: UntypedAssetId| {
+        asset_server.is_loaded_with_dependencies(handle)
+            || asset_server.load_state(handle) == LoadState::Failed
+    };
+    if done(chunks_folder.0.id().untyped())
+        && structures
+            .schematics()
+            .iter()
+            .all(|schematic| done(schematic.id().untyped()))
+        && biomes.0.iter().all(|biome| done(biome.id().untyped()))
+        && heightmap.0.iter().all(|image| done(image.id().untyped()))
+    {
+        next_state.set(ChunkLoadingState::Finished);
+    }
+}
+
+/// Where the chunks of the active world come from, its saved chunks and the generator of its
+/// [WorldManifest]
+#[derive(SystemParam)]
+struct WorldChunkSources<'w> {
+    loaded_folders: Res<'w, Assets<LoadedFolder>>,
+    chunk_handles: Res<'w, ChunksFolder>,
+    world: Res<'w, ActiveWorld>,
+    manifests: Res<'w, Assets<WorldManifest>>,
+    registry: Res<'w, BlockRegistry>,
+    saver: ResMut<'w, WorldSaver>,
+    structures: ResMut<'w, StructurePass>,
+    schematics: Res<'w, Assets<Schematic>>,
+    biomes: Res<'w, WorldBiomes>,
+    biome_assets: Res<'w, Assets<Biome>>,
+    heightmap: Res<'w, WorldHeightmap>,
+    /// Missing when [headless](ChunksPlugin::headless) without an `ImagePlugin`
+    images: Option<Res<'w, Assets<Image>>>,
+}
+
+impl WorldChunkSources<'_> {
+    /// The saved chunks, then the chunks generated around the spawn where none was saved
+    fn chunks(&mut self, assets_chunks: &Assets<Chunk>) -> Vec<Chunk> {
+        let mut chunks = Vec::new();
+        let mut saved_positions = HashSet::new();
+        let saved = self
+            .loaded_folders
+            .get(&self.chunk_handles.0)
+            .map(|folder| folder.handles.as_slice())
+            .unwrap_or_default();
+        for handle in saved {
+            let chunk_id = handle.id().typed_unchecked::<Chunk>();
+            let Some(chunk) = assets_chunks.get(chunk_id) else {
+                warn!(
+                    "{:?} did not resolve to an `Chunk` asset.",
+                    handle.path().unwrap()
+                );
+                continue;
+            };
+            if let Some(file) = handle
+                .path()
+                .and_then(|path| path.path().file_name())
+                .and_then(|file| file.to_str())
+            {
+                self.saver.set_file(chunk.position, file);
+            }
+            saved_positions.insert(chunk.position);
+            chunks.push(chunk.to_owned());
+        }
+
+        // Generated chunks are not marked dirty, they are only saved once edited
+        let manifest = self.manifests.get(&self.world.0).unwrap();
+        let generator = &manifest.generator;
+        let spawn_chunk = world_to_chunk(point_to_block(manifest.spawn));
+        let generation = WorldGeneration {
+            seed: manifest.seed,
+            settings: generator,
+            registry: &self.registry,
+            biomes: generator
+                .kind
+                .biome_map(manifest.seed, &self.biomes.0, &self.biome_assets),
+            heightmap: self
+                .heightmap
+                .0
+                .as_ref()
+                .zip(self.images.as_ref())
+                .and_then(|(image, images)| images.get(image)),
+            schematics: &self.schematics,
+        };
+        let positions: Vec<IVec3> = generator
+            .chunk_positions(spawn_chunk)
+            .into_iter()
+            .filter(|position| !saved_positions.contains(position))
+            .collect();
+        let generated = generation.generate(&positions, &mut self.structures);
+        for position in saved_positions {
+            self.structures.discard(position);
         }
+        chunks.extend(generated);
+        chunks
+    }
+
+    /// The colours of the biomes of the world's generator, for tinting its blocks
+    fn biome_tints(&self) -> BiomeTints {
+        let manifest = self.manifests.get(&self.world.0).unwrap();
+        BiomeTints::new(&manifest.generator.kind.biome_map(
+            manifest.seed,
+            &self.biomes.0,
+            &self.biome_assets,
+        ))
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn create_chunk_resource(
     mut commands: Commands,
-    loaded_folders: Res<Assets<LoadedFolder>>,
-    chunk_handles: Res<ChunksFolder>,
+    mut sources: WorldChunkSources,
     mut assets_chunks: ResMut<Assets<Chunk>>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<ChunkMaterial>>,
     texture_atlas: Res<BlockAtlas>,
     blocks: Res<Assets<Block>>,
+    meshing: Res<MeshingMode>,
+    vertex_format: Res<ChunkVertexFormat>,
 ) {
-    let mut chunks = Chunks::new();
-    let loaded_folder = loaded_folders.get(&chunk_handles.0).unwrap();
-    for handle in loaded_folder.handles.iter() {
-        let chunk_id = handle.id().typed_unchecked::<Chunk>();
-        let Some(chunk) = assets_chunks.get(chunk_id) else {
-            warn!(
-                "{:?} did not resolve to an `Chunk` asset.",
-                handle.path().unwrap()
-            );
-            continue;
-        };
-
+    let mut chunks = Chunks::with_meshing_mode(*meshing);
+    chunks.set_vertex_format(*vertex_format);
+    chunks.set_biome_tints(sources.biome_tints());
+    for chunk in sources.chunks(&assets_chunks) {
+        let position = chunk.position;
         chunks.insert_chunk_and_regenerate(
-            chunk.to_owned(),
-            chunk.position,
+            chunk,
+            position,
             &mut commands,
             &mut meshes,
             &mut materials,
@@ -76,29 +330,315 @@ fn create_chunk_resource(
     commands.insert_resource(chunks);
 }
 
+/// [create_chunk_resource] for [ChunksPlugin::headless], without meshes or materials
+fn create_headless_chunk_resource(
+    mut commands: Commands,
+    mut sources: WorldChunkSources,
+    mut assets_chunks: ResMut<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+) {
+    let mut chunks = Chunks::headless();
+    for chunk in sources.chunks(&assets_chunks) {
+        let position = chunk.position;
+        chunks.insert_chunk_data(
+            chunk,
+            position,
+            &mut commands,
+            &mut assets_chunks,
+            Res::clone(&blocks),
+        );
+    }
+    commands.insert_resource(chunks);
+}
+
+/// Hides chunks beyond the [RenderDistance] and those [CaveCulling] found hidden
+fn cull_distant_chunks(
+    render_distance: Res<RenderDistance>,
+    culling: Res<CaveCulling>,
+    chunks: Res<Chunks>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    let Some((_, camera)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let camera_chunk = point_to_chunk(camera.translation());
+    let radius = render_distance.0 as i32;
+
+    for (position, chunk_entity) in chunks.chunks.iter() {
+        let Ok(mut visibility) = visibilities.get_mut(chunk_entity.entity) else {
+            continue;
+        };
+        let distance = (*position - camera_chunk).abs().max_element();
+        visibility.set_if_neq(match distance <= radius && culling.is_visible(*position) {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        });
+    }
+}
+
+/// Remeshes chunks whose [ChunkLod] changed as the active camera moved
+fn update_chunk_lods(
+    lod_distances: Res<ChunkLodDistances>,
+    mut chunks: ResMut<Chunks>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    let Some((_, camera)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let camera_chunk = point_to_chunk(camera.translation());
+    let changed = chunks
+        .chunks
+        .iter()
+        .filter_map(|(position, chunk_entity)| {
+            let distance = (*position - camera_chunk).abs().max_element() as u32;
+            let lod = lod_distances.lod(distance);
+            (lod != chunk_entity.lod).then_some((*position, lod))
+        })
+        .collect::<Vec<_>>();
+    // Swapping meshes does not change the chunk data, don't wake up systems watching it
+    let chunks = chunks.bypass_change_detection();
+    for (position, lod) in changed {
+        if let Err(err) = chunks.set_chunk_lod(position, lod) {
+            warn!("Failed to change the level of detail of chunk {position}: {err}");
+        }
+    }
+}
+
+/// Starts meshing the queued chunks nearest to the active camera within the [RemeshBudget]
+fn dispatch_chunk_meshes(
+    budget: Res<RemeshBudget>,
+    mut chunks: ResMut<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    texture_atlas: Res<BlockAtlas>,
+    blocks: Res<Assets<Block>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    let camera_chunk = cameras
+        .iter()
+        .find(|(camera, _)| camera.is_active)
+        .map(|(_, camera)| point_to_chunk(camera.translation()))
+        .unwrap_or(IVec3::ZERO);
+    // Starting meshes does not change the chunk data, don't wake up systems watching it
+    chunks.bypass_change_detection().dispatch_mesh_tasks(
+        &budget,
+        camera_chunk,
+        texture_atlas.get_texture_atlas_layout(),
+        &assets_chunks,
+        &blocks,
+    );
+}
+
+fn poll_chunk_meshes(
+    mut commands: Commands,
+    mut chunks: ResMut<Chunks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    // Finishing a mesh does not change the chunk data, don't wake up systems watching it
+    chunks
+        .bypass_change_detection()
+        .poll_mesh_tasks(&mut commands, &mut meshes);
+}
+
+/// Updates the occupancy of and relights around every changed block, then regenerates every
+/// chunk with changed blocks once, along with its neighbours
+fn remesh_changed_chunks(
+    mut events: EventReader<BlockChanged>,
+    mut chunks: ResMut<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+) {
+    let mut changed = HashSet::new();
+    for event in events.read() {
+        chunks.update_occupancy(event.world_pos, &assets_chunks, &blocks);
+        chunks.relight_block(event.world_pos, &assets_chunks, &blocks);
+        changed.insert(world_to_chunk(event.world_pos));
+    }
+    for position in changed {
+        if let Err(err) = chunks.regenerate_chunk_at(position) {
+            warn!("Failed to remesh chunk {position}: {err}");
+        }
+    }
+}
+
+/// Points the chunk materials at the rebuilt [BlockAtlas] and relights and remeshes every chunk
+/// with the reloaded blocks
+fn reload_chunk_blocks(
+    mut events: EventReader<BlockAtlasRebuilt>,
+    mut chunks: ResMut<Chunks>,
+    mut materials: ResMut<Assets<ChunkMaterial>>,
+    assets_chunks: Res<Assets<Chunk>>,
+    texture_atlas: Res<BlockAtlas>,
+    blocks: Res<Assets<Block>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    for (_, material) in materials.iter_mut() {
+        use_block_atlas(material, &texture_atlas);
+    }
+    chunks.reload_blocks(&texture_atlas, &assets_chunks, &blocks);
+}
+
+fn apply_meshing_mode(meshing: Res<MeshingMode>, mut chunks: ResMut<Chunks>) {
+    if meshing.is_changed() && *meshing != chunks.meshing_mode() {
+        chunks.set_meshing_mode(*meshing);
+    }
+}
+
+fn apply_vertex_format(format: Res<ChunkVertexFormat>, mut chunks: ResMut<Chunks>) {
+    if format.is_changed() && *format != chunks.vertex_format() {
+        chunks.set_vertex_format(*format);
+    }
+}
+
+fn send_chunk_lifecycle(
+    mut chunks: ResMut<Chunks>,
+    mut loaded: EventWriter<ChunkLoaded>,
+    mut meshed: EventWriter<ChunkMeshed>,
+    mut unloaded: EventWriter<ChunkUnloaded>,
+) {
+    // Sending the events does not change the chunks
+    for event in chunks.bypass_change_detection().take_lifecycle() {
+        match event {
+            ChunkLifecycle::Loaded(position) => {
+                loaded.send(ChunkLoaded(position));
+            }
+            ChunkLifecycle::Meshed(position) => {
+                meshed.send(ChunkMeshed(position));
+            }
+            ChunkLifecycle::Unloaded(position) => {
+                unloaded.send(ChunkUnloaded(position));
+            }
+        }
+    }
+}
+
 fn move_to_loaded_chunks(mut next_state: ResMut<NextState<AppState>>) {
     next_state.set(AppState::ChunksLoaded);
 }
 
-fn begin_loading_chunks(mut next_state: ResMut<NextState<ChunkLoadingState>>) {
-    next_state.set(ChunkLoadingState::LoadChunks);
+#[derive(Default)]
+pub struct ChunksPlugin {
+    /// Initial value of the [MeshingMode] resource, which can be changed at runtime
+    pub meshing: MeshingMode,
+    /// Initial value of the [ChunkVertexFormat] resource, which can be changed at runtime
+    pub vertex_format: ChunkVertexFormat,
+    pub settings: ChunksPluginSettings,
+    /// Only loads, lights and saves the chunk data, without meshing or drawing anything, for
+    /// running without a renderer, e.g. a dedicated server on `MinimalPlugins`. Needs
+    /// [BlockPlugin::headless](cubizm_block::BlockPlugin::headless) as well
+    pub headless: bool,
 }
 
-pub struct ChunksPlugin;
 impl Plugin for ChunksPlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<ChunkLoadingState>()
-            .init_asset::<Chunk>()
-            .init_asset_loader::<crate::chunk::ChunkLoader>()
-            .add_systems(OnEnter(AppState::BlocksLoaded), begin_loading_chunks)
-            .add_systems(OnEnter(ChunkLoadingState::LoadChunks), load_chunks)
-            .add_systems(
-                Update,
-                check_chunk.run_if(in_state(ChunkLoadingState::LoadChunks)),
+        app.add_plugins((
+            WorldSaverPlugin,
+            EntityPersistencePlugin,
+            PopulationPlugin,
+            ChunkStreamingPlugin,
+            ChunkDiagnosticsPlugin,
+            BlockTickPlugin::default(),
+            FluidPlugin,
+        ))
+        .insert_resource(self.meshing)
+        .insert_resource(self.vertex_format)
+        .insert_resource(self.settings.clone())
+        .insert_resource(Worlds::new(&self.settings))
+        .add_loading_state(AppState::BlocksLoaded, ChunkLoadingState::LoadManifest)
+        .init_asset::<Chunk>()
+        .register_asset_reflect::<Chunk>()
+        .register_type::<Chunks>()
+        .register_type::<ChunkEntity>()
+        .add_event::<BlockChanged>()
+        .add_event::<BlockInteract>()
+        .init_asset::<WorldManifest>()
+        .init_asset_loader::<crate::chunk::ChunkLoader>()
+        .init_asset_loader::<crate::chunk::BinaryChunkLoader>()
+        .init_asset_loader::<WorldManifestLoader>()
+        .init_asset::<Schematic>()
+        .init_asset_loader::<SchematicLoader>()
+        .init_resource::<StructurePass>()
+        .init_asset::<Biome>()
+        .init_asset_loader::<BiomeLoader>()
+        .init_resource::<WorldBiomes>()
+        .init_resource::<WorldHeightmap>()
+        .add_systems(
+            OnEnter(ChunkLoadingState::LoadManifest),
+            load_world_manifest,
+        )
+        .add_systems(
+            Update,
+            check_world_manifest.run_if(in_state(ChunkLoadingState::LoadManifest)),
+        )
+        .add_systems(OnEnter(ChunkLoadingState::LoadChunks), load_chunks)
+        .add_systems(
+            Update,
+            check_chunk.run_if(in_state(ChunkLoadingState::LoadChunks)),
+        )
+        .init_resource::<RenderDistance>()
+        .init_resource::<ChunkLodDistances>()
+        .init_resource::<RemeshBudget>()
+        .init_resource::<FarTerrainDistance>()
+        .init_resource::<Impostors>()
+        .init_resource::<CaveCulling>()
+        .init_resource::<ChunkGizmos>()
+        .add_event::<ExportWorldMap>()
+        .add_event::<ChunkLoaded>()
+        .add_event::<ChunkMeshed>()
+        .add_event::<ChunkUnloaded>()
+        .add_event::<SwitchWorld>()
+        .add_systems(Last, send_chunk_lifecycle.run_if(resource_exists::<Chunks>))
+        .add_systems(Update, switch_world.run_if(resource_exists::<Chunks>));
+
+        // Worlds loaded by SwitchWorld later on don't start the game over
+        let move_to_loaded_chunks = move_to_loaded_chunks.run_if(in_state(AppState::BlocksLoaded));
+        if self.headless {
+            app.add_systems(
+                OnEnter(ChunkLoadingState::Finished),
+                (create_headless_chunk_resource, move_to_loaded_chunks),
             )
+            .add_systems(
+                PostUpdate,
+                remesh_changed_chunks.run_if(resource_exists::<Chunks>),
+            );
+            return;
+        }
+
+        app.add_plugins((ChunkMaterialPlugin, TileEntityPlugin))
             .add_systems(
                 OnEnter(ChunkLoadingState::Finished),
                 (create_chunk_resource, move_to_loaded_chunks),
+            )
+            .add_systems(
+                Update,
+                (
+                    (
+                        apply_meshing_mode,
+                        apply_vertex_format,
+                        update_chunk_lods,
+                        dispatch_chunk_meshes,
+                        poll_chunk_meshes,
+                    )
+                        .chain(),
+                    crate::map::export_world_map,
+                    (
+                        update_chunk_connectivity,
+                        update_visible_chunks,
+                        cull_distant_chunks,
+                    )
+                        .chain(),
+                    (build_impostors, cull_impostors).chain(),
+                    draw_chunk_gizmos,
+                )
+                    .run_if(resource_exists::<Chunks>),
             );
+
+        // The rebuilt BlockAtlas is inserted by commands during Update
+        app.add_systems(
+            PostUpdate,
+            (remesh_changed_chunks, reload_chunk_blocks).run_if(resource_exists::<Chunks>),
+        );
     }
 }
@@ -0,0 +1,53 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+
+use crate::Chunks;
+
+/// Measures the loaded [Chunks] for bevy's diagnostics, added by
+/// [ChunksPlugin](crate::ChunksPlugin)
+pub struct ChunkDiagnosticsPlugin;
+
+impl ChunkDiagnosticsPlugin {
+    /// Chunks in [Chunks::chunks]
+    pub const LOADED_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("cubizm/loaded_chunks");
+    /// Vertices of the opaque, transparent and cutout meshes of every loaded chunk. Chunks
+    /// sharing a cached mesh count it once each, as each draws it
+    pub const MESH_VERTICES: DiagnosticPath = DiagnosticPath::const_new("cubizm/mesh_vertices");
+
+    fn diagnostic_system(
+        mut diagnostics: Diagnostics,
+        chunks: Res<Chunks>,
+        meshes: Option<Res<Assets<Mesh>>>,
+    ) {
+        diagnostics.add_measurement(&Self::LOADED_CHUNKS, || chunks.chunks.len() as f64);
+        let Some(meshes) = meshes else {
+            return;
+        };
+        diagnostics.add_measurement(&Self::MESH_VERTICES, || {
+            chunks
+                .chunks
+                .values()
+                .flat_map(|chunk| {
+                    [
+                        &chunk.mesh_handle,
+                        &chunk.transparent_mesh_handle,
+                        &chunk.cutout_mesh_handle,
+                    ]
+                })
+                .filter_map(|handle| meshes.get(handle))
+                .map(|mesh| mesh.count_vertices())
+                .sum::<usize>() as f64
+        });
+    }
+}
+
+impl Plugin for ChunkDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::LOADED_CHUNKS))
+            .register_diagnostic(Diagnostic::new(Self::MESH_VERTICES))
+            .add_systems(
+                Update,
+                Self::diagnostic_system.run_if(resource_exists::<Chunks>),
+            );
+    }
+}
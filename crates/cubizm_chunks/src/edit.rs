@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+
+/// The blocks from `min` to `max` inclusive, in world coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockAabb {
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+impl BlockAabb {
+    /// The blocks between two opposite corners, given in any order
+    pub fn new(a: IVec3, b: IVec3) -> Self {
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    /// Blocks along each axis
+    pub fn size(&self) -> IVec3 {
+        self.max - self.min + IVec3::ONE
+    }
+
+    pub fn volume(&self) -> i64 {
+        self.size().as_i64vec3().to_array().iter().product()
+    }
+
+    pub fn contains(&self, position: IVec3) -> bool {
+        position.cmpge(self.min).all() && position.cmple(self.max).all()
+    }
+
+    /// Every block in the box, x fastest
+    pub fn iter(&self) -> impl Iterator<Item = IVec3> {
+        let (min, max) = (self.min, self.max);
+        (min.z..=max.z).flat_map(move |z| {
+            (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+        })
+    }
+}
@@ -0,0 +1,221 @@
+use bevy::{prelude::*, utils::HashMap};
+use block_mesh::ndshape::ConstShape;
+
+use cubizm_block::definition::{Block, Fluid};
+use cubizm_block::{block_key, BlockRegistry, BASE_NAMESPACE};
+use cubizm_core::{chunk_to_world, world_to_chunk};
+
+use crate::{
+    BlockChanged, BlockTick, BlockTickKind, BlockTickSet, BlockTickSettings, BlockTicks, Chunk,
+    Chunks, PaddedChunkShape, VoxelWorld,
+};
+
+const SIDES: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// Levels of the flowing fluid blocks in the loaded chunks, kept by [Chunks] and baked into
+/// chunk meshes. Fluid blocks without a level are sources, see [Fluid::source_level]
+#[derive(Debug, Default)]
+pub struct FluidLevels {
+    levels: HashMap<IVec3, u8>,
+}
+
+impl FluidLevels {
+    /// Level of the flowing fluid at world `position`, `None` for sources and blocks that
+    /// aren't fluids
+    pub fn level(&self, position: IVec3) -> Option<u8> {
+        self.levels.get(&position).copied()
+    }
+
+    /// Returns whether the level changed
+    pub(crate) fn set(&mut self, position: IVec3, level: Option<u8>) -> bool {
+        match level {
+            Some(level) => self.levels.insert(position, level) != Some(level),
+            None => self.levels.remove(&position).is_some(),
+        }
+    }
+
+    /// Forgets the levels within the chunk at `position`
+    pub(crate) fn remove_chunk(&mut self, position: IVec3) {
+        self.levels
+            .retain(|block, _| world_to_chunk(*block) != position);
+    }
+
+    /// Levels of the chunk at `position` laid out like [PaddedChunkShape], `0` for blocks
+    /// without one, as stored by
+    /// [ChunkSnapshot::with_fluid_levels](crate::ChunkSnapshot::with_fluid_levels)
+    pub(crate) fn padded_levels(&self, position: IVec3) -> Vec<u8> {
+        if self.levels.is_empty() {
+            return vec![0; PaddedChunkShape::SIZE as usize];
+        }
+        let origin = chunk_to_world(position);
+        (0..PaddedChunkShape::SIZE)
+            .map(|index| {
+                let local =
+                    IVec3::from_array(PaddedChunkShape::delinearize(index).map(|v| v as i32));
+                // The padding starts one block before the chunk
+                self.level(origin + local - IVec3::ONE).unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+/// Level of fluid falling from above, which spreads like fluid one block from its source.
+/// Kept above 0 so fluids that don't spread sideways can still fall
+fn falling_level(fluid: Fluid) -> u8 {
+    fluid.spread.max(1)
+}
+
+/// Game ticks until a fluid flows on by a block
+fn flow_delay(fluid: Fluid, settings: &BlockTickSettings) -> u64 {
+    settings.ticks_in(1.0 / fluid.flow_rate.max(0.01))
+}
+
+/// Lets every fluid block next to a changed block flow, so fluids spread into the air and
+/// drain away once their source is gone
+fn schedule_fluid_updates(
+    mut changed: EventReader<BlockChanged>,
+    settings: Res<BlockTickSettings>,
+    mut chunks: ResMut<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    mut ticks: ResMut<BlockTicks>,
+) {
+    for event in changed.read() {
+        // A block replacing a fluid keeps no level
+        if chunks.fluids().level(event.world_pos).is_some()
+            && blocks.get(&event.new).and_then(Block::fluid).is_none()
+        {
+            chunks.fluids_mut().set(event.world_pos, None);
+        }
+        let around = [IVec3::ZERO, IVec3::Y, IVec3::NEG_Y]
+            .into_iter()
+            .chain(SIDES)
+            .map(|offset| event.world_pos + offset);
+        for position in around {
+            let Ok(block) = chunks.get_block(position, &assets_chunks) else {
+                continue;
+            };
+            if let Some(fluid) = blocks.get(&block).and_then(Block::fluid) {
+                ticks.schedule(position, flow_delay(fluid, &settings));
+            }
+        }
+    }
+}
+
+fn flow_fluids(
+    mut block_ticks: EventReader<BlockTick>,
+    settings: Res<BlockTickSettings>,
+    registry: Res<BlockRegistry>,
+    blocks: Res<Assets<Block>>,
+    mut ticks: ResMut<BlockTicks>,
+    mut world: VoxelWorld,
+) {
+    let Some(air) = registry.get(&block_key(BASE_NAMESPACE, "Air")) else {
+        return;
+    };
+    let scheduled = block_ticks
+        .read()
+        .filter(|tick| tick.kind == BlockTickKind::Scheduled)
+        .map(|tick| tick.position)
+        .collect::<Vec<_>>();
+    for position in scheduled {
+        // Taken again, earlier fluid in the same tick may have changed it
+        let Some(block) = world.get_block(position) else {
+            continue;
+        };
+        let Some(fluid) = blocks.get(&block).and_then(Block::fluid) else {
+            continue;
+        };
+        let same_fluid = |world: &VoxelWorld, position: IVec3| {
+            world.get_block(position).as_ref() == Some(&block)
+        };
+        let level_at = |world: &VoxelWorld, position: IVec3| {
+            world.fluid_level(position).unwrap_or(fluid.source_level())
+        };
+
+        let mut level = level_at(&world, position);
+        if world.fluid_level(position).is_some() {
+            // Flowing fluid is fed from above or by a higher level beside it
+            let fed = match same_fluid(&world, position + IVec3::Y) {
+                true => falling_level(fluid),
+                false => SIDES
+                    .iter()
+                    .map(|side| position + *side)
+                    .filter(|side| same_fluid(&world, *side))
+                    .map(|side| level_at(&world, side).saturating_sub(1))
+                    .max()
+                    .unwrap_or_default(),
+            };
+            if fed == 0 {
+                if let Err(err) = world.set_block(position, air.clone()) {
+                    warn!("Failed to drain fluid at {position}: {err}");
+                }
+                continue;
+            }
+            if fed != level {
+                level = fed;
+                world.set_fluid_level(position, Some(level));
+                // Nothing else changed, the fluid around has to be told
+                for side in SIDES.into_iter().chain([IVec3::NEG_Y]) {
+                    if same_fluid(&world, position + side) {
+                        ticks.schedule(position + side, flow_delay(fluid, &settings));
+                    }
+                }
+            }
+        }
+
+        let below = position + IVec3::NEG_Y;
+        match world.get_block(below) {
+            None => continue,
+            Some(below_block) if below_block == *air => {
+                if world.set_block(below, block.clone()).is_ok() {
+                    world.set_fluid_level(below, Some(falling_level(fluid)));
+                }
+                continue;
+            }
+            // Fluid landing on itself keeps falling instead of spreading
+            Some(below_block) if below_block == block => continue,
+            Some(_) => {}
+        }
+        if level <= 1 {
+            continue;
+        }
+        for side in SIDES.map(|side| position + side) {
+            match world.get_block(side) {
+                // Fluid stops at the edge of the loaded chunks, where setting the block fails
+                Some(side_block)
+                    if side_block == *air && world.set_block(side, block.clone()).is_ok() =>
+                {
+                    world.set_fluid_level(side, Some(level - 1));
+                }
+                Some(side_block)
+                    if side_block == block
+                        && world
+                            .fluid_level(side)
+                            .is_some_and(|side_level| side_level < level - 1) =>
+                {
+                    ticks.schedule(side, flow_delay(fluid, &settings));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Lets [fluid](Block::fluid) blocks flow into the air around them, down first and then
+/// sideways, lowering their level by one for every block they flow sideways. Flowing fluid
+/// drains away once nothing feeds it anymore. Fluids flow in scheduled [BlockTick]s, so
+/// [BlockTickPlugin](crate::BlockTickPlugin) is needed as well. Added by
+/// [ChunksPlugin](crate::ChunksPlugin)
+pub struct FluidPlugin;
+
+impl Plugin for FluidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (schedule_fluid_updates, flow_fluids.after(BlockTickSet))
+                .chain()
+                .run_if(resource_exists::<Chunks>),
+        );
+    }
+}
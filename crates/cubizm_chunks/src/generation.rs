@@ -0,0 +1,76 @@
+use std::num::NonZeroUsize;
+
+use bevy::prelude::*;
+
+use cubizm_block::BlockRegistry;
+
+use crate::{BiomeMap, Chunk, GeneratorSettings, Schematic, StructurePass};
+
+/// The settings and loaded assets the chunks of a world are generated from
+pub struct WorldGeneration<'a> {
+    pub seed: u64,
+    pub settings: &'a GeneratorSettings,
+    pub registry: &'a BlockRegistry,
+    pub biomes: BiomeMap<'a>,
+    /// Needed by [WorldGenerator::Heightmap](crate::WorldGenerator::Heightmap)
+    pub heightmap: Option<&'a Image>,
+    pub schematics: &'a Assets<Schematic>,
+}
+
+impl WorldGeneration<'_> {
+    /// The terrain, ores and caves of the chunk at `position`, without structures. `None` if a
+    /// block it needs is not registered
+    pub fn terrain(&self, position: IVec3) -> Option<Chunk> {
+        let mut chunk =
+            self.settings
+                .kind
+                .generate(position, self.registry, &self.biomes, self.heightmap)?;
+        for (index, ore) in self.settings.ores.iter().enumerate() {
+            ore.scatter(&mut chunk, self.seed, index, self.registry);
+        }
+        if let Some(caves) = &self.settings.caves {
+            caves.carve(&mut chunk, self.seed, self.registry);
+        }
+        Some(chunk)
+    }
+
+    /// Generates the chunks at `positions`, their terrain spread over every available thread,
+    /// then places the structures of `structures` in order. A thread stops at the first chunk
+    /// that fails to generate
+    pub fn generate(&self, positions: &[IVec3], structures: &mut StructurePass) -> Vec<Chunk> {
+        let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let batch = positions.len().div_ceil(threads).max(1);
+        let mut chunks: Vec<Chunk> = std::thread::scope(|scope| {
+            let workers: Vec<_> = positions
+                .chunks(batch)
+                .map(|batch| {
+                    scope.spawn(|| {
+                        batch
+                            .iter()
+                            .map_while(|position| self.terrain(*position))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_default())
+                .collect()
+        });
+        for chunk in chunks.iter_mut() {
+            structures.place(
+                chunk,
+                self.seed,
+                &self.settings.structures,
+                self.schematics,
+                self.registry,
+                &self.biomes,
+            );
+        }
+        // Structures reach into the chunks around them, which may have been generated first
+        for chunk in chunks.iter_mut() {
+            structures.apply_deferred(chunk);
+        }
+        chunks
+    }
+}
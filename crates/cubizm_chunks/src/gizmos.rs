@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use block_mesh::{Voxel, VoxelVisibility};
+
+use cubizm_block::definition::Block;
+use cubizm_core::{chunk_to_world, point_to_chunk};
+
+use crate::{Chunk, Chunks, CHUNK_SIZE};
+
+const BORDER_COLOR: Color = Color::rgb(0.2, 0.6, 1.);
+const CAMERA_CHUNK_COLOR: Color = Color::YELLOW;
+/// Padding blocks sampled from a loaded neighbour
+const PADDING_COLOR: Color = Color::GREEN;
+/// Padding where the neighbour is not loaded, meshed as air
+const MISSING_PADDING_COLOR: Color = Color::RED;
+
+/// Debug gizmos drawn over the loaded [Chunks], all off by default
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkGizmos {
+    /// Outlines every loaded chunk, the one holding the camera in another colour
+    pub borders: bool,
+    /// Outlines the blocks the chunk holding the camera meshes from its neighbours: the ones
+    /// that are not empty, and every one whose neighbour is not loaded
+    pub padding: bool,
+}
+
+/// Centre and size of the blocks of the chunk at `position`
+fn chunk_bounds(position: IVec3) -> Transform {
+    let size = CHUNK_SIZE as f32;
+    Transform::from_translation(
+        (chunk_to_world(position) + IVec3::ONE).as_vec3() + Vec3::splat(size / 2.),
+    )
+    .with_scale(Vec3::splat(size))
+}
+
+pub(crate) fn draw_chunk_gizmos(
+    settings: Res<ChunkGizmos>,
+    chunks: Res<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut gizmos: Gizmos,
+) {
+    if !settings.borders && !settings.padding {
+        return;
+    }
+    let Some((_, camera)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let camera_chunk = point_to_chunk(camera.translation());
+
+    if settings.borders {
+        for position in chunks.chunks.keys() {
+            if *position != camera_chunk {
+                gizmos.cuboid(chunk_bounds(*position), BORDER_COLOR);
+            }
+        }
+        gizmos.cuboid(chunk_bounds(camera_chunk), CAMERA_CHUNK_COLOR);
+    }
+
+    if settings.padding {
+        let padded = CHUNK_SIZE as i32 + 2;
+        let origin = chunk_to_world(camera_chunk);
+        for x in 0..padded {
+            for y in 0..padded {
+                for z in 0..padded {
+                    let local = IVec3::new(x, y, z);
+                    // Only the outer layer comes from the neighbours
+                    if local.cmpgt(IVec3::ZERO).all() && local.cmplt(IVec3::splat(padded - 1)).all()
+                    {
+                        continue;
+                    }
+                    let position = origin + local;
+                    let color = match chunks.get_block(position, &assets_chunks) {
+                        Ok(block) => match blocks.get(&block) {
+                            Some(block) if block.get_visibility() != VoxelVisibility::Empty => {
+                                PADDING_COLOR
+                            }
+                            _ => continue,
+                        },
+                        Err(_) => MISSING_PADDING_COLOR,
+                    };
+                    let center = position.as_vec3() + Vec3::splat(0.5);
+                    gizmos.cuboid(
+                        Transform::from_translation(center).with_scale(Vec3::splat(0.9)),
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
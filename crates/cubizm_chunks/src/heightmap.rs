@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+
+/// The image of a [WorldGenerator::Heightmap](crate::WorldGenerator::Heightmap) of the
+/// [ActiveWorld](crate::ActiveWorld), `None` for other generators
+#[derive(Resource, Debug, Default)]
+pub struct WorldHeightmap(pub Option<Handle<Image>>);
+
+/// Brightness from 0 to 1 of the pixel of `image` for the block column at world `column`, pixel
+/// `(x, y)` being column `(x, z)`. `None` outside of the image. Only the first channel of each
+/// pixel is read, with 8 or 16 bits
+pub fn heightmap_sample(image: &Image, column: IVec2) -> Option<f32> {
+    let size = image.size();
+    let (x, z) = (u32::try_from(column.x).ok()?, u32::try_from(column.y).ok()?);
+    if x >= size.x || z >= size.y {
+        return None;
+    }
+    let format = image.texture_descriptor.format;
+    let stride = format.block_copy_size(None)? as usize;
+    let pixel = (z * size.x + x) as usize * stride;
+    // Bytes per channel
+    match stride / format.components() as usize {
+        2 => {
+            let value = u16::from_le_bytes([image.data[pixel], image.data[pixel + 1]]);
+            Some(value as f32 / u16::MAX as f32)
+        }
+        _ => Some(image.data[pixel] as f32 / u8::MAX as f32),
+    }
+}
@@ -0,0 +1,191 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+    utils::HashMap,
+};
+
+use crate::map::top_face_color;
+use crate::{Chunk, Chunks, ColumnSurface, RenderDistance, CHUNK_SIZE};
+use cubizm_block::definition::Block;
+use cubizm_core::point_to_chunk;
+
+/// Width in blocks of one impostor cell, must divide [CHUNK_SIZE]
+const CELL_SIZE: u32 = 4;
+/// How far cell walls reach down, hiding the gaps between cells of different heights
+const SKIRT_DEPTH: f32 = CHUNK_SIZE as f32;
+
+/// Radius in chunks around the active camera within which chunk columns beyond
+/// [RenderDistance] are drawn as coarse impostors
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FarTerrainDistance(pub u32);
+
+impl Default for FarTerrainDistance {
+    fn default() -> Self {
+        Self(32)
+    }
+}
+
+/// Low detail stand-in for the chunk column at `column` (x, z), shown when it is too far away
+/// to be drawn in full
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Impostor {
+    pub column: IVec2,
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct Impostors {
+    entities: HashMap<IVec2, Entity>,
+    material: Option<Handle<StandardMaterial>>,
+}
+
+impl Impostors {
+    /// Despawns every impostor, they are built again the next time [Chunks] change
+    pub(crate) fn clear(&mut self, commands: &mut Commands) {
+        for (_, entity) in self.entities.drain() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[derive(Default)]
+struct ImpostorMesh {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl ImpostorMesh {
+    /// Adds the quad spanned by `u` and `v` from `origin`, facing `u × v`
+    fn push_quad(&mut self, origin: Vec3, u: Vec3, v: Vec3, color: [f32; 4]) {
+        let start = self.positions.len() as u32;
+        let normal = u.cross(v).normalize();
+        for corner in [origin, origin + u, origin + u + v, origin + v] {
+            self.positions.push(corner.to_array());
+            self.normals.push(normal.to_array());
+            self.colors.push(color);
+        }
+        self.indices
+            .extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+    }
+
+    fn finish(self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+        .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
+
+/// One flat topped box per [CELL_SIZE] square, as high as the highest block in it and coloured
+/// by that block's top face
+fn impostor_mesh(
+    surface: &ColumnSurface,
+    colors: &mut HashMap<AssetId<Block>, [f32; 4]>,
+    blocks: &Assets<Block>,
+    images: &Assets<Image>,
+) -> Mesh {
+    let size = CELL_SIZE as f32;
+    let mut mesh = ImpostorMesh::default();
+    for cell_z in (0..CHUNK_SIZE).step_by(CELL_SIZE as usize) {
+        for cell_x in (0..CHUNK_SIZE).step_by(CELL_SIZE as usize) {
+            let highest = (cell_z..cell_z + CELL_SIZE)
+                .flat_map(|z| (cell_x..cell_x + CELL_SIZE).map(move |x| z * CHUNK_SIZE + x))
+                .filter_map(|column| surface[column as usize])
+                .max_by_key(|(y, _)| *y);
+            let Some((y, block)) = highest else {
+                continue;
+            };
+            let color = *colors.entry(block).or_insert_with(|| {
+                let [r, g, b] = top_face_color(block, blocks, images);
+                Color::rgb_u8(r, g, b).as_linear_rgba_f32()
+            });
+
+            let top = Vec3::new(cell_x as f32, (y + 1) as f32, cell_z as f32);
+            let (x, z, down) = (Vec3::X * size, Vec3::Z * size, Vec3::NEG_Y * SKIRT_DEPTH);
+            mesh.push_quad(top, z, x, color);
+            mesh.push_quad(top + x, z, down, color);
+            mesh.push_quad(top, down, z, color);
+            mesh.push_quad(top + z, down, x, color);
+            mesh.push_quad(top, x, down, color);
+        }
+    }
+    mesh.finish()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_impostors(
+    mut commands: Commands,
+    chunks: Res<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    images: Res<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut impostors: ResMut<Impostors>,
+) {
+    if !chunks.is_changed() {
+        return;
+    }
+    impostors.clear(&mut commands);
+    let material = impostors
+        .material
+        .get_or_insert_with(|| {
+            materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                perceptual_roughness: 1.,
+                ..default()
+            })
+        })
+        .clone();
+
+    let mut colors = HashMap::new();
+    for (column, surface) in chunks.surface(&assets_chunks, &blocks) {
+        let entity = commands
+            .spawn((
+                PbrBundle {
+                    transform: Transform::from_xyz(
+                        (column.x * CHUNK_SIZE as i32) as f32,
+                        0.,
+                        (column.y * CHUNK_SIZE as i32) as f32,
+                    ),
+                    mesh: meshes.add(impostor_mesh(&surface, &mut colors, &blocks, &images)),
+                    material: material.clone(),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                Impostor { column },
+            ))
+            .id();
+        impostors.entities.insert(column, entity);
+    }
+}
+
+pub(crate) fn cull_impostors(
+    render_distance: Res<RenderDistance>,
+    far_terrain_distance: Res<FarTerrainDistance>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut impostors: Query<(&Impostor, &mut Visibility)>,
+) {
+    let Some((_, camera)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let camera_column = point_to_chunk(camera.translation()).xz();
+    let near = render_distance.0 as i32;
+    let far = far_terrain_distance.0 as i32;
+
+    for (impostor, mut visibility) in impostors.iter_mut() {
+        let distance = (impostor.column - camera_column).abs().max_element();
+        visibility.set_if_neq(match distance > near && distance <= far {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        });
+    }
+}
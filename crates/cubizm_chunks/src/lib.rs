@@ -1,5 +1,69 @@
+#[cfg(feature = "anvil")]
+pub use anvil::*;
+pub use biome::*;
+pub use cave::*;
 pub use chunk::*;
 pub use chunks::*;
+pub use diagnostics::*;
+pub use edit::*;
+pub use fluid::*;
+pub use generation::*;
+pub use gizmos::*;
+pub use heightmap::*;
+pub use impostor::*;
+pub use light::*;
+pub use manifest::*;
+pub use map::*;
+pub use material::*;
+pub use noise::*;
+pub use occlusion::*;
+pub use occupancy::*;
+pub use ore::*;
+pub use persistence::*;
+pub use population::*;
+pub use raycast::*;
+#[cfg(feature = "raymarch")]
+pub use raymarch::*;
+pub use save::*;
+pub use schematic::*;
+pub use streaming::*;
+pub use structure::*;
+pub use tick::*;
+pub use tile_entity::*;
+pub use world::*;
+pub use worlds::*;
 
+#[cfg(feature = "anvil")]
+mod anvil;
+mod biome;
+mod cave;
 mod chunk;
 mod chunks;
+mod diagnostics;
+mod edit;
+mod fluid;
+mod generation;
+mod gizmos;
+mod heightmap;
+mod impostor;
+mod light;
+mod manifest;
+mod map;
+mod material;
+mod noise;
+mod occlusion;
+mod occupancy;
+mod ore;
+mod persistence;
+mod population;
+mod raycast;
+#[cfg(feature = "raymarch")]
+mod raymarch;
+mod save;
+mod schematic;
+mod streaming;
+mod structure;
+mod tick;
+mod tile_entity;
+mod world;
+mod worlds;
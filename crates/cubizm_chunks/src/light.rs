@@ -0,0 +1,310 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use block_mesh::ndshape::ConstShape;
+
+use cubizm_block::definition::{Block, MAX_LIGHT};
+use cubizm_core::{chunk_to_world, world_to_chunk, world_to_local};
+
+use crate::{PaddedChunkShape, CHUNK_SIZE};
+
+/// Light of blocks outside any loaded chunk, as seen by the meshes next to them
+const UNLOADED_LIGHT: u8 = MAX_LIGHT << 4;
+
+const DIRECTIONS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// How a block affects the light around it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LightProperties {
+    /// Stops light from passing through
+    pub opaque: bool,
+    /// Block light level given off
+    pub emission: u8,
+}
+
+impl From<&Block> for LightProperties {
+    fn from(block: &Block) -> Self {
+        Self {
+            opaque: block.occludes(),
+            emission: block.light_emission(),
+        }
+    }
+}
+
+/// The two kinds of light stored for every block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LightChannel {
+    /// Seeded from the top of the world, travels straight down without dimming
+    Sky,
+    /// Given off by blocks with a [LightProperties::emission]
+    Block,
+}
+
+impl LightChannel {
+    const ALL: [LightChannel; 2] = [LightChannel::Sky, LightChannel::Block];
+
+    fn get(self, packed: u8) -> u8 {
+        match self {
+            LightChannel::Sky => packed >> 4,
+            LightChannel::Block => packed & 0xf,
+        }
+    }
+
+    fn set(self, packed: &mut u8, value: u8) {
+        *packed = match self {
+            LightChannel::Sky => (*packed & 0xf) | (value << 4),
+            LightChannel::Block => (*packed & 0xf0) | value,
+        };
+    }
+
+    /// Light reaching a neighbour in `direction` from a block lit with `value`
+    fn spread(self, value: u8, direction: IVec3) -> u8 {
+        match self {
+            LightChannel::Sky if value == MAX_LIGHT && direction == IVec3::NEG_Y => MAX_LIGHT,
+            _ => value.saturating_sub(1),
+        }
+    }
+}
+
+/// Sky light in the high and block light in the low four bits of every block of a chunk
+#[derive(Debug, Clone)]
+struct LightVolume(Vec<u8>);
+
+impl LightVolume {
+    fn index(position: IVec3) -> usize {
+        let local = world_to_local(position);
+        (local.x + local.y * CHUNK_SIZE + local.z * CHUNK_SIZE * CHUNK_SIZE) as usize
+    }
+}
+
+/// Sky and block light of the loaded chunks, propagated breadth first from the sky and
+/// emissive blocks. Kept by [Chunks](crate::Chunks) and baked into chunk meshes
+#[derive(Debug, Default)]
+pub struct LightEngine {
+    volumes: HashMap<IVec3, LightVolume>,
+}
+
+impl LightEngine {
+    /// Light level of `channel` at world `position`, `None` outside the lit chunks
+    pub fn light(&self, position: IVec3, channel: LightChannel) -> Option<u8> {
+        self.packed(position).map(|packed| channel.get(packed))
+    }
+
+    fn packed(&self, position: IVec3) -> Option<u8> {
+        self.volumes
+            .get(&world_to_chunk(position))
+            .map(|volume| volume.0[LightVolume::index(position)])
+    }
+
+    fn set(&mut self, position: IVec3, channel: LightChannel, value: u8) {
+        if let Some(volume) = self.volumes.get_mut(&world_to_chunk(position)) {
+            channel.set(&mut volume.0[LightVolume::index(position)], value);
+        }
+    }
+
+    /// Packed light of the chunk at `position` laid out like [PaddedChunkShape], including the
+    /// border shared with its neighbours, as stored by
+    /// [ChunkSnapshot::with_light](crate::ChunkSnapshot::with_light)
+    pub(crate) fn padded_light(&self, position: IVec3) -> Vec<u8> {
+        let origin = chunk_to_world(position);
+        (0..PaddedChunkShape::SIZE)
+            .map(|index| {
+                let local =
+                    IVec3::from_array(PaddedChunkShape::delinearize(index).map(|v| v as i32));
+                self.packed(origin + local).unwrap_or(UNLOADED_LIGHT)
+            })
+            .collect()
+    }
+
+    /// Forgets the light of the chunk at `position`, its neighbours keep theirs
+    pub(crate) fn remove_chunk(&mut self, position: IVec3) {
+        self.volumes.remove(&position);
+    }
+
+    /// Lights the chunk at `position`, pulling light in from and pushing it out to its loaded
+    /// neighbours. `properties` looks up blocks by world position, `None` if unloaded.
+    /// Returns every chunk whose light changed
+    pub(crate) fn insert_chunk(
+        &mut self,
+        position: IVec3,
+        properties: impl Fn(IVec3) -> Option<LightProperties>,
+    ) -> HashSet<IVec3> {
+        let size = CHUNK_SIZE as i32;
+        let origin = chunk_to_world(position);
+        self.volumes.insert(
+            position,
+            LightVolume(vec![0; (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize]),
+        );
+        let mut changed = HashSet::from([position]);
+        let mut removals = Vec::new();
+        let mut sky = VecDeque::new();
+        let mut block = VecDeque::new();
+
+        for x in 1..=size {
+            for z in 1..=size {
+                // Open sky unless a loaded chunk above casts a shadow
+                let above = origin + IVec3::new(x, size + 1, z);
+                let mut lit = self
+                    .light(above, LightChannel::Sky)
+                    .is_none_or(|light| light == MAX_LIGHT);
+                for y in (1..=size).rev() {
+                    let block_position = origin + IVec3::new(x, y, z);
+                    let block_properties = properties(block_position).unwrap_or_default();
+                    lit &= !block_properties.opaque;
+                    if lit {
+                        self.set(block_position, LightChannel::Sky, MAX_LIGHT);
+                        sky.push_back(block_position);
+                    }
+                    if block_properties.emission > 0 {
+                        self.set(
+                            block_position,
+                            LightChannel::Block,
+                            block_properties.emission,
+                        );
+                        block.push_back(block_position);
+                    }
+                }
+                // A chunk below lit as open sky is now in this column's shadow
+                let below = origin + IVec3::new(x, 0, z);
+                if !lit && self.light(below, LightChannel::Sky) == Some(MAX_LIGHT) {
+                    removals.push(below);
+                }
+            }
+        }
+
+        // Neighbouring light flows in across every face
+        for x in 0..=size + 1 {
+            for y in 0..=size + 1 {
+                for z in 0..=size + 1 {
+                    let local = IVec3::new(x, y, z);
+                    let on_border = local.cmpeq(IVec3::ZERO) | local.cmpeq(IVec3::splat(size + 1));
+                    if on_border.bitmask().count_ones() != 1 {
+                        continue;
+                    }
+                    let neighbour = origin + local;
+                    if self.packed(neighbour).is_some() {
+                        sky.push_back(neighbour);
+                        block.push_back(neighbour);
+                    }
+                }
+            }
+        }
+
+        for below in removals {
+            self.remove(
+                below,
+                LightChannel::Sky,
+                &mut sky,
+                &properties,
+                &mut changed,
+            );
+        }
+        self.propagate(LightChannel::Sky, sky, &properties, &mut changed);
+        self.propagate(LightChannel::Block, block, &properties, &mut changed);
+        changed
+    }
+
+    /// Relights around a block that changed at world `position`, with `properties` looking
+    /// up blocks by world position as for [insert_chunk](LightEngine::insert_chunk).
+    /// Returns every chunk whose light changed
+    pub(crate) fn update_block(
+        &mut self,
+        position: IVec3,
+        properties: impl Fn(IVec3) -> Option<LightProperties>,
+    ) -> HashSet<IVec3> {
+        let mut changed = HashSet::new();
+        if self.packed(position).is_none() {
+            return changed;
+        }
+        for channel in LightChannel::ALL {
+            let mut queue = VecDeque::new();
+            self.remove(position, channel, &mut queue, &properties, &mut changed);
+            let emission = properties(position).unwrap_or_default().emission;
+            if channel == LightChannel::Block && emission > 0 {
+                self.set(position, channel, emission);
+                changed.insert(world_to_chunk(position));
+            }
+            queue.push_back(position);
+            queue.extend(DIRECTIONS.map(|direction| position + direction));
+            self.propagate(channel, queue, &properties, &mut changed);
+        }
+        changed
+    }
+
+    /// Darkens everything lit through `position`, queueing the brighter blocks around the
+    /// darkened area so [propagate](LightEngine::propagate) can fill it back in
+    fn remove(
+        &mut self,
+        position: IVec3,
+        channel: LightChannel,
+        refill: &mut VecDeque<IVec3>,
+        properties: &impl Fn(IVec3) -> Option<LightProperties>,
+        changed: &mut HashSet<IVec3>,
+    ) {
+        let Some(value) = self.light(position, channel) else {
+            return;
+        };
+        self.set(position, channel, 0);
+        changed.insert(world_to_chunk(position));
+        let mut queue = VecDeque::from([(position, value)]);
+        while let Some((position, value)) = queue.pop_front() {
+            for direction in DIRECTIONS {
+                let neighbour = position + direction;
+                let Some(light) = self.light(neighbour, channel) else {
+                    continue;
+                };
+                if light != 0 && light <= channel.spread(value, direction) {
+                    self.set(neighbour, channel, 0);
+                    changed.insert(world_to_chunk(neighbour));
+                    queue.push_back((neighbour, light));
+                    // Emissive blocks light themselves back up
+                    let emission = properties(neighbour).unwrap_or_default().emission;
+                    if channel == LightChannel::Block && emission > 0 {
+                        self.set(neighbour, channel, emission);
+                        refill.push_back(neighbour);
+                    }
+                } else if light > 0 {
+                    refill.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    /// Spreads light outwards from every block in `queue`
+    fn propagate(
+        &mut self,
+        channel: LightChannel,
+        mut queue: VecDeque<IVec3>,
+        properties: &impl Fn(IVec3) -> Option<LightProperties>,
+        changed: &mut HashSet<IVec3>,
+    ) {
+        while let Some(position) = queue.pop_front() {
+            let Some(value) = self.light(position, channel).filter(|value| *value > 1) else {
+                continue;
+            };
+            for direction in DIRECTIONS {
+                let neighbour = position + direction;
+                let spread = channel.spread(value, direction);
+                let Some(light) = self.light(neighbour, channel) else {
+                    continue;
+                };
+                if light >= spread || properties(neighbour).is_none_or(|block| block.opaque) {
+                    continue;
+                }
+                self.set(neighbour, channel, spread);
+                changed.insert(world_to_chunk(neighbour));
+                queue.push_back(neighbour);
+            }
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use block_mesh::ndshape::ConstShape;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use cubizm_block::{block_key, definition::Block, BlockRegistry, BASE_NAMESPACE};
+use cubizm_core::{chunk_to_world, local_to_world, CHUNK_SIZE};
+
+use crate::{
+    heightmap_sample, Biome, BiomeMap, CaveSettings, Chunk, ChunkShape, OreRule, StructureRule,
+    WorldId,
+};
+
+/// Describes a world: where its chunks are saved and how missing ones are generated.
+/// Loaded by [ChunksPlugin](crate::ChunksPlugin) from
+/// [ChunksPluginSettings::world_path](crate::ChunksPluginSettings::world_path)
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct WorldManifest {
+    pub name: String,
+    pub seed: u64,
+    /// Where the player starts, in world coordinates
+    pub spawn: Vec3,
+    /// Asset path of the folder of `.chunk` and `.chunkb` files
+    pub chunk_directory: String,
+    pub generator: GeneratorSettings,
+    pub portals: Vec<PortalLink>,
+}
+
+impl WorldManifest {
+    /// The first portal whose blocks include the block at `position`
+    pub fn portal_at(&self, position: IVec3) -> Option<&PortalLink> {
+        self.portals.iter().find(|portal| portal.contains(position))
+    }
+}
+
+/// [WorldManifest] as written in `.world.ron` files
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedWorldManifest {
+    pub name: String,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub spawn: Vec3,
+    /// Relative to the folder of the manifest
+    pub chunk_directory: String,
+    #[serde(default)]
+    pub generator: GeneratorSettings,
+    #[serde(default)]
+    pub portals: Vec<PortalLink>,
+}
+
+/// Takes whoever enters the portal blocks between two corners of a world to a position in
+/// another world of [Worlds](crate::Worlds)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PortalLink {
+    /// One corner of the portal's blocks, in world coordinates
+    pub from: IVec3,
+    /// The opposite corner, included
+    pub to: IVec3,
+    pub world: WorldId,
+    /// Where the portal leads in `world`, in world coordinates
+    pub destination: Vec3,
+}
+
+impl PortalLink {
+    /// Whether the block at `position` is between the corners of the portal
+    pub fn contains(&self, position: IVec3) -> bool {
+        let (min, max) = (self.from.min(self.to), self.from.max(self.to));
+        position.cmpge(min).all() && position.cmple(max).all()
+    }
+}
+
+/// How chunks that were never saved are filled in
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GeneratorSettings {
+    pub kind: WorldGenerator,
+    /// Chunks generated around the spawn chunk horizontally
+    pub radius: u32,
+    /// Scattered through the generated terrain in order, before caves are carved
+    #[serde(default)]
+    pub ores: Vec<OreRule>,
+    /// Carved out of the generated terrain before structures are placed, no caves when `None`
+    #[serde(default)]
+    pub caves: Option<CaveSettings>,
+    /// Placed on the generated terrain in order, see [StructurePass](crate::StructurePass)
+    #[serde(default)]
+    pub structures: Vec<StructureRule>,
+}
+
+impl GeneratorSettings {
+    /// Positions of the chunks to generate around the chunk at `center`, along with the chunks
+    /// above the terrain for structures to stand in when there are any
+    pub fn chunk_positions(&self, center: IVec3) -> Vec<IVec3> {
+        let mut positions = self.kind.chunk_positions(center, self.radius);
+        if !self.structures.is_empty() {
+            let top = positions.iter().map(|position| position.y).max();
+            let above: Vec<IVec3> = positions
+                .iter()
+                .filter(|position| Some(position.y) == top)
+                .map(|position| *position + IVec3::Y)
+                .collect();
+            positions.extend(above);
+        }
+        positions
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub enum WorldGenerator {
+    /// Only the saved chunks make up the world
+    #[default]
+    None,
+    /// Layers of blocks stacked upwards from the bottom of chunk height `0`, first layer lowest
+    Flat { layers: Vec<FlatLayer> },
+    /// Hills of the [Biome] the [BiomeMap] picks for every block column
+    Biomes {
+        /// Asset paths of the `.biome` files, relative to the folder of the manifest
+        biomes: Vec<String>,
+        /// Width in blocks of the regions of the biome map
+        scale: f32,
+        /// Chunk heights from `0` holding terrain, the chunks above are left empty
+        chunk_height: u32,
+    },
+    /// Columns as high as the pixels of a grayscale image are bright, see [heightmap_sample].
+    /// Columns outside of the image are left empty
+    Heightmap {
+        /// Asset path of the image, relative to the folder of the manifest
+        image: String,
+        /// World height of the surface of black pixels
+        min_height: i32,
+        /// World height of the surface of white pixels
+        max_height: i32,
+        /// Blocks of each column from the surface down, the last layer fills the rest of it
+        layers: Vec<FlatLayer>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlatLayer {
+    /// Registry name of the block, see [block_key]
+    pub block: String,
+    pub thickness: u32,
+}
+
+/// The [WorldManifest] of the active world of [Worlds](crate::Worlds)
+#[derive(Resource, Debug, Clone)]
+pub struct ActiveWorld(pub Handle<WorldManifest>);
+
+impl WorldGenerator {
+    /// Chunk heights holding generated blocks, the chunks above are left empty
+    fn heights(&self) -> std::ops::Range<i32> {
+        match self {
+            Self::None => 0..0,
+            Self::Flat { layers } => {
+                let height: u32 = layers.iter().map(|layer| layer.thickness).sum();
+                0..height.div_ceil(CHUNK_SIZE) as i32
+            }
+            Self::Biomes { chunk_height, .. } => 0..*chunk_height as i32,
+            Self::Heightmap { max_height, .. } => {
+                0..((*max_height).max(0) as u32).div_ceil(CHUNK_SIZE) as i32
+            }
+        }
+    }
+
+    /// Asset paths of the biomes the generator picks from
+    pub fn biomes(&self) -> &[String] {
+        match self {
+            Self::Biomes { biomes, .. } => biomes,
+            _ => &[],
+        }
+    }
+
+    /// Asset path of the image of a [Heightmap](WorldGenerator::Heightmap)
+    pub fn heightmap(&self) -> Option<&str> {
+        match self {
+            Self::Heightmap { image, .. } => Some(image),
+            _ => None,
+        }
+    }
+
+    /// The [BiomeMap] of the world with `seed` over the loaded `biomes`, in the order of
+    /// [biomes](WorldGenerator::biomes). A biome named like an earlier one takes its place,
+    /// so mods can replace the biomes of the world
+    pub fn biome_map<'a>(
+        &self,
+        seed: u64,
+        handles: &[Handle<Biome>],
+        biomes: &'a Assets<Biome>,
+    ) -> BiomeMap<'a> {
+        let scale = match self {
+            Self::Biomes { scale, .. } => *scale,
+            _ => 1.0,
+        };
+        let mut picked: Vec<&Biome> = Vec::new();
+        for biome in handles.iter().filter_map(|handle| biomes.get(handle)) {
+            match picked.iter_mut().find(|picked| picked.name == biome.name) {
+                Some(earlier) => *earlier = biome,
+                None => picked.push(biome),
+            }
+        }
+        BiomeMap::new(seed, scale, picked)
+    }
+
+    /// Positions of the chunks to generate around the chunk at `center`
+    pub fn chunk_positions(&self, center: IVec3, radius: u32) -> Vec<IVec3> {
+        let radius = radius as i32;
+        let mut positions = Vec::new();
+        for y in self.heights() {
+            for x in -radius..=radius {
+                for z in -radius..=radius {
+                    positions.push(IVec3::new(center.x + x, y, center.z + z));
+                }
+            }
+        }
+        positions
+    }
+
+    /// Generates the chunk at `position`, `None` if a block it needs is not registered
+    pub fn generate(
+        &self,
+        position: IVec3,
+        registry: &BlockRegistry,
+        biomes: &BiomeMap,
+        heightmap: Option<&Image>,
+    ) -> Option<Chunk> {
+        let Some(air) = registry.get(&block_key(BASE_NAMESPACE, "Air")) else {
+            warn!("Air is not registered, cannot generate chunk {position}");
+            return None;
+        };
+        let columns = match self {
+            Self::None => Vec::new(),
+            Self::Flat { layers } => {
+                let mut column = Vec::new();
+                for layer in layers {
+                    let Some(block) = registry.get(&layer.block) else {
+                        warn!("Generator layer {} is not a registered block", layer.block);
+                        return None;
+                    };
+                    column.extend(std::iter::repeat_n(block, layer.thickness as usize));
+                }
+                column
+            }
+            Self::Biomes { .. } => return Self::generate_biomes(position, air, registry, biomes),
+            Self::Heightmap {
+                min_height,
+                max_height,
+                layers,
+                ..
+            } => {
+                let Some(heightmap) = heightmap else {
+                    warn!("The heightmap was not loaded, cannot generate chunk {position}");
+                    return None;
+                };
+                let mut column = Vec::new();
+                for layer in layers {
+                    let Some(block) = registry.get(&layer.block) else {
+                        warn!("Generator layer {} is not a registered block", layer.block);
+                        return None;
+                    };
+                    column.extend(std::iter::repeat_n(block, layer.thickness as usize));
+                }
+                let heights = *min_height..=*max_height;
+                return Self::generate_heightmap(position, air, &column, heights, heightmap);
+            }
+        };
+
+        let origin = chunk_to_world(position);
+        let blocks = (0..ChunkShape::SIZE)
+            .map(|index| {
+                let [_, y, _] = ChunkShape::delinearize(index);
+                // Flat layers start at world height 1, the lowest block of chunk height 0
+                let height = origin.y + y as i32;
+                usize::try_from(height)
+                    .ok()
+                    .and_then(|height| columns.get(height))
+                    .copied()
+                    .unwrap_or(air)
+                    .clone()
+            })
+            .collect();
+        Some(Chunk {
+            blocks,
+            position,
+            states: Vec::new(),
+            tile_data: default(),
+            entities: Vec::new(),
+        })
+    }
+
+    /// Fills every block column of the chunk at `position` with `column` from the surface down
+    /// to the bottom, the last block repeating, up to a height in `heights` read from
+    /// `heightmap`
+    fn generate_heightmap(
+        position: IVec3,
+        air: &Handle<Block>,
+        column: &[&Handle<Block>],
+        heights: std::ops::RangeInclusive<i32>,
+        heightmap: &Image,
+    ) -> Option<Chunk> {
+        let mut blocks = vec![air.clone(); ChunkShape::SIZE as usize];
+        let Some(bottom) = column.last() else {
+            return Some(Chunk {
+                blocks,
+                position,
+                states: Vec::new(),
+                tile_data: default(),
+                entities: Vec::new(),
+            });
+        };
+        let (min, max) = (*heights.start() as f32, *heights.end() as f32);
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let world = local_to_world(position, UVec3::new(x, 0, z)).xz();
+                let Some(brightness) = heightmap_sample(heightmap, world) else {
+                    continue;
+                };
+                let height = (min + brightness * (max - min)).round() as i32;
+                for y in 0..CHUNK_SIZE {
+                    let depth = height - local_to_world(position, UVec3::new(x, y, z)).y;
+                    let Ok(depth) = usize::try_from(depth) else {
+                        continue;
+                    };
+                    let block = column.get(depth).unwrap_or(bottom);
+                    blocks[ChunkShape::linearize([x, y, z]) as usize] = (*block).clone();
+                }
+            }
+        }
+        Some(Chunk {
+            blocks,
+            position,
+            states: Vec::new(),
+            tile_data: default(),
+            entities: Vec::new(),
+        })
+    }
+
+    /// Fills every block column of the chunk at `position` with the blocks of its biome up to
+    /// the biome's height, with a decoration on top
+    fn generate_biomes(
+        position: IVec3,
+        air: &Handle<Block>,
+        registry: &BlockRegistry,
+        biomes: &BiomeMap,
+    ) -> Option<Chunk> {
+        let mut blocks = vec![air.clone(); ChunkShape::SIZE as usize];
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let column = local_to_world(position, UVec3::new(x, 0, z)).xz();
+                let Some(biome) = biomes.biome(column) else {
+                    warn!("No biome was loaded, cannot generate chunk {position}");
+                    return None;
+                };
+                let find = |name: &str| {
+                    let block = registry.get(name);
+                    if block.is_none() {
+                        warn!(
+                            "Biome {} uses {name}, which is not a registered block",
+                            biome.name
+                        );
+                    }
+                    block
+                };
+                let surface = find(&biome.surface)?;
+                let filler = find(&biome.filler)?;
+                let decoration = match biome
+                    .decorations
+                    .iter()
+                    .enumerate()
+                    .find(|(index, decoration)| biomes.decorated(column, *index, decoration.chance))
+                {
+                    Some((_, decoration)) => Some(find(&decoration.block)?),
+                    None => None,
+                };
+                let height = biomes.height(biome, column);
+                for y in 0..CHUNK_SIZE {
+                    let block = match local_to_world(position, UVec3::new(x, y, z)).y - height {
+                        ..=-1 => filler,
+                        0 => surface,
+                        1 => match decoration {
+                            Some(decoration) => decoration,
+                            None => continue,
+                        },
+                        _ => continue,
+                    };
+                    blocks[ChunkShape::linearize([x, y, z]) as usize] = block.clone();
+                }
+            }
+        }
+        Some(Chunk {
+            blocks,
+            position,
+            states: Vec::new(),
+            tile_data: default(),
+            entities: Vec::new(),
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WorldManifestLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error(transparent)]
+    ParseAssetPathError(#[from] bevy::asset::ParseAssetPathError),
+}
+
+#[derive(Default)]
+pub struct WorldManifestLoader;
+
+impl AssetLoader for WorldManifestLoader {
+    type Asset = WorldManifest;
+    type Settings = ();
+    type Error = WorldManifestLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let ron: SerializedWorldManifest = ron::de::from_bytes(&bytes)?;
+            let chunk_directory = load_context
+                .asset_path()
+                .resolve_embed(&ron.chunk_directory)?;
+            let mut generator = ron.generator;
+            for rule in generator.structures.iter_mut() {
+                rule.schematic = load_context
+                    .asset_path()
+                    .resolve_embed(&rule.schematic)?
+                    .to_string();
+            }
+            match &mut generator.kind {
+                WorldGenerator::Biomes { biomes, .. } => {
+                    for biome in biomes.iter_mut() {
+                        *biome = load_context.asset_path().resolve_embed(biome)?.to_string();
+                    }
+                }
+                WorldGenerator::Heightmap { image, .. } => {
+                    *image = load_context.asset_path().resolve_embed(image)?.to_string();
+                }
+                _ => {}
+            }
+            Ok(WorldManifest {
+                name: ron.name,
+                seed: ron.seed,
+                spawn: ron.spawn,
+                chunk_directory: chunk_directory.to_string(),
+                generator,
+                portals: ron.portals,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["world.ron", "world"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_portal_holding_a_block() {
+        let link = |from, to, world: &str| PortalLink {
+            from,
+            to,
+            world: WorldId::new(world),
+            destination: Vec3::ZERO,
+        };
+        let manifest = WorldManifest {
+            name: "Test".to_string(),
+            seed: 0,
+            spawn: Vec3::ZERO,
+            chunk_directory: "chunks".to_string(),
+            generator: GeneratorSettings::default(),
+            portals: vec![
+                link(IVec3::new(2, 1, 0), IVec3::new(0, 3, 0), "caves"),
+                link(IVec3::new(0, 0, 0), IVec3::new(8, 8, 8), "sky"),
+            ],
+        };
+        let portal = |position| {
+            manifest
+                .portal_at(position)
+                .map(|link| link.world.0.as_str())
+        };
+        assert_eq!(portal(IVec3::new(1, 2, 0)), Some("caves"));
+        assert_eq!(portal(IVec3::new(1, 0, 0)), Some("sky"));
+        assert_eq!(portal(IVec3::new(1, 2, -1)), None);
+    }
+}
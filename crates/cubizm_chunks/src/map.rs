@@ -0,0 +1,170 @@
+use std::path::PathBuf;
+
+use bevy::{prelude::*, utils::HashMap};
+use block_mesh::{ndshape::ConstShape, Voxel, VoxelVisibility};
+use image::{Rgb, RgbImage};
+
+use crate::{Chunk, ChunkShape, Chunks, CHUNK_SIZE};
+use cubizm_block::definition::Block;
+use cubizm_core::local_to_world;
+
+/// Colour used for surface blocks without a usable texture
+const MISSING_COLOR: [u8; 3] = [255, 0, 255];
+/// How strongly a one block height difference lightens or darkens a pixel
+const HILLSHADE_STEP: f32 = 0.15;
+const HILLSHADE_LIMIT: f32 = 0.4;
+
+/// Highest visible block of every block column in a chunk column, indexed by `z * CHUNK_SIZE + x`,
+/// with the world `y` of the block
+pub type ColumnSurface = [Option<(i32, AssetId<Block>)>; (CHUNK_SIZE * CHUNK_SIZE) as usize];
+
+/// Request a top-down map of the loaded world to be written as a PNG to `path`
+#[derive(Event, Debug, Clone)]
+pub struct ExportWorldMap {
+    pub path: PathBuf,
+}
+
+impl Chunks {
+    /// Highest visible block of every loaded block column, grouped by chunk column `(x, z)`
+    pub fn surface(
+        &self,
+        chunks: &Assets<Chunk>,
+        blocks: &Assets<Block>,
+    ) -> HashMap<IVec2, ColumnSurface> {
+        let mut surfaces: HashMap<IVec2, ColumnSurface> = HashMap::new();
+        for (position, chunk_entity) in self.chunks.iter() {
+            let Some(chunk) = chunks.get(&chunk_entity.chunk) else {
+                continue;
+            };
+            let surface = surfaces
+                .entry(position.xz())
+                .or_insert([None; (CHUNK_SIZE * CHUNK_SIZE) as usize]);
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let column = (z * CHUNK_SIZE + x) as usize;
+                    let top = (0..CHUNK_SIZE).rev().find_map(|y| {
+                        let handle = &chunk.blocks[ChunkShape::linearize([x, y, z]) as usize];
+                        let block = blocks.get(handle)?;
+                        let world_y = local_to_world(*position, UVec3::new(x, y, z)).y;
+                        (block.get_visibility() != VoxelVisibility::Empty)
+                            .then_some((world_y, handle.id()))
+                    });
+                    if let Some((y, block)) = top {
+                        if surface[column].is_none_or(|(highest, _)| y > highest) {
+                            surface[column] = Some((y, block));
+                        }
+                    }
+                }
+            }
+        }
+        surfaces
+    }
+
+    /// Renders the loaded world top-down, one pixel per block column coloured by its
+    /// highest visible block and hillshaded from the surrounding heights.
+    /// `+x` maps to the right of the image and `+z` to the bottom.
+    pub fn render_map(
+        &self,
+        chunks: &Assets<Chunk>,
+        blocks: &Assets<Block>,
+        images: &Assets<Image>,
+    ) -> Option<RgbImage> {
+        let min = self.chunks.keys().copied().reduce(IVec3::min)?;
+        let max = self.chunks.keys().copied().reduce(IVec3::max)?;
+        let width = (max.x - min.x + 1) as u32 * CHUNK_SIZE;
+        let height = (max.z - min.z + 1) as u32 * CHUNK_SIZE;
+
+        let mut surface: Vec<Option<(i32, AssetId<Block>)>> = vec![None; (width * height) as usize];
+        for (column, column_surface) in self.surface(chunks, blocks) {
+            let origin = (column - min.xz()) * CHUNK_SIZE as i32;
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    surface[((origin.y as u32 + z) * width + origin.x as u32 + x) as usize] =
+                        column_surface[(z * CHUNK_SIZE + x) as usize];
+                }
+            }
+        }
+
+        let mut colors = HashMap::new();
+        let mut map = RgbImage::new(width, height);
+        for z in 0..height {
+            for x in 0..width {
+                let Some((y, block)) = surface[(z * width + x) as usize] else {
+                    continue;
+                };
+                let color = *colors
+                    .entry(block)
+                    .or_insert_with(|| top_face_color(block, blocks, images));
+
+                // Light comes from the north west, so slopes facing it are brighter
+                let shade = match (x, z) {
+                    (0, _) | (_, 0) => 1.,
+                    _ => match surface[((z - 1) * width + x - 1) as usize] {
+                        Some((neighbour, _)) => {
+                            1. + ((y - neighbour) as f32 * HILLSHADE_STEP)
+                                .clamp(-HILLSHADE_LIMIT, HILLSHADE_LIMIT)
+                        }
+                        None => 1.,
+                    },
+                };
+                map.put_pixel(x, z, Rgb(color.map(|c| (c as f32 * shade).min(255.) as u8)));
+            }
+        }
+        Some(map)
+    }
+}
+
+/// Average colour of the top face strip of a block texture
+pub(crate) fn top_face_color(
+    block: AssetId<Block>,
+    blocks: &Assets<Block>,
+    images: &Assets<Image>,
+) -> [u8; 3] {
+    let Some(texture) = blocks
+        .get(block)
+        .and_then(Block::voxel_texture)
+        .and_then(|texture| images.get(texture))
+    else {
+        return MISSING_COLOR;
+    };
+    let Ok(texture) = texture.clone().try_into_dynamic() else {
+        return MISSING_COLOR;
+    };
+    let texture = texture.to_rgb8();
+
+    // Voxel textures are six faces stacked vertically, the top face is the second one
+    let face_height = texture.height() / 6;
+    let (mut sum, mut count) = ([0u64; 3], 0u64);
+    for y in face_height..face_height * 2 {
+        for x in 0..texture.width() {
+            let Rgb(pixel) = texture.get_pixel(x, y);
+            for (sum, channel) in sum.iter_mut().zip(pixel) {
+                *sum += *channel as u64;
+            }
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return MISSING_COLOR;
+    }
+    sum.map(|channel| (channel / count) as u8)
+}
+
+pub(crate) fn export_world_map(
+    mut events: EventReader<ExportWorldMap>,
+    chunks: Res<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    images: Res<Assets<Image>>,
+) {
+    for event in events.read() {
+        let Some(map) = chunks.render_map(&assets_chunks, &blocks, &images) else {
+            warn!("No chunks are loaded, skipping world map export");
+            continue;
+        };
+        match map.save(&event.path) {
+            Ok(()) => info!("World map saved to {}", event.path.display()),
+            Err(err) => error!("Cannot save world map: {err}"),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::{
+    asset::load_internal_asset,
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline},
+    prelude::*,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+        },
+    },
+};
+
+use cubizm_block::{texture_atlas::BlockAtlas, BlockTextureMode};
+
+use crate::{
+    ATTRIBUTE_EMISSIVE, ATTRIBUTE_LIGHT, ATTRIBUTE_OCCLUSION, ATTRIBUTE_PACKED, ATTRIBUTE_TINT,
+};
+
+const CHUNK_MATERIAL_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0x6a0d_5f3b_9c2e_4e71_8b1f_2d7c_04a9_e613);
+const CHUNK_PREPASS_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0x1f4b_8e62_07ad_4c93_a5d8_3b91_c6e0_72f4);
+const PACKED_VERTEX_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0x93c7_25ea_6b14_48d0_8f3e_d0a6_5721_b98c);
+
+/// Material of chunk meshes, a [StandardMaterial] with the block atlas as its base colour
+/// texture, or no base colour texture and the [AtlasTiling::array] for
+/// [BlockTextureMode::Array](cubizm_block::BlockTextureMode::Array).
+/// Faces repeat their block's texture once per block, so merged faces from
+/// [MeshingMode::Greedy](crate::MeshingMode::Greedy) are not stretched. Meshes need an
+/// [ATTRIBUTE_LIGHT] and [ATTRIBUTE_OCCLUSION], which darken them where little light reaches
+/// and in corners, an [ATTRIBUTE_EMISSIVE] making glowing blocks shine regardless and an
+/// [ATTRIBUTE_TINT] colouring them by their biome. Meshes with tangents use the
+/// [BlockMaps](cubizm_block::definition::BlockMaps) of the atlas for normal mapping. Meshes
+/// with an [ATTRIBUTE_PACKED] instead have everything decoded from it in the shaders, their
+/// own one for the depth and shadow prepasses too
+pub type ChunkMaterial = ExtendedMaterial<StandardMaterial, AtlasTiling>;
+
+/// Points `material` at the textures of `atlas`, its base colour and the atlases of the
+/// block maps, or the texture array for [BlockTextureMode::Array]
+pub(crate) fn use_block_atlas(material: &mut ChunkMaterial, atlas: &BlockAtlas) {
+    let base = &mut material.base;
+    // Atlases with several pages are sampled as a texture array of the pages
+    base.base_color_texture = match (atlas.texture_mode(), atlas.clone_page_array()) {
+        (BlockTextureMode::Atlas, None) => Some(atlas.clone_image()),
+        _ => None,
+    };
+    let maps = atlas.maps();
+    base.normal_map_texture = maps.normal.clone();
+    // The map holds the actual values, blocks without one get the defaults in it
+    (base.metallic, base.perceptual_roughness) = match maps.metallic_roughness {
+        Some(_) => (1.0, 1.0),
+        None => (0.0, 0.5),
+    };
+    base.metallic_roughness_texture = maps.metallic_roughness.clone();
+    base.emissive = match maps.emissive {
+        Some(_) => Color::WHITE,
+        None => Color::BLACK,
+    };
+    base.emissive_texture = maps.emissive.clone();
+    material.extension.array = atlas
+        .clone_array_image()
+        .or_else(|| atlas.clone_page_array());
+}
+
+/// Samples the texture rect stored in each vertex's colour, repeating it over the face's `UV_0`
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone, Default)]
+pub struct AtlasTiling {
+    /// Block textures or atlas pages by layer, used by meshes with the layer in `UV_1`
+    #[texture(100, dimension = "2d_array")]
+    #[sampler(101)]
+    pub array: Option<Handle<Image>>,
+}
+
+impl MaterialExtension for AtlasTiling {
+    fn vertex_shader() -> ShaderRef {
+        CHUNK_MATERIAL_SHADER.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        CHUNK_MATERIAL_SHADER.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let packed = ATTRIBUTE_PACKED.at_shader_location(12);
+        // Prepass pipelines keep their own vertex shader and layout, but bevy's read a float
+        // position packed meshes don't have
+        if descriptor.vertex.shader.id() != CHUNK_MATERIAL_SHADER.id() {
+            if layout.contains(ATTRIBUTE_PACKED) {
+                descriptor.vertex.shader = CHUNK_PREPASS_SHADER;
+                descriptor.vertex.buffers = vec![layout.get_layout(&[packed])?];
+            }
+            return Ok(());
+        }
+        if layout.contains(ATTRIBUTE_PACKED) {
+            // The UVs and texture layer are decoded from the packed vertex
+            let defs = ["PACKED_VERTEX", "VERTEX_UVS", "VERTEX_UVS_B"];
+            descriptor.vertex.shader_defs.extend(defs.map(Into::into));
+            if let Some(fragment) = descriptor.fragment.as_mut() {
+                fragment.shader_defs.extend(defs.map(Into::into));
+            }
+            descriptor.vertex.buffers = vec![layout.get_layout(&[packed])?];
+            return Ok(());
+        }
+        let mut attributes = vec![
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(5),
+            ATTRIBUTE_LIGHT.at_shader_location(8),
+            ATTRIBUTE_OCCLUSION.at_shader_location(9),
+            ATTRIBUTE_EMISSIVE.at_shader_location(10),
+            ATTRIBUTE_TINT.at_shader_location(11),
+        ];
+        if layout.contains(Mesh::ATTRIBUTE_UV_1) {
+            attributes.push(Mesh::ATTRIBUTE_UV_1.at_shader_location(3));
+        }
+        if layout.contains(Mesh::ATTRIBUTE_TANGENT) {
+            attributes.push(Mesh::ATTRIBUTE_TANGENT.at_shader_location(4));
+        }
+        descriptor.vertex.buffers = vec![layout.get_layout(&attributes)?];
+        Ok(())
+    }
+}
+
+pub(crate) struct ChunkMaterialPlugin;
+impl Plugin for ChunkMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            PACKED_VERTEX_SHADER,
+            "packed_vertex.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            CHUNK_PREPASS_SHADER,
+            "chunk_prepass.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            CHUNK_MATERIAL_SHADER,
+            "chunk_material.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_plugins(MaterialPlugin::<ChunkMaterial>::default());
+    }
+}
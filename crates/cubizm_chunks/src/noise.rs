@@ -0,0 +1,45 @@
+use std::hash::BuildHasher;
+
+use bevy::{prelude::*, utils::FixedState};
+
+/// A value from 0 to 1 for every `key`, the same for the same key
+pub fn hash_unit(key: impl std::hash::Hash) -> f32 {
+    (FixedState.hash_one(key) >> 40) as f32 / (1u32 << 24) as f32
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+/// Smooth value noise from 0 to 1 at `point`, the same for the same `seed`
+pub fn value_noise(seed: u64, point: Vec2) -> f32 {
+    let cell = point.floor();
+    let corner = |offset: IVec2| {
+        let corner = cell.as_ivec2() + offset;
+        hash_unit((seed, corner.x, corner.y))
+    };
+    let t = (point - cell).to_array().map(smoothstep);
+    let bottom = lerp(corner(IVec2::ZERO), corner(IVec2::X), t[0]);
+    let top = lerp(corner(IVec2::Y), corner(IVec2::ONE), t[0]);
+    lerp(bottom, top, t[1])
+}
+
+/// [value_noise] in 3D
+pub fn value_noise_3d(seed: u64, point: Vec3) -> f32 {
+    let cell = point.floor();
+    let corner = |x: i32, y: i32, z: i32| {
+        let corner = cell.as_ivec3() + IVec3::new(x, y, z);
+        hash_unit((seed, corner.x, corner.y, corner.z))
+    };
+    let t = (point - cell).to_array().map(smoothstep);
+    let layer = |z: i32| {
+        let bottom = lerp(corner(0, 0, z), corner(1, 0, z), t[0]);
+        let top = lerp(corner(0, 1, z), corner(1, 1, z), t[0]);
+        lerp(bottom, top, t[1])
+    };
+    lerp(layer(0), layer(1), t[2])
+}
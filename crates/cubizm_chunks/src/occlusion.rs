@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use block_mesh::ndshape::ConstShape;
+
+use crate::{
+    BlockChanged, Chunk, ChunkFace, ChunkShape, Chunks, Opposite, RenderDistance, CHUNK_SIZE,
+};
+use cubizm_block::definition::Block;
+use cubizm_block::texture_atlas::BlockAtlasRebuilt;
+use cubizm_core::{point_to_chunk, world_to_chunk};
+
+/// Bit of `face` in the masks of [ChunkConnectivity]
+fn face_bit(face: ChunkFace) -> u8 {
+    match face {
+        ChunkFace::Front => 1 << 0,
+        ChunkFace::Back => 1 << 1,
+        ChunkFace::Top => 1 << 2,
+        ChunkFace::Bottom => 1 << 3,
+        ChunkFace::Left => 1 << 4,
+        ChunkFace::Right => 1 << 5,
+    }
+}
+
+/// Which faces of a chunk can see each other through the blocks that are not opaque, one mask
+/// of reachable faces per face in [face_bit] order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkConnectivity([u8; 6]);
+
+impl ChunkConnectivity {
+    /// Every face sees every other, as for chunks of air
+    pub const OPEN: Self = Self([0b11_1111; 6]);
+
+    /// Flood fills every region of blocks that are not opaque and connects the faces each
+    /// region touches
+    pub fn compute(chunk: &Chunk, blocks: &Assets<Block>) -> Self {
+        let open = |index: u32| {
+            blocks
+                .get(&chunk.blocks[index as usize])
+                .is_none_or(|block| !block.occludes())
+        };
+        let mut visited = vec![false; ChunkShape::SIZE as usize];
+        let mut connectivity = [0; 6];
+        let mut stack = Vec::new();
+        for start in 0..ChunkShape::SIZE {
+            if visited[start as usize] || !open(start) {
+                continue;
+            }
+            visited[start as usize] = true;
+            stack.push(start);
+            let mut touched = 0;
+            while let Some(index) = stack.pop() {
+                let position = IVec3::from_array(ChunkShape::delinearize(index).map(|v| v as i32));
+                for face in ChunkFace::ALL {
+                    let next = position + face.offset();
+                    if next.cmplt(IVec3::ZERO).any()
+                        || next.cmpge(IVec3::splat(CHUNK_SIZE as i32)).any()
+                    {
+                        touched |= face_bit(face);
+                        continue;
+                    }
+                    let next = ChunkShape::linearize(next.as_uvec3().to_array());
+                    if !visited[next as usize] && open(next) {
+                        visited[next as usize] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            for face in ChunkFace::ALL {
+                if touched & face_bit(face) != 0 {
+                    connectivity[face_bit(face).trailing_zeros() as usize] |= touched;
+                }
+            }
+        }
+        Self(connectivity)
+    }
+
+    /// Whether something entering through `from` can leave through `to`
+    pub fn connects(&self, from: ChunkFace, to: ChunkFace) -> bool {
+        self.0[face_bit(from).trailing_zeros() as usize] & face_bit(to) != 0
+    }
+}
+
+/// Hides chunks the active camera cannot see through connected air, such as caves behind solid
+/// rock. Unloaded chunks within [RenderDistance] are treated as open air
+#[derive(Resource, Debug)]
+pub struct CaveCulling {
+    pub enabled: bool,
+    connectivity: HashMap<IVec3, ChunkConnectivity>,
+    /// Chunks reachable from `camera_chunk`, recomputed when either changes
+    visible: HashSet<IVec3>,
+    camera_chunk: Option<IVec3>,
+}
+
+impl Default for CaveCulling {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            connectivity: HashMap::new(),
+            visible: HashSet::new(),
+            camera_chunk: None,
+        }
+    }
+}
+
+impl CaveCulling {
+    /// Whether the chunk at `position` can be seen from the active camera, always `true` when
+    /// disabled
+    pub fn is_visible(&self, position: IVec3) -> bool {
+        !self.enabled || self.visible.contains(&position)
+    }
+
+    /// Walks outwards from `camera_chunk` through the faces each chunk connects, never turning
+    /// back towards the camera, up to `radius` chunks away
+    fn walk(&mut self, camera_chunk: IVec3, radius: i32) {
+        self.visible.clear();
+        self.visible.insert(camera_chunk);
+        let mut queue = VecDeque::from([(camera_chunk, None::<ChunkFace>, 0u8)]);
+        while let Some((position, entered, directions)) = queue.pop_front() {
+            let connectivity = self
+                .connectivity
+                .get(&position)
+                .copied()
+                .unwrap_or(ChunkConnectivity::OPEN);
+            for face in ChunkFace::ALL {
+                if directions & face_bit(face.opposite()) != 0 {
+                    continue;
+                }
+                if entered.is_some_and(|entered| !connectivity.connects(entered, face)) {
+                    continue;
+                }
+                let next = position + face.offset();
+                if (next - camera_chunk).abs().max_element() > radius || !self.visible.insert(next)
+                {
+                    continue;
+                }
+                queue.push_back((next, Some(face.opposite()), directions | face_bit(face)));
+            }
+        }
+    }
+}
+
+/// Recomputes the [ChunkConnectivity] of new chunks and of chunks whose blocks changed
+pub(crate) fn update_chunk_connectivity(
+    mut culling: ResMut<CaveCulling>,
+    mut changed: EventReader<BlockChanged>,
+    mut rebuilt: EventReader<BlockAtlasRebuilt>,
+    chunks: Res<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+) {
+    if chunks.is_added() || rebuilt.read().count() > 0 {
+        culling.connectivity.clear();
+    }
+    let mut stale: HashSet<IVec3> = changed
+        .read()
+        .map(|event| world_to_chunk(event.world_pos))
+        .collect();
+    stale.extend(
+        chunks
+            .chunks
+            .keys()
+            .filter(|position| !culling.connectivity.contains_key(*position)),
+    );
+    let mut updated = false;
+    for position in stale {
+        let Some(chunk) = chunks
+            .chunks
+            .get(&position)
+            .and_then(|chunk_entity| assets_chunks.get(&chunk_entity.chunk))
+        else {
+            continue;
+        };
+        culling
+            .connectivity
+            .insert(position, ChunkConnectivity::compute(chunk, &blocks));
+        updated = true;
+    }
+    if updated {
+        // Walk again with the new connectivity
+        culling.camera_chunk = None;
+    }
+}
+
+/// Finds the chunks reachable from the active camera whenever it moves to another chunk or the
+/// [ChunkConnectivity] changed
+pub(crate) fn update_visible_chunks(
+    mut culling: ResMut<CaveCulling>,
+    render_distance: Res<RenderDistance>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    let Some((_, camera)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let camera_chunk = point_to_chunk(camera.translation());
+    if !culling.enabled
+        || (culling.camera_chunk == Some(camera_chunk) && !render_distance.is_changed())
+    {
+        return;
+    }
+    culling.camera_chunk = Some(camera_chunk);
+    culling.walk(camera_chunk, render_distance.0 as i32);
+}
@@ -0,0 +1,280 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use block_mesh::ndshape::ConstShape;
+
+use crate::{Chunk, ChunkShape, RaySteps, CHUNK_SIZE};
+use cubizm_block::definition::Block;
+use cubizm_core::{world_to_chunk, world_to_local};
+
+/// Width in blocks of the regions a [ChunkOccupancy] groups its blocks in
+pub const REGION_SIZE: i32 = 4;
+
+/// Regions along each axis of a chunk
+const REGIONS: i32 = CHUNK_SIZE as i32 / REGION_SIZE;
+
+/// Which blocks of a chunk rays can hit, see [Block::is_hit_by_rays]. One bit per block, the
+/// bits of each region of [REGION_SIZE] blocks along every axis in one word, so empty regions
+/// are found without looking at their blocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkOccupancy {
+    regions: [u64; (REGIONS * REGIONS * REGIONS) as usize],
+}
+
+impl Default for ChunkOccupancy {
+    fn default() -> Self {
+        Self {
+            regions: [0; (REGIONS * REGIONS * REGIONS) as usize],
+        }
+    }
+}
+
+impl ChunkOccupancy {
+    /// The occupancy of `chunk`, taking blocks without a loaded definition as occupied
+    pub fn new(chunk: &Chunk, blocks: &Assets<Block>) -> Self {
+        let mut occupancy = Self::default();
+        for (index, block) in chunk.blocks.iter().enumerate() {
+            if blocks.get(block).is_none_or(Block::is_hit_by_rays) {
+                let local = UVec3::from_array(ChunkShape::delinearize(index as u32));
+                occupancy.set(local, true);
+            }
+        }
+        occupancy
+    }
+
+    /// The word of the region holding the block at `local` in the chunk and the block's bit
+    fn index(local: UVec3) -> (usize, u32) {
+        let region = local.as_ivec3() / REGION_SIZE;
+        let block = local.as_ivec3() % REGION_SIZE;
+        (
+            region_index(region),
+            (block.x + (block.y + block.z * REGION_SIZE) * REGION_SIZE) as u32,
+        )
+    }
+
+    pub fn is_occupied(&self, local: UVec3) -> bool {
+        let (region, bit) = Self::index(local);
+        self.regions[region] & (1 << bit) != 0
+    }
+
+    pub fn set(&mut self, local: UVec3, occupied: bool) {
+        let (region, bit) = Self::index(local);
+        match occupied {
+            true => self.regions[region] |= 1 << bit,
+            false => self.regions[region] &= !(1 << bit),
+        }
+    }
+
+    /// Whether any block of the region at `region` in the chunk, counted in regions, is
+    /// occupied
+    pub fn is_region_occupied(&self, region: IVec3) -> bool {
+        self.regions[region_index(region)] != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.iter().all(|region| *region == 0)
+    }
+}
+
+fn region_index(region: IVec3) -> usize {
+    (region.x + (region.y + region.z * REGIONS) * REGIONS) as usize
+}
+
+/// The [ChunkOccupancy] of every loaded chunk and which of them hold any occupied block, kept
+/// by [Chunks](crate::Chunks) as blocks change so long rays skip the empty parts of the world
+#[derive(Debug, Default)]
+pub struct OccupancyMap {
+    chunks: HashMap<IVec3, ChunkOccupancy>,
+    /// Chunks with an occupied block
+    occupied: HashSet<IVec3>,
+}
+
+impl OccupancyMap {
+    pub fn get(&self, chunk: IVec3) -> Option<&ChunkOccupancy> {
+        self.chunks.get(&chunk)
+    }
+
+    /// Whether the chunk at `chunk` is loaded and holds an occupied block
+    pub fn is_chunk_occupied(&self, chunk: IVec3) -> bool {
+        self.occupied.contains(&chunk)
+    }
+
+    pub(crate) fn insert_chunk(&mut self, position: IVec3, occupancy: ChunkOccupancy) {
+        match occupancy.is_empty() {
+            true => self.occupied.remove(&position),
+            false => self.occupied.insert(position),
+        };
+        self.chunks.insert(position, occupancy);
+    }
+
+    pub(crate) fn remove_chunk(&mut self, position: IVec3) {
+        self.chunks.remove(&position);
+        self.occupied.remove(&position);
+    }
+
+    /// Marks the block at world `position` occupied or not, in loaded chunks only
+    pub(crate) fn set(&mut self, position: IVec3, occupied: bool) {
+        let (chunk, local) = (world_to_chunk(position), world_to_local(position));
+        let Some(occupancy) = self.chunks.get_mut(&chunk) else {
+            return;
+        };
+        occupancy.set(local, occupied);
+        match occupied || !occupancy.is_empty() {
+            true => self.occupied.insert(chunk),
+            false => self.occupied.remove(&chunk),
+        };
+    }
+
+    /// The steps of [RaySteps] through the occupied blocks only, skipping the chunks and regions
+    /// without any. `None` if `direction` is zero
+    pub fn ray_steps(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+    ) -> Option<impl Iterator<Item = (IVec3, IVec3, f32)> + '_> {
+        let direction = direction.try_normalize()?;
+        // Chunks span the blocks from one past their origin, as do the regions inside them
+        let chunk_grid = (CHUNK_SIZE as i32, 1);
+        let region_grid = (REGION_SIZE, 1);
+        let block_grid = (1, 0);
+        let chunks = RaySteps::through_grid(
+            origin,
+            direction,
+            max_distance,
+            chunk_grid,
+            grid_cell(origin, chunk_grid),
+            IVec3::ZERO,
+            0.,
+        );
+        let cells = move |grid, first: IVec3, count: i32, normal, distance| {
+            let last = first + IVec3::splat(count - 1);
+            let cell = grid_cell(origin + direction * distance, grid).clamp(first, last);
+            RaySteps::through_grid(
+                origin,
+                direction,
+                max_distance,
+                grid,
+                cell,
+                normal,
+                distance,
+            )
+            .take_while(move |(cell, ..)| cell.cmpge(first).all() && cell.cmple(last).all())
+        };
+        Some(
+            chunks
+                .filter_map(|(chunk, normal, distance)| {
+                    let occupancy = self
+                        .chunks
+                        .get(&chunk)
+                        .filter(|_| self.occupied.contains(&chunk))?;
+                    Some((occupancy, chunk, normal, distance))
+                })
+                .flat_map(move |(occupancy, chunk, normal, distance)| {
+                    let first_region = chunk * REGIONS;
+                    cells(region_grid, first_region, REGIONS, normal, distance)
+                        .filter(move |(region, ..)| {
+                            occupancy.is_region_occupied(*region - first_region)
+                        })
+                        .flat_map(move |(region, normal, distance)| {
+                            let first_block = region * REGION_SIZE + IVec3::ONE;
+                            cells(block_grid, first_block, REGION_SIZE, normal, distance).filter(
+                                move |(block, ..)| occupancy.is_occupied(world_to_local(*block)),
+                            )
+                        })
+                }),
+        )
+    }
+}
+
+/// The cell of a grid of `size` blocks wide cells starting at block `offset` holding `point`
+fn grid_cell(point: Vec3, (size, offset): (i32, i32)) -> IVec3 {
+    ((point - offset as f32) / size as f32).floor().as_ivec3()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(blocks: &[IVec3]) -> OccupancyMap {
+        let mut map = OccupancyMap::default();
+        for block in blocks {
+            let chunk = world_to_chunk(*block);
+            if map.get(chunk).is_none() {
+                map.insert_chunk(chunk, ChunkOccupancy::default());
+            }
+            map.set(*block, true);
+        }
+        map
+    }
+
+    #[test]
+    fn tracks_blocks_regions_and_chunks() {
+        let block = IVec3::new(17, -3, 5);
+        let mut map = map(&[block]);
+        let (chunk, local) = (world_to_chunk(block), world_to_local(block));
+        let occupancy = map.get(chunk).unwrap();
+        assert!(occupancy.is_occupied(local));
+        assert!(!occupancy.is_occupied(world_to_local(block + IVec3::X)));
+        let region = local.as_ivec3() / REGION_SIZE;
+        assert!(occupancy.is_region_occupied(region));
+        assert!(!occupancy.is_region_occupied(region + IVec3::X));
+        assert!(map.is_chunk_occupied(chunk));
+
+        map.set(block, false);
+        assert!(map.get(chunk).unwrap().is_empty());
+        assert!(!map.is_chunk_occupied(chunk));
+    }
+
+    #[test]
+    fn steps_like_the_block_steps_through_occupied_blocks() {
+        for (origin, direction) in [
+            (Vec3::new(0.3, 0.6, 0.7), Vec3::new(1., 0.43, 0.57)),
+            (Vec3::new(75.2, 33.1, 42.9), Vec3::new(-1., -0.41, -0.53)),
+            (Vec3::new(-5.5, 4.5, -2.5), Vec3::new(0.93, -0.11, 0.37)),
+        ] {
+            let steps = RaySteps::new(origin, direction, 120.).unwrap();
+            // Blocks far apart along the ray and a few next to it
+            let mut occupied: Vec<IVec3> =
+                steps.clone().step_by(23).map(|(block, ..)| block).collect();
+            occupied.extend(
+                occupied
+                    .clone()
+                    .iter()
+                    .map(|block| *block + IVec3::new(0, 5, 0)),
+            );
+            let map = map(&occupied);
+
+            let expected = steps
+                .filter(|(block, ..)| occupied.contains(block))
+                .collect::<Vec<_>>();
+            let fast = map
+                .ray_steps(origin, direction, 120.)
+                .unwrap()
+                .collect::<Vec<_>>();
+            assert_eq!(fast.len(), expected.len());
+            for ((block, normal, distance), (expected, expected_normal, expected_distance)) in
+                fast.into_iter().zip(expected)
+            {
+                assert_eq!((block, normal), (expected, expected_normal));
+                assert!((distance - expected_distance).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn skips_unloaded_and_empty_chunks() {
+        let mut map = map(&[IVec3::new(40, 8, 8)]);
+        map.insert_chunk(IVec3::new(1, 0, 0), ChunkOccupancy::default());
+        assert!(!map.is_chunk_occupied(IVec3::new(1, 0, 0)));
+        let steps = map
+            .ray_steps(Vec3::new(0.5, 8.5, 8.5), Vec3::X, 64.)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].0, IVec3::new(40, 8, 8));
+        assert_eq!(steps[0].1, IVec3::NEG_X);
+        assert!(map.ray_steps(Vec3::ZERO, Vec3::ZERO, 1.).is_none());
+    }
+}
@@ -0,0 +1,80 @@
+use std::hash::BuildHasher;
+
+use bevy::{prelude::*, utils::FixedState};
+use block_mesh::ndshape::ConstShape;
+use serde::{Deserialize, Serialize};
+
+use cubizm_block::{block_key, BlockRegistry, BASE_NAMESPACE};
+use cubizm_core::{chunk_to_world, CHUNK_SIZE};
+
+use crate::{Chunk, ChunkShape};
+
+/// Veins of a block the generator scatters through the terrain
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OreRule {
+    /// Registry name of the block, see [block_key]
+    pub block: String,
+    /// Most blocks in a vein
+    pub cluster_size: u32,
+    /// Lowest world height of the start of a vein
+    pub min_height: i32,
+    /// Highest world height of the start of a vein
+    pub max_height: i32,
+    /// Veins tried per chunk, each only starts in a solid block within the heights
+    pub attempts: u32,
+}
+
+impl OreRule {
+    /// Grows the veins of the rule in `chunk`, the same ones for the same `seed`. Veins only
+    /// replace solid blocks and stop at the border of the chunk
+    pub fn scatter(
+        &self,
+        chunk: &mut Chunk,
+        seed: u64,
+        rule_index: usize,
+        registry: &BlockRegistry,
+    ) {
+        let Some(air) = registry.get(&block_key(BASE_NAMESPACE, "Air")) else {
+            return;
+        };
+        let Some(ore) = registry.get(&self.block) else {
+            warn!("Ore {} is not a registered block", self.block);
+            return;
+        };
+        // Local heights of the chunk within the heights of the rule
+        let bottom = chunk_to_world(chunk.position).y + 1;
+        let min = (self.min_height - bottom).max(0);
+        let max = (self.max_height - bottom).min(CHUNK_SIZE as i32 - 1);
+        if min > max {
+            return;
+        }
+        let position = chunk.position.to_array();
+        for attempt in 0..self.attempts {
+            let roll = |step: u32| FixedState.hash_one((seed, position, rule_index, attempt, step));
+            let start = roll(0);
+            let mut local = IVec3::new(
+                (start % CHUNK_SIZE as u64) as i32,
+                min + ((start >> 16) % (max - min + 1) as u64) as i32,
+                ((start >> 32) % CHUNK_SIZE as u64) as i32,
+            );
+            for step in 0..self.cluster_size {
+                if local.cmplt(IVec3::ZERO).any()
+                    || local.cmpge(IVec3::splat(CHUNK_SIZE as i32)).any()
+                {
+                    break;
+                }
+                let index = ChunkShape::linearize(local.as_uvec3().to_array()) as usize;
+                if chunk.blocks[index] != *air {
+                    chunk.blocks[index] = ore.clone();
+                }
+                // Walks to a random face neighbour for the next block
+                let direction = roll(step + 1) % 6;
+                let axis = IVec3::AXES[(direction / 2) as usize];
+                local += match direction % 2 {
+                    0 => axis,
+                    _ => -axis,
+                };
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+#define_import_path cubizm_chunks::packed_vertex
+
+// Decodes the `Vertex_Packed` attribute of packed chunk meshes, see `MeshBuffers::pack`
+
+fn position(packed: vec4<u32>) -> vec3<f32> {
+    let position = vec3<u32>(packed.x, packed.x >> 10u, packed.x >> 20u) & vec3<u32>(0x3ffu);
+    return vec3<f32>(position) / 32.0;
+}
+
+// The six sides and then the two diagonals of cross blocks
+fn normal(packed: vec4<u32>) -> vec3<f32> {
+    var normals = array<vec3<f32>, 8>(
+        vec3<f32>(1.0, 0.0, 0.0),
+        vec3<f32>(-1.0, 0.0, 0.0),
+        vec3<f32>(0.0, 1.0, 0.0),
+        vec3<f32>(0.0, -1.0, 0.0),
+        vec3<f32>(0.0, 0.0, 1.0),
+        vec3<f32>(0.0, 0.0, -1.0),
+        vec3<f32>(-0.70710678, 0.0, 0.70710678),
+        vec3<f32>(-0.70710678, 0.0, -0.70710678),
+    );
+    return normals[(packed.y >> 24u) & 7u];
+}
+
+fn uv(packed: vec4<u32>) -> vec2<f32> {
+    return vec2<f32>(f32(packed.y & 0xfffu), f32((packed.y >> 12u) & 0xfffu)) / 128.0;
+}
+
+// Which sixth of its texture the face shows
+fn texture_face(packed: vec4<u32>) -> f32 {
+    return f32((packed.y >> 27u) & 7u);
+}
+
+fn occlusion(packed: vec4<u32>) -> f32 {
+    return f32(packed.y >> 30u) / 3.0;
+}
+
+fn tint(packed: vec4<u32>) -> vec3<f32> {
+    return unpack4x8unorm(packed.z).rgb;
+}
+
+fn texture_layer(packed: vec4<u32>) -> f32 {
+    return f32(packed.z >> 24u);
+}
+
+fn emissive(packed: vec4<u32>) -> vec3<f32> {
+    return unpack4x8unorm(packed.w).rgb;
+}
+
+// Sky and block light, from 0 to 1
+fn light(packed: vec4<u32>) -> vec2<f32> {
+    return vec2<f32>(f32((packed.w >> 24u) & 15u), f32(packed.w >> 28u)) / 15.0;
+}
@@ -0,0 +1,355 @@
+use bevy::{
+    app::AppExit,
+    asset::ron,
+    ecs::world::{EntityRef, EntityWorldMut},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+use cubizm_core::point_to_chunk;
+
+use crate::{Chunk, ChunkLoaded, ChunkUnloaded, Chunks, SaveWorld};
+
+#[derive(Debug, Error)]
+pub enum PersistenceError {
+    #[error("No entity kind {0} is registered")]
+    UnknownKind(String),
+    #[error("Entity has no {0} to save")]
+    MissingComponent(&'static str),
+    #[error(transparent)]
+    Serialize(#[from] ron::Error),
+    #[error(transparent)]
+    Deserialize(#[from] ron::error::SpannedError),
+}
+
+/// An entity saved with the chunk it stood in, spawned again when the chunk loads
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedEntity {
+    /// Name of its kind in [PersistentEntities]
+    pub kind: String,
+    /// Translation of its [Transform]
+    pub position: Vec3,
+    /// Its state as RON, as the kind's [SaveEntityFn] wrote it
+    pub data: String,
+}
+
+/// Spawns the entity into the world like a saved one, e.g. as sent by the
+/// [PopulationRule](crate::PopulationRule)s. Entities of kinds that aren't registered are not
+/// spawned
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct SpawnRequest(pub SavedEntity);
+
+/// Saves the entity with the chunk it stands in, as the kind registered in
+/// [PersistentEntities] under the name
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Persistent(pub String);
+
+/// Writes the state of an entity as RON
+pub type SaveEntityFn = fn(EntityRef) -> Result<String, PersistenceError>;
+
+/// Restores the state [SaveEntityFn] wrote onto a newly spawned entity, which already has its
+/// [Persistent] kind and a [SpatialBundle] at its saved position
+pub type SpawnEntityFn = fn(&mut EntityWorldMut, &str) -> Result<(), PersistenceError>;
+
+#[derive(Debug, Clone, Copy)]
+struct EntityKind {
+    save: SaveEntityFn,
+    spawn: SpawnEntityFn,
+}
+
+fn save_component<T: Component + Serialize>(entity: EntityRef) -> Result<String, PersistenceError> {
+    let component = entity.get::<T>().ok_or(PersistenceError::MissingComponent(
+        std::any::type_name::<T>(),
+    ))?;
+    Ok(ron::ser::to_string(component)?)
+}
+
+fn spawn_component<T: Component + DeserializeOwned>(
+    entity: &mut EntityWorldMut,
+    data: &str,
+) -> Result<(), PersistenceError> {
+    entity.insert(ron::de::from_str::<T>(data)?);
+    Ok(())
+}
+
+/// The kinds of entities saved with chunks, each with the functions saving and spawning them.
+/// Entities are saved by giving them the [Persistent] component with their kind
+#[derive(Resource, Debug, Default)]
+pub struct PersistentEntities {
+    kinds: HashMap<String, EntityKind>,
+}
+
+impl PersistentEntities {
+    /// Registers `kind`, saving the component `T` of its entities and inserting it again when
+    /// they spawn. Anything else, like meshes, is added by systems watching for `T`
+    pub fn register<T: Component + Serialize + DeserializeOwned>(
+        &mut self,
+        kind: impl Into<String>,
+    ) {
+        self.register_with(kind, save_component::<T>, spawn_component::<T>);
+    }
+
+    /// Registers `kind` with its own functions, replacing the ones it had
+    pub fn register_with(
+        &mut self,
+        kind: impl Into<String>,
+        save: SaveEntityFn,
+        spawn: SpawnEntityFn,
+    ) {
+        self.kinds.insert(kind.into(), EntityKind { save, spawn });
+    }
+
+    pub fn contains(&self, kind: &str) -> bool {
+        self.kinds.contains_key(kind)
+    }
+
+    /// The entity as it is saved, with its [Persistent] kind and [Transform]
+    pub fn save(&self, entity: EntityRef) -> Result<SavedEntity, PersistenceError> {
+        let kind = entity
+            .get::<Persistent>()
+            .ok_or(PersistenceError::MissingComponent("Persistent"))?;
+        let position = entity
+            .get::<Transform>()
+            .ok_or(PersistenceError::MissingComponent("Transform"))?
+            .translation;
+        let registered = self
+            .kinds
+            .get(&kind.0)
+            .ok_or_else(|| PersistenceError::UnknownKind(kind.0.clone()))?;
+        Ok(SavedEntity {
+            kind: kind.0.clone(),
+            position,
+            data: (registered.save)(entity)?,
+        })
+    }
+
+    /// Spawns `saved` into the world, despawning it again if its state can't be restored
+    pub fn spawn(
+        &self,
+        world: &mut World,
+        saved: &SavedEntity,
+    ) -> Result<Entity, PersistenceError> {
+        let registered = self
+            .kinds
+            .get(&saved.kind)
+            .ok_or_else(|| PersistenceError::UnknownKind(saved.kind.clone()))?;
+        let mut entity = world.spawn((
+            Persistent(saved.kind.clone()),
+            SpatialBundle::from_transform(Transform::from_translation(saved.position)),
+        ));
+        match (registered.spawn)(&mut entity, &saved.data) {
+            Ok(()) => Ok(entity.id()),
+            Err(err) => {
+                entity.despawn();
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Spawns the entities saved with the chunks that loaded, taking them out of the chunks. Those
+/// chunks are saved again, so the entities are saved where they are by then
+pub(crate) fn spawn_saved_entities(
+    mut commands: Commands,
+    mut loaded: EventReader<ChunkLoaded>,
+    mut chunks: ResMut<Chunks>,
+    mut assets_chunks: ResMut<Assets<Chunk>>,
+) {
+    for ChunkLoaded(position) in loaded.read() {
+        let Some(chunk) = chunks
+            .chunks
+            .get(position)
+            .and_then(|chunk_entity| assets_chunks.get_mut(&chunk_entity.chunk))
+            .filter(|chunk| !chunk.entities.is_empty())
+        else {
+            continue;
+        };
+        let saved = std::mem::take(&mut chunk.entities);
+        chunks.bypass_change_detection().mark_dirty(*position);
+        commands.add(move |world: &mut World| {
+            world.resource_scope(|world, kinds: Mut<PersistentEntities>| {
+                for entity in &saved {
+                    if let Err(err) = kinds.spawn(world, entity) {
+                        warn!("Failed to spawn saved {} entity: {err}", entity.kind);
+                    }
+                }
+            });
+        });
+    }
+}
+
+fn spawn_requested_entities(mut commands: Commands, mut requests: EventReader<SpawnRequest>) {
+    let requested: Vec<SavedEntity> = requests
+        .read()
+        .map(|SpawnRequest(entity)| entity.clone())
+        .collect();
+    if requested.is_empty() {
+        return;
+    }
+    commands.add(move |world: &mut World| {
+        world.resource_scope(|world, kinds: Mut<PersistentEntities>| {
+            for entity in &requested {
+                if let Err(err) = kinds.spawn(world, entity) {
+                    warn!("Failed to spawn requested {} entity: {err}", entity.kind);
+                }
+            }
+        });
+    });
+}
+
+/// Despawns the persistent entities of unloaded chunks, which like their blocks are only
+/// kept as far as they were saved
+fn despawn_unloaded_entities(
+    mut commands: Commands,
+    mut unloaded: EventReader<ChunkUnloaded>,
+    entities: Query<(Entity, &Transform), With<Persistent>>,
+) {
+    let unloaded: HashSet<IVec3> = unloaded.read().map(|ChunkUnloaded(chunk)| *chunk).collect();
+    if unloaded.is_empty() {
+        return;
+    }
+    for (entity, transform) in entities.iter() {
+        if unloaded.contains(&point_to_chunk(transform.translation)) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Puts every persistent entity into the chunk it stands in before the world is saved,
+/// marking the chunks whose entities changed dirty. Entities outside the loaded chunks are
+/// not saved
+pub(crate) fn store_persistent_entities(
+    mut save_events: EventReader<SaveWorld>,
+    mut exit_events: EventReader<AppExit>,
+    kinds: Res<PersistentEntities>,
+    entities: Query<EntityRef, With<Persistent>>,
+    mut chunks: ResMut<Chunks>,
+    mut assets_chunks: ResMut<Assets<Chunk>>,
+) {
+    let requested = save_events.read().count() > 0;
+    let exiting = exit_events.read().count() > 0;
+    if !requested && !exiting {
+        return;
+    }
+    let mut by_chunk: HashMap<IVec3, Vec<SavedEntity>> = HashMap::new();
+    for entity in entities.iter() {
+        match kinds.save(entity) {
+            Ok(saved) => by_chunk
+                .entry(point_to_chunk(saved.position))
+                .or_default()
+                .push(saved),
+            Err(err) => warn!("Failed to save entity {:?}: {err}", entity.id()),
+        }
+    }
+    // Saving does not change the blocks, don't wake up systems watching the chunks
+    let chunks = chunks.bypass_change_detection();
+    let positions: Vec<IVec3> = chunks.chunks.keys().copied().collect();
+    for position in positions {
+        let Some(chunk) = chunks
+            .chunks
+            .get(&position)
+            .and_then(|chunk_entity| assets_chunks.get_mut(&chunk_entity.chunk))
+        else {
+            continue;
+        };
+        let entities = by_chunk.remove(&position).unwrap_or_default();
+        if chunk.entities != entities {
+            chunk.entities = entities;
+            chunks.mark_dirty(position);
+        }
+    }
+}
+
+/// Saves the entities with a [Persistent] kind along with the chunk they stand in, and spawns
+/// them again when it loads, see [PersistentEntities]
+pub(crate) struct EntityPersistencePlugin;
+impl Plugin for EntityPersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PersistentEntities>()
+            .add_event::<SpawnRequest>()
+            .add_systems(
+                Update,
+                (spawn_saved_entities, despawn_unloaded_entities).run_if(resource_exists::<Chunks>),
+            )
+            .add_systems(Update, spawn_requested_entities);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        name: String,
+        count: u32,
+    }
+
+    fn kinds() -> PersistentEntities {
+        let mut kinds = PersistentEntities::default();
+        kinds.register::<Item>("item");
+        kinds
+    }
+
+    #[test]
+    fn saves_and_spawns_registered_entities() {
+        let kinds = kinds();
+        let mut world = World::new();
+        let item = Item {
+            name: "stick".to_string(),
+            count: 3,
+        };
+        let entity = world
+            .spawn((
+                Persistent("item".to_string()),
+                Transform::from_xyz(1., 2., -3.),
+                item.clone(),
+            ))
+            .id();
+        let saved = kinds.save(world.entity(entity)).unwrap();
+        assert_eq!(saved.kind, "item");
+        assert_eq!(saved.position, Vec3::new(1., 2., -3.));
+
+        let spawned = kinds.spawn(&mut world, &saved).unwrap();
+        let spawned = world.entity(spawned);
+        assert_eq!(spawned.get::<Item>(), Some(&item));
+        assert_eq!(
+            spawned.get::<Transform>().unwrap().translation,
+            saved.position
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_and_broken_entities() {
+        let kinds = kinds();
+        let mut world = World::new();
+        let unknown = world
+            .spawn((Persistent("mob".to_string()), Transform::default()))
+            .id();
+        assert!(matches!(
+            kinds.save(world.entity(unknown)),
+            Err(PersistenceError::UnknownKind(_))
+        ));
+        let missing = world
+            .spawn((Persistent("item".to_string()), Transform::default()))
+            .id();
+        assert!(matches!(
+            kinds.save(world.entity(missing)),
+            Err(PersistenceError::MissingComponent(_))
+        ));
+
+        let broken = SavedEntity {
+            kind: "item".to_string(),
+            position: Vec3::ZERO,
+            data: "(name:".to_string(),
+        };
+        let before = world.entities().len();
+        assert!(matches!(
+            kinds.spawn(&mut world, &broken),
+            Err(PersistenceError::Deserialize(_))
+        ));
+        assert_eq!(world.entities().len(), before);
+    }
+}
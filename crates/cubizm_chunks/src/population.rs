@@ -0,0 +1,459 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    prelude::*,
+    utils::{BoxedFuture, HashMap, HashSet},
+};
+use block_mesh::{Voxel, VoxelVisibility};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use cubizm_block::definition::{Block, MAX_LIGHT};
+use cubizm_block::BlockRegistry;
+use cubizm_core::{chunk_to_world, mods::ModPacks, point_to_chunk, AppState};
+
+use crate::persistence::spawn_saved_entities;
+use crate::{
+    hash_unit, BiomeLookup, Chunk, ChunkLoaded, Chunks, ChunksPluginSettings, LightChannel,
+    Persistent, SavedEntity, SpawnRequest, CHUNK_SIZE,
+};
+
+/// Where an entity kind spawns on its own and how many of it a chunk holds, loaded from
+/// `.population` files. Rules are tried on every chunk as it loads and again on every
+/// [PopulationSettings::tick]
+#[derive(Asset, TypePath, Debug, Clone, PartialEq)]
+pub struct PopulationRule {
+    /// Kind of the spawned entities in [PersistentEntities](crate::PersistentEntities)
+    pub kind: String,
+    /// State of the spawned entities as RON, as the kind's
+    /// [SpawnEntityFn](crate::SpawnEntityFn) reads it
+    pub data: String,
+    /// Names of the [Biome](crate::Biome)s it spawns in, every biome if empty
+    pub biomes: Vec<String>,
+    pub surface: SurfaceCondition,
+    /// Least and most light, the brighter of sky and block light, both included
+    pub light: (u8, u8),
+    /// Registry names of the blocks it spawns on, any opaque block if empty
+    pub ground: Vec<String>,
+    /// Chance from 0 to 1 that an attempt spawns an entity
+    pub chance: f32,
+    /// Spawn positions tried per chunk each time the rule is tried
+    pub attempts: u32,
+    /// Most entities of the kind in a chunk, those spawned otherwise included
+    pub max_per_chunk: u32,
+}
+
+/// Whether a [PopulationRule] spawns under the open sky
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SurfaceCondition {
+    #[default]
+    Any,
+    /// Only where the sky light is full, nothing covers the spawn position
+    Open,
+    /// Only where something covers the spawn position, like in caves
+    Covered,
+}
+
+/// [PopulationRule] as written in `.population` files
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedPopulationRule {
+    pub kind: String,
+    /// A unit struct if left out
+    #[serde(default = "SerializedPopulationRule::unit")]
+    pub data: String,
+    #[serde(default)]
+    pub biomes: Vec<String>,
+    #[serde(default)]
+    pub surface: SurfaceCondition,
+    #[serde(default = "SerializedPopulationRule::any_light")]
+    pub light: (u8, u8),
+    #[serde(default)]
+    pub ground: Vec<String>,
+    pub chance: f32,
+    #[serde(default = "SerializedPopulationRule::one")]
+    pub attempts: u32,
+    pub max_per_chunk: u32,
+}
+
+impl SerializedPopulationRule {
+    fn unit() -> String {
+        "()".to_string()
+    }
+
+    fn any_light() -> (u8, u8) {
+        (0, MAX_LIGHT)
+    }
+
+    fn one() -> u32 {
+        1
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PopulationLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error("{0} spawns in light between {1} and {2}, which are not light levels in order")]
+    InvalidLight(String, u8, u8),
+    #[error("{0} spawns with chance {1}, which is not between 0 and 1")]
+    InvalidChance(String, f32),
+}
+
+impl TryFrom<SerializedPopulationRule> for PopulationRule {
+    type Error = PopulationLoaderError;
+
+    fn try_from(value: SerializedPopulationRule) -> Result<Self, Self::Error> {
+        let (min, max) = value.light;
+        if min > max || max > MAX_LIGHT {
+            return Err(PopulationLoaderError::InvalidLight(value.kind, min, max));
+        }
+        if !(0. ..=1.).contains(&value.chance) {
+            return Err(PopulationLoaderError::InvalidChance(
+                value.kind,
+                value.chance,
+            ));
+        }
+        Ok(Self {
+            kind: value.kind,
+            data: value.data,
+            biomes: value.biomes,
+            surface: value.surface,
+            light: value.light,
+            ground: value.ground,
+            chance: value.chance,
+            attempts: value.attempts,
+            max_per_chunk: value.max_per_chunk,
+        })
+    }
+}
+
+/// A position a [PopulationRule] may spawn an entity at, the empty block above an opaque one
+#[derive(Debug, Clone)]
+pub struct SpawnSite<'a> {
+    /// The empty block the entity stands in
+    pub position: IVec3,
+    /// Name of the biome of its column
+    pub biome: Option<&'a str>,
+    /// The block below it
+    pub ground: Handle<Block>,
+    pub sky_light: u8,
+    pub block_light: u8,
+}
+
+impl PopulationRule {
+    /// Whether the rule spawns its kind at `site`. `registry` looks up the
+    /// [ground](PopulationRule::ground) blocks, rules with some never spawn without it
+    pub fn allows(&self, site: &SpawnSite, registry: Option<&BlockRegistry>) -> bool {
+        let biome = self.biomes.is_empty()
+            || site
+                .biome
+                .is_some_and(|biome| self.biomes.iter().any(|name| name == biome));
+        let surface = match self.surface {
+            SurfaceCondition::Any => true,
+            SurfaceCondition::Open => site.sky_light == MAX_LIGHT,
+            SurfaceCondition::Covered => site.sky_light < MAX_LIGHT,
+        };
+        let light = site.sky_light.max(site.block_light);
+        let ground = self.ground.is_empty()
+            || registry.is_some_and(|registry| {
+                self.ground
+                    .iter()
+                    .any(|name| registry.find(name) == Some(&site.ground))
+            });
+        biome && surface && (self.light.0..=self.light.1).contains(&light) && ground
+    }
+}
+
+/// Configuration of the population of chunks by [PopulationRule]s
+#[derive(Resource, Debug)]
+pub struct PopulationSettings {
+    /// Tries the rules on every loaded chunk again on each tick, `None` only tries them as
+    /// chunks load
+    pub tick: Option<Timer>,
+}
+
+impl Default for PopulationSettings {
+    fn default() -> Self {
+        Self {
+            tick: Some(Timer::new(Duration::from_secs(20), TimerMode::Repeating)),
+        }
+    }
+}
+
+/// Folders of `.population` files, in the base assets and every mod
+#[derive(Resource, Debug)]
+pub struct PopulationFolder(pub Vec<Handle<LoadedFolder>>);
+
+#[derive(Default)]
+pub struct PopulationLoader;
+
+impl AssetLoader for PopulationLoader {
+    type Asset = PopulationRule;
+    type Settings = ();
+    type Error = PopulationLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let ron: SerializedPopulationRule = ron::de::from_bytes(&bytes)?;
+            ron.try_into()
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["population"]
+    }
+}
+
+fn load_population_rules(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<ChunksPluginSettings>,
+    mods: Option<Res<ModPacks>>,
+) {
+    let path = &settings.population_path;
+    let mod_folders = mods
+        .iter()
+        .flat_map(|mods| mods.iter())
+        .filter(|pack| pack.has_dir(path))
+        .map(|pack| asset_server.load_folder(pack.asset_path(path)));
+    commands.insert_resource(PopulationFolder(
+        std::iter::once(asset_server.load_folder(path.clone()))
+            .chain(mod_folders)
+            .collect(),
+    ));
+}
+
+/// The sites in the block column at world `column` of the chunk at `position`, lowest first
+fn spawn_sites(
+    chunks: &Chunks,
+    position: IVec3,
+    column: IVec2,
+    assets_chunks: &Assets<Chunk>,
+    blocks: &Assets<Block>,
+) -> Vec<(IVec3, Handle<Block>)> {
+    let block = |position: IVec3| {
+        let handle = chunks.get_block(position, assets_chunks).ok()?;
+        let definition = blocks.get(&handle)?;
+        Some((handle, definition))
+    };
+    let bottom = chunk_to_world(position).y + 1;
+    (bottom..bottom + CHUNK_SIZE as i32)
+        .filter_map(|y| {
+            let site = IVec3::new(column.x, y, column.y);
+            let (ground, below) = block(site - IVec3::Y)?;
+            let (_, above) = block(site)?;
+            (below.get_visibility() == VoxelVisibility::Opaque
+                && above.get_visibility() == VoxelVisibility::Empty)
+                .then_some((site, ground))
+        })
+        .collect()
+}
+
+/// Tries every [PopulationRule] on the chunks that loaded and, on every
+/// [PopulationSettings::tick], on every loaded chunk, sending a [SpawnRequest] for each entity
+/// they spawn while the chunk holds fewer than their cap
+#[allow(clippy::too_many_arguments)]
+fn populate_chunks(
+    time: Res<Time>,
+    mut settings: ResMut<PopulationSettings>,
+    mut loaded: EventReader<ChunkLoaded>,
+    mut requests: EventWriter<SpawnRequest>,
+    folder: Res<PopulationFolder>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    rules: Res<Assets<PopulationRule>>,
+    chunks: Res<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    registry: Option<Res<BlockRegistry>>,
+    biomes: BiomeLookup,
+    entities: Query<(&Persistent, &Transform)>,
+    mut passes: Local<u64>,
+) {
+    let mut positions: HashSet<IVec3> = loaded.read().map(|ChunkLoaded(chunk)| *chunk).collect();
+    let ticked = settings
+        .tick
+        .as_mut()
+        .is_some_and(|timer| timer.tick(time.delta()).just_finished());
+    if ticked {
+        positions.extend(chunks.chunks.keys().copied());
+    }
+    let rules: Vec<&PopulationRule> = folder
+        .0
+        .iter()
+        .filter_map(|handle| loaded_folders.get(handle))
+        .flat_map(|folder| folder.handles.iter())
+        .filter_map(|handle| rules.get(handle.id().typed_unchecked::<PopulationRule>()))
+        .collect();
+    if positions.is_empty() || rules.is_empty() {
+        return;
+    }
+    *passes += 1;
+
+    let mut counts: HashMap<(IVec3, &str), u32> = HashMap::new();
+    for (kind, transform) in entities.iter() {
+        *counts
+            .entry((point_to_chunk(transform.translation), kind.0.as_str()))
+            .or_default() += 1;
+    }
+    let light = chunks.light();
+    for position in positions {
+        let origin = chunk_to_world(position);
+        for (index, rule) in rules.iter().enumerate() {
+            let count = counts.entry((position, rule.kind.as_str())).or_default();
+            for attempt in 0..rule.attempts {
+                if *count >= rule.max_per_chunk {
+                    break;
+                }
+                let random = |salt: u32| hash_unit((*passes, position, index, attempt, salt));
+                if random(0) >= rule.chance {
+                    continue;
+                }
+                let offset = (Vec2::new(random(1), random(2)) * CHUNK_SIZE as f32).as_ivec2();
+                let column =
+                    origin.xz() + IVec2::ONE + offset.min(IVec2::splat(CHUNK_SIZE as i32 - 1));
+                let sites = spawn_sites(&chunks, position, column, &assets_chunks, &blocks);
+                let picked = (random(3) * sites.len() as f32) as usize;
+                let Some((site, ground)) = sites.get(picked).cloned() else {
+                    continue;
+                };
+                let site = SpawnSite {
+                    position: site,
+                    biome: biomes.biome(column).map(|biome| biome.name.as_str()),
+                    ground,
+                    sky_light: light.light(site, LightChannel::Sky).unwrap_or(MAX_LIGHT),
+                    block_light: light.light(site, LightChannel::Block).unwrap_or_default(),
+                };
+                if !rule.allows(&site, registry.as_deref()) {
+                    continue;
+                }
+                *count += 1;
+                requests.send(SpawnRequest(SavedEntity {
+                    kind: rule.kind.clone(),
+                    // Standing on the ground in the middle of the block
+                    position: site.position.as_vec3() + Vec3::new(0.5, 0., 0.5),
+                    data: rule.data.clone(),
+                }));
+            }
+        }
+    }
+}
+
+/// Spawns entities into the loaded chunks by the [PopulationRule]s of `.population` files in
+/// [ChunksPluginSettings::population_path], through [SpawnRequest]s
+pub(crate) struct PopulationPlugin;
+impl Plugin for PopulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<PopulationRule>()
+            .init_asset_loader::<PopulationLoader>()
+            .init_resource::<PopulationSettings>()
+            .add_systems(OnEnter(AppState::Setup), load_population_rules)
+            .add_systems(
+                Update,
+                populate_chunks
+                    // Saved entities count towards the caps of the chunks they loaded with
+                    .after(spawn_saved_entities)
+                    .run_if(
+                        resource_exists::<Chunks>.and_then(resource_exists::<PopulationFolder>),
+                    ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(biomes: &[&str], surface: SurfaceCondition, light: (u8, u8)) -> PopulationRule {
+        SerializedPopulationRule {
+            kind: "sheep".to_string(),
+            data: SerializedPopulationRule::unit(),
+            biomes: biomes.iter().map(|biome| biome.to_string()).collect(),
+            surface,
+            light,
+            ground: Vec::new(),
+            chance: 1.,
+            attempts: 1,
+            max_per_chunk: 2,
+        }
+        .try_into()
+        .unwrap()
+    }
+
+    fn site(biome: Option<&str>, sky_light: u8, block_light: u8) -> SpawnSite<'_> {
+        SpawnSite {
+            position: IVec3::ZERO,
+            biome,
+            ground: Handle::default(),
+            sky_light,
+            block_light,
+        }
+    }
+
+    #[test]
+    fn reads_defaults_and_rejects_invalid_rules() {
+        let rule: SerializedPopulationRule =
+            ron::de::from_str("(kind: \"sheep\", chance: 0.5, max_per_chunk: 3)").unwrap();
+        let rule = PopulationRule::try_from(rule).unwrap();
+        assert_eq!(rule.data, "()");
+        assert_eq!(rule.light, (0, MAX_LIGHT));
+        assert_eq!(rule.surface, SurfaceCondition::Any);
+        assert_eq!(rule.attempts, 1);
+
+        let invalid = |light, chance| {
+            let rule: SerializedPopulationRule = ron::de::from_str(&format!(
+                "(kind: \"sheep\", light: {light:?}, chance: {chance}, max_per_chunk: 1)"
+            ))
+            .unwrap();
+            PopulationRule::try_from(rule)
+        };
+        assert!(matches!(
+            invalid((8, 4), 1.),
+            Err(PopulationLoaderError::InvalidLight(..))
+        ));
+        assert!(matches!(
+            invalid((0, 16), 1.),
+            Err(PopulationLoaderError::InvalidLight(..))
+        ));
+        assert!(matches!(
+            invalid((0, 15), 1.5),
+            Err(PopulationLoaderError::InvalidChance(..))
+        ));
+    }
+
+    #[test]
+    fn filters_biomes_and_light() {
+        let rule = rule(&["Plains"], SurfaceCondition::Any, (8, MAX_LIGHT));
+        assert!(rule.allows(&site(Some("Plains"), 0, 8), None));
+        assert!(!rule.allows(&site(Some("Desert"), MAX_LIGHT, 0), None));
+        assert!(!rule.allows(&site(None, MAX_LIGHT, 0), None));
+        assert!(!rule.allows(&site(Some("Plains"), 7, 3), None));
+    }
+
+    #[test]
+    fn filters_open_and_covered_sites() {
+        let open = rule(&[], SurfaceCondition::Open, (0, MAX_LIGHT));
+        let covered = rule(&[], SurfaceCondition::Covered, (0, MAX_LIGHT));
+        assert!(open.allows(&site(None, MAX_LIGHT, 0), None));
+        assert!(!open.allows(&site(None, 14, MAX_LIGHT), None));
+        assert!(covered.allows(&site(None, 0, 0), None));
+        assert!(!covered.allows(&site(None, MAX_LIGHT, 0), None));
+    }
+
+    #[test]
+    fn needs_the_registry_for_ground_blocks() {
+        let mut rule = rule(&[], SurfaceCondition::Any, (0, MAX_LIGHT));
+        rule.ground = vec!["grass".to_string()];
+        assert!(!rule.allows(&site(None, MAX_LIGHT, 0), None));
+        assert!(!rule.allows(&site(None, MAX_LIGHT, 0), Some(&BlockRegistry::default())));
+    }
+}
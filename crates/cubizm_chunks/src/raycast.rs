@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+
+use crate::{Chunk, Chunks};
+use cubizm_block::definition::Block;
+
+/// The first non empty block hit by [Chunks::raycast]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaycastHit {
+    /// World position of the hit block, the block spans `block..block + 1`
+    pub block: IVec3,
+    /// Position of the chunk holding the hit block, the key into [Chunks::chunks]
+    pub chunk: IVec3,
+    pub block_handle: Handle<Block>,
+    /// Normal of the face the ray entered through, zero if the ray started inside the block
+    pub normal: IVec3,
+    /// The empty block in front of the hit face, where a block placed against it would go
+    pub place: IVec3,
+    /// Distance along the ray to the hit face
+    pub distance: f32,
+}
+
+/// The blocks a ray passes through in order, with the normal of the face it entered each
+/// through and the distance along the ray to that face
+#[derive(Debug, Clone)]
+pub struct RaySteps {
+    /// The cell the ray is in, a block unless stepping through a coarser grid
+    block: IVec3,
+    step: IVec3,
+    /// Distance along the ray between crossings of each axis' block boundaries
+    delta: Vec3,
+    to_boundary: Vec3,
+    normal: IVec3,
+    distance: f32,
+    max_distance: f32,
+}
+
+impl RaySteps {
+    /// `None` if `direction` is zero
+    pub fn new(origin: Vec3, direction: Vec3, max_distance: f32) -> Option<Self> {
+        let direction = direction.try_normalize()?;
+        Some(Self::through_grid(
+            origin,
+            direction,
+            max_distance,
+            (1, 0),
+            origin.floor().as_ivec3(),
+            IVec3::ZERO,
+            0.,
+        ))
+    }
+
+    /// Steps through a grid of cells `size` blocks wide, the cell zero starting at block
+    /// `offset`, beginning with `cell`, which the ray enters through the face with `normal` at
+    /// `distance`. `direction` must be normalized
+    pub(crate) fn through_grid(
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        (size, offset): (i32, i32),
+        cell: IVec3,
+        normal: IVec3,
+        distance: f32,
+    ) -> Self {
+        let step = direction.signum().as_ivec3();
+        let next_boundary = ((cell + step.max(IVec3::ZERO)) * size + offset).as_vec3();
+        let to_boundary = Vec3::select(
+            direction.cmpeq(Vec3::ZERO),
+            Vec3::splat(f32::INFINITY),
+            (next_boundary - origin) / direction,
+        );
+        Self {
+            block: cell,
+            step,
+            delta: direction.recip().abs() * size as f32,
+            to_boundary,
+            normal,
+            distance,
+            max_distance,
+        }
+    }
+}
+
+impl Iterator for RaySteps {
+    /// The block, the normal of the face the ray entered it through and the distance to it
+    type Item = (IVec3, IVec3, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.distance > self.max_distance {
+            return None;
+        }
+        let item = (self.block, self.normal, self.distance);
+
+        // Step into the neighbour across the closest boundary
+        let to_boundary = self.to_boundary;
+        let axis = if to_boundary.x < to_boundary.y && to_boundary.x < to_boundary.z {
+            0
+        } else if to_boundary.y < to_boundary.z {
+            1
+        } else {
+            2
+        };
+        self.distance = to_boundary[axis];
+        self.to_boundary[axis] += self.delta[axis];
+        self.block[axis] += self.step[axis];
+        self.normal = IVec3::ZERO;
+        self.normal[axis] = -self.step[axis];
+        Some(item)
+    }
+}
+
+impl Chunks {
+    /// Walks the blocks along the ray from `origin` in `direction`, returning the first non empty
+    /// one within `max_distance`. Blocks in chunks that are not loaded count as empty. Only the
+    /// blocks the [OccupancyMap](crate::OccupancyMap) holds occupied are looked at, empty chunks
+    /// and regions are stepped over at once
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        chunks: &Assets<Chunk>,
+        blocks: &Assets<Block>,
+    ) -> Option<RaycastHit> {
+        let mut steps = self
+            .occupancy()
+            .ray_steps(origin, direction, max_distance)?;
+        steps.find_map(|(block, normal, distance)| {
+            let (chunk, index) = Self::block_index(block);
+            let block_handle = self
+                .chunks
+                .get(&chunk)
+                .and_then(|chunk_entity| chunks.get(&chunk_entity.chunk))
+                .map(|chunk| &chunk.blocks[index as usize])
+                .filter(|handle| blocks.get(*handle).is_some_and(Block::is_hit_by_rays))?;
+            Some(RaycastHit {
+                block,
+                chunk,
+                block_handle: block_handle.clone(),
+                normal,
+                place: block + normal,
+                distance,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(origin: Vec3, direction: Vec3, max_distance: f32) -> Vec<IVec3> {
+        RaySteps::new(origin, direction, max_distance)
+            .unwrap()
+            .map(|(block, _, _)| block)
+            .collect()
+    }
+
+    #[test]
+    fn zero_direction_casts_nothing() {
+        assert!(RaySteps::new(Vec3::ZERO, Vec3::ZERO, 10.).is_none());
+    }
+
+    #[test]
+    fn walks_along_an_axis() {
+        let steps = RaySteps::new(Vec3::new(0.5, 0.5, 0.5), Vec3::X, 3.)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            vec![
+                (IVec3::ZERO, IVec3::ZERO, 0.),
+                (IVec3::X, IVec3::NEG_X, 0.5),
+                (IVec3::new(2, 0, 0), IVec3::NEG_X, 1.5),
+                (IVec3::new(3, 0, 0), IVec3::NEG_X, 2.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn walks_into_negative_coordinates() {
+        assert_eq!(
+            blocks(Vec3::new(0.5, 1.5, -0.5), Vec3::NEG_Y, 2.),
+            vec![
+                IVec3::new(0, 1, -1),
+                IVec3::new(0, 0, -1),
+                IVec3::new(0, -1, -1),
+            ]
+        );
+        let steps = RaySteps::new(Vec3::new(-0.5, 0.5, 0.5), Vec3::NEG_X, 1.)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(steps[1], (IVec3::new(-2, 0, 0), IVec3::X, 0.5));
+    }
+
+    #[test]
+    fn diagonal_steps_through_face_neighbours() {
+        let blocks = blocks(Vec3::new(0.2, 0.5, 0.7), Vec3::new(1., 0., -1.), 4.);
+        assert!(blocks.windows(2).all(|pair| {
+            let step = (pair[1] - pair[0]).abs();
+            step.x + step.y + step.z == 1
+        }));
+        assert!(blocks.contains(&IVec3::new(2, 0, -2)));
+    }
+
+    #[test]
+    fn stops_at_max_distance() {
+        let steps = RaySteps::new(Vec3::new(0.5, 0.5, 0.5), Vec3::Z, 5.).unwrap();
+        assert!(steps.clone().all(|(_, _, distance)| distance <= 5.));
+        assert_eq!(steps.count(), 6);
+    }
+}
@@ -0,0 +1,276 @@
+use bevy::{
+    asset::load_internal_asset,
+    pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster},
+    prelude::*,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, Extent3d, Face, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError, TextureDimension, TextureFormat,
+        },
+    },
+    utils::{HashMap, HashSet},
+};
+use block_mesh::{Voxel, VoxelVisibility};
+
+use crate::map::top_face_color;
+use crate::{BlockChanged, Chunk, ChunkLoaded, Chunks, CHUNK_SIZE};
+use cubizm_block::definition::Block;
+use cubizm_block::texture_atlas::BlockAtlasRebuilt;
+use cubizm_core::{chunk_to_world, world_to_chunk};
+
+const RAYMARCH_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0xee38_5016_d817_41ca_83dd_3f8f_e78b_1829);
+
+/// Draws a chunk by marching rays through its blocks in the fragment shader instead of meshing
+/// it. [RaymarchPlugin] gives every chunk a cube of this material as a child, whose back faces
+/// start the rays, so chunks are drawn from inside as well. Blocks are flat coloured by the top
+/// face of their texture and shaded by the face the ray hit
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone)]
+pub struct RaymarchMaterial {
+    /// Index into the [palette](RaymarchMaterial::palette) of every block of the chunk laid out
+    /// like [ChunkShape](crate::ChunkShape), zero for blocks rays pass through
+    #[texture(0, dimension = "3d", sample_type = "u_int")]
+    pub voxels: Handle<Image>,
+    /// Linear colour of each index of the voxels, the first is never drawn
+    #[storage(1, read_only)]
+    pub palette: Vec<Vec4>,
+    /// World position of the lowest corner of the chunk's first block
+    #[uniform(2)]
+    pub origin: Vec4,
+}
+
+impl Material for RaymarchMaterial {
+    fn fragment_shader() -> ShaderRef {
+        RAYMARCH_SHADER.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // Only the back faces, which are in front of the camera even when it is inside the chunk
+        descriptor.primitive.cull_mode = Some(Face::Front);
+        Ok(())
+    }
+}
+
+/// The palette index of every block in `blocks` and the blocks of the palette, starting at
+/// index one. Blocks that aren't `visible` are index zero
+pub fn voxel_palette<'a>(
+    blocks: impl IntoIterator<Item = &'a Handle<Block>>,
+    visible: impl Fn(&Handle<Block>) -> bool,
+) -> (Vec<u16>, Vec<Handle<Block>>) {
+    let mut palette: Vec<Handle<Block>> = Vec::new();
+    let mut indices: HashMap<&Handle<Block>, u16> = HashMap::new();
+    let voxels = blocks
+        .into_iter()
+        .map(|block| {
+            if !visible(block) {
+                return 0;
+            }
+            *indices.entry(block).or_insert_with(|| {
+                palette.push(block.clone());
+                palette.len() as u16
+            })
+        })
+        .collect();
+    (voxels, palette)
+}
+
+/// 3D texture of the palette indices of a chunk
+fn voxel_image(voxels: &[u16]) -> Image {
+    Image::new(
+        Extent3d {
+            width: CHUNK_SIZE,
+            height: CHUNK_SIZE,
+            depth_or_array_layers: CHUNK_SIZE,
+        },
+        TextureDimension::D3,
+        voxels
+            .iter()
+            .flat_map(|index| index.to_le_bytes())
+            .collect(),
+        TextureFormat::R16Uint,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// The voxels of `chunk` and the colours of its palette, looking the colours up in `colors`
+/// first
+fn chunk_voxels(
+    chunk: &Chunk,
+    blocks: &Assets<Block>,
+    images: &Assets<Image>,
+    colors: &mut HashMap<AssetId<Block>, Vec4>,
+) -> (Image, Vec<Vec4>) {
+    let (voxels, palette) = voxel_palette(&chunk.blocks, |block| {
+        blocks
+            .get(block)
+            .is_some_and(|block| block.get_visibility() != VoxelVisibility::Empty)
+    });
+    let palette = std::iter::once(Vec4::ZERO)
+        .chain(palette.iter().map(|block| {
+            *colors.entry(block.id()).or_insert_with(|| {
+                let [red, green, blue] = top_face_color(block.id(), blocks, images);
+                Vec4::from(Color::rgb_u8(red, green, blue).as_linear_rgba_f32())
+            })
+        }))
+        .collect();
+    (voxel_image(&voxels), palette)
+}
+
+/// Drops the meshes of the chunks and stops meshing them whenever a world's [Chunks] are
+/// inserted
+fn stop_chunk_meshing(mut chunks: ResMut<Chunks>, mut meshes: ResMut<Assets<Mesh>>) {
+    chunks.stop_meshing(&mut meshes);
+}
+
+/// Gives every chunk that loaded a [RaymarchMaterial] cube and uploads the blocks of the chunks
+/// whose blocks changed again
+#[allow(clippy::too_many_arguments)]
+fn update_raymarched_chunks(
+    mut commands: Commands,
+    mut loaded: EventReader<ChunkLoaded>,
+    mut changes: EventReader<BlockChanged>,
+    mut rebuilt: EventReader<BlockAtlasRebuilt>,
+    chunks: Res<Chunks>,
+    (assets_chunks, blocks): (Res<Assets<Chunk>>, Res<Assets<Block>>),
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<RaymarchMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    drawn: Query<(&Parent, &Handle<RaymarchMaterial>)>,
+    mut cube: Local<Option<Handle<Mesh>>>,
+    mut colors: Local<HashMap<AssetId<Block>, Vec4>>,
+) {
+    // The colours come from the block textures, which were reloaded
+    if rebuilt.read().count() > 0 {
+        colors.clear();
+    }
+    let loaded: HashSet<IVec3> = loaded.read().map(|ChunkLoaded(chunk)| *chunk).collect();
+    let changed: HashSet<IVec3> = changes
+        .read()
+        .map(|change| world_to_chunk(change.world_pos))
+        .filter(|chunk| !loaded.contains(chunk))
+        .collect();
+    if loaded.is_empty() && changed.is_empty() {
+        return;
+    }
+    let chunk_data = |position: &IVec3| {
+        let chunk_entity = chunks.chunks.get(position)?;
+        Some((chunk_entity.entity, assets_chunks.get(&chunk_entity.chunk)?))
+    };
+
+    let cube = cube
+        .get_or_insert_with(|| meshes.add(Cuboid::from_size(Vec3::splat(CHUNK_SIZE as f32))))
+        .clone();
+    for position in &loaded {
+        let Some((entity, chunk)) = chunk_data(position) else {
+            continue;
+        };
+        let (voxels, palette) = chunk_voxels(chunk, &blocks, &images, &mut colors);
+        let material = materials.add(RaymarchMaterial {
+            voxels: images.add(voxels),
+            palette,
+            // Chunks hold the blocks from one past their origin
+            origin: (chunk_to_world(*position) + IVec3::ONE)
+                .as_vec3()
+                .extend(0.),
+        });
+        let child = commands
+            .spawn((
+                MaterialMeshBundle {
+                    mesh: cube.clone(),
+                    material,
+                    transform: Transform::from_translation(Vec3::splat(
+                        1. + CHUNK_SIZE as f32 / 2.,
+                    )),
+                    ..default()
+                },
+                // Nor is the cube's shadow
+                NotShadowCaster,
+            ))
+            .id();
+        commands.entity(entity).add_child(child);
+    }
+
+    if changed.is_empty() {
+        return;
+    }
+    let by_chunk: HashMap<Entity, &Handle<RaymarchMaterial>> = drawn
+        .iter()
+        .map(|(parent, material)| (parent.get(), material))
+        .collect();
+    for position in &changed {
+        let Some((material, chunk)) =
+            chunk_data(position).and_then(|(entity, chunk)| Some((by_chunk.get(&entity)?, chunk)))
+        else {
+            continue;
+        };
+        let (voxels, palette) = chunk_voxels(chunk, &blocks, &images, &mut colors);
+        let Some(material) = materials.get_mut(*material) else {
+            continue;
+        };
+        images.insert(&material.voxels, voxels);
+        material.palette = palette;
+    }
+}
+
+/// Draws the chunks by ray marching their blocks on the GPU with [RaymarchMaterial], instead
+/// of the meshes [ChunksPlugin](crate::ChunksPlugin) generates, which it stops generating.
+/// Blocks are drawn flat coloured, without their textures, baked light or biome tints, and
+/// [ChunkMeshed](crate::ChunkMeshed) is never sent
+pub struct RaymarchPlugin;
+impl Plugin for RaymarchPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, RAYMARCH_SHADER, "raymarch.wgsl", Shader::from_wgsl);
+        app.add_plugins(MaterialPlugin::<RaymarchMaterial> {
+            // The cube's own depth is not that of the blocks inside
+            prepass_enabled: false,
+            ..default()
+        })
+        .add_systems(
+            Update,
+            (
+                stop_chunk_meshing.run_if(resource_added::<Chunks>),
+                update_raymarched_chunks.run_if(resource_exists::<Chunks>),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexes_the_visible_blocks_in_order() {
+        let air = Handle::weak_from_u128(1);
+        let dirt = Handle::weak_from_u128(2);
+        let stone = Handle::weak_from_u128(3);
+        let blocks = [
+            air.clone(),
+            stone.clone(),
+            dirt.clone(),
+            stone.clone(),
+            air.clone(),
+        ];
+        let (voxels, palette) = voxel_palette(&blocks, |block| *block != air);
+        assert_eq!(voxels, vec![0, 1, 2, 1, 0]);
+        assert_eq!(palette, vec![stone, dirt]);
+    }
+
+    #[test]
+    fn uploads_a_chunk_sized_texture() {
+        let voxels = vec![0; (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize];
+        let image = voxel_image(&voxels);
+        assert_eq!(
+            image.texture_descriptor.size.depth_or_array_layers,
+            CHUNK_SIZE
+        );
+        assert_eq!(image.data.len(), voxels.len() * 2);
+    }
+}
@@ -0,0 +1,85 @@
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::view,
+}
+
+@group(2) @binding(0) var voxels: texture_3d<u32>;
+@group(2) @binding(1) var<storage, read> palette: array<vec4<f32>>;
+@group(2) @binding(2) var<uniform> origin: vec4<f32>;
+
+const CHUNK_SIZE: i32 = 16;
+// Faces turned towards the light are the brightest, those turned away get the ambient light
+const LIGHT_DIRECTION: vec3<f32> = vec3<f32>(0.3, 0.9, 0.5);
+const AMBIENT: f32 = 0.55;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    let size = f32(CHUNK_SIZE);
+    // The ray in chunk space, where the blocks span 0 to CHUNK_SIZE
+    let start = view.world_position - origin.xyz;
+    var direction = normalize(in.world_position.xyz - view.world_position);
+    direction = select(direction, vec3<f32>(1e-6), abs(direction) < vec3<f32>(1e-6));
+    let inverse = 1.0 / direction;
+
+    // Start where the ray enters the chunk, or at the camera inside it
+    let to_min = -start * inverse;
+    let to_max = (vec3<f32>(size) - start) * inverse;
+    let near = min(to_min, to_max);
+    let entry = max(max(near.x, near.y), near.z);
+    var distance = max(entry, 0.0);
+    var normal = vec3<f32>(0.0);
+    if entry > 0.0 {
+        normal = select(vec3<f32>(0.0), -sign(direction), near == vec3<f32>(entry));
+    }
+
+    let step = vec3<i32>(sign(direction));
+    let delta = abs(inverse);
+    var cell = clamp(
+        vec3<i32>(floor(start + direction * distance)),
+        vec3<i32>(0),
+        vec3<i32>(CHUNK_SIZE - 1),
+    );
+    var to_boundary = (vec3<f32>(cell + max(step, vec3<i32>(0))) - start) * inverse;
+
+    // Enough steps to cross the chunk along its diagonal
+    for (var i = 0; i < 3 * CHUNK_SIZE; i++) {
+        if any(cell < vec3<i32>(0)) || any(cell >= vec3<i32>(CHUNK_SIZE)) {
+            break;
+        }
+        let index = textureLoad(voxels, cell, 0).r;
+        if index != 0u {
+            let facing = max(dot(normal, normalize(LIGHT_DIRECTION)), 0.0);
+            let shade = AMBIENT + (1.0 - AMBIENT) * facing;
+            let hit = view.world_position + direction * distance;
+            let clip = view.view_proj * vec4<f32>(hit, 1.0);
+            out.color = vec4<f32>(palette[index].rgb * shade, 1.0);
+            out.depth = clip.z / clip.w;
+            return out;
+        }
+
+        // Step into the neighbour across the closest boundary
+        if to_boundary.x < to_boundary.y && to_boundary.x < to_boundary.z {
+            distance = to_boundary.x;
+            to_boundary.x += delta.x;
+            cell.x += step.x;
+            normal = vec3<f32>(-f32(step.x), 0.0, 0.0);
+        } else if to_boundary.y < to_boundary.z {
+            distance = to_boundary.y;
+            to_boundary.y += delta.y;
+            cell.y += step.y;
+            normal = vec3<f32>(0.0, -f32(step.y), 0.0);
+        } else {
+            distance = to_boundary.z;
+            to_boundary.z += delta.z;
+            cell.z += step.z;
+            normal = vec3<f32>(0.0, 0.0, -f32(step.z));
+        }
+    }
+    discard;
+}
@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::{
+    app::AppExit,
+    asset::{io::file::FileAssetReader, ron},
+    prelude::*,
+    utils::HashMap,
+};
+use thiserror::Error;
+
+use crate::persistence::store_persistent_entities;
+use crate::{BinaryChunkError, Chunk, Chunks, SerializedChunk};
+
+/// Systems writing modified chunks back to disk, run in [Last] so edits made during the frame
+/// are included
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WorldSaverSet;
+
+/// Request every modified chunk to be written back to [WorldSaver::directory]
+#[derive(Event, Debug, Clone, Default)]
+pub struct SaveWorld;
+
+/// Where and how often modified chunks are saved, see [Chunks::mark_dirty]
+#[derive(Resource, Debug)]
+pub struct WorldSaver {
+    pub directory: PathBuf,
+    /// Saves on every tick, `None` only saves on [SaveWorld] and on exit
+    pub autosave: Option<Timer>,
+    /// File each chunk was loaded from, chunks without one are saved as `x_y_z.chunkb`
+    files: HashMap<IVec3, String>,
+}
+
+impl Default for WorldSaver {
+    fn default() -> Self {
+        Self {
+            directory: FileAssetReader::get_base_path().join("assets/world/chunks"),
+            autosave: Some(Timer::new(Duration::from_secs(60), TimerMode::Repeating)),
+            files: HashMap::new(),
+        }
+    }
+}
+
+impl WorldSaver {
+    /// Saves to the folder at asset path `chunk_directory` in the `assets` folder, set from the
+    /// [WorldManifest](crate::WorldManifest) of the active world of [Worlds](crate::Worlds)
+    /// when its chunks load. Forgets the files of the chunks saved before
+    pub fn set_chunk_directory(&mut self, chunk_directory: &str) {
+        self.directory = FileAssetReader::get_base_path()
+            .join("assets")
+            .join(chunk_directory);
+        self.files.clear();
+    }
+
+    /// Saves the chunk at `position` to `file` in [directory](WorldSaver::directory).
+    /// The format follows the extension: RON for `.chunk`, binary otherwise
+    pub fn set_file(&mut self, position: IVec3, file: impl Into<String>) {
+        self.files.insert(position, file.into());
+    }
+
+    /// Whether the chunk at `position` was loaded from a file
+    pub fn has_file(&self, position: IVec3) -> bool {
+        self.files.contains_key(&position)
+    }
+
+    /// Takes the directory and files of the active world when [Worlds](crate::Worlds) switches
+    /// away from it, to [restore](WorldSaver::restore_world) when switching back
+    pub(crate) fn take_world(&mut self) -> (PathBuf, HashMap<IVec3, String>) {
+        (self.directory.clone(), std::mem::take(&mut self.files))
+    }
+
+    pub(crate) fn restore_world(&mut self, (directory, files): (PathBuf, HashMap<IVec3, String>)) {
+        self.directory = directory;
+        self.files = files;
+    }
+
+    /// Writes every chunk marked dirty, returning how many were saved. Chunks that failed to
+    /// save stay dirty
+    pub(crate) fn save_dirty(&self, chunks: &mut Chunks, assets_chunks: &Assets<Chunk>) -> usize {
+        let mut saved = 0;
+        for position in chunks.take_dirty() {
+            let Some(chunk) = chunks
+                .chunks
+                .get(&position)
+                .and_then(|chunk_entity| assets_chunks.get(&chunk_entity.chunk))
+            else {
+                continue;
+            };
+            let path = self.path(position);
+            match write_chunk(chunk, &path) {
+                Ok(()) => saved += 1,
+                Err(err) => {
+                    error!(
+                        "Failed to save chunk {position} to {}: {err}",
+                        path.display()
+                    );
+                    chunks.mark_dirty(position);
+                }
+            }
+        }
+        if saved > 0 {
+            info!("Saved {saved} chunks to {}", self.directory.display());
+        }
+        saved
+    }
+
+    fn path(&self, position: IVec3) -> PathBuf {
+        self.directory.join(match self.files.get(&position) {
+            Some(file) => file.clone(),
+            None => chunk_file_name(position),
+        })
+    }
+}
+
+/// File a chunk at `position` without a file of its own is saved to, `x_y_z.chunkb`
+pub fn chunk_file_name(position: IVec3) -> String {
+    format!("{}_{}_{}.chunkb", position.x, position.y, position.z)
+}
+
+#[derive(Debug, Error)]
+pub enum SaveChunkError {
+    #[error("Block {0} was not loaded from a file")]
+    UnnamedBlock(usize),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+    #[error(transparent)]
+    Binary(#[from] BinaryChunkError),
+}
+
+impl Chunk {
+    /// The chunk with every block referred to by its asset path
+    pub fn serialize(&self) -> Result<SerializedChunk, SaveChunkError> {
+        let blocks = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(index, handle)| {
+                handle
+                    .path()
+                    .map(ToString::to_string)
+                    .ok_or(SaveChunkError::UnnamedBlock(index))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(SerializedChunk {
+            blocks,
+            position: self.position,
+            states: self.states.clone(),
+            tile_data: self.tile_data.clone(),
+            entities: self.entities.clone(),
+        })
+    }
+}
+
+/// Writes `chunk` to `path`, as RON for `.chunk` files and binary otherwise. The file is written
+/// next to `path` first and then renamed over it, so a crash while writing leaves the old one
+pub fn write_chunk(chunk: &Chunk, path: &Path) -> Result<(), SaveChunkError> {
+    let serialized = chunk.serialize()?;
+    let bytes = match path
+        .extension()
+        .is_some_and(|extension| extension == "chunk")
+    {
+        true => ron::ser::to_string(&serialized)?.into_bytes(),
+        false => serialized.to_binary()?,
+    };
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    std::fs::write(&temporary, bytes)?;
+    if let Err(err) = std::fs::rename(&temporary, path) {
+        // Best effort, the rename error is the one worth reporting
+        let _ = std::fs::remove_file(&temporary);
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+fn autosave_world(
+    time: Res<Time>,
+    mut saver: ResMut<WorldSaver>,
+    mut save_events: EventWriter<SaveWorld>,
+) {
+    let Some(timer) = saver.autosave.as_mut() else {
+        return;
+    };
+    if timer.tick(time.delta()).just_finished() {
+        save_events.send(SaveWorld);
+    }
+}
+
+fn save_world(
+    mut save_events: EventReader<SaveWorld>,
+    mut exit_events: EventReader<AppExit>,
+    saver: Res<WorldSaver>,
+    mut chunks: ResMut<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+) {
+    // Saving on exit has to happen this frame, the app is gone by the next
+    let requested = save_events.read().count() > 0;
+    let exiting = exit_events.read().count() > 0;
+    if !requested && !exiting {
+        return;
+    }
+    // Saving does not change the chunk data, don't wake up systems watching it
+    saver.save_dirty(chunks.bypass_change_detection(), &assets_chunks);
+}
+
+pub(crate) struct WorldSaverPlugin;
+
+impl Plugin for WorldSaverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldSaver>()
+            .add_event::<SaveWorld>()
+            .add_systems(
+                Last,
+                (autosave_world, store_persistent_entities, save_world)
+                    .chain()
+                    .in_set(WorldSaverSet)
+                    .run_if(resource_exists::<Chunks>),
+            );
+    }
+}
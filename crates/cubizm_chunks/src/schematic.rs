@@ -0,0 +1,214 @@
+use std::path::Path;
+
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use cubizm_block::definition::Block;
+
+/// A box of blocks to [paste](crate::VoxelWorld::paste) into the world, copied out of it with
+/// [copy](crate::VoxelWorld::copy), built by hand or loaded from a `.schematic` file.
+/// Positions without a block leave the world as it is
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct Schematic {
+    size: UVec3,
+    /// Position in the schematic placed on the position it is pasted at
+    pub anchor: IVec3,
+    /// x fastest, then y, then z
+    blocks: Vec<Option<Handle<Block>>>,
+}
+
+impl Schematic {
+    /// An empty schematic of `size` blocks anchored at its first corner
+    pub fn new(size: UVec3) -> Self {
+        Self {
+            size,
+            anchor: IVec3::ZERO,
+            blocks: vec![None; (size.x * size.y * size.z) as usize],
+        }
+    }
+
+    pub fn with_anchor(mut self, anchor: IVec3) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    fn index(&self, local: UVec3) -> Option<usize> {
+        local
+            .cmplt(self.size)
+            .all()
+            .then(|| (local.x + self.size.x * (local.y + self.size.y * local.z)) as usize)
+    }
+
+    fn local(&self, index: usize) -> UVec3 {
+        let index = index as u32;
+        UVec3::new(
+            index % self.size.x,
+            index / self.size.x % self.size.y,
+            index / (self.size.x * self.size.y),
+        )
+    }
+
+    pub fn get(&self, local: UVec3) -> Option<&Handle<Block>> {
+        self.blocks[self.index(local)?].as_ref()
+    }
+
+    /// Sets or clears the block at `local`, positions outside the schematic are ignored
+    pub fn set(&mut self, local: UVec3, block: Option<Handle<Block>>) {
+        if let Some(index) = self.index(local) {
+            self.blocks[index] = block;
+        }
+    }
+
+    /// Every block in the schematic with its position in it
+    pub fn iter(&self) -> impl Iterator<Item = (UVec3, &Handle<Block>)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, block)| Some((self.local(index), block.as_ref()?)))
+    }
+
+    /// The schematic with every block referred to by its asset path
+    pub fn serialize(&self) -> Result<SerializedSchematic, SchematicError> {
+        let mut palette: Vec<String> = Vec::new();
+        let mut runs: Vec<(Option<u16>, u32)> = Vec::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            let entry = match block {
+                Some(block) => {
+                    let path = block
+                        .path()
+                        .map(ToString::to_string)
+                        .ok_or_else(|| SchematicError::UnnamedBlock(self.local(index)))?;
+                    let entry = match palette.iter().position(|known| *known == path) {
+                        Some(entry) => entry,
+                        None => {
+                            palette.push(path);
+                            palette.len() - 1
+                        }
+                    };
+                    Some(u16::try_from(entry).map_err(|_| SchematicError::PaletteTooLarge)?)
+                }
+                None => None,
+            };
+            match runs.last_mut() {
+                Some((run_entry, length)) if *run_entry == entry => *length += 1,
+                _ => runs.push((entry, 1)),
+            }
+        }
+        Ok(SerializedSchematic {
+            size: self.size,
+            anchor: self.anchor,
+            palette,
+            runs,
+        })
+    }
+
+    /// Writes the schematic to `path` as RON, to load it back as a `.schematic` asset
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SchematicError> {
+        let path = path.as_ref();
+        let ron = ron::ser::to_string_pretty(&self.serialize()?, default())?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(path, ron)?;
+        Ok(())
+    }
+}
+
+/// [Schematic] as written in `.schematic` files
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SerializedSchematic {
+    pub size: UVec3,
+    #[serde(default)]
+    pub anchor: IVec3,
+    /// Asset path of every block the schematic uses
+    pub palette: Vec<String>,
+    /// Runs of blocks in [Schematic] order as an index into `palette` and a length, `None` for
+    /// positions without a block
+    pub runs: Vec<(Option<u16>, u32)>,
+}
+
+#[derive(Debug, Error)]
+pub enum SchematicError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+    #[error("Block at {0} was not loaded from a file")]
+    UnnamedBlock(UVec3),
+    #[error("Schematic uses more than {} different blocks", u16::MAX)]
+    PaletteTooLarge,
+    #[error("Palette index {0} is out of range for a palette of {1} blocks")]
+    InvalidPaletteIndex(u16, usize),
+    #[error("Schematic holds {0} blocks, expected {1}")]
+    WrongBlockCount(u64, u64),
+}
+
+#[derive(Default)]
+pub struct SchematicLoader;
+
+impl AssetLoader for SchematicLoader {
+    type Asset = Schematic;
+    type Settings = ();
+    type Error = SchematicError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let serialized = ron::de::from_bytes::<SerializedSchematic>(&bytes)?;
+            let size = serialized.size;
+            let expected = size.x as u64 * size.y as u64 * size.z as u64;
+            let found = serialized
+                .runs
+                .iter()
+                .map(|(_, length)| *length as u64)
+                .sum();
+            if found != expected {
+                return Err(SchematicError::WrongBlockCount(found, expected));
+            }
+            let palette: Vec<Handle<Block>> = serialized
+                .palette
+                .iter()
+                .map(|path| load_context.load(path))
+                .collect();
+            let mut blocks = Vec::with_capacity(expected as usize);
+            for (entry, length) in serialized.runs {
+                let block = match entry {
+                    Some(entry) => Some(
+                        palette
+                            .get(entry as usize)
+                            .cloned()
+                            .ok_or(SchematicError::InvalidPaletteIndex(entry, palette.len()))?,
+                    ),
+                    None => None,
+                };
+                blocks.extend(std::iter::repeat_n(block, length as usize));
+            }
+            Ok(Schematic {
+                size,
+                anchor: serialized.anchor,
+                blocks,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["schematic"]
+    }
+}
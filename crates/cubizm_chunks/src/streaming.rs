@@ -0,0 +1,139 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use cubizm_block::BlockRegistry;
+use cubizm_core::{point_to_block, world_to_chunk};
+
+use crate::worlds::switch_world;
+use crate::{
+    ActiveWorld, Biome, Chunks, Schematic, StructurePass, VoxelWorld, WorldBiomes, WorldGeneration,
+    WorldHeightmap, WorldId, WorldManifest, WorldSaver, Worlds,
+};
+
+/// Generates the chunks the generator of the world's [WorldManifest] fills around `center`, as
+/// it does around the spawn when the world loads, leaving out the chunks that are loaded or
+/// saved. Waits until `world` is the active world and loaded, e.g. after a
+/// [SwitchWorld](crate::SwitchWorld) to it
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct StreamChunks {
+    pub world: WorldId,
+    pub center: Vec3,
+}
+
+/// [StreamChunks] waiting for their world
+#[derive(Resource, Debug, Default)]
+struct PendingStreams(Vec<StreamChunks>);
+
+fn queue_streams(mut events: EventReader<StreamChunks>, mut pending: ResMut<PendingStreams>) {
+    pending.0.extend(events.read().cloned());
+}
+
+/// The chunks `generate` fills around every center, once each, that `skip` does not
+fn stream_positions(
+    centers: &[Vec3],
+    generate: impl Fn(IVec3) -> Vec<IVec3>,
+    skip: impl Fn(IVec3) -> bool,
+) -> Vec<IVec3> {
+    let mut seen = HashSet::new();
+    centers
+        .iter()
+        .flat_map(|center| generate(world_to_chunk(point_to_block(*center))))
+        .filter(|position| !skip(*position) && seen.insert(*position))
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn stream_chunks(
+    mut pending: ResMut<PendingStreams>,
+    worlds: Res<Worlds>,
+    mut world: VoxelWorld,
+    saver: Res<WorldSaver>,
+    active: Res<ActiveWorld>,
+    manifests: Res<Assets<WorldManifest>>,
+    registry: Res<BlockRegistry>,
+    mut structures: ResMut<StructurePass>,
+    schematics: Res<Assets<Schematic>>,
+    biomes: Res<WorldBiomes>,
+    biome_assets: Res<Assets<Biome>>,
+    heightmap: Res<WorldHeightmap>,
+    images: Option<Res<Assets<Image>>>,
+) {
+    if pending.0.is_empty() {
+        return;
+    }
+    let Some(manifest) = manifests.get(&active.0) else {
+        return;
+    };
+    let (requests, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut pending.0)
+        .into_iter()
+        .partition(|request| request.world == *worlds.active());
+    pending.0 = waiting;
+    let centers: Vec<Vec3> = requests.iter().map(|request| request.center).collect();
+
+    let generator = &manifest.generator;
+    // Saved chunks that aren't loaded were unloaded, not missing
+    let positions = stream_positions(
+        &centers,
+        |center| generator.chunk_positions(center),
+        |position| world.chunks().chunks.contains_key(&position) || saver.has_file(position),
+    );
+    if positions.is_empty() {
+        return;
+    }
+    let generation = WorldGeneration {
+        seed: manifest.seed,
+        settings: generator,
+        registry: &registry,
+        biomes: generator
+            .kind
+            .biome_map(manifest.seed, &biomes.0, &biome_assets),
+        heightmap: heightmap
+            .0
+            .as_ref()
+            .zip(images.as_ref())
+            .and_then(|(image, images)| images.get(image)),
+        schematics: &schematics,
+    };
+    info!(
+        "Streaming {} chunks into world {}",
+        positions.len(),
+        worlds.active()
+    );
+    for chunk in generation.generate(&positions, &mut structures) {
+        world.insert_chunk(chunk);
+    }
+}
+
+/// Generates the chunks [StreamChunks] asks for
+pub(crate) struct ChunkStreamingPlugin;
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingStreams>()
+            .add_event::<StreamChunks>()
+            .add_systems(
+                Update,
+                (
+                    queue_streams,
+                    stream_chunks
+                        .after(switch_world)
+                        .run_if(resource_exists::<Chunks>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streams_each_missing_chunk_once() {
+        let around = |center: IVec3| vec![center, center + IVec3::X];
+        let positions = stream_positions(
+            &[Vec3::new(1.5, 1.5, 1.5), Vec3::new(17.5, 1.5, 1.5)],
+            around,
+            |position| position == IVec3::new(2, 0, 0),
+        );
+        assert_eq!(positions, vec![IVec3::ZERO, IVec3::X]);
+    }
+}
@@ -0,0 +1,155 @@
+use std::hash::BuildHasher;
+
+use bevy::{
+    prelude::*,
+    utils::{FixedState, HashMap},
+};
+use block_mesh::ndshape::ConstShape;
+use serde::{Deserialize, Serialize};
+
+use cubizm_block::{block_key, definition::Block, BlockRegistry, BASE_NAMESPACE};
+use cubizm_core::{local_to_world, CHUNK_SIZE};
+
+use crate::{BiomeMap, Chunk, ChunkShape, Chunks, Schematic};
+
+/// A [Schematic] the generator scatters over the surface of generated chunks, e.g. trees or
+/// ruins
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StructureRule {
+    /// Asset path of the `.schematic`, relative to the folder of the manifest
+    pub schematic: String,
+    /// How many of the structure are tried per chunk on average, each attempt only succeeds
+    /// on a fitting surface
+    pub density: f32,
+    /// Registry names of the blocks the structure may stand on, any block when empty
+    #[serde(default)]
+    pub surface: Vec<String>,
+    /// Names of the [Biome](crate::Biome)s the structure appears in, any biome when empty
+    #[serde(default)]
+    pub biomes: Vec<String>,
+}
+
+/// Places the [StructureRule]s of the active world into generated chunks. Blocks of a
+/// structure that fall into a chunk which was not generated yet are kept until it is
+#[derive(Resource, Debug, Default)]
+pub struct StructurePass {
+    /// The schematic of every rule of the generator, in the same order
+    schematics: Vec<Handle<Schematic>>,
+    /// Blocks waiting for their chunk, by chunk position and index in [ChunkShape]
+    deferred: HashMap<IVec3, Vec<(u32, Handle<Block>)>>,
+}
+
+impl StructurePass {
+    pub fn new(schematics: Vec<Handle<Schematic>>) -> Self {
+        Self {
+            schematics,
+            deferred: HashMap::new(),
+        }
+    }
+
+    pub fn schematics(&self) -> &[Handle<Schematic>] {
+        &self.schematics
+    }
+
+    /// Places the structures rooted in `chunk`, the same ones for the same `seed` and chunk
+    /// position. Blocks outside of `chunk` are deferred, see
+    /// [apply_deferred](StructurePass::apply_deferred). Surfaces on the top layer of the chunk
+    /// are skipped, the block above them is not known yet
+    pub fn place(
+        &mut self,
+        chunk: &mut Chunk,
+        seed: u64,
+        rules: &[StructureRule],
+        schematics: &Assets<Schematic>,
+        registry: &BlockRegistry,
+        biomes: &BiomeMap,
+    ) {
+        let Some(air) = registry.get(&block_key(BASE_NAMESPACE, "Air")) else {
+            return;
+        };
+        let position = chunk.position;
+        for (rule_index, (rule, handle)) in rules.iter().zip(&self.schematics).enumerate() {
+            let Some(schematic) = schematics.get(handle) else {
+                continue;
+            };
+            let roll = |attempt: u32| {
+                FixedState.hash_one((seed, position.to_array(), rule_index, attempt))
+            };
+            // The fraction of the density is the chance of one more attempt
+            let extra = (roll(u32::MAX) as f64 / u64::MAX as f64) < rule.density.fract() as f64;
+            let attempts = rule.density.max(0.0) as u32 + extra as u32;
+            for attempt in 0..attempts {
+                let roll = roll(attempt);
+                let (x, z) = (roll as u32 % CHUNK_SIZE, (roll >> 32) as u32 % CHUNK_SIZE);
+                let Some(y) = Self::surface(chunk, x, z, air, rule, registry) else {
+                    continue;
+                };
+                let anchor = local_to_world(position, UVec3::new(x, y + 1, z));
+                let in_biome = rule.biomes.is_empty()
+                    || biomes
+                        .biome(anchor.xz())
+                        .is_some_and(|biome| rule.biomes.contains(&biome.name));
+                if !in_biome {
+                    continue;
+                }
+                Self::paste(&mut self.deferred, chunk, schematic, anchor);
+            }
+        }
+    }
+
+    /// Height in `chunk` of the highest block of the column at `x`, `z` with air above it,
+    /// if it is a surface of `rule`
+    fn surface(
+        chunk: &Chunk,
+        x: u32,
+        z: u32,
+        air: &Handle<Block>,
+        rule: &StructureRule,
+        registry: &BlockRegistry,
+    ) -> Option<u32> {
+        let block = |y: u32| &chunk.blocks[ChunkShape::linearize([x, y, z]) as usize];
+        let y = (0..CHUNK_SIZE - 1)
+            .rev()
+            .find(|y| block(*y) != air && block(y + 1) == air)?;
+        let fits = rule.surface.is_empty()
+            || rule
+                .surface
+                .iter()
+                .any(|name| registry.find(name) == Some(block(y)));
+        fits.then_some(y)
+    }
+
+    /// Sets the blocks of `schematic` with its anchor at world `anchor`, adding those in other
+    /// chunks to `deferred`
+    fn paste(
+        deferred: &mut HashMap<IVec3, Vec<(u32, Handle<Block>)>>,
+        chunk: &mut Chunk,
+        schematic: &Schematic,
+        anchor: IVec3,
+    ) {
+        let origin = anchor - schematic.anchor;
+        for (local, block) in schematic.iter() {
+            let (position, index) = Chunks::block_index(origin + local.as_ivec3());
+            match position == chunk.position {
+                true => chunk.blocks[index as usize] = block.clone(),
+                false => deferred
+                    .entry(position)
+                    .or_default()
+                    .push((index, block.clone())),
+            }
+        }
+    }
+
+    /// Sets the blocks structures in other chunks deferred for `chunk`
+    pub fn apply_deferred(&mut self, chunk: &mut Chunk) {
+        for (index, block) in self.deferred.remove(&chunk.position).unwrap_or_default() {
+            chunk.blocks[index as usize] = block;
+        }
+    }
+
+    /// Drops the blocks deferred for the chunk at `position`, for chunks that are loaded from
+    /// a save and already hold their structures
+    pub fn discard(&mut self, position: IVec3) {
+        self.deferred.remove(&position);
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::{prelude::*, utils::HashMap};
+use block_mesh::ndshape::ConstShape;
+
+use cubizm_block::definition::Block;
+use cubizm_core::chunk_to_world;
+
+use crate::{hash_unit, Chunk, ChunkShape, Chunks};
+
+/// Most game ticks run in a frame, the rest are dropped so a long frame doesn't make the
+/// following ones longer
+const MAX_TICKS_PER_FRAME: f32 = 10.0;
+
+/// How often blocks tick, see [BlockTickPlugin]
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct BlockTickSettings {
+    /// Game ticks per second
+    pub tick_rate: f32,
+    /// Blocks picked at random in every loaded chunk each game tick, those that
+    /// [tick](Block::ticks) get a [BlockTickKind::Random] tick
+    pub random_ticks: u32,
+}
+
+impl Default for BlockTickSettings {
+    fn default() -> Self {
+        Self {
+            tick_rate: 20.0,
+            random_ticks: 3,
+        }
+    }
+}
+
+impl BlockTickSettings {
+    /// Game ticks in `seconds`, at least one
+    pub fn ticks_in(&self, seconds: f32) -> u64 {
+        ((seconds * self.tick_rate).round() as u64).max(1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockTickKind {
+    /// Picked at random, only for blocks that [tick](Block::ticks)
+    Random,
+    /// Asked for with [BlockTicks::schedule], for any block
+    Scheduled,
+}
+
+/// Sent for every block that ticks in a game tick
+#[derive(Event, Debug, Clone)]
+pub struct BlockTick {
+    pub position: IVec3,
+    /// The block at `position` when it ticked
+    pub block: Handle<Block>,
+    pub kind: BlockTickKind,
+}
+
+/// Systems sending [BlockTick], run those reading it after them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockTickSet;
+
+/// The game tick count and the ticks scheduled for later
+#[derive(Resource, Debug, Default)]
+pub struct BlockTicks {
+    tick: u64,
+    /// Seconds since the last game tick
+    elapsed: f32,
+    /// By the game tick they are due in
+    scheduled: HashMap<IVec3, u64>,
+}
+
+impl BlockTicks {
+    /// Game ticks so far
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Ticks the block at `position` in `delay` game ticks, or earlier if it already is.
+    /// A delay of 0 ticks it in the next game tick
+    pub fn schedule(&mut self, position: IVec3, delay: u64) {
+        let due = self.tick + delay.max(1);
+        let scheduled = self.scheduled.entry(position).or_insert(due);
+        *scheduled = (*scheduled).min(due);
+    }
+
+    /// Whether a tick is scheduled for the block at `position`
+    pub fn is_scheduled(&self, position: IVec3) -> bool {
+        self.scheduled.contains_key(&position)
+    }
+
+    fn take_due(&mut self) -> Vec<IVec3> {
+        let tick = self.tick;
+        let due: Vec<IVec3> = self
+            .scheduled
+            .iter()
+            .filter(|(_, due)| **due <= tick)
+            .map(|(position, _)| *position)
+            .collect();
+        for position in due.iter() {
+            self.scheduled.remove(position);
+        }
+        due
+    }
+}
+
+/// Runs the game ticks that passed since the last frame, sending a [BlockTick] for the
+/// random and scheduled ticks of each
+fn tick_blocks(
+    time: Res<Time>,
+    settings: Res<BlockTickSettings>,
+    mut ticks: ResMut<BlockTicks>,
+    chunks: Res<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    mut events: EventWriter<BlockTick>,
+) {
+    if settings.tick_rate <= 0.0 {
+        return;
+    }
+    let interval = 1.0 / settings.tick_rate;
+    ticks.elapsed = (ticks.elapsed + time.delta_seconds()).min(interval * MAX_TICKS_PER_FRAME);
+    while ticks.elapsed >= interval {
+        ticks.elapsed -= interval;
+        ticks.tick += 1;
+        let tick = ticks.tick;
+
+        for (position, chunk_entity) in chunks.chunks.iter() {
+            let Some(chunk) = assets_chunks.get(&chunk_entity.chunk) else {
+                continue;
+            };
+            for index in 0..settings.random_ticks {
+                let block_index = (hash_unit((position.to_array(), tick, index))
+                    * ChunkShape::SIZE as f32) as usize
+                    % ChunkShape::SIZE as usize;
+                let block = &chunk.blocks[block_index];
+                if !blocks.get(block).is_some_and(Block::ticks) {
+                    continue;
+                }
+                let local = UVec3::from_array(ChunkShape::delinearize(block_index as u32));
+                events.send(BlockTick {
+                    position: chunk_to_world(*position) + local.as_ivec3(),
+                    block: block.clone(),
+                    kind: BlockTickKind::Random,
+                });
+            }
+        }
+
+        for position in ticks.take_due() {
+            let Ok(block) = chunks.get_block(position, &assets_chunks) else {
+                continue;
+            };
+            events.send(BlockTick {
+                position,
+                block,
+                kind: BlockTickKind::Scheduled,
+            });
+        }
+    }
+}
+
+/// Ticks blocks in game ticks of a fixed rate: a few random blocks of every loaded chunk, for
+/// blocks that change over time like crops, and the blocks scheduled with
+/// [BlockTicks::schedule], for delayed updates like flowing fluids. Added by
+/// [ChunksPlugin](crate::ChunksPlugin)
+#[derive(Default)]
+pub struct BlockTickPlugin {
+    pub settings: BlockTickSettings,
+}
+
+impl Plugin for BlockTickPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings)
+            .init_resource::<BlockTicks>()
+            .add_event::<BlockTick>()
+            .add_systems(
+                Update,
+                tick_blocks
+                    .run_if(resource_exists::<Chunks>)
+                    .in_set(BlockTickSet),
+            );
+    }
+}
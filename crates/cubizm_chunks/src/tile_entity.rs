@@ -0,0 +1,155 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use block_mesh::ndshape::ConstShape;
+
+use cubizm_block::definition::Block;
+use cubizm_core::chunk_to_world;
+
+use crate::{BlockChanged, Chunk, ChunkShape, Chunks};
+
+/// The entities drawing the [TileEntity](Block::TileEntity) blocks of the loaded chunks, by
+/// world position
+#[derive(Resource, Debug, Default)]
+pub struct TileEntities {
+    entities: HashMap<IVec3, Entity>,
+    /// Chunks whose tile entities were spawned
+    scanned: HashSet<IVec3>,
+    /// Shared by every tile entity of a block
+    materials: HashMap<AssetId<Block>, Handle<StandardMaterial>>,
+}
+
+impl TileEntities {
+    /// The entity of the tile entity at world `position`, for adding components to it
+    pub fn get(&self, position: IVec3) -> Option<Entity> {
+        self.entities.get(&position).copied()
+    }
+
+    fn spawn(
+        &mut self,
+        commands: &mut Commands,
+        position: IVec3,
+        block: &Handle<Block>,
+        definition: &Block,
+        materials: &mut Assets<StandardMaterial>,
+    ) {
+        self.despawn(commands, position);
+        let Some(mesh) = definition.tile_entity_mesh() else {
+            return;
+        };
+        let material = self
+            .materials
+            .entry(block.id())
+            .or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color_texture: definition.tile_entity_texture(),
+                    ..default()
+                })
+            })
+            .clone();
+        let entity = commands
+            .spawn(PbrBundle {
+                mesh,
+                material,
+                // Meshes are centred on their block, which spans `position..position + 1`
+                transform: Transform::from_translation(position.as_vec3() + Vec3::splat(0.5)),
+                ..default()
+            })
+            .id();
+        self.entities.insert(position, entity);
+    }
+
+    /// Despawns every tile entity, those of the loaded chunks are spawned again the next time
+    /// [Chunks] change
+    pub(crate) fn clear(&mut self, commands: &mut Commands) {
+        for (_, entity) in self.entities.drain() {
+            commands.entity(entity).despawn_recursive();
+        }
+        self.scanned.clear();
+    }
+
+    fn despawn(&mut self, commands: &mut Commands, position: IVec3) {
+        if let Some(entity) = self.entities.remove(&position) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Spawns the tile entities of chunks the first time they are seen
+fn spawn_chunk_tile_entities(
+    mut commands: Commands,
+    chunks: Res<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut tile_entities: ResMut<TileEntities>,
+) {
+    if !chunks.is_changed() {
+        return;
+    }
+    for (position, chunk_entity) in chunks.chunks.iter() {
+        if tile_entities.scanned.contains(position) {
+            continue;
+        }
+        let Some(chunk) = assets_chunks.get(&chunk_entity.chunk) else {
+            continue;
+        };
+        tile_entities.scanned.insert(*position);
+        let origin = chunk_to_world(*position);
+        for (index, block) in chunk.blocks.iter().enumerate() {
+            let Some(definition) = blocks.get(block).filter(|block| !block.is_voxel()) else {
+                continue;
+            };
+            let local = UVec3::from_array(ChunkShape::delinearize(index as u32));
+            tile_entities.spawn(
+                &mut commands,
+                origin + local.as_ivec3(),
+                block,
+                definition,
+                &mut materials,
+            );
+        }
+    }
+}
+
+/// Replaces the tile entity of every changed block
+fn update_tile_entities(
+    mut commands: Commands,
+    mut changed: EventReader<BlockChanged>,
+    blocks: Res<Assets<Block>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut tile_entities: ResMut<TileEntities>,
+) {
+    for event in changed.read() {
+        if event.old == event.new {
+            continue;
+        }
+        tile_entities.despawn(&mut commands, event.world_pos);
+        if let Some(definition) = blocks.get(&event.new).filter(|block| !block.is_voxel()) {
+            tile_entities.spawn(
+                &mut commands,
+                event.world_pos,
+                &event.new,
+                definition,
+                &mut materials,
+            );
+        }
+    }
+}
+
+/// Draws the [TileEntity](Block::TileEntity) blocks, which aren't part of the chunk meshes,
+/// as an entity each with the block's mesh and texture, see [TileEntities]. Added by
+/// [ChunksPlugin](crate::ChunksPlugin) unless it is headless
+pub struct TileEntityPlugin;
+
+impl Plugin for TileEntityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileEntities>().add_systems(
+            Update,
+            (spawn_chunk_tile_entities, update_tile_entities)
+                .chain()
+                .run_if(resource_exists::<Chunks>),
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    BlockAabb, BlockChanged, Chunk, ChunkError, ChunkMaterial, Chunks, RaycastHit, Schematic,
+    TileDataError,
+};
+use cubizm_block::{definition::Block, texture_atlas::BlockAtlas, BlockState};
+
+/// Reads and edits the voxel world without passing every resource [Chunks] needs by hand
+#[derive(SystemParam)]
+pub struct VoxelWorld<'w, 's> {
+    commands: Commands<'w, 's>,
+    chunks: ResMut<'w, Chunks>,
+    assets_chunks: ResMut<'w, Assets<Chunk>>,
+    /// Missing when [headless](crate::ChunksPlugin::headless)
+    meshes: Option<ResMut<'w, Assets<Mesh>>>,
+    materials: Option<ResMut<'w, Assets<ChunkMaterial>>>,
+    blocks: Res<'w, Assets<Block>>,
+    texture_atlas: Option<Res<'w, BlockAtlas>>,
+    block_changed: EventWriter<'w, BlockChanged>,
+}
+
+impl VoxelWorld<'_, '_> {
+    pub fn chunks(&self) -> &Chunks {
+        &self.chunks
+    }
+
+    /// The block at world `position`, `None` if its chunk is not loaded
+    pub fn get_block(&self, position: IVec3) -> Option<Handle<Block>> {
+        self.chunks.get_block(position, &self.assets_chunks).ok()
+    }
+
+    pub fn set_block(&mut self, position: IVec3, block: Handle<Block>) -> Result<(), ChunkError> {
+        self.chunks.set_block(
+            position,
+            block,
+            &mut self.assets_chunks,
+            &mut self.block_changed,
+        )
+    }
+
+    /// See [FluidLevels::level](crate::FluidLevels::level)
+    pub fn fluid_level(&self, position: IVec3) -> Option<u8> {
+        self.chunks.fluids().level(position)
+    }
+
+    /// See [Chunks::set_fluid_level]
+    pub fn set_fluid_level(&mut self, position: IVec3, level: Option<u8>) {
+        self.chunks.set_fluid_level(position, level);
+    }
+
+    /// See [Chunks::get_block_state]
+    pub fn block_state(&self, position: IVec3) -> Option<BlockState> {
+        self.chunks
+            .get_block_state(position, &self.assets_chunks)
+            .ok()
+    }
+
+    /// See [Chunks::set_block_state]
+    pub fn set_block_state(
+        &mut self,
+        position: IVec3,
+        state: BlockState,
+    ) -> Result<(), ChunkError> {
+        self.chunks
+            .set_block_state(position, state, &mut self.assets_chunks)
+    }
+
+    /// See [Chunks::tile_data]
+    pub fn tile_data<T: DeserializeOwned>(
+        &self,
+        position: IVec3,
+    ) -> Result<Option<T>, TileDataError> {
+        self.chunks.tile_data(position, &self.assets_chunks)
+    }
+
+    /// See [Chunks::set_tile_data]
+    pub fn set_tile_data<T: Serialize>(
+        &mut self,
+        position: IVec3,
+        data: &T,
+    ) -> Result<(), TileDataError> {
+        self.chunks
+            .set_tile_data(position, data, &mut self.assets_chunks)
+    }
+
+    /// See [Chunks::remove_tile_data]
+    pub fn remove_tile_data(&mut self, position: IVec3) -> Result<bool, ChunkError> {
+        self.chunks
+            .remove_tile_data(position, &mut self.assets_chunks)
+    }
+
+    /// Replaces many blocks, see [Chunks::set_blocks]. Returns how many blocks changed
+    pub fn set_blocks(&mut self, edits: impl IntoIterator<Item = (IVec3, Handle<Block>)>) -> usize {
+        self.chunks
+            .set_blocks(edits, &mut self.assets_chunks, &mut self.block_changed)
+    }
+
+    /// Sets every block in `aabb`, skipping those in chunks that are not loaded. Returns how
+    /// many blocks changed
+    pub fn fill(&mut self, aabb: BlockAabb, block: Handle<Block>) -> usize {
+        self.set_blocks(aabb.iter().map(|position| (position, block.clone())))
+    }
+
+    /// Replaces every `from` block in `aabb` with `to`. Returns how many blocks changed
+    pub fn replace(&mut self, aabb: BlockAabb, from: &Handle<Block>, to: Handle<Block>) -> usize {
+        let edits: Vec<_> = aabb
+            .iter()
+            .filter(|position| self.get_block(*position).as_ref() == Some(from))
+            .map(|position| (position, to.clone()))
+            .collect();
+        self.set_blocks(edits)
+    }
+
+    /// Sets every block whose position is within `radius` of `center`. Returns how many
+    /// blocks changed
+    pub fn sphere(&mut self, center: IVec3, radius: u32, block: Handle<Block>) -> usize {
+        let radius = radius as i32;
+        let aabb = BlockAabb::new(center - IVec3::splat(radius), center + IVec3::splat(radius));
+        self.set_blocks(
+            aabb.iter()
+                .filter(|position| (*position - center).length_squared() <= radius * radius)
+                .map(|position| (position, block.clone())),
+        )
+    }
+
+    /// The blocks in `aabb` anchored at its first corner, without those in chunks that are not
+    /// loaded
+    pub fn copy(&self, aabb: BlockAabb) -> Schematic {
+        let mut schematic = Schematic::new(aabb.size().as_uvec3());
+        for position in aabb.iter() {
+            schematic.set((position - aabb.min).as_uvec3(), self.get_block(position));
+        }
+        schematic
+    }
+
+    /// Sets the blocks of `schematic` with its [anchor](Schematic::anchor) at `position`.
+    /// Returns how many blocks changed
+    pub fn paste(&mut self, schematic: &Schematic, position: IVec3) -> usize {
+        let origin = position - schematic.anchor;
+        self.set_blocks(
+            schematic
+                .iter()
+                .map(|(local, block)| (origin + local.as_ivec3(), block.clone())),
+        )
+    }
+
+    /// See [Chunks::raycast]
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RaycastHit> {
+        self.chunks.raycast(
+            origin,
+            direction,
+            max_distance,
+            &self.assets_chunks,
+            &self.blocks,
+        )
+    }
+
+    /// Adds `chunk` at its position and remeshes its neighbours, or only adds its data when
+    /// [headless](crate::ChunksPlugin::headless)
+    pub fn insert_chunk(&mut self, chunk: Chunk) {
+        let position = chunk.position;
+        let (Some(meshes), Some(materials), Some(texture_atlas)) = (
+            self.meshes.as_mut(),
+            self.materials.as_mut(),
+            self.texture_atlas.as_ref(),
+        ) else {
+            self.chunks.insert_chunk_data(
+                chunk,
+                position,
+                &mut self.commands,
+                &mut self.assets_chunks,
+                Res::clone(&self.blocks),
+            );
+            return;
+        };
+        self.chunks.insert_chunk_and_regenerate(
+            chunk,
+            position,
+            &mut self.commands,
+            meshes,
+            materials,
+            Res::clone(texture_atlas),
+            &mut self.assets_chunks,
+            Res::clone(&self.blocks),
+        );
+    }
+}
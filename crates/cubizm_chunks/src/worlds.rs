@@ -0,0 +1,225 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::chunks::ChunkLoadingState;
+use crate::impostor::Impostors;
+use crate::{
+    ActiveWorld, BlockTicks, Chunk, Chunks, ChunksPluginSettings, StructurePass, TileEntities,
+    WorldBiomes, WorldHeightmap, WorldManifest, WorldSaver,
+};
+
+/// Name a world is registered under in [Worlds], like `overworld` or `caves`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, Reflect)]
+pub struct WorldId(pub String);
+
+impl WorldId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+}
+
+/// The world at [ChunksPluginSettings::world_path]
+impl Default for WorldId {
+    fn default() -> Self {
+        Self::new("overworld")
+    }
+}
+
+impl fmt::Display for WorldId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Switches the drawn and edited world to the one registered in [Worlds] under the id,
+/// loading it the first time
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct SwitchWorld(pub WorldId);
+
+/// The state of a world while another one is active, restored as it was when switching back
+struct ParkedWorld {
+    chunks: Chunks,
+    ticks: BlockTicks,
+    saver: (PathBuf, HashMap<IVec3, String>),
+    /// What the chunks streamed in later are generated with
+    generation: (StructurePass, WorldBiomes, WorldHeightmap),
+}
+
+struct WorldEntry {
+    /// Asset path of the [WorldManifest], whose generator fills the world and whose chunk
+    /// directory it is saved to
+    manifest_path: String,
+    /// Set once the world started loading
+    manifest: Option<Handle<WorldManifest>>,
+    /// `None` while the world is active and until it first loads
+    parked: Option<ParkedWorld>,
+}
+
+impl WorldEntry {
+    fn new(manifest_path: impl Into<String>) -> Self {
+        Self {
+            manifest_path: manifest_path.into(),
+            manifest: None,
+            parked: None,
+        }
+    }
+}
+
+/// Every world the player can be in, each with its own chunks, generator and save directory
+/// from its [WorldManifest]. Only the active world is in the [Chunks] resource and drawn, the
+/// others keep their chunks in memory, frozen, until [SwitchWorld] brings them back
+#[derive(Resource)]
+pub struct Worlds {
+    worlds: HashMap<WorldId, WorldEntry>,
+    active: WorldId,
+}
+
+impl Worlds {
+    /// The worlds of `settings`, starting in the one at its `world_path`
+    pub fn new(settings: &ChunksPluginSettings) -> Self {
+        let mut worlds = HashMap::from([(
+            WorldId::default(),
+            WorldEntry::new(settings.world_path.clone()),
+        )]);
+        for (id, manifest_path) in &settings.worlds {
+            worlds
+                .entry(id.clone())
+                .or_insert_with(|| WorldEntry::new(manifest_path.clone()));
+        }
+        Self {
+            worlds,
+            active: WorldId::default(),
+        }
+    }
+
+    /// The world in the [Chunks] resource, or loading into it
+    pub fn active(&self) -> &WorldId {
+        &self.active
+    }
+
+    /// Adds a world with the [WorldManifest] at asset path `manifest_path`, loaded the first
+    /// time it is switched to. Returns `false` and keeps the old one if `id` is taken
+    pub fn register(&mut self, id: WorldId, manifest_path: impl Into<String>) -> bool {
+        if self.worlds.contains_key(&id) {
+            return false;
+        }
+        self.worlds.insert(id, WorldEntry::new(manifest_path));
+        true
+    }
+
+    pub fn contains(&self, id: &WorldId) -> bool {
+        self.worlds.contains_key(id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &WorldId> {
+        self.worlds.keys()
+    }
+
+    pub fn manifest_path(&self, id: &WorldId) -> Option<&str> {
+        self.worlds
+            .get(id)
+            .map(|entry| entry.manifest_path.as_str())
+    }
+
+    /// Whether the inactive world `id` keeps its chunks in memory, switching to it is instant
+    pub fn is_parked(&self, id: &WorldId) -> bool {
+        self.worlds
+            .get(id)
+            .is_some_and(|entry| entry.parked.is_some())
+    }
+
+    /// Loads the [WorldManifest] of the active world, remembering it for switching back
+    pub(crate) fn load_active_manifest(
+        &mut self,
+        asset_server: &AssetServer,
+    ) -> Handle<WorldManifest> {
+        let entry = self
+            .worlds
+            .get_mut(&self.active)
+            .expect("the active world is registered");
+        entry
+            .manifest
+            .get_or_insert_with(|| asset_server.load(entry.manifest_path.clone()))
+            .clone()
+    }
+}
+
+/// Parks the active world and restores the one switched to, or starts loading it from its
+/// manifest if it never loaded
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn switch_world(
+    mut commands: Commands,
+    mut events: EventReader<SwitchWorld>,
+    mut worlds: ResMut<Worlds>,
+    mut chunks: ResMut<Chunks>,
+    mut ticks: ResMut<BlockTicks>,
+    mut saver: ResMut<WorldSaver>,
+    mut structures: ResMut<StructurePass>,
+    mut biomes: ResMut<WorldBiomes>,
+    mut heightmap: ResMut<WorldHeightmap>,
+    assets_chunks: Res<Assets<Chunk>>,
+    tile_entities: Option<ResMut<TileEntities>>,
+    impostors: Option<ResMut<Impostors>>,
+    mut visibilities: Query<&mut Visibility>,
+    mut next_state: ResMut<NextState<ChunkLoadingState>>,
+) {
+    let Some(SwitchWorld(id)) = events.read().last().cloned() else {
+        return;
+    };
+    if id == worlds.active {
+        return;
+    }
+    let Some(entry) = worlds.worlds.get_mut(&id) else {
+        warn!("Can't switch to world {id}, it was never registered");
+        return;
+    };
+    let restored = entry.parked.take().zip(entry.manifest.clone());
+
+    // Nothing of the parked world is saved while it is away
+    saver.save_dirty(&mut chunks, &assets_chunks);
+    for chunk_entity in chunks.chunks.values() {
+        if let Ok(mut visibility) = visibilities.get_mut(chunk_entity.entity) {
+            *visibility = Visibility::Hidden;
+        }
+    }
+    if let Some(mut tile_entities) = tile_entities {
+        tile_entities.clear(&mut commands);
+    }
+    if let Some(mut impostors) = impostors {
+        impostors.clear(&mut commands);
+    }
+    let parked = ParkedWorld {
+        chunks: std::mem::replace(&mut *chunks, Chunks::headless()),
+        ticks: std::mem::take(&mut *ticks),
+        saver: saver.take_world(),
+        generation: (
+            std::mem::take(&mut *structures),
+            std::mem::take(&mut *biomes),
+            std::mem::take(&mut *heightmap),
+        ),
+    };
+    // Removed and inserted again so systems see the restored chunks as added
+    commands.remove_resource::<Chunks>();
+    let previous = std::mem::replace(&mut worlds.active, id.clone());
+    if let Some(entry) = worlds.worlds.get_mut(&previous) {
+        entry.parked = Some(parked);
+    }
+
+    match restored {
+        Some((world, manifest)) => {
+            *ticks = world.ticks;
+            saver.restore_world(world.saver);
+            (*structures, *biomes, *heightmap) = world.generation;
+            commands.insert_resource(world.chunks);
+            commands.insert_resource(ActiveWorld(manifest));
+            info!("Switched from world {previous} to {id}");
+        }
+        None => {
+            next_state.set(ChunkLoadingState::LoadManifest);
+            info!("Switched from world {previous} to {id}, loading it");
+        }
+    }
+}
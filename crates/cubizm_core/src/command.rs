@@ -0,0 +1,147 @@
+use bevy::math::{IVec3, Vec3};
+use thiserror::Error;
+
+/// Most blocks a `fill` changes at once
+pub const MAX_FILL_VOLUME: i64 = 32 * 32 * 32;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CommandError {
+    #[error("Unknown command {0}")]
+    Unknown(String),
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("Unknown block {0}")]
+    UnknownBlock(String),
+    #[error("Cannot fill {0} blocks at once, at most {MAX_FILL_VOLUME}")]
+    FillTooLarge(i64),
+    #[error("Only admins can run commands")]
+    NotAdmin,
+}
+
+/// Splits a typed command line into the name of the command and its arguments, dropping a
+/// leading `/` as in chat. `None` for empty lines
+pub fn split_command(line: &str) -> Option<(&str, Vec<&str>)> {
+    let line = line.trim();
+    let mut words = line.strip_prefix('/').unwrap_or(line).split_whitespace();
+    let name = words.next()?;
+    Some((name, words.collect()))
+}
+
+/// A command editing the world, typed into the developer console or sent by admins over chat
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldCommand {
+    /// `tp <x> <y> <z>` moves the player
+    Teleport(Vec3),
+    /// `setblock <x> <y> <z> <block>` replaces a block
+    SetBlock { position: IVec3, block: String },
+    /// `fill <x1> <y1> <z1> <x2> <y2> <z2> <block>` replaces every block between two corners
+    Fill {
+        min: IVec3,
+        max: IVec3,
+        block: String,
+    },
+}
+
+impl WorldCommand {
+    pub const TELEPORT_USAGE: &'static str = "tp <x> <y> <z>";
+    pub const SET_BLOCK_USAGE: &'static str = "setblock <x> <y> <z> <block>";
+    pub const FILL_USAGE: &'static str = "fill <x1> <y1> <z1> <x2> <y2> <z2> <block>";
+
+    /// Parses the command `name` with its `arguments`, see [split_command]. `None` if `name`
+    /// is not a world command
+    pub fn parse(name: &str, arguments: &[&str]) -> Option<Result<Self, CommandError>> {
+        let ivec3 = |words: &[&str]| -> Option<IVec3> {
+            Some(IVec3::new(
+                words[0].parse().ok()?,
+                words[1].parse().ok()?,
+                words[2].parse().ok()?,
+            ))
+        };
+        Some(match (name, arguments) {
+            ("tp", [x, y, z]) => match (x.parse(), y.parse(), z.parse()) {
+                (Ok(x), Ok(y), Ok(z)) => Ok(Self::Teleport(Vec3::new(x, y, z))),
+                _ => Err(CommandError::Usage(Self::TELEPORT_USAGE)),
+            },
+            ("tp", _) => Err(CommandError::Usage(Self::TELEPORT_USAGE)),
+            ("setblock", [position @ .., block]) if position.len() == 3 => ivec3(position)
+                .map(|position| Self::SetBlock {
+                    position,
+                    block: block.to_string(),
+                })
+                .ok_or(CommandError::Usage(Self::SET_BLOCK_USAGE)),
+            ("setblock", _) => Err(CommandError::Usage(Self::SET_BLOCK_USAGE)),
+            ("fill", [corners @ .., block]) if corners.len() == 6 => {
+                match (ivec3(&corners[..3]), ivec3(&corners[3..])) {
+                    (Some(from), Some(to)) => Ok(Self::Fill {
+                        min: from.min(to),
+                        max: from.max(to),
+                        block: block.to_string(),
+                    }),
+                    _ => Err(CommandError::Usage(Self::FILL_USAGE)),
+                }
+            }
+            ("fill", _) => Err(CommandError::Usage(Self::FILL_USAGE)),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Option<Result<WorldCommand, CommandError>> {
+        let (name, arguments) = split_command(line)?;
+        WorldCommand::parse(name, &arguments)
+    }
+
+    #[test]
+    fn splits_lines_with_and_without_slash() {
+        assert_eq!(
+            split_command("  /tp 1 2  3 "),
+            Some(("tp", vec!["1", "2", "3"]))
+        );
+        assert_eq!(split_command("seed"), Some(("seed", vec![])));
+        assert_eq!(split_command("   "), None);
+    }
+
+    #[test]
+    fn parses_world_commands() {
+        assert_eq!(
+            parse("tp 1 -2.5 3"),
+            Some(Ok(WorldCommand::Teleport(Vec3::new(1., -2.5, 3.))))
+        );
+        assert_eq!(
+            parse("/setblock 1 2 -3 stone"),
+            Some(Ok(WorldCommand::SetBlock {
+                position: IVec3::new(1, 2, -3),
+                block: "stone".into()
+            }))
+        );
+        assert_eq!(
+            parse("fill 4 0 -1 1 2 3 dirt"),
+            Some(Ok(WorldCommand::Fill {
+                min: IVec3::new(1, 0, -1),
+                max: IVec3::new(4, 2, 3),
+                block: "dirt".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_usage_of_malformed_commands() {
+        assert_eq!(
+            parse("tp 1 2"),
+            Some(Err(CommandError::Usage(WorldCommand::TELEPORT_USAGE)))
+        );
+        assert_eq!(
+            parse("setblock 1 x 3 stone"),
+            Some(Err(CommandError::Usage(WorldCommand::SET_BLOCK_USAGE)))
+        );
+        assert_eq!(
+            parse("fill 1 2 3 stone"),
+            Some(Err(CommandError::Usage(WorldCommand::FILL_USAGE)))
+        );
+        assert_eq!(parse("help"), None);
+    }
+}
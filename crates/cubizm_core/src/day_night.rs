@@ -0,0 +1,164 @@
+use std::f32::consts::{PI, TAU};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+
+/// Time of day in the game world, advanced by [DayNightPlugin]. Also extracted to the render
+/// world for shaders
+#[derive(Resource, ExtractResource, Debug, Clone, PartialEq)]
+pub struct GameTime {
+    /// Fraction of the current day from 0 to 1, `0.0` is midnight, `0.25` sunrise, `0.5` noon
+    /// and `0.75` sunset
+    pub time_of_day: f32,
+    /// Days passed since the world started
+    pub day: u32,
+    /// Real time a full day takes
+    pub day_length: Duration,
+    /// Stops the time from advancing
+    pub paused: bool,
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        Self {
+            time_of_day: 0.3,
+            day: 0,
+            day_length: Duration::from_secs(20 * 60),
+            paused: false,
+        }
+    }
+}
+
+impl GameTime {
+    /// Angle of the sun above the eastern horizon in radians, negative at night
+    pub fn sun_angle(&self) -> f32 {
+        (self.time_of_day - 0.25) * TAU
+    }
+
+    /// How bright the day is, from 0 at night to 1 at noon
+    pub fn daylight(&self) -> f32 {
+        self.sun_angle().sin().max(0.0)
+    }
+
+    /// Advances the time by `delta` of real time
+    pub fn advance(&mut self, delta: Duration) {
+        if self.paused || self.day_length.is_zero() {
+            return;
+        }
+        let time = self.time_of_day + delta.as_secs_f32() / self.day_length.as_secs_f32();
+        self.day += time.floor() as u32;
+        self.time_of_day = time.fract();
+    }
+}
+
+/// The directional light of the sun, turned by [DayNightPlugin]
+#[derive(Component, Debug)]
+pub struct Sun;
+
+/// The directional light of the moon, opposite the [Sun]
+#[derive(Component, Debug)]
+pub struct Moon;
+
+/// Brightness of the sky lights over the day
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct DayNightLighting {
+    /// Illuminance of the sun at noon in lux
+    pub sun_illuminance: f32,
+    /// Illuminance of the moon at midnight in lux, far brighter than a real moon by default so
+    /// the night stays playable
+    pub moon_illuminance: f32,
+    /// [AmbientLight] brightness at noon
+    pub day_ambient: f32,
+    /// [AmbientLight] brightness at night
+    pub night_ambient: f32,
+}
+
+impl Default for DayNightLighting {
+    fn default() -> Self {
+        Self {
+            sun_illuminance: light_consts::lux::OVERCAST_DAY,
+            moon_illuminance: light_consts::lux::DARK_OVERCAST_DAY,
+            day_ambient: 80.0,
+            night_ambient: 8.0,
+        }
+    }
+}
+
+/// Moves a sun and a moon light across the sky and fades the ambient light with the
+/// [GameTime]
+#[derive(Default)]
+pub struct DayNightPlugin {
+    /// Initial value of the [GameTime] resource
+    pub time: GameTime,
+    pub lighting: DayNightLighting,
+}
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.time.clone())
+            .insert_resource(self.lighting)
+            .add_plugins(ExtractResourcePlugin::<GameTime>::default())
+            .add_systems(Startup, spawn_sky_lights)
+            .add_systems(Update, (advance_game_time, update_sky_lights).chain());
+    }
+}
+
+fn spawn_sky_lights(mut commands: Commands) {
+    commands.spawn((
+        Sun,
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                shadows_enabled: true,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+    commands.spawn((
+        Moon,
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                color: Color::rgb(0.7, 0.75, 1.0),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+fn advance_game_time(time: Res<Time>, mut game_time: ResMut<GameTime>) {
+    game_time.advance(time.delta());
+}
+
+#[allow(clippy::type_complexity)]
+fn update_sky_lights(
+    game_time: Res<GameTime>,
+    lighting: Res<DayNightLighting>,
+    // Missing without bevy's `PbrPlugin`
+    ambient: Option<ResMut<AmbientLight>>,
+    mut suns: Query<(&mut DirectionalLight, &mut Transform), (With<Sun>, Without<Moon>)>,
+    mut moons: Query<(&mut DirectionalLight, &mut Transform), (With<Moon>, Without<Sun>)>,
+) {
+    if !game_time.is_changed() && !lighting.is_changed() {
+        return;
+    }
+    let angle = game_time.sun_angle();
+    // Lights shine along their forward axis, which starts out level towards -z. Tilted
+    // slightly so shadows never line up with the block grid
+    let rotation = Quat::from_rotation_y(PI / 8.0) * Quat::from_rotation_x(-angle);
+    let daylight = game_time.daylight();
+    let moonlight = (-angle.sin()).max(0.0);
+    for (mut light, mut transform) in suns.iter_mut() {
+        transform.rotation = rotation;
+        light.illuminance = lighting.sun_illuminance * daylight;
+    }
+    for (mut light, mut transform) in moons.iter_mut() {
+        transform.rotation = rotation * Quat::from_rotation_x(PI);
+        light.illuminance = lighting.moon_illuminance * moonlight;
+    }
+    if let Some(mut ambient) = ambient {
+        ambient.brightness =
+            lighting.night_ambient + (lighting.day_ambient - lighting.night_ambient) * daylight;
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::pbr::{FogFalloff, FogSettings};
+use bevy::prelude::*;
+
+use crate::CHUNK_SIZE;
+
+/// Radius in chunks around the active camera within which chunks are drawn
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderDistance(pub u32);
+
+impl Default for RenderDistance {
+    fn default() -> Self {
+        Self(8)
+    }
+}
+
+/// Fog over the distance of the [RenderDistance], so chunks fade out at its edge instead of
+/// popping in and out of view
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct DistanceFog {
+    pub enabled: bool,
+    /// `None` follows the [ClearColor], so the fog blends into the sky
+    pub color: Option<Color>,
+    /// Fraction of the render distance where the fog starts
+    pub start: f32,
+    /// Fraction of the render distance where the fog hides everything. Above 1 for the far
+    /// terrain impostors to stay visible past the render distance
+    pub end: f32,
+}
+
+impl Default for DistanceFog {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            color: None,
+            start: 0.6,
+            end: 1.0,
+        }
+    }
+}
+
+/// Keeps [FogSettings] on every 3D camera matched to the [DistanceFog] and [RenderDistance]
+#[derive(Default)]
+pub struct DistanceFogPlugin {
+    pub fog: DistanceFog,
+}
+
+impl Plugin for DistanceFogPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.fog)
+            .init_resource::<RenderDistance>()
+            .add_systems(PostUpdate, update_distance_fog);
+    }
+}
+
+fn update_distance_fog(
+    mut commands: Commands,
+    fog: Res<DistanceFog>,
+    render_distance: Res<RenderDistance>,
+    clear_color: Res<ClearColor>,
+    mut cameras: Query<(Entity, Option<&mut FogSettings>), With<Camera3d>>,
+    added: Query<(), Added<Camera3d>>,
+) {
+    let changed = fog.is_changed()
+        || render_distance.is_changed()
+        || (fog.color.is_none() && clear_color.is_changed())
+        || !added.is_empty();
+    if !changed {
+        return;
+    }
+    // Blocks are measured from the camera, the chunk it is in reaches half a chunk further
+    let distance = (render_distance.0 as f32 + 0.5) * CHUNK_SIZE as f32;
+    let settings = FogSettings {
+        color: fog.color.unwrap_or(clear_color.0),
+        falloff: FogFalloff::Linear {
+            start: distance * fog.start,
+            end: distance * fog.end,
+        },
+        ..default()
+    };
+    for (entity, current) in cameras.iter_mut() {
+        match (fog.enabled, current) {
+            (true, Some(mut current)) => *current = settings.clone(),
+            (true, None) => {
+                commands.entity(entity).insert(settings.clone());
+            }
+            (false, Some(_)) => {
+                commands.entity(entity).remove::<FogSettings>();
+            }
+            (false, None) => {}
+        }
+    }
+}
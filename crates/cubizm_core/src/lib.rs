@@ -1,10 +1,14 @@
-use std::f32::consts::PI;
-
 use bevy::app::App;
 use bevy::prelude::*;
 
+pub use command::*;
+pub use day_night::*;
+pub use fog::*;
+pub use state::*;
 pub use util::*;
 
+/// The loading phases of the game shared by every crate. Each crate loads in its own state
+/// machine scoped to one of them, see [LoadingStateApp]
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, States)]
 pub enum AppState {
     #[default]
@@ -14,6 +18,11 @@ pub enum AppState {
     Finished,
 }
 
+mod command;
+mod day_night;
+mod fog;
+pub mod mods;
+mod state;
 mod util;
 
 pub struct Cubizm;
@@ -21,26 +30,9 @@ impl Plugin for Cubizm {
     fn build(&self, app: &mut App) {
         app.init_state::<AppState>();
         app.add_systems(OnEnter(AppState::ChunksLoaded), finish);
-        app.add_systems(Startup, setup);
     }
 }
 
-fn setup(mut commands: Commands) {
-    commands.spawn(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            illuminance: light_consts::lux::OVERCAST_DAY,
-            shadows_enabled: true,
-            ..default()
-        },
-        transform: Transform {
-            translation: Vec3::new(0.0, 2.0, 0.0),
-            rotation: Quat::from_rotation_x(-PI / 4.),
-            ..default()
-        },
-        ..default()
-    });
-}
-
 fn finish(mut next_state: ResMut<NextState<AppState>>) {
     next_state.set(AppState::Finished);
 }
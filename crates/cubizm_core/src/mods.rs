@@ -0,0 +1,320 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::asset::io::{file::FileAssetReader, AssetSource};
+use bevy::asset::ron;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// File at the root of every pack describing it
+pub const MOD_MANIFEST: &str = "pack.ron";
+
+/// How long file changes in a pack are debounced before being reloaded
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ModDependency {
+    pub name: String,
+    /// Any version is accepted if unset
+    #[serde(default)]
+    pub version: Option<VersionReq>,
+}
+
+/// Contents of a pack's [MOD_MANIFEST]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ModManifest {
+    /// Also the asset source the pack's files are loaded from, `<name>://blocks/info`
+    pub name: String,
+    pub version: Version,
+    #[serde(default)]
+    pub dependencies: Vec<ModDependency>,
+    /// Namespaces whose content the pack replaces, such as `"cubizm"` for the base blocks.
+    /// A block or item of the pack takes the place of the one of the same name in each of
+    /// them, later packs overriding earlier ones
+    #[serde(default)]
+    pub overrides: Vec<String>,
+}
+
+/// A pack found in the mods directory.
+/// Content is laid out like the base `assets` folder: blocks in `blocks/info`, textures
+/// wherever the blocks point to, and so on
+#[derive(Debug, Clone)]
+pub struct ModPack {
+    pub manifest: ModManifest,
+    /// Directory of the pack relative to the asset base path
+    pub path: PathBuf,
+}
+
+impl ModPack {
+    pub fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    /// Whether the pack ships a directory at `path`, such as `blocks/info`
+    pub fn has_dir(&self, path: impl AsRef<Path>) -> bool {
+        FileAssetReader::get_base_path()
+            .join(&self.path)
+            .join(path)
+            .is_dir()
+    }
+
+    /// Asset paths of the files ending in `.{extension}` in the pack's directory `path`,
+    /// sorted by name. Empty if the pack has no such directory
+    pub fn files(&self, path: &str, extension: &str) -> Vec<String> {
+        let Ok(entries) =
+            std::fs::read_dir(FileAssetReader::get_base_path().join(&self.path).join(path))
+        else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(&format!(".{extension}")))
+            .collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| self.asset_path(&format!("{path}/{name}")))
+            .collect()
+    }
+
+    /// `path` inside this pack's asset source
+    pub fn asset_path(&self, path: &str) -> String {
+        format!("{}://{path}", self.manifest.name)
+    }
+}
+
+/// Every pack that loaded successfully, in load order: each pack comes after its dependencies
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ModPacks(Vec<ModPack>);
+
+impl ModPacks {
+    pub fn iter(&self) -> impl Iterator<Item = &ModPack> {
+        self.0.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ModPack> {
+        self.0.iter().find(|pack| pack.name() == name)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ModError {
+    #[error("Cannot read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Invalid manifest {0}: {1}")]
+    Manifest(PathBuf, ron::error::SpannedError),
+    #[error("Mod name {0:?} may only contain lowercase letters, digits, `-` and `_`")]
+    InvalidName(String),
+    #[error("Mod {0} is provided by both {1} and {2}")]
+    Duplicate(String, PathBuf, PathBuf),
+    #[error("Mod {0} depends on {1}, which is not installed")]
+    MissingDependency(String, String),
+    #[error("Mod {0} requires {1} {2} but {3} is installed")]
+    IncompatibleDependency(String, String, VersionReq, Version),
+    #[error("Mod {0} depends on {1}, which failed to load")]
+    FailedDependency(String, String),
+    #[error("Mods {0:?} depend on each other")]
+    DependencyCycle(Vec<String>),
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn read_manifest(path: &Path) -> Result<ModManifest, ModError> {
+    let bytes = std::fs::read(path).map_err(|err| ModError::Io(path.to_owned(), err))?;
+    let manifest: ModManifest =
+        ron::de::from_bytes(&bytes).map_err(|err| ModError::Manifest(path.to_owned(), err))?;
+    if !valid_name(&manifest.name) {
+        return Err(ModError::InvalidName(manifest.name));
+    }
+    Ok(manifest)
+}
+
+/// Reads the manifest of every pack in `directory`, relative to the asset base path
+pub fn discover_mods(directory: &Path) -> (Vec<ModPack>, Vec<ModError>) {
+    let root = FileAssetReader::get_base_path().join(directory);
+    let entries = match std::fs::read_dir(&root) {
+        Ok(entries) => entries,
+        // No mods installed
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return default(),
+        Err(err) => return (Vec::new(), vec![ModError::Io(root, err)]),
+    };
+
+    let mut packs: Vec<ModPack> = Vec::new();
+    let mut errors = Vec::new();
+    let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let manifest_path = entry.path().join(MOD_MANIFEST);
+        if !manifest_path.is_file() {
+            continue;
+        }
+        let manifest = match read_manifest(&manifest_path) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+        let path = directory.join(entry.file_name());
+        if let Some(existing) = packs.iter().find(|pack| pack.name() == manifest.name) {
+            errors.push(ModError::Duplicate(
+                manifest.name,
+                existing.path.clone(),
+                path,
+            ));
+            continue;
+        }
+        packs.push(ModPack { manifest, path });
+    }
+    (packs, errors)
+}
+
+/// Orders `packs` so that every pack comes after its dependencies, ties broken by name.
+/// Packs with missing, incompatible or cyclic dependencies are left out along with anything
+/// depending on them
+pub fn resolve_load_order(packs: Vec<ModPack>) -> (Vec<ModPack>, Vec<ModError>) {
+    let mut errors = Vec::new();
+    let versions: HashMap<String, Version> = packs
+        .iter()
+        .map(|pack| (pack.manifest.name.clone(), pack.manifest.version.clone()))
+        .collect();
+
+    let mut pending: Vec<ModPack> = Vec::new();
+    for pack in packs {
+        let broken = pack.manifest.dependencies.iter().find_map(|dependency| {
+            let Some(installed) = versions.get(&dependency.name) else {
+                return Some(ModError::MissingDependency(
+                    pack.manifest.name.clone(),
+                    dependency.name.clone(),
+                ));
+            };
+            match &dependency.version {
+                Some(required) if !required.matches(installed) => {
+                    Some(ModError::IncompatibleDependency(
+                        pack.manifest.name.clone(),
+                        dependency.name.clone(),
+                        required.clone(),
+                        installed.clone(),
+                    ))
+                }
+                _ => None,
+            }
+        });
+        match broken {
+            Some(err) => errors.push(err),
+            None => pending.push(pack),
+        }
+    }
+    pending.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+
+    let mut failed: HashSet<String> = errors
+        .iter()
+        .filter_map(|err| match err {
+            ModError::MissingDependency(name, _) | ModError::IncompatibleDependency(name, ..) => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    let mut loaded: HashSet<String> = HashSet::new();
+    let mut ordered = Vec::new();
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|pack| {
+            pack.manifest
+                .dependencies
+                .iter()
+                .all(|dependency| loaded.contains(&dependency.name))
+        });
+        if let Some(index) = ready {
+            let pack = pending.remove(index);
+            loaded.insert(pack.manifest.name.clone());
+            ordered.push(pack);
+            continue;
+        }
+
+        let blocked = pending.iter().position(|pack| {
+            pack.manifest
+                .dependencies
+                .iter()
+                .any(|dependency| failed.contains(&dependency.name))
+        });
+        if let Some(index) = blocked {
+            let pack = pending.remove(index);
+            let dependency = pack
+                .manifest
+                .dependencies
+                .iter()
+                .find(|dependency| failed.contains(&dependency.name))
+                .map(|dependency| dependency.name.clone())
+                .unwrap_or_default();
+            failed.insert(pack.manifest.name.clone());
+            errors.push(ModError::FailedDependency(pack.manifest.name, dependency));
+            continue;
+        }
+
+        // Everything left waits on something else that is left
+        errors.push(ModError::DependencyCycle(
+            pending.drain(..).map(|pack| pack.manifest.name).collect(),
+        ));
+    }
+    (ordered, errors)
+}
+
+/// Problems found while discovering mods, logged once logging is set up
+#[derive(Resource, Default)]
+struct ModReport(Vec<ModError>);
+
+fn report_mods(mut report: ResMut<ModReport>, packs: Res<ModPacks>) {
+    for err in report.0.drain(..) {
+        error!("{err}");
+    }
+    for pack in packs.iter() {
+        info!("Loaded mod {} {}", pack.name(), pack.manifest.version);
+    }
+}
+
+/// Discovers content packs in `directory` and registers an asset source for each of them.
+/// Must be added before `DefaultPlugins`, as asset sources cannot be registered after the
+/// [AssetPlugin] is built
+pub struct ModsPlugin {
+    /// Relative to the asset base path, the parent directory of `assets`
+    pub directory: PathBuf,
+}
+
+impl Default for ModsPlugin {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("mods"),
+        }
+    }
+}
+
+impl Plugin for ModsPlugin {
+    fn build(&self, app: &mut App) {
+        let (packs, mut errors) = discover_mods(&self.directory);
+        let (packs, resolve_errors) = resolve_load_order(packs);
+        errors.extend(resolve_errors);
+
+        for pack in packs.iter() {
+            let path = pack.path.to_string_lossy().into_owned();
+            app.register_asset_source(
+                pack.manifest.name.clone(),
+                AssetSource::build()
+                    .with_reader(AssetSource::get_default_reader(path.clone()))
+                    .with_watcher(AssetSource::get_default_watcher(path, WATCH_DEBOUNCE)),
+            );
+        }
+        app.insert_resource(ModPacks(packs))
+            .insert_resource(ModReport(errors))
+            .add_systems(Startup, report_mods);
+    }
+}
@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+
+use crate::AppState;
+
+/// Adds the loading state machines of the crates as sub-states of [AppState], which bevy only
+/// supports from 0.14 on
+pub trait LoadingStateApp {
+    /// Adds `S`, which moves from its default to `start` whenever the app enters `parent`, and
+    /// back to its default whenever the app goes back to [AppState::Setup] after that, so it
+    /// loads again from scratch
+    fn add_loading_state<S: States + Default>(&mut self, parent: AppState, start: S) -> &mut Self;
+}
+
+impl LoadingStateApp for App {
+    fn add_loading_state<S: States + Default>(&mut self, parent: AppState, start: S) -> &mut Self {
+        self.init_state::<S>().add_systems(
+            OnEnter(parent),
+            move |mut next_state: ResMut<NextState<S>>| next_state.set(start.clone()),
+        );
+        if parent != AppState::Setup {
+            self.add_systems(
+                OnEnter(AppState::Setup),
+                |mut next_state: ResMut<NextState<S>>| next_state.set(S::default()),
+            );
+        }
+        self
+    }
+}
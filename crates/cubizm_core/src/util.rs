@@ -1,3 +1,4 @@
+use bevy::math::{IVec3, UVec3, Vec3};
 use bevy::prelude::default;
 use bevy::render::render_asset::RenderAssetUsages;
 use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
@@ -41,3 +42,118 @@ pub fn uv_debug_texture() -> bevy::prelude::Image {
     img.sampler = image_sampler;
     img
 }
+
+/// Width of a chunk in blocks
+pub const CHUNK_SIZE: u32 = 16;
+
+/// The chunk holding the block at world `block`.
+/// Chunk meshes have one block of padding on each side, so the block at world `block` spans
+/// `block..block + 1` in the chunk meshes. Rounds towards negative infinity, block `0` is in
+/// chunk `-1`
+pub fn world_to_chunk(block: IVec3) -> IVec3 {
+    (block - IVec3::ONE).div_euclid(IVec3::splat(CHUNK_SIZE as i32))
+}
+
+/// Position of the block at world `block` in its chunk, within 0..[CHUNK_SIZE]
+pub fn world_to_local(block: IVec3) -> UVec3 {
+    (block - IVec3::ONE)
+        .rem_euclid(IVec3::splat(CHUNK_SIZE as i32))
+        .as_uvec3()
+}
+
+/// World position of the block at `local` in the chunk at `chunk`
+pub fn local_to_world(chunk: IVec3, local: UVec3) -> IVec3 {
+    chunk_to_world(chunk) + local.as_ivec3() + IVec3::ONE
+}
+
+/// World position of the padded corner of the chunk at `chunk`, where its entity is placed
+pub fn chunk_to_world(chunk: IVec3) -> IVec3 {
+    chunk * CHUNK_SIZE as i32
+}
+
+/// The block containing the point `position`
+pub fn point_to_block(position: Vec3) -> IVec3 {
+    position.floor().as_ivec3()
+}
+
+/// The chunk holding the block containing the point `position`
+pub fn point_to_chunk(position: Vec3) -> IVec3 {
+    world_to_chunk(point_to_block(position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: i32 = CHUNK_SIZE as i32;
+
+    #[test]
+    fn chunks_hold_the_blocks_after_their_corner() {
+        assert_eq!(world_to_chunk(IVec3::splat(1)), IVec3::ZERO);
+        assert_eq!(world_to_chunk(IVec3::splat(SIZE)), IVec3::ZERO);
+        assert_eq!(world_to_chunk(IVec3::splat(SIZE + 1)), IVec3::ONE);
+        assert_eq!(world_to_chunk(IVec3::ZERO), IVec3::NEG_ONE);
+    }
+
+    #[test]
+    fn negative_blocks_round_towards_negative_infinity() {
+        assert_eq!(world_to_chunk(IVec3::splat(-1)), IVec3::NEG_ONE);
+        assert_eq!(world_to_chunk(IVec3::splat(1 - SIZE)), IVec3::NEG_ONE);
+        assert_eq!(world_to_chunk(IVec3::splat(-SIZE)), IVec3::splat(-2));
+        assert_eq!(world_to_local(IVec3::ZERO), UVec3::splat(CHUNK_SIZE - 1));
+        assert_eq!(
+            world_to_local(IVec3::splat(-1)),
+            UVec3::splat(CHUNK_SIZE - 2)
+        );
+        assert_eq!(world_to_local(IVec3::splat(1 - SIZE)), UVec3::ZERO);
+    }
+
+    #[test]
+    fn local_positions_cover_the_chunk() {
+        assert_eq!(world_to_local(IVec3::ONE), UVec3::ZERO);
+        assert_eq!(
+            world_to_local(IVec3::splat(SIZE)),
+            UVec3::splat(CHUNK_SIZE - 1)
+        );
+        assert_eq!(world_to_local(IVec3::splat(SIZE + 1)), UVec3::ZERO);
+        assert_eq!(world_to_local(IVec3::new(3, -20, 40)), UVec3::new(2, 11, 7));
+    }
+
+    #[test]
+    fn local_to_world_inverts_world_to_chunk_and_local() {
+        for x in -2 * SIZE - 1..=2 * SIZE + 1 {
+            let block = IVec3::new(x, -x, x / 3 - 7);
+            let chunk = world_to_chunk(block);
+            let local = world_to_local(block);
+            assert!(local.cmplt(UVec3::splat(CHUNK_SIZE)).all());
+            assert_eq!(local_to_world(chunk, local), block);
+        }
+    }
+
+    #[test]
+    fn chunk_corner_is_the_block_before_the_first() {
+        for chunk in [IVec3::ZERO, IVec3::new(-1, 2, -3)] {
+            assert_eq!(
+                local_to_world(chunk, UVec3::ZERO),
+                chunk_to_world(chunk) + IVec3::ONE
+            );
+            assert_eq!(world_to_chunk(chunk_to_world(chunk)), chunk - IVec3::ONE);
+        }
+    }
+
+    #[test]
+    fn points_floor_into_blocks() {
+        assert_eq!(
+            point_to_block(Vec3::new(0.5, -0.5, -1.)),
+            IVec3::new(0, -1, -1)
+        );
+        assert_eq!(
+            point_to_chunk(Vec3::new(0.5, 1.5, 16.9)),
+            IVec3::new(-1, 0, 0)
+        );
+        assert_eq!(
+            point_to_chunk(Vec3::new(-0.1, 17., 16.)),
+            IVec3::new(-1, 1, 0)
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::path::Path;
+
+use bevy::{asset::ron, prelude::*};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{ItemStack, MAX_STACK_SIZE};
+
+/// Slots holding [ItemStack]s, up to a stack limit each
+#[derive(Component, Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+    stack_limit: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum InventoryError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+    #[error("Slot {0} is out of range")]
+    SlotOutOfRange(usize),
+}
+
+impl Inventory {
+    /// An empty inventory of `size` slots holding [MAX_STACK_SIZE] items each
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: vec![None; size],
+            stack_limit: MAX_STACK_SIZE,
+        }
+    }
+
+    pub fn with_stack_limit(mut self, stack_limit: u32) -> Self {
+        self.stack_limit = stack_limit.max(1);
+        self
+    }
+
+    pub fn stack_limit(&self) -> u32 {
+        self.stack_limit
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+        self.slots.get(slot)?.as_ref()
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    /// How many of `item` the inventory holds over all its slots
+    pub fn count(&self, item: &str) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// Adds `stack`, topping up stacks of the same item before filling empty slots. Returns
+    /// the items that did not fit, `None` if all of them did
+    pub fn add(&mut self, stack: ItemStack) -> Option<ItemStack> {
+        self.add_limited(stack, self.stack_limit)
+    }
+
+    /// Like [Inventory::add] with stacks of the item holding at most `limit`, for items with a
+    /// lower [max_stack_size](crate::Item::max_stack_size) than the inventory's stack limit
+    pub fn add_limited(&mut self, mut stack: ItemStack, limit: u32) -> Option<ItemStack> {
+        let limit = limit.clamp(1, self.stack_limit);
+        for slot in self.slots.iter_mut().flatten() {
+            if stack.is_empty() {
+                break;
+            }
+            if slot.stacks_with(&stack) {
+                let moved = stack.count.min(limit.saturating_sub(slot.count));
+                slot.count += moved;
+                stack.count -= moved;
+            }
+        }
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if stack.is_empty() {
+                break;
+            }
+            *slot = Some(stack.split(limit));
+        }
+        (!stack.is_empty()).then_some(stack)
+    }
+
+    /// Removes up to `count` of `item`, emptying the last slots holding it first. Returns how
+    /// many were removed
+    pub fn remove(&mut self, item: &str, count: u32) -> u32 {
+        let mut removed = 0;
+        for slot in self.slots.iter_mut().rev() {
+            let Some(stack) = slot.as_mut().filter(|stack| stack.item == item) else {
+                continue;
+            };
+            removed += stack.split(count - removed).count;
+            if stack.is_empty() {
+                *slot = None;
+            }
+            if removed == count {
+                break;
+            }
+        }
+        removed
+    }
+
+    /// Takes up to `count` items out of `slot`
+    pub fn take(&mut self, slot: usize, count: u32) -> Result<Option<ItemStack>, InventoryError> {
+        let stored = self
+            .slots
+            .get_mut(slot)
+            .ok_or(InventoryError::SlotOutOfRange(slot))?;
+        let Some(stack) = stored.as_mut() else {
+            return Ok(None);
+        };
+        let taken = stack.split(count);
+        if stack.is_empty() {
+            *stored = None;
+        }
+        Ok(Some(taken).filter(|taken| !taken.is_empty()))
+    }
+
+    /// Puts `stack` into `slot`, returning what was there before. Stacks over the limit are
+    /// kept whole, the limit only applies to [Inventory::add]
+    pub fn set(
+        &mut self,
+        slot: usize,
+        stack: Option<ItemStack>,
+    ) -> Result<Option<ItemStack>, InventoryError> {
+        let stored = self
+            .slots
+            .get_mut(slot)
+            .ok_or(InventoryError::SlotOutOfRange(slot))?;
+        Ok(std::mem::replace(
+            stored,
+            stack.filter(|stack| !stack.is_empty()),
+        ))
+    }
+
+    /// Swaps the contents of two slots. Stacks of the same item are merged into `b` instead,
+    /// as far as the stack limit allows
+    pub fn swap(&mut self, a: usize, b: usize) -> Result<(), InventoryError> {
+        for slot in [a, b] {
+            if slot >= self.slots.len() {
+                return Err(InventoryError::SlotOutOfRange(slot));
+            }
+        }
+        if a == b {
+            return Ok(());
+        }
+        if let (Some(from), Some(to)) = (&self.slots[a], &self.slots[b]) {
+            if from.stacks_with(to) && to.count < self.stack_limit {
+                let moved = from.count.min(self.stack_limit - to.count);
+                if let Some(to) = self.slots[b].as_mut() {
+                    to.count += moved;
+                }
+                if let Some(from) = self.slots[a].as_mut() {
+                    from.count -= moved;
+                    if from.is_empty() {
+                        self.slots[a] = None;
+                    }
+                }
+                return Ok(());
+            }
+        }
+        self.slots.swap(a, b);
+        Ok(())
+    }
+
+    /// Writes the inventory to `path` as RON
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), InventoryError> {
+        let path = path.as_ref();
+        let ron = ron::ser::to_string_pretty(self, default())?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(path, ron)?;
+        Ok(())
+    }
+
+    /// Reads an inventory written by [Inventory::save]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, InventoryError> {
+        Ok(ron::de::from_bytes(&std::fs::read(path)?)?)
+    }
+}
+
+/// Asks [InventoryPlugin](crate::InventoryPlugin) to add items to the [Inventory] of an entity
+#[derive(Event, Debug, Clone)]
+pub struct AddItems {
+    pub inventory: Entity,
+    pub stack: ItemStack,
+}
+
+/// Asks [InventoryPlugin](crate::InventoryPlugin) to remove items from the [Inventory] of an
+/// entity
+#[derive(Event, Debug, Clone)]
+pub struct RemoveItems {
+    pub inventory: Entity,
+    pub item: String,
+    pub count: u32,
+}
+
+/// Sent when an [AddItems] did not fit, with the items left over
+#[derive(Event, Debug, Clone)]
+pub struct InventoryFull {
+    pub inventory: Entity,
+    pub stack: ItemStack,
+}
+
+/// Sent whenever the [Inventory] of an entity is added or changes
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InventoryChanged {
+    pub inventory: Entity,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_tops_up_stacks_before_empty_slots() {
+        let mut inventory = Inventory::new(3).with_stack_limit(10);
+        inventory.set(1, Some(ItemStack::new("stone", 8))).unwrap();
+        assert_eq!(inventory.add(ItemStack::new("stone", 5)), None);
+        assert_eq!(inventory.get(1), Some(&ItemStack::new("stone", 10)));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("stone", 3)));
+        assert_eq!(inventory.count("stone"), 13);
+    }
+
+    #[test]
+    fn add_returns_what_does_not_fit() {
+        let mut inventory = Inventory::new(2).with_stack_limit(4);
+        let left = inventory.add(ItemStack::new("dirt", 11));
+        assert_eq!(left, Some(ItemStack::new("dirt", 3)));
+        assert_eq!(
+            inventory.add(ItemStack::new("sand", 1)),
+            Some(ItemStack::new("sand", 1))
+        );
+    }
+
+    #[test]
+    fn add_limited_keeps_to_the_lower_limit() {
+        let mut inventory = Inventory::new(3);
+        assert_eq!(inventory.add_limited(ItemStack::new("bucket", 2), 1), None);
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("bucket", 1)));
+        assert_eq!(inventory.get(1), Some(&ItemStack::new("bucket", 1)));
+    }
+
+    #[test]
+    fn remove_empties_the_last_slots_first() {
+        let mut inventory = Inventory::new(3).with_stack_limit(5);
+        inventory.add(ItemStack::new("stone", 12));
+        assert_eq!(inventory.remove("stone", 3), 3);
+        assert_eq!(inventory.get(2), None);
+        assert_eq!(inventory.get(1), Some(&ItemStack::new("stone", 4)));
+        assert_eq!(inventory.remove("stone", 100), 9);
+        assert!(inventory.is_empty());
+    }
+
+    #[test]
+    fn take_splits_and_clears_slots() {
+        let mut inventory = Inventory::new(2);
+        inventory.add(ItemStack::new("stick", 4));
+        assert_eq!(
+            inventory.take(0, 3).unwrap(),
+            Some(ItemStack::new("stick", 3))
+        );
+        assert_eq!(
+            inventory.take(0, 3).unwrap(),
+            Some(ItemStack::new("stick", 1))
+        );
+        assert_eq!(inventory.get(0), None);
+        assert_eq!(inventory.take(1, 1).unwrap(), None);
+        assert!(matches!(
+            inventory.take(2, 1),
+            Err(InventoryError::SlotOutOfRange(2))
+        ));
+    }
+
+    #[test]
+    fn swap_merges_stacks_of_the_same_item() {
+        let mut inventory = Inventory::new(3).with_stack_limit(10);
+        inventory.set(0, Some(ItemStack::new("stone", 7))).unwrap();
+        inventory.set(1, Some(ItemStack::new("stone", 6))).unwrap();
+        inventory.set(2, Some(ItemStack::new("dirt", 1))).unwrap();
+        inventory.swap(0, 1).unwrap();
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("stone", 3)));
+        assert_eq!(inventory.get(1), Some(&ItemStack::new("stone", 10)));
+        inventory.swap(0, 2).unwrap();
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("dirt", 1)));
+        assert_eq!(inventory.get(2), Some(&ItemStack::new("stone", 3)));
+        assert!(matches!(
+            inventory.swap(0, 3),
+            Err(InventoryError::SlotOutOfRange(3))
+        ));
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let mut inventory = Inventory::new(4).with_stack_limit(16);
+        inventory.add(ItemStack::new("stone", 20));
+        let path = std::env::temp_dir()
+            .join(format!("cubizm_inventory_{}", std::process::id()))
+            .join("inventory.ron");
+        inventory.save(&path).unwrap();
+        let loaded = Inventory::load(&path);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded.unwrap(), inventory);
+    }
+}
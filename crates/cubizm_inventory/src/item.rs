@@ -0,0 +1,107 @@
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AssetPath, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::MAX_STACK_SIZE;
+
+/// Something that can be held in an [Inventory](crate::Inventory), loaded from `.item` files
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Item {
+    pub name: String,
+    pub icon: Option<Handle<Image>>,
+    /// Most of the item one stack holds
+    pub max_stack_size: u32,
+    /// Registry name of the block the item places, see
+    /// [BlockRegistry::find](cubizm_block::BlockRegistry::find)
+    pub block: Option<String>,
+}
+
+/// [Item] as written in `.item` files
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedItem {
+    pub name: String,
+    /// Asset path, or a file name in
+    /// [ItemPluginSettings::textures_path](crate::ItemPluginSettings::textures_path)
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default = "default_max_stack_size")]
+    pub max_stack_size: u32,
+    #[serde(default)]
+    pub block: Option<String>,
+}
+
+fn default_max_stack_size() -> u32 {
+    MAX_STACK_SIZE
+}
+
+#[derive(Debug, Error)]
+pub enum ItemLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+}
+
+pub struct ItemLoader {
+    /// See [ItemPluginSettings::textures_path](crate::ItemPluginSettings::textures_path)
+    textures_path: String,
+    /// See [ItemPlugin::headless](crate::ItemPlugin::headless)
+    headless: bool,
+}
+
+impl ItemLoader {
+    pub(crate) fn new(textures_path: &str, headless: bool) -> Self {
+        Self {
+            textures_path: textures_path.trim_end_matches('/').to_string(),
+            headless,
+        }
+    }
+
+    /// `path` as is if it is an asset path, otherwise the file of that name in
+    /// [textures_path](ItemLoader::textures_path), from the same source as the item
+    fn icon_path(&self, path: String, load_context: &LoadContext) -> AssetPath<'static> {
+        if path.contains('/') {
+            return path.into();
+        }
+        AssetPath::from(format!("{}/{path}", self.textures_path))
+            .with_source(load_context.asset_path().source().clone_owned())
+    }
+}
+
+impl AssetLoader for ItemLoader {
+    type Asset = Item;
+    type Settings = ();
+    type Error = ItemLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let ron: SerializedItem = ron::de::from_bytes(&bytes)?;
+            // Nothing draws the icon without a renderer
+            let icon = ron
+                .icon
+                .filter(|_| !self.headless)
+                .map(|path| load_context.load(self.icon_path(path, load_context)));
+            Ok(Item {
+                name: ron.name,
+                icon,
+                max_stack_size: ron.max_stack_size.max(1),
+                block: ron.block,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["item"]
+    }
+}
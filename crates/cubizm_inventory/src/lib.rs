@@ -0,0 +1,173 @@
+use bevy::{asset::LoadedFolder, prelude::*};
+
+pub use inventory::*;
+pub use item::*;
+pub use loot::*;
+pub use recipe::*;
+pub use registry::*;
+pub use stack::*;
+
+use cubizm_core::mods::ModPacks;
+use cubizm_core::AppState;
+
+mod inventory;
+mod item;
+mod loot;
+mod recipe;
+mod registry;
+mod stack;
+
+/// Systems applying [AddItems] and [RemoveItems], run those sending them before and those
+/// reading [InventoryChanged] after
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InventorySet;
+
+/// Sent each time the [RecipeBook] or the [LootTables] are rebuilt, as their files first load
+/// and when one changes, so content can be rebalanced without restarting
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataReloaded {
+    Recipes,
+    LootTables,
+}
+
+fn apply_inventory_events(
+    mut adds: EventReader<AddItems>,
+    mut removes: EventReader<RemoveItems>,
+    registry: Option<Res<ItemRegistry>>,
+    items: Option<Res<Assets<Item>>>,
+    mut inventories: Query<&mut Inventory>,
+    mut full: EventWriter<InventoryFull>,
+) {
+    for event in adds.read() {
+        let Ok(mut inventory) = inventories.get_mut(event.inventory) else {
+            warn!("Entity {:?} has no inventory to add to", event.inventory);
+            continue;
+        };
+        let limit = registry
+            .as_ref()
+            .zip(items.as_ref())
+            .and_then(|(registry, items)| registry.max_stack_size(&event.stack.item, items))
+            .unwrap_or(inventory.stack_limit());
+        if let Some(stack) = inventory.add_limited(event.stack.clone(), limit) {
+            full.send(InventoryFull {
+                inventory: event.inventory,
+                stack,
+            });
+        }
+    }
+    for event in removes.read() {
+        if let Ok(mut inventory) = inventories.get_mut(event.inventory) {
+            inventory.remove(&event.item, event.count);
+        }
+    }
+}
+
+fn send_inventory_changes(
+    inventories: Query<Entity, Changed<Inventory>>,
+    mut changed: EventWriter<InventoryChanged>,
+) {
+    changed.send_batch(
+        inventories
+            .iter()
+            .map(|inventory| InventoryChanged { inventory }),
+    );
+}
+
+/// Applies [AddItems] and [RemoveItems] to [Inventory] components and reports their changes
+/// with [InventoryChanged]. Stacks of items from the [ItemRegistry] hold at most their
+/// [Item::max_stack_size] if [ItemPlugin] is added
+pub struct InventoryPlugin;
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AddItems>()
+            .add_event::<RemoveItems>()
+            .add_event::<InventoryFull>()
+            .add_event::<InventoryChanged>()
+            .add_systems(
+                Update,
+                (apply_inventory_events, send_inventory_changes)
+                    .chain()
+                    .in_set(InventorySet),
+            );
+    }
+}
+
+/// Where [ItemPlugin] looks for item assets, as asset paths. Mods use the same paths within
+/// their own folder
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct ItemPluginSettings {
+    /// Folder of `.item` files, all of which are loaded
+    pub info_path: String,
+    /// Folder `.item` files load their icons from when they only give a file name
+    pub textures_path: String,
+    /// Folder of `.recipe` files, all of which are loaded
+    pub recipes_path: String,
+    /// Folder of `.loot` files, all of which are loaded
+    pub loot_path: String,
+}
+
+impl Default for ItemPluginSettings {
+    fn default() -> Self {
+        Self {
+            info_path: "items/info".to_string(),
+            textures_path: "items/textures".to_string(),
+            recipes_path: "recipes".to_string(),
+            loot_path: "loot".to_string(),
+        }
+    }
+}
+
+fn load_items(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<ItemPluginSettings>,
+    mods: Option<Res<ModPacks>>,
+) {
+    let load_folders = |path: &String| -> Vec<Handle<LoadedFolder>> {
+        let mod_folders = mods
+            .iter()
+            .flat_map(|mods| mods.iter())
+            .filter(|pack| pack.has_dir(path))
+            .map(|pack| asset_server.load_folder(pack.asset_path(path)));
+        std::iter::once(asset_server.load_folder(path.clone()))
+            .chain(mod_folders)
+            .collect()
+    };
+    commands.insert_resource(ItemInfoFolder(load_folders(&settings.info_path)));
+    commands.insert_resource(RecipeFolder(load_folders(&settings.recipes_path)));
+    commands.insert_resource(LootFolder(load_folders(&settings.loot_path)));
+}
+
+/// Loads every `.item` file into the [ItemRegistry], like
+/// [BlockPlugin](cubizm_block::BlockPlugin) does for blocks, every `.recipe` file into the
+/// [RecipeBook] and every `.loot` file into the [LootTables], rebuilding them as the files
+/// change
+#[derive(Default)]
+pub struct ItemPlugin {
+    pub settings: ItemPluginSettings,
+    /// Loads items without their icons, for running without a renderer
+    pub headless: bool,
+}
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .init_asset::<Item>()
+            .register_asset_loader(ItemLoader::new(&self.settings.textures_path, self.headless))
+            .init_asset::<Recipe>()
+            .init_asset_loader::<RecipeLoader>()
+            .init_asset::<LootTable>()
+            .init_asset_loader::<LootLoader>()
+            .add_event::<DataReloaded>()
+            .add_systems(OnEnter(AppState::Setup), load_items)
+            .add_systems(
+                Update,
+                (
+                    build_item_registry.run_if(resource_exists::<ItemInfoFolder>),
+                    build_recipe_book.run_if(resource_exists::<RecipeFolder>),
+                    build_loot_tables.run_if(resource_exists::<LootFolder>),
+                )
+                    .before(InventorySet),
+            );
+    }
+}
@@ -0,0 +1,271 @@
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{qualified, DataReloaded, ItemStack};
+
+/// The items a block drops when it is broken instead of itself, loaded from `.loot` files
+#[derive(Asset, TypePath, Debug, Clone, PartialEq)]
+pub struct LootTable {
+    /// Registry name of the block
+    pub block: String,
+    pub drops: Vec<LootDrop>,
+}
+
+/// An item a [LootTable] may drop, rolled on its own
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LootDrop {
+    /// Registry name of the item, names without a namespace are base items
+    pub item: String,
+    /// Fewest and most items dropped, both included
+    #[serde(default = "LootDrop::one")]
+    pub count: (u32, u32),
+    /// Chance from 0 to 1 that the item drops at all
+    #[serde(default = "LootDrop::always")]
+    pub chance: f32,
+}
+
+impl LootDrop {
+    fn one() -> (u32, u32) {
+        (1, 1)
+    }
+
+    fn always() -> f32 {
+        1.
+    }
+}
+
+/// [LootTable] as written in `.loot` files. Names without a namespace are base blocks and
+/// items
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedLootTable {
+    pub block: String,
+    pub drops: Vec<LootDrop>,
+}
+
+#[derive(Debug, Error)]
+pub enum LootLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error("{0} drops between {1} and {2} items, the fewest must come first")]
+    InvalidCount(String, u32, u32),
+    #[error("{0} drops with chance {1}, which is not between 0 and 1")]
+    InvalidChance(String, f32),
+}
+
+impl TryFrom<SerializedLootTable> for LootTable {
+    type Error = LootLoaderError;
+
+    fn try_from(value: SerializedLootTable) -> Result<Self, Self::Error> {
+        let drops = value
+            .drops
+            .into_iter()
+            .map(|drop| {
+                let (min, max) = drop.count;
+                if min > max {
+                    return Err(LootLoaderError::InvalidCount(drop.item, min, max));
+                }
+                if !(0. ..=1.).contains(&drop.chance) {
+                    return Err(LootLoaderError::InvalidChance(drop.item, drop.chance));
+                }
+                Ok(LootDrop {
+                    item: qualified(drop.item),
+                    ..drop
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            block: qualified(value.block),
+            drops,
+        })
+    }
+}
+
+impl LootTable {
+    /// The stacks one break drops, `random` giving values from 0 to 1, excluding 1
+    pub fn roll(&self, mut random: impl FnMut() -> f32) -> Vec<ItemStack> {
+        self.drops
+            .iter()
+            .filter_map(|drop| {
+                if random() >= drop.chance {
+                    return None;
+                }
+                let (min, max) = drop.count;
+                let extra = (random() * (max - min + 1) as f32) as u32;
+                let count = min + extra.min(max - min);
+                (count > 0).then(|| ItemStack::new(drop.item.clone(), count))
+            })
+            .collect()
+    }
+}
+
+/// Folders of `.loot` files, read when the [LootTables] are rebuilt
+#[derive(Resource, Debug)]
+pub(crate) struct LootFolder(pub(crate) Vec<Handle<LoadedFolder>>);
+
+/// Every loaded [LootTable] by the registry name of its block. Blocks without one drop
+/// themselves
+#[derive(Resource, Debug, Default)]
+pub struct LootTables {
+    tables: HashMap<String, Handle<LootTable>>,
+}
+
+impl LootTables {
+    pub fn get(&self, block: &str) -> Option<&Handle<LootTable>> {
+        self.tables.get(block)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Handle<LootTable>)> {
+        self.tables
+            .iter()
+            .map(|(block, handle)| (block.as_str(), handle))
+    }
+}
+
+#[derive(Default)]
+pub struct LootLoader;
+
+impl AssetLoader for LootLoader {
+    type Asset = LootTable;
+    type Settings = ();
+    type Error = LootLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let ron: SerializedLootTable = ron::de::from_bytes(&bytes)?;
+            ron.try_into()
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["loot"]
+    }
+}
+
+/// Rebuilds the [LootTables] once every `.loot` file loaded, and again each time one changes
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_loot_tables(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<LootTable>>,
+    mut folder_events: EventReader<AssetEvent<LoadedFolder>>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    loot_folder: Res<LootFolder>,
+    tables: Res<Assets<LootTable>>,
+    asset_server: Res<AssetServer>,
+    mut reloaded: EventWriter<DataReloaded>,
+) {
+    let changed = events.read().count() + folder_events.read().count() > 0;
+    if !changed
+        || !loot_folder
+            .0
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+    {
+        return;
+    }
+    let mut loot_tables = LootTables::default();
+    for handle in loot_folder
+        .0
+        .iter()
+        .filter_map(|handle| loaded_folders.get(handle))
+        .flat_map(|folder| folder.handles.iter())
+    {
+        let handle = handle.clone().typed_unchecked::<LootTable>();
+        let Some(table) = tables.get(&handle) else {
+            continue;
+        };
+        if let Some(existing) = loot_tables.tables.get(&table.block) {
+            warn!(
+                "{:?} and {:?} are both loot tables of {}, keeping the first",
+                existing.path(),
+                handle.path(),
+                table.block
+            );
+            continue;
+        }
+        loot_tables.tables.insert(table.block.clone(), handle);
+    }
+    commands.insert_resource(loot_tables);
+    reloaded.send(DataReloaded::LootTables);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(drops: Vec<LootDrop>) -> LootTable {
+        SerializedLootTable {
+            block: "grass".to_string(),
+            drops,
+        }
+        .try_into()
+        .unwrap()
+    }
+
+    fn drop(item: &str, count: (u32, u32), chance: f32) -> LootDrop {
+        LootDrop {
+            item: item.to_string(),
+            count,
+            chance,
+        }
+    }
+
+    #[test]
+    fn qualifies_names() {
+        let table = table(vec![
+            drop("mod:seeds", (1, 1), 1.),
+            drop("dirt", (1, 1), 1.),
+        ]);
+        assert_eq!(table.block, "cubizm:grass");
+        assert_eq!(table.drops[0].item, "mod:seeds");
+        assert_eq!(table.drops[1].item, "cubizm:dirt");
+    }
+
+    #[test]
+    fn rejects_invalid_drops() {
+        let invalid = |drop| {
+            LootTable::try_from(SerializedLootTable {
+                block: "grass".to_string(),
+                drops: vec![drop],
+            })
+        };
+        assert!(matches!(
+            invalid(drop("dirt", (2, 1), 1.)),
+            Err(LootLoaderError::InvalidCount(..))
+        ));
+        assert!(matches!(
+            invalid(drop("dirt", (1, 1), 1.5)),
+            Err(LootLoaderError::InvalidChance(..))
+        ));
+    }
+
+    #[test]
+    fn rolls_counts_between_the_bounds() {
+        let table = table(vec![drop("dirt", (1, 3), 1.)]);
+        let count = |random: f32| table.roll(|| random)[0].count;
+        assert_eq!(count(0.), 1);
+        assert_eq!(count(0.5), 2);
+        assert_eq!(count(0.999), 3);
+    }
+
+    #[test]
+    fn rolls_the_chance_of_each_drop() {
+        let table = table(vec![drop("dirt", (1, 1), 0.25), drop("stick", (0, 1), 1.)]);
+        assert_eq!(table.roll(|| 0.1), vec![ItemStack::new("cubizm:dirt", 1)]);
+        // The stick rolls no items at all
+        assert!(table.roll(|| 0.3).is_empty());
+    }
+}
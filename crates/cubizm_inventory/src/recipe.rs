@@ -0,0 +1,401 @@
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    ecs::system::SystemParam,
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use cubizm_block::{block_key, BASE_NAMESPACE};
+
+use crate::{DataReloaded, Inventory, ItemStack};
+
+/// Turns ingredients laid out in a crafting grid into an item, loaded from `.recipe` files
+#[derive(Asset, TypePath, Debug, Clone, PartialEq)]
+pub struct Recipe {
+    pub shape: RecipeShape,
+    pub output: ItemStack,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecipeShape {
+    /// Ingredients in a fixed layout, which can sit anywhere in a large enough grid. Empty
+    /// rows and columns at the edges of the pattern are trimmed off when it is loaded
+    Shaped {
+        width: usize,
+        height: usize,
+        /// Registry names of the ingredients row by row, `None` for cells left empty
+        cells: Vec<Option<String>>,
+    },
+    /// Ingredients in any layout, one item each
+    Shapeless(Vec<String>),
+}
+
+/// [Recipe] as written in `.recipe` files. Item names without a namespace are base items
+#[derive(Debug, Deserialize, Serialize)]
+pub enum SerializedRecipe {
+    Shaped {
+        /// One string per row, each character a key into `key`, spaces for empty cells
+        pattern: Vec<String>,
+        key: HashMap<char, String>,
+        output: ItemStack,
+    },
+    Shapeless {
+        ingredients: Vec<String>,
+        output: ItemStack,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum RecipeLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error("Pattern uses {0:?}, which is not in the key")]
+    UnknownKey(char),
+    #[error("Recipe has no ingredients")]
+    Empty,
+}
+
+/// `name` as a registry name, taking names without a namespace as base items
+pub(crate) fn qualified(name: String) -> String {
+    match name.contains(':') {
+        true => name,
+        false => block_key(BASE_NAMESPACE, &name),
+    }
+}
+
+impl TryFrom<SerializedRecipe> for Recipe {
+    type Error = RecipeLoaderError;
+
+    fn try_from(value: SerializedRecipe) -> Result<Self, Self::Error> {
+        let (shape, output) = match value {
+            SerializedRecipe::Shaped {
+                pattern,
+                key,
+                output,
+            } => {
+                // Ingredients by position, the grid is matched against them from their corner
+                let mut filled = Vec::new();
+                for (y, row) in pattern.iter().enumerate() {
+                    for (x, symbol) in row.chars().enumerate().filter(|(_, c)| *c != ' ') {
+                        let item = key
+                            .get(&symbol)
+                            .ok_or(RecipeLoaderError::UnknownKey(symbol))?;
+                        filled.push((x, y, qualified(item.clone())));
+                    }
+                }
+                let left = filled.iter().map(|(x, _, _)| *x).min();
+                let left = left.ok_or(RecipeLoaderError::Empty)?;
+                let top = filled.iter().map(|(_, y, _)| *y).min().unwrap_or_default();
+                let width = filled.iter().map(|(x, _, _)| x - left + 1).max();
+                let height = filled.iter().map(|(_, y, _)| y - top + 1).max();
+                let (width, height) = (width.unwrap_or(1), height.unwrap_or(1));
+                let mut cells = vec![None; width * height];
+                for (x, y, item) in filled {
+                    cells[(y - top) * width + x - left] = Some(item);
+                }
+                let shape = RecipeShape::Shaped {
+                    width,
+                    height,
+                    cells,
+                };
+                (shape, output)
+            }
+            SerializedRecipe::Shapeless {
+                ingredients,
+                output,
+            } => {
+                if ingredients.is_empty() {
+                    return Err(RecipeLoaderError::Empty);
+                }
+                let ingredients = ingredients.into_iter().map(qualified).collect();
+                (RecipeShape::Shapeless(ingredients), output)
+            }
+        };
+        Ok(Recipe {
+            shape,
+            output: ItemStack::new(qualified(output.item), output.count),
+        })
+    }
+}
+
+impl Recipe {
+    /// Whether the items in `grid`, an inventory `width` slots wide, are laid out like the
+    /// recipe's ingredients. The amount of each stack does not matter
+    pub fn matches(&self, grid: &Inventory, width: usize) -> bool {
+        let width = width.max(1);
+        let filled: Vec<(usize, usize, &str)> = grid
+            .slots()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let stack = slot.as_ref()?;
+                Some((index % width, index / width, stack.item.as_str()))
+            })
+            .collect();
+        match &self.shape {
+            RecipeShape::Shapeless(ingredients) => {
+                let mut remaining: Vec<&str> = ingredients.iter().map(String::as_str).collect();
+                filled.len() == remaining.len()
+                    && filled.iter().all(|(_, _, item)| {
+                        let Some(index) =
+                            remaining.iter().position(|ingredient| ingredient == item)
+                        else {
+                            return false;
+                        };
+                        remaining.swap_remove(index);
+                        true
+                    })
+            }
+            RecipeShape::Shaped {
+                width: recipe_width,
+                height: recipe_height,
+                cells,
+            } => {
+                let Some(left) = filled.iter().map(|(x, _, _)| *x).min() else {
+                    return false;
+                };
+                let top = filled.iter().map(|(_, y, _)| *y).min().unwrap_or_default();
+                let right = filled.iter().map(|(x, _, _)| *x).max().unwrap_or_default();
+                let bottom = filled.iter().map(|(_, y, _)| *y).max().unwrap_or_default();
+                // The pattern has no empty edges, so its corner lines up with the one of the
+                // filled cells of the grid
+                let used = cells.iter().filter(|cell| cell.is_some()).count();
+                used == filled.len()
+                    && right - left < *recipe_width
+                    && bottom - top < *recipe_height
+                    && (0..*recipe_height).all(|y| {
+                        (0..*recipe_width).all(|x| {
+                            let (grid_x, grid_y) = (left + x, top + y);
+                            let item = match grid_x < width {
+                                true => grid
+                                    .get(grid_y * width + grid_x)
+                                    .map(|stack| stack.item.as_str()),
+                                false => None,
+                            };
+                            cells[y * recipe_width + x].as_deref() == item
+                        })
+                    })
+            }
+        }
+    }
+}
+
+/// Folders of `.recipe` files, read when the [RecipeBook] is rebuilt
+#[derive(Resource, Debug)]
+pub(crate) struct RecipeFolder(#[allow(dead_code)] pub(crate) Vec<Handle<LoadedFolder>>);
+
+/// Every loaded [Recipe] in the order [CraftingSystem] tries them: base recipes first, then
+/// those of each mod, each folder sorted by path
+#[derive(Resource, Debug, Default)]
+pub struct RecipeBook {
+    recipes: Vec<Handle<Recipe>>,
+}
+
+impl RecipeBook {
+    pub fn iter(&self) -> impl Iterator<Item = &Handle<Recipe>> {
+        self.recipes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.recipes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+}
+
+/// Rebuilds the [RecipeBook] once every `.recipe` file loaded, and again each time one changes
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_recipe_book(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Recipe>>,
+    mut folder_events: EventReader<AssetEvent<LoadedFolder>>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    recipe_folder: Res<RecipeFolder>,
+    recipes: Res<Assets<Recipe>>,
+    asset_server: Res<AssetServer>,
+    mut reloaded: EventWriter<DataReloaded>,
+) {
+    let changed = events.read().count() + folder_events.read().count() > 0;
+    if !changed
+        || !recipe_folder
+            .0
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+    {
+        return;
+    }
+    let mut book = RecipeBook::default();
+    for folder in recipe_folder
+        .0
+        .iter()
+        .filter_map(|handle| loaded_folders.get(handle))
+    {
+        let mut handles: Vec<Handle<Recipe>> = folder
+            .handles
+            .iter()
+            .map(|handle| handle.clone().typed_unchecked::<Recipe>())
+            .filter(|handle| recipes.contains(handle))
+            .collect();
+        handles.sort_by_key(|handle| handle.path().map(|path| path.to_string()));
+        book.recipes.extend(handles);
+    }
+    commands.insert_resource(book);
+    reloaded.send(DataReloaded::Recipes);
+}
+
+#[derive(Default)]
+pub struct RecipeLoader;
+
+impl AssetLoader for RecipeLoader {
+    type Asset = Recipe;
+    type Settings = ();
+    type Error = RecipeLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let ron: SerializedRecipe = ron::de::from_bytes(&bytes)?;
+            ron.try_into()
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["recipe"]
+    }
+}
+
+/// Matches crafting grids against the recipes of the [RecipeBook]
+#[derive(SystemParam)]
+pub struct CraftingSystem<'w> {
+    /// Missing until every recipe loaded
+    book: Option<Res<'w, RecipeBook>>,
+    recipes: Res<'w, Assets<Recipe>>,
+}
+
+impl CraftingSystem<'_> {
+    /// The first recipe `grid`, an inventory `width` slots wide, is laid out for
+    pub fn find(&self, grid: &Inventory, width: usize) -> Option<&Recipe> {
+        self.book
+            .iter()
+            .flat_map(|book| book.iter())
+            .filter_map(|handle| self.recipes.get(handle))
+            .find(|recipe| recipe.matches(grid, width))
+    }
+
+    /// Crafts the recipe `grid` is laid out for, using up one item of every filled slot.
+    /// Returns the crafted items, `None` if no recipe matches and the grid is left as is
+    pub fn craft(&self, grid: &mut Inventory, width: usize) -> Option<ItemStack> {
+        let output = self.find(grid, width)?.output.clone();
+        for slot in 0..grid.len() {
+            // The slot is in range, taking from it can't fail
+            let _ = grid.take(slot, 1);
+        }
+        Some(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shaped(pattern: &[&str]) -> Recipe {
+        SerializedRecipe::Shaped {
+            pattern: pattern.iter().map(ToString::to_string).collect(),
+            key: HashMap::from([('A', "stick".to_string()), ('B', "stone".to_string())]),
+            output: ItemStack::new("torch", 1),
+        }
+        .try_into()
+        .unwrap()
+    }
+
+    /// A 3 by 3 grid with `items` at `(x, y)`
+    fn grid(items: &[(usize, usize, &str)]) -> Inventory {
+        let mut grid = Inventory::new(9);
+        for (x, y, item) in items {
+            let item = block_key(BASE_NAMESPACE, item);
+            grid.set(y * 3 + x, Some(ItemStack::new(item, 1))).unwrap();
+        }
+        grid
+    }
+
+    #[test]
+    fn trims_empty_edges_of_the_pattern() {
+        let recipe = shaped(&["   ", " A ", " A "]);
+        let RecipeShape::Shaped {
+            width,
+            height,
+            cells,
+        } = &recipe.shape
+        else {
+            panic!("not shaped");
+        };
+        assert_eq!((*width, *height), (1, 2));
+        assert!(cells.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn matches_trimmed_pattern_anywhere_in_the_grid() {
+        let recipe = shaped(&[" A", " A"]);
+        assert!(recipe.matches(&grid(&[(0, 0, "stick"), (0, 1, "stick")]), 3));
+        assert!(recipe.matches(&grid(&[(2, 1, "stick"), (2, 2, "stick")]), 3));
+        assert!(!recipe.matches(&grid(&[(0, 0, "stick"), (1, 1, "stick")]), 3));
+    }
+
+    #[test]
+    fn matches_offset_pattern_with_gaps() {
+        let recipe = shaped(&["A ", " B"]);
+        assert!(recipe.matches(&grid(&[(1, 1, "stick"), (2, 2, "stone")]), 3));
+        assert!(!recipe.matches(&grid(&[(1, 1, "stone"), (2, 2, "stick")]), 3));
+        assert!(!recipe.matches(
+            &grid(&[(1, 1, "stick"), (2, 2, "stone"), (0, 0, "stone")]),
+            3
+        ));
+    }
+
+    #[test]
+    fn pattern_does_not_wrap_around_grid_rows() {
+        let recipe = shaped(&["AA"]);
+        assert!(recipe.matches(&grid(&[(1, 0, "stick"), (2, 0, "stick")]), 3));
+        assert!(!recipe.matches(&grid(&[(2, 0, "stick"), (0, 1, "stick")]), 3));
+    }
+
+    #[test]
+    fn shapeless_matches_any_layout() {
+        let recipe: Recipe = SerializedRecipe::Shapeless {
+            ingredients: vec!["stick".to_string(), "stone".to_string()],
+            output: ItemStack::new("torch", 1),
+        }
+        .try_into()
+        .unwrap();
+        assert!(recipe.matches(&grid(&[(2, 2, "stick"), (0, 0, "stone")]), 3));
+        assert!(!recipe.matches(&grid(&[(2, 2, "stick")]), 3));
+    }
+
+    #[test]
+    fn rejects_empty_and_unknown_patterns() {
+        let load = |pattern: &str| {
+            Recipe::try_from(SerializedRecipe::Shaped {
+                pattern: vec![pattern.to_string()],
+                key: HashMap::from([('A', "stick".to_string())]),
+                output: ItemStack::new("torch", 1),
+            })
+        };
+        assert!(matches!(load("   "), Err(RecipeLoaderError::Empty)));
+        assert!(matches!(
+            load("AC"),
+            Err(RecipeLoaderError::UnknownKey('C'))
+        ));
+    }
+}
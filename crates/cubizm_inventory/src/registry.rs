@@ -0,0 +1,124 @@
+use bevy::{
+    asset::{io::AssetSourceId, LoadedFolder},
+    prelude::*,
+    utils::HashMap,
+};
+
+use cubizm_block::{block_key, BASE_NAMESPACE};
+use cubizm_core::mods::ModPacks;
+
+use crate::Item;
+
+/// The base `items/info` folder followed by the one of every mod that ships items
+#[derive(Resource, Debug, Default)]
+pub(crate) struct ItemInfoFolder(pub(crate) Vec<Handle<LoadedFolder>>);
+
+/// Every loaded [Item] by its namespaced name, named like blocks, see
+/// [block_key](cubizm_block::block_key)
+#[derive(Resource, Debug, Default)]
+pub struct ItemRegistry {
+    items: HashMap<String, Handle<Item>>,
+}
+
+impl ItemRegistry {
+    pub fn get(&self, name: &str) -> Option<&Handle<Item>> {
+        self.items.get(name)
+    }
+
+    /// Like [get](ItemRegistry::get), with names without a namespace taken as base items
+    pub fn find(&self, name: &str) -> Option<&Handle<Item>> {
+        match name.contains(':') {
+            true => self.get(name),
+            false => self.get(&format!("{BASE_NAMESPACE}:{name}")),
+        }
+    }
+
+    /// The name `item` is registered as
+    pub fn name(&self, item: &Handle<Item>) -> Option<&str> {
+        self.iter()
+            .find(|(_, handle)| *handle == item)
+            .map(|(name, _)| name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.items.contains_key(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Handle<Item>)> {
+        self.items
+            .iter()
+            .map(|(name, handle)| (name.as_str(), handle))
+    }
+
+    /// Most of the item called `name` a stack holds, `None` for items that are not registered,
+    /// such as blocks without an item of their own
+    pub fn max_stack_size(&self, name: &str, items: &Assets<Item>) -> Option<u32> {
+        Some(items.get(self.get(name)?)?.max_stack_size)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_item_registry(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Item>>,
+    mut folder_events: EventReader<AssetEvent<LoadedFolder>>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    item_info_folder: Res<ItemInfoFolder>,
+    items: Res<Assets<Item>>,
+    asset_server: Res<AssetServer>,
+    mods: Option<Res<ModPacks>>,
+) {
+    // Rebuilt as items load, and when they are modified for hot reloading
+    let changed = events.read().count() + folder_events.read().count() > 0;
+    if !changed
+        || !item_info_folder
+            .0
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle))
+    {
+        return;
+    }
+    let mut registry = ItemRegistry::default();
+    let mut overrides = Vec::new();
+    for handle in item_info_folder
+        .0
+        .iter()
+        .filter_map(|handle| loaded_folders.get(handle))
+        .flat_map(|folder| folder.handles.iter())
+    {
+        let handle = handle.clone().typed_unchecked::<Item>();
+        let Some(item) = items.get(&handle) else {
+            continue;
+        };
+        let namespace = match handle.path().map(|path| path.source()) {
+            Some(AssetSourceId::Name(name)) => name.to_string(),
+            _ => BASE_NAMESPACE.to_string(),
+        };
+        if let Some(pack) = mods.as_ref().and_then(|mods| mods.get(&namespace)) {
+            overrides.extend(
+                pack.manifest
+                    .overrides
+                    .iter()
+                    .map(|overridden| (block_key(overridden, &item.name), handle.clone())),
+            );
+        }
+        let key = block_key(&namespace, &item.name);
+        if let Some(existing) = registry.items.get(&key) {
+            warn!(
+                "{:?} and {:?} are both registered as {key}, keeping the first",
+                existing.path(),
+                handle.path()
+            );
+            continue;
+        }
+        registry.items.insert(key, handle);
+    }
+    // Like blocks, see [ModManifest::overrides](cubizm_core::mods::ModManifest::overrides)
+    for (key, handle) in overrides {
+        match registry.items.get_mut(&key) {
+            Some(existing) => *existing = handle,
+            None => warn!("{:?} overrides {key}, which does not exist", handle.path()),
+        }
+    }
+    commands.insert_resource(registry);
+}
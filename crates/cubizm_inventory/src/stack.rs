@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// Most items a stack holds unless its inventory sets another limit
+pub const MAX_STACK_SIZE: u32 = 64;
+
+/// Some number of the same item
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ItemStack {
+    /// Registry name of the item, for blocks the one in the `BlockRegistry`
+    pub item: String,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(item: impl Into<String>, count: u32) -> Self {
+        Self {
+            item: item.into(),
+            count,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Whether the items of `other` can be added to this stack
+    pub fn stacks_with(&self, other: &ItemStack) -> bool {
+        self.item == other.item
+    }
+
+    /// Takes up to `count` items off the stack
+    pub fn split(&mut self, count: u32) -> ItemStack {
+        let count = count.min(self.count);
+        self.count -= count;
+        ItemStack::new(self.item.clone(), count)
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+
+use cubizm_block::BlockRegistry;
+use cubizm_chunks::{BlockAabb, VoxelWorld};
+use cubizm_core::{split_command, CommandError, WorldCommand, MAX_FILL_VOLUME};
+
+use crate::{ClientId, NetServer, ServerMessage};
+
+/// Longest chat message in characters, longer ones are cut off
+pub const MAX_CHAT_LENGTH: usize = 256;
+
+/// A chat message received from the client `sender`, or from the server with `None`. Read by
+/// the chat UI on clients, the server runs the [WorldCommand]s among them
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub sender: Option<ClientId>,
+    pub text: String,
+}
+
+/// The server moved the player to `destination` with `/tp`
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ServerTeleport {
+    pub destination: Vec3,
+}
+
+/// Runs the `command` of the client `sender`, giving the reply to send back
+fn run_command(
+    command: WorldCommand,
+    sender: ClientId,
+    server: &mut NetServer,
+    registry: &BlockRegistry,
+    world: &mut VoxelWorld,
+) -> Result<String, CommandError> {
+    let block = |name: &str| {
+        registry
+            .find(name)
+            .cloned()
+            .ok_or_else(|| CommandError::UnknownBlock(name.to_string()))
+    };
+    match command {
+        WorldCommand::Teleport(destination) => {
+            server.send(sender, &ServerMessage::Teleport(destination));
+            server.set_player_position(sender, destination);
+            Ok(format!("Teleported to {destination}"))
+        }
+        WorldCommand::SetBlock {
+            position,
+            block: name,
+        } => match world.set_block(position, block(&name)?) {
+            Ok(()) => Ok(format!("Set {position} to {name}")),
+            Err(err) => Ok(format!("Could not set {position}: {err}")),
+        },
+        WorldCommand::Fill {
+            min,
+            max,
+            block: name,
+        } => {
+            let aabb = BlockAabb::new(min, max);
+            if aabb.volume() > MAX_FILL_VOLUME {
+                return Err(CommandError::FillTooLarge(aabb.volume()));
+            }
+            let set = world.fill(aabb, block(&name)?);
+            Ok(format!("Filled {set} blocks with {name}"))
+        }
+    }
+}
+
+/// A chat message starting with `/` parsed as a [WorldCommand], `None` for other messages
+fn parse_command(text: &str) -> Option<Result<WorldCommand, CommandError>> {
+    text.strip_prefix('/')?;
+    let (name, arguments) = split_command(text).unwrap_or_default();
+    Some(
+        WorldCommand::parse(name, &arguments)
+            .unwrap_or_else(|| Err(CommandError::Unknown(format!("/{name}")))),
+    )
+}
+
+/// Runs the [WorldCommand]s of admins and passes the other chat messages on to every client,
+/// including those the server sends itself
+pub(crate) fn run_chat_commands(
+    mut server: ResMut<NetServer>,
+    mut messages: EventReader<ChatMessage>,
+    registry: Res<BlockRegistry>,
+    mut world: VoxelWorld,
+) {
+    for ChatMessage { sender, text } in messages.read().cloned() {
+        let command = sender.and_then(|client| Some((client, parse_command(&text)?)));
+        let Some((client, command)) = command else {
+            server.broadcast(&ServerMessage::Chat { sender, text });
+            continue;
+        };
+        let reply = match server.is_admin(client) {
+            true => command.and_then(|command| {
+                run_command(command, client, &mut server, &registry, &mut world)
+            }),
+            false => Err(CommandError::NotAdmin),
+        };
+        info!("Client {client}: {text}");
+        let text = reply.unwrap_or_else(|err| err.to_string());
+        server.send(client, &ServerMessage::Chat { sender: None, text });
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use cubizm_block::definition::Block;
+use cubizm_chunks::{Chunk, Chunks, RenderDistance, VoxelWorld, WorldSaverSet};
+use cubizm_core::point_to_chunk;
+
+use crate::{
+    BlockEdit, BlockPalette, ChatMessage, ClientMessage, ClientTransport, ServerMessage,
+    ServerTeleport, MAX_CHAT_LENGTH, MAX_REQUESTED_CHUNKS,
+};
+
+/// How far the player moves before [ClientMessage::PlayerPosition] is sent again
+const POSITION_UPDATE_DISTANCE: f32 = 0.25;
+
+/// An edit shown before the server answered it
+#[derive(Debug)]
+struct PredictedEdit {
+    sequence: u32,
+    position: IVec3,
+}
+
+/// The client side of a connection, insert it to play on a server, see [NetClientPlugin]
+#[derive(Resource)]
+pub struct NetClient {
+    transport: Box<dyn ClientTransport>,
+    /// Chunks asked for, whether the server had them or not
+    requested: HashSet<IVec3>,
+    connected: bool,
+    palette: BlockPalette,
+    /// Edits waiting to be shown and sent, see [edit_block](NetClient::edit_block)
+    queued: Vec<(IVec3, Handle<Block>)>,
+    next_sequence: u32,
+    /// Shown but not yet answered by the server, oldest first
+    predicted: VecDeque<PredictedEdit>,
+    /// Block of the server at each position with predicted edits, shown once they are all
+    /// answered
+    confirmed: HashMap<IVec3, Handle<Block>>,
+    sent_position: Option<Vec3>,
+}
+
+impl NetClient {
+    pub fn new(transport: impl ClientTransport) -> Self {
+        Self {
+            transport: Box::new(transport),
+            requested: HashSet::new(),
+            connected: true,
+            palette: BlockPalette::default(),
+            queued: Vec::new(),
+            next_sequence: 0,
+            predicted: VecDeque::new(),
+            confirmed: HashMap::new(),
+            sent_position: None,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Whether edits at world `position` are waiting for the server
+    pub fn is_predicted(&self, position: IVec3) -> bool {
+        self.confirmed.contains_key(&position)
+    }
+
+    fn send(&mut self, message: &ClientMessage) {
+        if !self.connected {
+            return;
+        }
+        if let Err(err) = self.transport.send(&message.encode()) {
+            error!("Lost connection to the server: {err}");
+            self.connected = false;
+        }
+    }
+
+    /// Asks the server for the chunks at `positions` that were not asked for before, in
+    /// requests of at most [MAX_REQUESTED_CHUNKS]
+    pub fn request_chunks(&mut self, positions: impl IntoIterator<Item = IVec3>) {
+        let positions: Vec<IVec3> = positions
+            .into_iter()
+            .filter(|position| self.requested.insert(*position))
+            .collect();
+        for batch in positions.chunks(MAX_REQUESTED_CHUNKS) {
+            self.send(&ClientMessage::RequestChunks(batch.to_vec()));
+        }
+    }
+
+    /// Sends a chat message, or runs a [WorldCommand](cubizm_core::WorldCommand) on the server when
+    /// it starts with `/`. Messages come back as [ChatMessage]s
+    pub fn send_chat(&mut self, text: &str) {
+        self.send(&ClientMessage::Chat(
+            text.chars().take(MAX_CHAT_LENGTH).collect(),
+        ));
+    }
+
+    /// Replaces the block at world `position` right away and asks the server to do the same.
+    /// The block goes back to the one of the server if it rejects the edit
+    pub fn edit_block(&mut self, position: IVec3, block: Handle<Block>) {
+        self.queued.push((position, block));
+    }
+
+    /// Forgets the edit the server answered and shows the block of the server once no other
+    /// edit at its position is waiting
+    fn resolve(&mut self, sequence: u32, world: &mut VoxelWorld) {
+        let Some(index) = self
+            .predicted
+            .iter()
+            .position(|edit| edit.sequence == sequence)
+        else {
+            return;
+        };
+        let position = self.predicted.remove(index).unwrap().position;
+        if self.predicted.iter().any(|edit| edit.position == position) {
+            return;
+        }
+        if let Some(block) = self.confirmed.remove(&position) {
+            if world.get_block(position).as_ref() != Some(&block) {
+                let _ = world.set_block(position, block);
+            }
+        }
+    }
+}
+
+/// Inserts the chunks the server sent and applies its block edits
+fn receive_server_messages(
+    mut client: ResMut<NetClient>,
+    mut world: VoxelWorld,
+    asset_server: Res<AssetServer>,
+    mut chat: EventWriter<ChatMessage>,
+    mut teleports: EventWriter<ServerTeleport>,
+) {
+    if !client.connected {
+        return;
+    }
+    let messages = match client.transport.poll() {
+        Ok(messages) => messages,
+        Err(err) => {
+            error!("Lost connection to the server: {err}");
+            client.connected = false;
+            return;
+        }
+    };
+    for bytes in messages {
+        let message = match ServerMessage::decode(&bytes) {
+            Ok(message) => message,
+            Err(err) => {
+                warn!("Invalid message from the server: {err}");
+                continue;
+            }
+        };
+        match message {
+            ServerMessage::Palette(paths) => {
+                client.palette = BlockPalette::from_paths(paths, &asset_server);
+            }
+            ServerMessage::Chunk(serialized) => {
+                if world.chunks().chunks.contains_key(&serialized.position) {
+                    continue;
+                }
+                let blocks = serialized
+                    .blocks
+                    .into_iter()
+                    .map(|path| asset_server.load::<Block>(path))
+                    .collect();
+                world.insert_chunk(Chunk {
+                    blocks,
+                    position: serialized.position,
+                    states: serialized.states,
+                    tile_data: serialized.tile_data,
+                    entities: serialized.entities,
+                });
+            }
+            ServerMessage::MissingChunk(position) => {
+                debug!("Server has no chunk at {position}");
+            }
+            ServerMessage::BlockEdit(edit) => {
+                let Some(block) = client.palette.block(edit.block).cloned() else {
+                    warn!(
+                        "Server placed unknown block {} at {}",
+                        edit.block, edit.position
+                    );
+                    continue;
+                };
+                // Keep showing the predicted block until the server answered it
+                if let Some(confirmed) = client.confirmed.get_mut(&edit.position) {
+                    *confirmed = block;
+                    continue;
+                }
+                // Chunks that were not received yet arrive with the edit
+                let _ = world.set_block(edit.position, block);
+            }
+            ServerMessage::EditResult { sequence, accepted } => {
+                if !accepted {
+                    debug!("Server rejected edit {sequence}");
+                }
+                client.resolve(sequence, &mut world);
+            }
+            ServerMessage::Chat { sender, text } => {
+                chat.send(ChatMessage { sender, text });
+            }
+            ServerMessage::Teleport(destination) => {
+                teleports.send(ServerTeleport { destination });
+            }
+        }
+    }
+}
+
+/// Shows the queued edits and sends them to the server
+fn send_block_edits(mut client: ResMut<NetClient>, mut world: VoxelWorld) {
+    for (position, block) in std::mem::take(&mut client.queued) {
+        let Some(id) = client.palette.id(&block) else {
+            warn!("Block placed at {position} is not in the palette of the server");
+            continue;
+        };
+        let Some(current) = world.get_block(position) else {
+            continue;
+        };
+        if world.set_block(position, block).is_err() {
+            continue;
+        }
+        client.confirmed.entry(position).or_insert(current);
+        let sequence = client.next_sequence;
+        client.next_sequence = client.next_sequence.wrapping_add(1);
+        client
+            .predicted
+            .push_back(PredictedEdit { sequence, position });
+        client.send(&ClientMessage::BlockEdit(BlockEdit {
+            position,
+            block: id,
+            sequence,
+        }));
+    }
+}
+
+/// Tells the server where the active camera is, which it checks edits against
+fn send_player_position(
+    mut client: ResMut<NetClient>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    let Some((_, camera)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let position = camera.translation();
+    if client
+        .sent_position
+        .is_some_and(|sent| sent.distance(position) < POSITION_UPDATE_DISTANCE)
+    {
+        return;
+    }
+    client.sent_position = Some(position);
+    client.send(&ClientMessage::PlayerPosition(position));
+}
+
+/// Requests the chunks within the [RenderDistance] of the active camera that are not loaded
+/// whenever it moves to another chunk
+fn request_nearby_chunks(
+    mut client: ResMut<NetClient>,
+    mut last_chunk: Local<Option<IVec3>>,
+    chunks: Res<Chunks>,
+    render_distance: Res<RenderDistance>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    let Some((_, camera)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let camera_chunk = point_to_chunk(camera.translation());
+    if *last_chunk == Some(camera_chunk) && !render_distance.is_changed() {
+        return;
+    }
+    *last_chunk = Some(camera_chunk);
+    let radius = render_distance.0 as i32;
+    let mut positions = Vec::new();
+    for x in -radius..=radius {
+        for y in -radius..=radius {
+            for z in -radius..=radius {
+                let position = camera_chunk + IVec3::new(x, y, z);
+                if !chunks.chunks.contains_key(&position) {
+                    positions.push(position);
+                }
+            }
+        }
+    }
+    // Nearest first, so the server answers with the chunks in front of the camera first
+    positions.sort_by_key(|position| (*position - camera_chunk).abs().max_element());
+    client.request_chunks(positions);
+}
+
+/// Plays on a [NetServerPlugin](crate::NetServerPlugin) once a [NetClient] is inserted. Chunks
+/// come from the server, so [ChunksPlugin](cubizm_chunks::ChunksPlugin) should load a world
+/// without chunks of its own; local chunks are kept over the ones of the server. Block edits
+/// have to go through [NetClient::edit_block] to reach the server. Chat arrives as
+/// [ChatMessage]s and `/tp` as [ServerTeleport]s
+pub struct NetClientPlugin;
+
+impl Plugin for NetClientPlugin {
+    fn build(&self, app: &mut App) {
+        // The server saves the world, the chunks of a client are only a copy
+        app.add_event::<ChatMessage>()
+            .add_event::<ServerTeleport>()
+            .configure_sets(
+                Last,
+                WorldSaverSet.run_if(not(resource_exists::<NetClient>)),
+            )
+            .add_systems(
+                Update,
+                (
+                    receive_server_messages,
+                    send_player_position,
+                    send_block_edits,
+                    request_nearby_chunks,
+                )
+                    .chain()
+                    .run_if(resource_exists::<NetClient>.and_then(resource_exists::<Chunks>)),
+            );
+    }
+}
@@ -0,0 +1,13 @@
+pub use chat::*;
+pub use client::*;
+pub use palette::*;
+pub use protocol::*;
+pub use server::*;
+pub use transport::*;
+
+mod chat;
+mod client;
+mod palette;
+mod protocol;
+mod server;
+mod transport;
@@ -0,0 +1,61 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use cubizm_block::{definition::Block, BlockRegistry};
+
+/// Numbers the blocks of the server so [BlockEdit](crate::BlockEdit)s name them with a `u16`
+/// instead of their asset path. Built by the server from its [BlockRegistry] and sent to its
+/// clients as [ServerMessage::Palette](crate::ServerMessage::Palette)
+#[derive(Debug, Clone, Default)]
+pub struct BlockPalette {
+    blocks: Vec<Handle<Block>>,
+    ids: HashMap<AssetId<Block>, u16>,
+}
+
+impl BlockPalette {
+    fn from_blocks(blocks: Vec<Handle<Block>>) -> Self {
+        let ids = blocks
+            .iter()
+            .enumerate()
+            .map(|(id, block)| (block.id(), id as u16))
+            .collect();
+        Self { blocks, ids }
+    }
+
+    /// Every registered block, ordered by registry name
+    pub fn from_registry(registry: &BlockRegistry) -> Self {
+        let mut blocks: Vec<(&str, &Handle<Block>)> = registry.iter().collect();
+        blocks.sort_by_key(|(name, _)| *name);
+        let blocks = blocks
+            .into_iter()
+            .take(u16::MAX as usize)
+            .map(|(_, block)| block.clone())
+            .collect();
+        Self::from_blocks(blocks)
+    }
+
+    /// The palette [paths](BlockPalette::paths) of the server describe
+    pub fn from_paths(paths: Vec<String>, asset_server: &AssetServer) -> Self {
+        Self::from_blocks(
+            paths
+                .into_iter()
+                .map(|path| asset_server.load(path))
+                .collect(),
+        )
+    }
+
+    /// Asset path of every block, in palette order
+    pub fn paths(&self) -> Vec<String> {
+        self.blocks
+            .iter()
+            .map(|block| block.path().map(ToString::to_string).unwrap_or_default())
+            .collect()
+    }
+
+    pub fn id(&self, block: &Handle<Block>) -> Option<u16> {
+        self.ids.get(&block.id()).copied()
+    }
+
+    pub fn block(&self, id: u16) -> Option<&Handle<Block>> {
+        self.blocks.get(id as usize)
+    }
+}
@@ -0,0 +1,418 @@
+use bevy::prelude::*;
+use thiserror::Error;
+
+use cubizm_chunks::{BinaryChunkError, SerializedChunk};
+
+use crate::ClientId;
+
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("Message ended early")]
+    UnexpectedEof,
+    #[error("Unknown message kind {0}")]
+    UnknownMessage(u8),
+    #[error("Block path is not valid UTF-8")]
+    InvalidPath(#[from] std::string::FromUtf8Error),
+    #[error(transparent)]
+    Chunk(#[from] BinaryChunkError),
+}
+
+/// Replaces the block at world `position` with the block `block` of the [BlockPalette]
+///
+/// [BlockPalette]: crate::BlockPalette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockEdit {
+    pub position: IVec3,
+    pub block: u16,
+    /// Sent by a client, numbers its edits so it can match them with their
+    /// [ServerMessage::EditResult]. Sent by the server, the order it applied the edits in
+    pub sequence: u32,
+}
+
+/// Most chunks a [ClientMessage::RequestChunks] gets answered, the server ignores the rest
+pub const MAX_REQUESTED_CHUNKS: usize = 1024;
+
+/// Sent by [NetClient](crate::NetClient) to the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    /// Asks for the chunks at these chunk positions, at most [MAX_REQUESTED_CHUNKS] within the
+    /// [max_distance](crate::ChunkRequestRules::max_distance) of the player
+    RequestChunks(Vec<IVec3>),
+    /// Asks to apply an edit, answered with a [ServerMessage::EditResult]
+    BlockEdit(BlockEdit),
+    /// Where the player is, edits further than the
+    /// [reach](crate::BlockEditRules::reach) are rejected
+    PlayerPosition(Vec3),
+    /// A chat message, or a [WorldCommand](cubizm_core::WorldCommand) when it starts with `/`
+    Chat(String),
+}
+
+/// Sent by [NetServer](crate::NetServer) to its clients
+#[derive(Debug)]
+pub enum ServerMessage {
+    /// Asset path of every block in the [BlockPalette](crate::BlockPalette), sent on connecting
+    /// and whenever the blocks change
+    Palette(Vec<String>),
+    /// A requested chunk, encoded as a `.chunkb` file, see [SerializedChunk::to_binary]
+    Chunk(SerializedChunk),
+    /// A requested chunk the server does not have
+    MissingChunk(IVec3),
+    /// An edit the server applied, sent to every client including the one that made it
+    BlockEdit(BlockEdit),
+    /// Whether the edit of the client with `sequence` was applied, sent after its
+    /// [ServerMessage::BlockEdit]
+    EditResult { sequence: u32, accepted: bool },
+    /// A chat message of the client `sender`, or of the server itself with `None`
+    Chat {
+        sender: Option<ClientId>,
+        text: String,
+    },
+    /// Moves the player, sent by the `/tp` [WorldCommand](cubizm_core::WorldCommand)
+    Teleport(Vec3),
+}
+
+struct ByteReader<'a>(&'a [u8]);
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ProtocolError> {
+        let (bytes, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(ProtocolError::UnexpectedEof)?;
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, ProtocolError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, ProtocolError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, ProtocolError> {
+        let mut coordinate = || self.take().map(f32::from_le_bytes);
+        Ok(Vec3::new(coordinate()?, coordinate()?, coordinate()?))
+    }
+
+    fn ivec3(&mut self) -> Result<IVec3, ProtocolError> {
+        let mut coordinate = || self.take().map(i32::from_le_bytes);
+        Ok(IVec3::new(coordinate()?, coordinate()?, coordinate()?))
+    }
+
+    fn string(&mut self) -> Result<String, ProtocolError> {
+        let len = self.u32()? as usize;
+        if self.0.len() < len {
+            return Err(ProtocolError::UnexpectedEof);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+
+    fn block_edit(&mut self) -> Result<BlockEdit, ProtocolError> {
+        Ok(BlockEdit {
+            position: self.ivec3()?,
+            block: self.u16()?,
+            sequence: self.u32()?,
+        })
+    }
+}
+
+fn write_ivec3(bytes: &mut Vec<u8>, position: IVec3) {
+    for coordinate in position.to_array() {
+        bytes.extend_from_slice(&coordinate.to_le_bytes());
+    }
+}
+
+fn write_vec3(bytes: &mut Vec<u8>, position: Vec3) {
+    for coordinate in position.to_array() {
+        bytes.extend_from_slice(&coordinate.to_le_bytes());
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    bytes.extend_from_slice(&(string.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+fn write_block_edit(bytes: &mut Vec<u8>, edit: &BlockEdit) {
+    write_ivec3(bytes, edit.position);
+    bytes.extend_from_slice(&edit.block.to_le_bytes());
+    bytes.extend_from_slice(&edit.sequence.to_le_bytes());
+}
+
+/// Every message starts with a byte telling its kind, integers are little endian
+impl ClientMessage {
+    const REQUEST_CHUNKS: u8 = 0;
+    const BLOCK_EDIT: u8 = 1;
+    const PLAYER_POSITION: u8 = 2;
+    const CHAT: u8 = 3;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Self::RequestChunks(positions) => {
+                bytes.push(Self::REQUEST_CHUNKS);
+                bytes.extend_from_slice(&(positions.len() as u32).to_le_bytes());
+                for position in positions {
+                    write_ivec3(&mut bytes, *position);
+                }
+            }
+            Self::BlockEdit(edit) => {
+                bytes.push(Self::BLOCK_EDIT);
+                write_block_edit(&mut bytes, edit);
+            }
+            Self::PlayerPosition(position) => {
+                bytes.push(Self::PLAYER_POSITION);
+                write_vec3(&mut bytes, *position);
+            }
+            Self::Chat(text) => {
+                bytes.push(Self::CHAT);
+                write_string(&mut bytes, text);
+            }
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        let mut reader = ByteReader(bytes);
+        let [kind] = reader.take()?;
+        match kind {
+            Self::REQUEST_CHUNKS => {
+                let count = reader.u32()?;
+                // Each position takes 12 bytes, don't trust the count for the allocation
+                let mut positions = Vec::with_capacity((count as usize).min(reader.0.len() / 12));
+                for _ in 0..count {
+                    positions.push(reader.ivec3()?);
+                }
+                Ok(Self::RequestChunks(positions))
+            }
+            Self::BLOCK_EDIT => Ok(Self::BlockEdit(reader.block_edit()?)),
+            Self::PLAYER_POSITION => Ok(Self::PlayerPosition(reader.vec3()?)),
+            Self::CHAT => Ok(Self::Chat(reader.string()?)),
+            kind => Err(ProtocolError::UnknownMessage(kind)),
+        }
+    }
+}
+
+impl ServerMessage {
+    const PALETTE: u8 = 0;
+    const CHUNK: u8 = 1;
+    const MISSING_CHUNK: u8 = 2;
+    const BLOCK_EDIT: u8 = 3;
+    const EDIT_RESULT: u8 = 4;
+    const CHAT: u8 = 5;
+    const TELEPORT: u8 = 6;
+
+    pub fn encode(&self) -> Result<Vec<u8>, ProtocolError> {
+        let mut bytes = Vec::new();
+        match self {
+            Self::Palette(paths) => {
+                bytes.push(Self::PALETTE);
+                bytes.extend_from_slice(&(paths.len() as u32).to_le_bytes());
+                for path in paths {
+                    write_string(&mut bytes, path);
+                }
+            }
+            Self::Chunk(chunk) => {
+                bytes.push(Self::CHUNK);
+                bytes.extend(chunk.to_binary()?);
+            }
+            Self::MissingChunk(position) => {
+                bytes.push(Self::MISSING_CHUNK);
+                write_ivec3(&mut bytes, *position);
+            }
+            Self::BlockEdit(edit) => {
+                bytes.push(Self::BLOCK_EDIT);
+                write_block_edit(&mut bytes, edit);
+            }
+            Self::EditResult { sequence, accepted } => {
+                bytes.push(Self::EDIT_RESULT);
+                bytes.extend_from_slice(&sequence.to_le_bytes());
+                bytes.push(*accepted as u8);
+            }
+            Self::Chat { sender, text } => {
+                bytes.push(Self::CHAT);
+                match sender {
+                    Some(sender) => {
+                        bytes.push(1);
+                        bytes.extend_from_slice(&sender.to_le_bytes());
+                    }
+                    None => bytes.push(0),
+                }
+                write_string(&mut bytes, text);
+            }
+            Self::Teleport(destination) => {
+                bytes.push(Self::TELEPORT);
+                write_vec3(&mut bytes, *destination);
+            }
+        }
+        Ok(bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        let mut reader = ByteReader(bytes);
+        let [kind] = reader.take()?;
+        match kind {
+            Self::PALETTE => {
+                let count = reader.u32()?;
+                // Each path takes at least 4 bytes, don't trust the count for the allocation
+                let mut paths = Vec::with_capacity((count as usize).min(reader.0.len() / 4));
+                for _ in 0..count {
+                    paths.push(reader.string()?);
+                }
+                Ok(Self::Palette(paths))
+            }
+            Self::CHUNK => Ok(Self::Chunk(SerializedChunk::from_binary(reader.0)?)),
+            Self::MISSING_CHUNK => Ok(Self::MissingChunk(reader.ivec3()?)),
+            Self::BLOCK_EDIT => Ok(Self::BlockEdit(reader.block_edit()?)),
+            Self::EDIT_RESULT => {
+                let sequence = reader.u32()?;
+                let [accepted] = reader.take()?;
+                Ok(Self::EditResult {
+                    sequence,
+                    accepted: accepted != 0,
+                })
+            }
+            Self::CHAT => {
+                let sender = match reader.take()? {
+                    [0] => None,
+                    _ => Some(reader.take().map(u64::from_le_bytes)?),
+                };
+                Ok(Self::Chat {
+                    sender,
+                    text: reader.string()?,
+                })
+            }
+            Self::TELEPORT => Ok(Self::Teleport(reader.vec3()?)),
+            kind => Err(ProtocolError::UnknownMessage(kind)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_round_trip(message: ClientMessage) {
+        assert_eq!(ClientMessage::decode(&message.encode()).unwrap(), message);
+    }
+
+    fn server_round_trip(message: ServerMessage) -> ServerMessage {
+        ServerMessage::decode(&message.encode().unwrap()).unwrap()
+    }
+
+    fn edit() -> BlockEdit {
+        BlockEdit {
+            position: IVec3::new(-17, 3, 40),
+            block: 513,
+            sequence: 9,
+        }
+    }
+
+    #[test]
+    fn client_messages_round_trip() {
+        client_round_trip(ClientMessage::RequestChunks(vec![
+            IVec3::ZERO,
+            IVec3::new(-1, 2, -3),
+        ]));
+        client_round_trip(ClientMessage::RequestChunks(Vec::new()));
+        client_round_trip(ClientMessage::BlockEdit(edit()));
+        client_round_trip(ClientMessage::PlayerPosition(Vec3::new(0.5, -64., 1e6)));
+        client_round_trip(ClientMessage::Chat("/tp 1 2 3 ✓".into()));
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        let paths = vec!["blocks/stone.block".to_string(), String::new()];
+        assert!(matches!(
+            server_round_trip(ServerMessage::Palette(paths.clone())),
+            ServerMessage::Palette(read) if read == paths
+        ));
+        assert!(matches!(
+            server_round_trip(ServerMessage::MissingChunk(IVec3::NEG_ONE)),
+            ServerMessage::MissingChunk(IVec3::NEG_ONE)
+        ));
+        assert!(matches!(
+            server_round_trip(ServerMessage::BlockEdit(edit())),
+            ServerMessage::BlockEdit(read) if read == edit()
+        ));
+        assert!(matches!(
+            server_round_trip(ServerMessage::EditResult {
+                sequence: 7,
+                accepted: true
+            }),
+            ServerMessage::EditResult {
+                sequence: 7,
+                accepted: true
+            }
+        ));
+        for sender in [None, Some(u64::MAX)] {
+            assert!(matches!(
+                server_round_trip(ServerMessage::Chat {
+                    sender,
+                    text: "hello".into()
+                }),
+                ServerMessage::Chat { sender: read, text } if read == sender && text == "hello"
+            ));
+        }
+        assert!(matches!(
+            server_round_trip(ServerMessage::Teleport(Vec3::new(1., 2., 3.))),
+            ServerMessage::Teleport(destination) if destination == Vec3::new(1., 2., 3.)
+        ));
+    }
+
+    #[test]
+    fn chunks_round_trip() {
+        let mut chunk = SerializedChunk {
+            position: IVec3::new(2, -1, 0),
+            ..default()
+        };
+        chunk.blocks[5] = "blocks/stone.block".into();
+        let ServerMessage::Chunk(read) = server_round_trip(ServerMessage::Chunk(chunk.clone()))
+        else {
+            panic!("expected a chunk");
+        };
+        assert_eq!(read.position, chunk.position);
+        assert_eq!(read.blocks, chunk.blocks);
+    }
+
+    #[test]
+    fn rejects_unknown_and_truncated_messages() {
+        assert!(matches!(
+            ClientMessage::decode(&[200]),
+            Err(ProtocolError::UnknownMessage(200))
+        ));
+        assert!(matches!(
+            ServerMessage::decode(&[]),
+            Err(ProtocolError::UnexpectedEof)
+        ));
+        let bytes = ClientMessage::BlockEdit(edit()).encode();
+        assert!(matches!(
+            ClientMessage::decode(&bytes[..bytes.len() - 1]),
+            Err(ProtocolError::UnexpectedEof)
+        ));
+        let bytes = ClientMessage::Chat("hello".into()).encode();
+        assert!(matches!(
+            ClientMessage::decode(&bytes[..bytes.len() - 2]),
+            Err(ProtocolError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn huge_counts_fail_without_allocating_them() {
+        let mut bytes = vec![ClientMessage::REQUEST_CHUNKS];
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            ClientMessage::decode(&bytes),
+            Err(ProtocolError::UnexpectedEof)
+        ));
+        bytes[0] = ServerMessage::PALETTE;
+        assert!(matches!(
+            ServerMessage::decode(&bytes),
+            Err(ProtocolError::UnexpectedEof)
+        ));
+    }
+}
@@ -0,0 +1,401 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use cubizm_block::{definition::Block, BlockRegistry};
+use cubizm_chunks::{BlockChanged, Chunk, Chunks};
+use cubizm_core::point_to_chunk;
+
+use crate::chat::run_chat_commands;
+use crate::{
+    BlockEdit, BlockPalette, ChatMessage, ClientId, ClientMessage, ServerMessage, ServerTransport,
+    ServerTransportEvent, MAX_CHAT_LENGTH, MAX_REQUESTED_CHUNKS,
+};
+
+/// What the server knows about a connected client
+#[derive(Debug, Default)]
+struct ConnectedClient {
+    /// Last accepted [ClientMessage::PlayerPosition], edits and chunk requests are rejected
+    /// until one arrived
+    position: Option<Vec3>,
+    /// When `position` was accepted
+    moved_at: Duration,
+    /// Can run [WorldCommand](cubizm_core::WorldCommand)s
+    admin: bool,
+}
+
+/// The server side of a connection, insert it to start serving the [Chunks] of this app, see
+/// [NetServerPlugin]
+#[derive(Resource)]
+pub struct NetServer {
+    transport: Box<dyn ServerTransport>,
+    clients: HashMap<ClientId, ConnectedClient>,
+    palette: BlockPalette,
+    /// Numbers the [BlockEdit]s sent to the clients
+    sequence: u32,
+    /// [ServerMessage::EditResult]s sent after the edits of the frame
+    results: Vec<(ClientId, u32, bool)>,
+    /// Whether clients are admins when they connect
+    admins_by_default: bool,
+}
+
+impl NetServer {
+    pub fn new(transport: impl ServerTransport) -> Self {
+        Self {
+            transport: Box::new(transport),
+            clients: HashMap::new(),
+            palette: BlockPalette::default(),
+            sequence: 0,
+            results: Vec::new(),
+            admins_by_default: false,
+        }
+    }
+
+    /// Makes every client an admin when it connects, e.g. for a server on a trusted network
+    pub fn with_admins_by_default(mut self, admins_by_default: bool) -> Self {
+        self.admins_by_default = admins_by_default;
+        self
+    }
+
+    pub fn is_admin(&self, client: ClientId) -> bool {
+        self.clients.get(&client).is_some_and(|client| client.admin)
+    }
+
+    /// Lets the connected `client` run [WorldCommand](cubizm_core::WorldCommand)s or not
+    pub fn set_admin(&mut self, client: ClientId, admin: bool) {
+        if let Some(client) = self.clients.get_mut(&client) {
+            client.admin = admin;
+        }
+    }
+
+    /// Where the server assumes the player of `client` is until it tells otherwise
+    pub(crate) fn set_player_position(&mut self, client: ClientId, position: Vec3) {
+        if let Some(client) = self.clients.get_mut(&client) {
+            client.position = Some(position);
+        }
+    }
+
+    pub fn clients(&self) -> impl Iterator<Item = ClientId> + '_ {
+        self.clients.keys().copied()
+    }
+
+    pub fn send(&mut self, client: ClientId, message: &ServerMessage) {
+        match message.encode() {
+            Ok(bytes) => self.transport.send(client, &bytes),
+            Err(err) => error!("Failed to encode message for client {client}: {err}"),
+        }
+    }
+
+    /// Sends `message` to every connected client
+    pub fn broadcast(&mut self, message: &ServerMessage) {
+        let bytes = match message.encode() {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error!("Failed to encode message: {err}");
+                return;
+            }
+        };
+        for client in self.clients.keys() {
+            self.transport.send(*client, &bytes);
+        }
+    }
+}
+
+/// Limits on the [BlockEdit]s clients make
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct BlockEditRules {
+    /// Furthest distance in blocks from the player to the centre of a block it edits
+    pub reach: f32,
+    /// Fastest a player moves in blocks per second. [ClientMessage::PlayerPosition]s further
+    /// from the last accepted one are ignored, so the reach is measured from where the server
+    /// last saw the player
+    pub max_speed: f32,
+}
+
+impl Default for BlockEditRules {
+    fn default() -> Self {
+        Self {
+            reach: 8.0,
+            max_speed: 40.0,
+        }
+    }
+}
+
+/// Limits on the [ClientMessage::RequestChunks] of clients, on top of answering at most
+/// [MAX_REQUESTED_CHUNKS] chunks per request
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct ChunkRequestRules {
+    /// Furthest distance in chunks along any axis from the player to a chunk it gets, further
+    /// ones are answered with [ServerMessage::MissingChunk]
+    pub max_distance: u32,
+}
+
+impl Default for ChunkRequestRules {
+    fn default() -> Self {
+        Self { max_distance: 16 }
+    }
+}
+
+/// Blocks a player may be off from [BlockEditRules::max_speed], for messages arriving in
+/// bursts
+const MOVE_TOLERANCE: f32 = 2.0;
+
+/// Checks a [ClientMessage::PlayerPosition] that arrived at `now` against the last accepted one
+fn validate_move(
+    position: Vec3,
+    now: Duration,
+    client: &ConnectedClient,
+    rules: &BlockEditRules,
+) -> Result<(), &'static str> {
+    if !position.is_finite() {
+        return Err("not a position");
+    }
+    let Some(last) = client.position else {
+        return Ok(());
+    };
+    let elapsed = now.saturating_sub(client.moved_at).as_secs_f32();
+    match last.distance(position) > rules.max_speed * elapsed + MOVE_TOLERANCE {
+        true => Err("moved too fast"),
+        false => Ok(()),
+    }
+}
+
+/// Whether the chunk at `position` is close enough to the player of `client` to send it
+fn in_request_range(position: IVec3, client: &ConnectedClient, rules: &ChunkRequestRules) -> bool {
+    client.position.is_some_and(|player| {
+        let offset = position.as_vec3() - point_to_chunk(player).as_vec3();
+        offset.abs().max_element() <= rules.max_distance as f32
+    })
+}
+
+/// Checks a [BlockEdit] of a client against the [BlockEditRules], giving the block it places or
+/// why it is rejected. The reach is measured from the position the server accepted last
+fn validate_edit<'a>(
+    edit: &BlockEdit,
+    client: &ConnectedClient,
+    rules: &BlockEditRules,
+    palette: &'a BlockPalette,
+) -> Result<&'a Handle<Block>, &'static str> {
+    let player = client.position.ok_or("player position unknown")?;
+    if player.distance(edit.position.as_vec3() + Vec3::splat(0.5)) > rules.reach {
+        return Err("out of reach");
+    }
+    palette.block(edit.block).ok_or("unknown block")
+}
+
+/// Answers the chunk requests of clients and applies their block edits to the authoritative
+/// [Chunks]
+#[allow(clippy::too_many_arguments)]
+fn receive_client_messages(
+    mut server: ResMut<NetServer>,
+    mut chunks: ResMut<Chunks>,
+    mut assets_chunks: ResMut<Assets<Chunk>>,
+    rules: Res<BlockEditRules>,
+    request_rules: Res<ChunkRequestRules>,
+    time: Res<Time>,
+    mut block_changed: EventWriter<BlockChanged>,
+    mut chat: EventWriter<ChatMessage>,
+) {
+    for event in server.transport.poll() {
+        let (client, bytes) = match event {
+            ServerTransportEvent::Connected(client) => {
+                info!("Client {client} connected");
+                let admin = server.admins_by_default;
+                server
+                    .clients
+                    .insert(client, ConnectedClient { admin, ..default() });
+                let palette = ServerMessage::Palette(server.palette.paths());
+                server.send(client, &palette);
+                continue;
+            }
+            ServerTransportEvent::Disconnected(client) => {
+                info!("Client {client} disconnected");
+                server.clients.remove(&client);
+                continue;
+            }
+            ServerTransportEvent::Message(client, bytes) => (client, bytes),
+        };
+        let message = match ClientMessage::decode(&bytes) {
+            Ok(message) => message,
+            Err(err) => {
+                warn!("Invalid message from client {client}: {err}");
+                continue;
+            }
+        };
+        match message {
+            ClientMessage::RequestChunks(positions) => {
+                let Some(connected) = server.clients.get(&client) else {
+                    continue;
+                };
+                if positions.len() > MAX_REQUESTED_CHUNKS {
+                    debug!(
+                        "Client {client} requested {} chunks, answering the first {}",
+                        positions.len(),
+                        MAX_REQUESTED_CHUNKS
+                    );
+                }
+                let positions = positions
+                    .into_iter()
+                    .take(MAX_REQUESTED_CHUNKS)
+                    .map(|position| {
+                        (
+                            position,
+                            in_request_range(position, connected, &request_rules),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                for (position, in_range) in positions {
+                    let serialized = in_range
+                        .then(|| chunks.chunks.get(&position))
+                        .flatten()
+                        .and_then(|chunk_entity| assets_chunks.get(&chunk_entity.chunk))
+                        .map(|chunk| chunk.serialize());
+                    let message = match serialized {
+                        Some(Ok(chunk)) => ServerMessage::Chunk(chunk),
+                        Some(Err(err)) => {
+                            error!("Failed to serialize chunk {position}: {err}");
+                            ServerMessage::MissingChunk(position)
+                        }
+                        None => ServerMessage::MissingChunk(position),
+                    };
+                    server.send(client, &message);
+                }
+            }
+            ClientMessage::PlayerPosition(position) => {
+                let now = time.elapsed();
+                let Some(connected) = server.clients.get_mut(&client) else {
+                    continue;
+                };
+                match validate_move(position, now, connected, &rules) {
+                    Ok(()) => {
+                        connected.position = Some(position);
+                        connected.moved_at = now;
+                    }
+                    Err(reason) => {
+                        debug!("Ignored position {position} of client {client}: {reason}");
+                    }
+                }
+            }
+            ClientMessage::Chat(text) => {
+                chat.send(ChatMessage {
+                    sender: Some(client),
+                    text: text.chars().take(MAX_CHAT_LENGTH).collect(),
+                });
+            }
+            ClientMessage::BlockEdit(edit) => {
+                let Some(connected) = server.clients.get(&client) else {
+                    continue;
+                };
+                let validated = validate_edit(&edit, connected, &rules, &server.palette)
+                    .cloned()
+                    .and_then(|block| {
+                        chunks
+                            .set_block(edit.position, block, &mut assets_chunks, &mut block_changed)
+                            .map_err(|_| "chunk not loaded")
+                    });
+                if let Err(reason) = validated {
+                    debug!(
+                        "Rejected edit {} of client {client} at {}: {reason}",
+                        edit.sequence, edit.position
+                    );
+                }
+                server
+                    .results
+                    .push((client, edit.sequence, validated.is_ok()));
+            }
+        }
+    }
+}
+
+/// Numbers the blocks of the [BlockRegistry] again and sends them to the clients
+fn update_palette(mut server: ResMut<NetServer>, registry: Res<BlockRegistry>) {
+    server.palette = BlockPalette::from_registry(&registry);
+    let palette = ServerMessage::Palette(server.palette.paths());
+    server.broadcast(&palette);
+}
+
+/// Sends every [BlockChanged] to the clients as a [BlockEdit], then the results of the edits
+/// the clients made
+fn broadcast_block_changes(mut server: ResMut<NetServer>, mut events: EventReader<BlockChanged>) {
+    for event in events.read() {
+        let Some(block) = server.palette.id(&event.new) else {
+            warn!("Block at {} is not in the palette", event.world_pos);
+            continue;
+        };
+        let edit = BlockEdit {
+            position: event.world_pos,
+            block,
+            sequence: server.sequence,
+        };
+        server.sequence = server.sequence.wrapping_add(1);
+        server.broadcast(&ServerMessage::BlockEdit(edit));
+    }
+    // After the edits, so clients already know the block the server settled on
+    for (client, sequence, accepted) in std::mem::take(&mut server.results) {
+        server.send(client, &ServerMessage::EditResult { sequence, accepted });
+    }
+}
+
+/// Serves the [Chunks] of this app to [NetClientPlugin](crate::NetClientPlugin)s once a
+/// [NetServer] is inserted. Pairs with a
+/// [headless](cubizm_chunks::ChunksPlugin::headless) [ChunksPlugin](cubizm_chunks::ChunksPlugin)
+/// for a dedicated server
+pub struct NetServerPlugin;
+
+impl Plugin for NetServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockEditRules>()
+            .init_resource::<ChunkRequestRules>()
+            .add_event::<ChatMessage>()
+            .add_systems(
+                Update,
+                (
+                    update_palette.run_if(resource_exists_and_changed::<BlockRegistry>),
+                    (receive_client_messages, run_chat_commands)
+                        .chain()
+                        .run_if(resource_exists::<Chunks>),
+                )
+                    .chain()
+                    .run_if(resource_exists::<NetServer>),
+            )
+            // Blocks are changed during Update, send them all once it is over
+            .add_systems(
+                PostUpdate,
+                broadcast_block_changes.run_if(resource_exists::<NetServer>),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_at(position: Vec3) -> ConnectedClient {
+        ConnectedClient {
+            position: Some(position),
+            moved_at: Duration::from_secs(10),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn rejects_moves_faster_than_the_max_speed() {
+        let rules = BlockEditRules::default();
+        let client = client_at(Vec3::ZERO);
+        let now = Duration::from_secs(11);
+        assert!(validate_move(Vec3::X * 30.0, now, &client, &rules).is_ok());
+        assert!(validate_move(Vec3::X * 100.0, now, &client, &rules).is_err());
+        assert!(validate_move(Vec3::NAN, now, &client, &rules).is_err());
+        assert!(validate_move(Vec3::X * 100.0, now, &default(), &rules).is_ok());
+    }
+
+    #[test]
+    fn only_answers_chunk_requests_near_the_player() {
+        let rules = ChunkRequestRules::default();
+        let client = client_at(Vec3::splat(8.0));
+        assert!(in_request_range(IVec3::new(16, 0, -16), &client, &rules));
+        assert!(!in_request_range(IVec3::new(17, 0, 0), &client, &rules));
+        assert!(!in_request_range(IVec3::MAX, &client, &rules));
+        assert!(!in_request_range(IVec3::ZERO, &default(), &rules));
+    }
+}
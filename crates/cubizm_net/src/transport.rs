@@ -0,0 +1,215 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use bevy::utils::HashMap;
+use thiserror::Error;
+
+/// Identifies a client connected to a [ServerTransport]
+pub type ClientId = u64;
+
+/// Largest message a [TcpServerTransport] or [TcpClientTransport] accepts, a chunk of
+/// different blocks in every position stays well below it
+const MAX_MESSAGE_LENGTH: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum TransportError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Connection closed")]
+    Closed,
+    #[error("Message of {0} bytes is larger than {MAX_MESSAGE_LENGTH}")]
+    MessageTooLarge(usize),
+}
+
+/// What happened on a [ServerTransport] since it was last polled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerTransportEvent {
+    Connected(ClientId),
+    Disconnected(ClientId),
+    Message(ClientId, Vec<u8>),
+}
+
+/// Moves whole messages between the server and its clients without blocking. Implement it to
+/// run [NetServerPlugin](crate::NetServerPlugin) over another transport, e.g. renet or quinn,
+/// instead of [TcpServerTransport]
+pub trait ServerTransport: Send + Sync + 'static {
+    /// Accepts clients and reads their messages
+    fn poll(&mut self) -> Vec<ServerTransportEvent>;
+
+    /// Queues `message` for `client`, dropped if it is not connected
+    fn send(&mut self, client: ClientId, message: &[u8]);
+}
+
+/// The client side of a [ServerTransport], see [TcpClientTransport]
+pub trait ClientTransport: Send + Sync + 'static {
+    /// The messages received since the last poll, an error once disconnected
+    fn poll(&mut self) -> Result<Vec<Vec<u8>>, TransportError>;
+
+    fn send(&mut self, message: &[u8]) -> Result<(), TransportError>;
+}
+
+/// A non blocking [TcpStream] sending each message after its `u32` little endian length
+struct TcpConnection {
+    stream: TcpStream,
+    received: Vec<u8>,
+    unsent: Vec<u8>,
+}
+
+impl TcpConnection {
+    fn new(stream: TcpStream) -> std::io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            received: Vec::new(),
+            unsent: Vec::new(),
+        })
+    }
+
+    fn send(&mut self, message: &[u8]) -> Result<(), TransportError> {
+        if message.len() > MAX_MESSAGE_LENGTH {
+            return Err(TransportError::MessageTooLarge(message.len()));
+        }
+        self.unsent
+            .extend_from_slice(&(message.len() as u32).to_le_bytes());
+        self.unsent.extend_from_slice(message);
+        self.flush()
+    }
+
+    /// Writes as much of the queued messages as the socket takes without blocking
+    fn flush(&mut self) -> Result<(), TransportError> {
+        while !self.unsent.is_empty() {
+            match self.stream.write(&self.unsent) {
+                Ok(0) => return Err(TransportError::Closed),
+                Ok(written) => {
+                    self.unsent.drain(..written);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads everything available and splits off the complete messages
+    fn receive(&mut self) -> Result<Vec<Vec<u8>>, TransportError> {
+        let mut buffer = [0; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(TransportError::Closed),
+                Ok(read) => self.received.extend_from_slice(&buffer[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some((length, rest)) = self.received.split_first_chunk::<4>() {
+            let length = u32::from_le_bytes(*length) as usize;
+            if length > MAX_MESSAGE_LENGTH {
+                return Err(TransportError::MessageTooLarge(length));
+            }
+            if rest.len() < length {
+                break;
+            }
+            messages.push(rest[..length].to_vec());
+            self.received.drain(..4 + length);
+        }
+        self.flush()?;
+        Ok(messages)
+    }
+}
+
+/// [ServerTransport] over TCP
+pub struct TcpServerTransport {
+    listener: TcpListener,
+    connections: HashMap<ClientId, TcpConnection>,
+    next_id: ClientId,
+}
+
+impl TcpServerTransport {
+    pub fn bind(address: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            connections: HashMap::new(),
+            next_id: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+impl ServerTransport for TcpServerTransport {
+    fn poll(&mut self) -> Vec<ServerTransportEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    let Ok(connection) = TcpConnection::new(stream) else {
+                        continue;
+                    };
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    self.connections.insert(id, connection);
+                    events.push(ServerTransportEvent::Connected(id));
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => break,
+            }
+        }
+
+        let mut closed = Vec::new();
+        for (id, connection) in self.connections.iter_mut() {
+            match connection.receive() {
+                Ok(messages) => events.extend(
+                    messages
+                        .into_iter()
+                        .map(|message| ServerTransportEvent::Message(*id, message)),
+                ),
+                Err(_) => closed.push(*id),
+            }
+        }
+        for id in closed {
+            self.connections.remove(&id);
+            events.push(ServerTransportEvent::Disconnected(id));
+        }
+        events
+    }
+
+    fn send(&mut self, client: ClientId, message: &[u8]) {
+        // A failed client is disconnected by the next poll
+        if let Some(connection) = self.connections.get_mut(&client) {
+            let _ = connection.send(message);
+        }
+    }
+}
+
+/// [ClientTransport] over TCP
+pub struct TcpClientTransport {
+    connection: TcpConnection,
+}
+
+impl TcpClientTransport {
+    /// Connects to a [TcpServerTransport], blocking until connected
+    pub fn connect(address: impl ToSocketAddrs) -> std::io::Result<Self> {
+        Ok(Self {
+            connection: TcpConnection::new(TcpStream::connect(address)?)?,
+        })
+    }
+}
+
+impl ClientTransport for TcpClientTransport {
+    fn poll(&mut self) -> Result<Vec<Vec<u8>>, TransportError> {
+        self.connection.receive()
+    }
+
+    fn send(&mut self, message: &[u8]) -> Result<(), TransportError> {
+        self.connection.send(message)
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_rapier3d::prelude::*;
+use block_mesh::{ndshape::ConstShape, Voxel, VoxelVisibility};
+
+use cubizm_block::definition::Block;
+use cubizm_chunks::{Chunk, ChunkShape, Chunks, CHUNK_SIZE};
+
+/// How the colliders attached to chunk entities are generated
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkColliderShape {
+    /// A triangle mesh of the chunk mesh, matching the rendered geometry exactly
+    #[default]
+    Trimesh,
+    /// A compound of boxes merged from the solid voxels, cheaper to collide against
+    Boxes,
+}
+
+/// Whether the block at `position` in mesh space, one past its position in the chunk, is solid
+fn is_solid(chunk: &Chunk, blocks: &Assets<Block>, position: [u32; 3]) -> bool {
+    blocks
+        .get(&chunk.blocks[ChunkShape::linearize(position.map(|v| v - 1)) as usize])
+        .is_some_and(|block| block.get_visibility() != VoxelVisibility::Empty)
+}
+
+/// Merges the solid voxels of each layer into boxes, first along x then along z.
+/// Positions are in the same space as the chunk mesh
+fn box_collider(chunk: &Chunk, blocks: &Assets<Block>) -> Option<Collider> {
+    let mut boxes = Vec::new();
+    let mut push_box = |y: u32, (x_start, x_end): (u32, u32), (z_start, z_end): (u32, u32)| {
+        let min = Vec3::new(x_start as f32, y as f32, z_start as f32);
+        let max = Vec3::new(x_end as f32, (y + 1) as f32, z_end as f32);
+        let half_extents = (max - min) / 2.;
+        boxes.push((
+            min + half_extents,
+            Quat::IDENTITY,
+            Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+        ));
+    };
+
+    for y in 1..=CHUNK_SIZE {
+        // x spans of the previous row, with the row each was first seen on
+        let mut open: HashMap<(u32, u32), u32> = HashMap::new();
+        // One row past the end flushes every span still open
+        for z in 1..=CHUNK_SIZE + 1 {
+            let mut runs = Vec::new();
+            if z <= CHUNK_SIZE {
+                let mut start = None;
+                for x in 1..=CHUNK_SIZE + 1 {
+                    let solid = x <= CHUNK_SIZE && is_solid(chunk, blocks, [x, y, z]);
+                    match (solid, start) {
+                        (true, None) => start = Some(x),
+                        (false, Some(run_start)) => {
+                            runs.push((run_start, x));
+                            start = None;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            let mut next = HashMap::new();
+            for run in runs {
+                next.insert(run, open.remove(&run).unwrap_or(z));
+            }
+            for (run, z_start) in open.drain() {
+                push_box(y, run, (z_start, z));
+            }
+            open = next;
+        }
+    }
+    (!boxes.is_empty()).then(|| Collider::compound(boxes))
+}
+
+fn trimesh_collider(mesh: &Mesh) -> Option<Collider> {
+    if mesh.count_vertices() == 0 {
+        return None;
+    }
+    Collider::from_bevy_mesh(mesh, &ComputedColliderShape::TriMesh)
+}
+
+/// Rebuilds the collider of every chunk whose mesh was regenerated
+fn update_chunk_colliders(
+    mut commands: Commands,
+    remeshed: Query<Entity, Changed<Handle<Mesh>>>,
+    shape: Res<ChunkColliderShape>,
+    chunks: Res<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    // Chunks are pointed at new meshes when remeshed, the meshes themselves never change
+    let remeshed: HashSet<Entity> = remeshed.iter().collect();
+    let rebuild_all = shape.is_changed() || chunks.is_added();
+    if remeshed.is_empty() && !rebuild_all {
+        return;
+    }
+
+    for chunk_entity in chunks.chunks.values() {
+        if !rebuild_all && !remeshed.contains(&chunk_entity.entity) {
+            continue;
+        }
+        let collider = match *shape {
+            ChunkColliderShape::Trimesh => meshes
+                .get(&chunk_entity.mesh_handle)
+                .and_then(trimesh_collider),
+            ChunkColliderShape::Boxes => assets_chunks
+                .get(&chunk_entity.chunk)
+                .and_then(|chunk| box_collider(chunk, &blocks)),
+        };
+        match collider {
+            Some(collider) => {
+                commands
+                    .entity(chunk_entity.entity)
+                    .insert((RigidBody::Fixed, collider));
+            }
+            None => {
+                commands.entity(chunk_entity.entity).remove::<Collider>();
+            }
+        }
+    }
+}
+
+/// Gives every chunk a fixed [RigidBody] and a [Collider] kept up to date with its blocks, shaped
+/// by the [ChunkColliderShape] resource. The rapier plugins themselves are left to the game
+pub struct PhysicsPlugin;
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkColliderShape>().add_systems(
+            Update,
+            update_chunk_colliders.run_if(resource_exists::<Chunks>),
+        );
+    }
+}
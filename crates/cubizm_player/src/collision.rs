@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+/// Gap kept between the body and the blocks it rests against, so it is not counted as overlapping them
+const SKIN: f32 = 1e-4;
+/// Longest distance moved along an axis before checking for blocks again, to avoid tunneling
+const MAX_STEP: f32 = 0.5;
+
+/// The blocks overlapping `min..max` along one axis, a block at `w` spans `w..w + 1`
+fn block_span(min: f32, max: f32) -> (i32, i32) {
+    (min.floor() as i32, max.ceil() as i32 - 1)
+}
+
+/// Moves the box at `min` with size `size` by `motion`, one axis at a time, stopping each
+/// axis at the first solid block and sliding along the rest. Blocks the box already
+/// overlaps are ignored so it can always move out of them.
+///
+/// Returns the new `min` and which axes were blocked
+pub fn collide_and_slide(
+    mut min: Vec3,
+    size: Vec3,
+    motion: Vec3,
+    is_solid: impl Fn(IVec3) -> bool,
+) -> (Vec3, BVec3) {
+    let mut blocked = [false; 3];
+    let steps = (motion.abs().max_element() / MAX_STEP).ceil().max(1.);
+    let step = motion / steps;
+
+    for _ in 0..steps as u32 {
+        // Vertical first so the body lands before sliding over the ground
+        for axis in [1, 0, 2] {
+            if blocked[axis] || step[axis] == 0. {
+                continue;
+            }
+            let mut moved = min;
+            moved[axis] += step[axis];
+
+            let old_span = block_span(min[axis], min[axis] + size[axis]);
+            let spans = [0, 1, 2].map(|other| block_span(moved[other], moved[other] + size[other]));
+            let mut hit: Option<i32> = None;
+            for x in spans[0].0..=spans[0].1 {
+                for y in spans[1].0..=spans[1].1 {
+                    for z in spans[2].0..=spans[2].1 {
+                        let block = IVec3::new(x, y, z);
+                        if (old_span.0..=old_span.1).contains(&block[axis]) || !is_solid(block) {
+                            continue;
+                        }
+                        hit = Some(match (hit, step[axis] > 0.) {
+                            (Some(nearest), true) => nearest.min(block[axis]),
+                            (Some(nearest), false) => nearest.max(block[axis]),
+                            (None, _) => block[axis],
+                        });
+                    }
+                }
+            }
+
+            match hit {
+                Some(block) if step[axis] > 0. => {
+                    moved[axis] = block as f32 - size[axis] - SKIN;
+                    blocked[axis] = true;
+                }
+                Some(block) => {
+                    moved[axis] = (block + 1) as f32 + SKIN;
+                    blocked[axis] = true;
+                }
+                None => {}
+            }
+            min = moved;
+        }
+    }
+
+    (min, BVec3::new(blocked[0], blocked[1], blocked[2]))
+}
@@ -0,0 +1,302 @@
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use block_mesh::{Voxel, VoxelVisibility};
+
+use cubizm_block::definition::Block;
+use cubizm_chunks::{Chunk, Chunks};
+use cubizm_core::point_to_block;
+
+pub use collision::*;
+
+mod collision;
+
+/// How far the camera can look up or down, in radians
+pub const PITCH_LIMIT: f32 = 1.54;
+
+/// The camera steered by [PlayerInput]. Remove it to stop controlling an entity
+/// without losing its [CharacterController] state
+#[derive(Component, Debug, Default)]
+pub struct Player;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MovementMode {
+    /// Gravity, jumping and collisions against the loaded chunks
+    #[default]
+    Walking,
+    /// Free flight through blocks
+    Flying,
+}
+
+/// Movement state of a [Player]
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct CharacterController {
+    pub velocity: Vec3,
+    /// Standing on a block, only updated while walking
+    pub grounded: bool,
+    pub mode: MovementMode,
+}
+
+impl CharacterController {
+    pub fn flying() -> Self {
+        Self {
+            mode: MovementMode::Flying,
+            ..default()
+        }
+    }
+}
+
+/// Body size, speeds and look sensitivity shared by every [Player]
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct PlayerSettings {
+    /// Mouse look in degrees per pixel, scaled by the smaller window dimension
+    pub sensitivity: f32,
+    /// Blocks per second
+    pub walk_speed: f32,
+    /// Blocks per second
+    pub fly_speed: f32,
+    /// Applied to both walking and flying speeds, for sprinting and sneaking
+    pub speed_multiplier: f32,
+    /// Upwards velocity of a jump, in blocks per second
+    pub jump_speed: f32,
+    /// Blocks per second squared
+    pub gravity: f32,
+    /// Fastest the player can fall, in blocks per second
+    pub terminal_velocity: f32,
+    /// Size of the collision box, centred horizontally on the camera
+    pub body_size: Vec3,
+    /// Height of the camera above the bottom of the collision box
+    pub eye_height: f32,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.00012,
+            walk_speed: 4.3,
+            fly_speed: 12.,
+            speed_multiplier: 1.,
+            jump_speed: 8.4,
+            gravity: 28.,
+            terminal_velocity: 78.,
+            body_size: Vec3::new(0.6, 1.8, 0.6),
+            eye_height: 1.62,
+        }
+    }
+}
+
+/// What the player wants to do this frame, filled in by the game's input handling before
+/// [PlayerSet] and cleared once applied. Writers should add to the values so keyboard and
+/// gamepad input can be combined
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerInput {
+    /// Horizontal movement relative to the facing direction, `+y` forward and `+x` right
+    pub movement: Vec2,
+    /// Ascend with positive values and descend with negative ones while flying
+    pub vertical: f32,
+    /// Turn right by `x` and up by `y` radians, on top of mouse look
+    pub look: Vec2,
+    pub jump: bool,
+    pub toggle_flying: bool,
+    pub toggle_grab_cursor: bool,
+}
+
+/// Systems moving the [Player] from [PlayerInput]
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerSet;
+
+fn toggle_grab_cursor(window: &mut Window) {
+    match window.cursor.grab_mode {
+        CursorGrabMode::None => {
+            window.cursor.grab_mode = CursorGrabMode::Confined;
+            window.cursor.visible = false;
+        }
+        _ => {
+            window.cursor.grab_mode = CursorGrabMode::None;
+            window.cursor.visible = true;
+        }
+    }
+}
+
+fn initial_grab_cursor(mut primary_window: Query<&mut Window, With<PrimaryWindow>>) {
+    if let Ok(mut window) = primary_window.get_single_mut() {
+        toggle_grab_cursor(&mut window);
+    } else {
+        warn!("Primary window not found for `initial_grab_cursor`!");
+    }
+}
+
+fn cursor_grab(
+    input: Res<PlayerInput>,
+    mut primary_window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !input.toggle_grab_cursor {
+        return;
+    }
+    if let Ok(mut window) = primary_window.get_single_mut() {
+        toggle_grab_cursor(&mut window);
+    } else {
+        warn!("Primary window not found for `cursor_grab`!");
+    }
+}
+
+fn setup_player(mut commands: Commands, spawn: Res<PlayerSpawn>) {
+    commands.spawn((
+        Camera3dBundle {
+            transform: spawn.0,
+            ..default()
+        },
+        Player,
+        CharacterController::default(),
+    ));
+}
+
+fn player_look(
+    settings: Res<PlayerSettings>,
+    input: Res<PlayerInput>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    mut motion: EventReader<MouseMotion>,
+    mut player: Query<&mut Transform, With<Player>>,
+) {
+    let mouse: Vec2 = motion.read().map(|event| event.delta).sum();
+    let mut turn = input.look;
+    if let Ok(window) = primary_window.get_single() {
+        if window.cursor.grab_mode != CursorGrabMode::None {
+            // Using smallest of height or width ensures equal vertical and horizontal sensitivity
+            let window_scale = window.height().min(window.width());
+            let degrees = Vec2::new(mouse.x, -mouse.y) * settings.sensitivity * window_scale;
+            turn += degrees * std::f32::consts::PI / 180.;
+        }
+    }
+    if turn == Vec2::ZERO {
+        return;
+    }
+
+    for mut transform in player.iter_mut() {
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let yaw = yaw - turn.x;
+        let pitch = (pitch + turn.y).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        // Order is important to prevent unintended roll
+        transform.rotation =
+            Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
+    }
+}
+
+fn toggle_flying(
+    input: Res<PlayerInput>,
+    mut player: Query<&mut CharacterController, With<Player>>,
+) {
+    if !input.toggle_flying {
+        return;
+    }
+    for mut controller in player.iter_mut() {
+        controller.mode = match controller.mode {
+            MovementMode::Walking => MovementMode::Flying,
+            MovementMode::Flying => MovementMode::Walking,
+        };
+        controller.velocity = Vec3::ZERO;
+        controller.grounded = false;
+    }
+}
+
+fn move_player(
+    time: Res<Time>,
+    settings: Res<PlayerSettings>,
+    input: Res<PlayerInput>,
+    chunks: Option<Res<Chunks>>,
+    chunk_assets: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    mut player: Query<(&mut Transform, &mut CharacterController), With<Player>>,
+) {
+    let delta = time.delta_seconds();
+    let is_solid = |position: IVec3| {
+        chunks
+            .as_ref()
+            .and_then(|chunks| chunks.get_block(position, &chunk_assets).ok())
+            .and_then(|block| blocks.get(&block))
+            .is_some_and(|block| block.get_visibility() != VoxelVisibility::Empty)
+    };
+
+    for (mut transform, mut controller) in player.iter_mut() {
+        let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let heading = Quat::from_axis_angle(Vec3::Y, yaw)
+            * Vec3::new(input.movement.x, 0., -input.movement.y).clamp_length_max(1.);
+
+        if controller.mode == MovementMode::Flying {
+            let direction = (heading + Vec3::Y * input.vertical).clamp_length_max(1.);
+            controller.velocity = direction * settings.fly_speed * settings.speed_multiplier;
+            transform.translation += controller.velocity * delta;
+            continue;
+        }
+
+        let feet = transform.translation - Vec3::Y * settings.eye_height;
+        // Hold still until the ground under the player has loaded
+        let loaded = chunks.as_ref().is_some_and(|chunks| {
+            chunks
+                .get_block(point_to_block(feet), &chunk_assets)
+                .is_ok()
+        });
+        if !loaded {
+            controller.velocity = Vec3::ZERO;
+            continue;
+        }
+
+        let walk = heading * settings.walk_speed * settings.speed_multiplier;
+        let mut velocity = Vec3::new(walk.x, controller.velocity.y, walk.z);
+        if input.jump && controller.grounded {
+            velocity.y = settings.jump_speed;
+        }
+        velocity.y = (velocity.y - settings.gravity * delta).max(-settings.terminal_velocity);
+
+        let size = settings.body_size;
+        let min = feet - Vec3::new(size.x, 0., size.z) / 2.;
+        let (moved, blocked) = collide_and_slide(min, size, velocity * delta, is_solid);
+        transform.translation += moved - min;
+
+        controller.grounded = blocked.y && velocity.y < 0.;
+        controller.velocity = Vec3::select(blocked, Vec3::ZERO, velocity);
+    }
+}
+
+fn clear_input(mut input: ResMut<PlayerInput>) {
+    *input = PlayerInput::default();
+}
+
+/// Where [PlayerPlugin] spawns the player camera
+#[derive(Resource, Debug, Clone, Copy)]
+struct PlayerSpawn(Transform);
+
+/// Spawns a first person camera that walks on the loaded chunks or flies through them,
+/// steered by [PlayerInput] and the mouse
+pub struct PlayerPlugin {
+    pub spawn: Transform,
+}
+
+impl Default for PlayerPlugin {
+    fn default() -> Self {
+        Self {
+            spawn: Transform::from_xyz(-2.0, 5.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        }
+    }
+}
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerSettings>()
+            .init_resource::<PlayerInput>()
+            .insert_resource(PlayerSpawn(self.spawn))
+            .add_systems(Startup, (setup_player, initial_grab_cursor))
+            .add_systems(
+                Update,
+                (
+                    cursor_grab,
+                    player_look,
+                    toggle_flying,
+                    move_player,
+                    clear_input,
+                )
+                    .chain()
+                    .in_set(PlayerSet),
+            );
+    }
+}
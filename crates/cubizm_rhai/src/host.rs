@@ -0,0 +1,360 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rhai::{Array, Dynamic, Engine, FnPtr, Map, FLOAT, INT};
+
+use cubizm_block::definition::Block;
+
+/// How far in blocks from where a handler runs a script can read and set blocks
+pub const SCRIPT_REACH: i32 = 2;
+
+/// How far in blocks from where a handler runs `raycast` sees. Only scripts calling it get the
+/// blocks this far copied for them
+pub const SCRIPT_RAYCAST_RANGE: i32 = 8;
+
+/// Name scripts know a block by, its asset path
+pub fn block_name(block: &Handle<Block>) -> String {
+    block.path().map(ToString::to_string).unwrap_or_default()
+}
+
+/// The loaded blocks as scripts see them, by [block_name]
+#[derive(Resource, Debug, Default, Clone)]
+pub struct ScriptBlocks {
+    names: Arc<HashMap<String, Handle<Block>>>,
+    /// Blocks rays stop at
+    solid: Arc<HashSet<AssetId<Block>>>,
+}
+
+impl ScriptBlocks {
+    pub fn from_assets(assets: &Assets<Block>, server: &AssetServer) -> Self {
+        let blocks: Vec<Handle<Block>> = assets
+            .ids()
+            .filter_map(|id| server.get_id_handle(id))
+            .collect();
+        Self {
+            names: Arc::new(
+                blocks
+                    .iter()
+                    .map(|block| (block_name(block), block.clone()))
+                    .collect(),
+            ),
+            solid: Arc::new(
+                blocks
+                    .iter()
+                    .filter(|block| assets.get(*block).is_some_and(Block::is_hit_by_rays))
+                    .map(Handle::id)
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Handle<Block>> {
+        self.names.get(name)
+    }
+
+    pub fn is_solid(&self, block: &Handle<Block>) -> bool {
+        self.solid.contains(&block.id())
+    }
+}
+
+/// The blocks a ray from `origin` passes through in order, up to `max_distance` along it.
+/// Nothing if `direction` is zero
+pub fn ray_blocks(origin: Vec3, direction: Vec3, max_distance: f32) -> RayBlocks {
+    let direction = direction.normalize_or_zero();
+    let block = origin.floor().as_ivec3();
+    let axis = |origin: f32, direction: f32| -> (i32, f32, f32) {
+        if direction > 0. {
+            (
+                1,
+                1. / direction,
+                (origin.floor() + 1. - origin) / direction,
+            )
+        } else if direction < 0. {
+            (-1, -1. / direction, (origin - origin.floor()) / -direction)
+        } else {
+            (0, f32::INFINITY, f32::INFINITY)
+        }
+    };
+    let (x, y, z) = (
+        axis(origin.x, direction.x),
+        axis(origin.y, direction.y),
+        axis(origin.z, direction.z),
+    );
+    RayBlocks {
+        block,
+        step: IVec3::new(x.0, y.0, z.0),
+        delta: Vec3::new(x.1, y.1, z.1),
+        next: Vec3::new(x.2, y.2, z.2),
+        distance: 0.,
+        max_distance: if direction == Vec3::ZERO {
+            -1.
+        } else {
+            max_distance
+        },
+    }
+}
+
+/// See [ray_blocks]
+#[derive(Debug, Clone)]
+pub struct RayBlocks {
+    block: IVec3,
+    step: IVec3,
+    /// Distance along the ray between crossings of each axis' block boundaries
+    delta: Vec3,
+    /// Distance along the ray to the next crossing on each axis
+    next: Vec3,
+    distance: f32,
+    max_distance: f32,
+}
+
+impl Iterator for RayBlocks {
+    type Item = IVec3;
+
+    fn next(&mut self) -> Option<IVec3> {
+        if self.distance > self.max_distance {
+            return None;
+        }
+        let block = self.block;
+        let axis = match self.next.min_element() {
+            min if min == self.next.x => 0,
+            min if min == self.next.y => 1,
+            _ => 2,
+        };
+        self.distance = self.next[axis];
+        self.next[axis] += self.delta[axis];
+        self.block[axis] += self.step[axis];
+        Some(block)
+    }
+}
+
+/// What a script can see of the world while one of its handlers runs. Scripts only get
+/// copies of the blocks around them, their edits are applied once the handler returns
+#[derive(Debug)]
+pub struct ScriptHost {
+    /// World position the handler runs at
+    pub origin: IVec3,
+    /// The loaded blocks within `reach` of `origin`, with the script's edits
+    blocks: HashMap<IVec3, Handle<Block>>,
+    reach: i32,
+    names: ScriptBlocks,
+    /// Blocks the script set, in order
+    pub edits: Vec<(IVec3, Handle<Block>)>,
+    /// Names of the [ScriptEvent](crate::ScriptEvent)s the script sent, in order
+    pub events: Vec<String>,
+    /// Lines the script printed, in order
+    pub log: Vec<String>,
+}
+
+impl ScriptHost {
+    /// `get_block` looks up the blocks within `reach` of `origin` by world position, `None` if
+    /// unloaded. Scripts still only read and set blocks within [SCRIPT_REACH], the rest are
+    /// only seen by `raycast`
+    pub fn new(
+        origin: IVec3,
+        reach: i32,
+        names: &ScriptBlocks,
+        get_block: impl Fn(IVec3) -> Option<Handle<Block>>,
+    ) -> Self {
+        let reach = reach.max(SCRIPT_REACH);
+        let range = -reach..=reach;
+        let mut blocks = HashMap::new();
+        for x in range.clone() {
+            for y in range.clone() {
+                for z in range.clone() {
+                    let position = origin + IVec3::new(x, y, z);
+                    if let Some(block) = get_block(position) {
+                        blocks.insert(position, block);
+                    }
+                }
+            }
+        }
+        Self {
+            origin,
+            blocks,
+            reach,
+            names: names.clone(),
+            edits: Vec::new(),
+            events: Vec::new(),
+            log: Vec::new(),
+        }
+    }
+
+    fn in_reach(&self, position: IVec3) -> bool {
+        (position - self.origin).abs().max_element() <= SCRIPT_REACH
+    }
+
+    fn get_block(&self, position: IVec3) -> Option<&Handle<Block>> {
+        self.in_reach(position)
+            .then(|| self.blocks.get(&position))
+            .flatten()
+    }
+
+    /// Queues setting the block at `position` to the block named `name`, `false` if it is out
+    /// of reach, unloaded or there is no such block
+    fn set_block(&mut self, position: IVec3, name: &str) -> bool {
+        let Some(block) = self.names.get(name).cloned() else {
+            return false;
+        };
+        if !self.in_reach(position) || !self.blocks.contains_key(&position) {
+            return false;
+        }
+        self.blocks.insert(position, block.clone());
+        self.edits.push((position, block));
+        true
+    }
+
+    /// The first block along the ray rays stop at, as far as the copied blocks reach.
+    /// Unloaded blocks count as empty
+    fn raycast(&self, origin: Vec3, direction: Vec3) -> Option<(IVec3, &Handle<Block>)> {
+        ray_blocks(origin, direction, SCRIPT_RAYCAST_RANGE as f32)
+            .take_while(|block| (*block - self.origin).abs().max_element() <= self.reach)
+            .find_map(|block| {
+                let handle = self.blocks.get(&block)?;
+                self.names.is_solid(handle).then_some((block, handle))
+            })
+    }
+}
+
+/// The handlers a script registers while its top level runs, by the block or command name
+/// they handle
+#[derive(Debug, Default, Clone)]
+pub struct ScriptHandlers {
+    pub uses: Vec<(String, FnPtr)>,
+    pub ticks: Vec<(String, FnPtr)>,
+    pub commands: Vec<(String, FnPtr)>,
+}
+
+/// What the host functions of [register_host] work on
+#[derive(Debug, Default)]
+pub(crate) struct HostState {
+    /// The handler being run, `None` while a script registers its handlers
+    pub(crate) host: Option<ScriptHost>,
+    /// Handlers registered by the script whose top level is running
+    pub(crate) handlers: ScriptHandlers,
+}
+
+pub(crate) type SharedHost = Arc<Mutex<HostState>>;
+
+fn lock(state: &SharedHost) -> MutexGuard<'_, HostState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn position(x: INT, y: INT, z: INT) -> Option<IVec3> {
+    Some(IVec3::new(
+        i32::try_from(x).ok()?,
+        i32::try_from(y).ok()?,
+        i32::try_from(z).ok()?,
+    ))
+}
+
+/// Registers the functions scripts call. Positions are world positions, and functions that
+/// can fail return `()`, `""` or `false` instead of throwing:
+/// - `on_use(block, |x, y, z| ..)`: runs the handler when the player uses the block named
+///   `block`, see [BlockInteract](cubizm_chunks::BlockInteract)
+/// - `on_tick(block, |x, y, z| ..)`: runs the handler on every
+///   [BlockTick](cubizm_chunks::BlockTick) of a block named `block`, random ticks only reach
+///   blocks that [tick](Block::ticks)
+/// - `command(name, |arguments, x, y, z| ..)`: adds the script command `name`, run at the
+///   block the player is in with its arguments as an array of strings, see
+///   [RunScriptCommand](crate::RunScriptCommand)
+/// - `get_block(x, y, z)`: name of the block, `""` if unloaded or out of reach
+/// - `set_block(x, y, z, block)`: `true` if the block will be set to the block named `block`
+/// - `raycast(x, y, z, dx, dy, dz)`: the first block along the ray from `(x, y, z)` that isn't
+///   empty within [SCRIPT_RAYCAST_RANGE], as `#{x, y, z, block}`. `()` if the ray hit nothing
+/// - `send_event(name)`: sends a [ScriptEvent](crate::ScriptEvent) named `name`
+/// - `print(text)`: logs `text`, printed to the console by script commands
+///
+/// Blocks are named by their asset path, like `"blocks/info/dirt.block"`
+pub(crate) fn register_host(engine: &mut Engine, state: &SharedHost) {
+    let shared = state.clone();
+    engine.register_fn("on_use", move |block: &str, handler: FnPtr| {
+        lock(&shared)
+            .handlers
+            .uses
+            .push((block.to_string(), handler));
+    });
+    let shared = state.clone();
+    engine.register_fn("on_tick", move |block: &str, handler: FnPtr| {
+        lock(&shared)
+            .handlers
+            .ticks
+            .push((block.to_string(), handler));
+    });
+    let shared = state.clone();
+    engine.register_fn("command", move |name: &str, handler: FnPtr| {
+        lock(&shared)
+            .handlers
+            .commands
+            .push((name.to_string(), handler));
+    });
+    let shared = state.clone();
+    engine.register_fn("get_block", move |x: INT, y: INT, z: INT| -> String {
+        let state = lock(&shared);
+        let block = state
+            .host
+            .as_ref()
+            .zip(position(x, y, z))
+            .and_then(|(host, position)| host.get_block(position));
+        block.map(block_name).unwrap_or_default()
+    });
+    let shared = state.clone();
+    engine.register_fn(
+        "set_block",
+        move |x: INT, y: INT, z: INT, block: &str| -> bool {
+            let mut state = lock(&shared);
+            match (state.host.as_mut(), position(x, y, z)) {
+                (Some(host), Some(position)) => host.set_block(position, block),
+                _ => false,
+            }
+        },
+    );
+    let shared = state.clone();
+    engine.register_fn(
+        "raycast",
+        move |x: FLOAT, y: FLOAT, z: FLOAT, dx: FLOAT, dy: FLOAT, dz: FLOAT| -> Dynamic {
+            let state = lock(&shared);
+            let origin = Vec3::new(x as f32, y as f32, z as f32);
+            let direction = Vec3::new(dx as f32, dy as f32, dz as f32);
+            let hit = state
+                .host
+                .as_ref()
+                .and_then(|host| host.raycast(origin, direction));
+            let Some((position, block)) = hit else {
+                return Dynamic::UNIT;
+            };
+            let mut map = Map::new();
+            map.insert("x".into(), INT::from(position.x).into());
+            map.insert("y".into(), INT::from(position.y).into());
+            map.insert("z".into(), INT::from(position.z).into());
+            map.insert("block".into(), block_name(block).into());
+            map.into()
+        },
+    );
+    let shared = state.clone();
+    engine.register_fn("send_event", move |name: &str| {
+        if let Some(host) = lock(&shared).host.as_mut() {
+            host.events.push(name.to_string());
+        }
+    });
+    let shared = state.clone();
+    engine.on_print(move |text| {
+        let mut state = lock(&shared);
+        match state.host.as_mut() {
+            Some(host) => {
+                info!("Script at {}: {text}", host.origin);
+                host.log.push(text.to_string());
+            }
+            None => info!("Script: {text}"),
+        }
+    });
+}
+
+/// The arguments of a script command as scripts get them
+pub(crate) fn arguments(arguments: &[String]) -> Array {
+    arguments.iter().cloned().map(Dynamic::from).collect()
+}
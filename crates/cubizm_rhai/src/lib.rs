@@ -0,0 +1,558 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::asset::LoadedFolder;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use block_mesh::ndshape::ConstShape;
+use rhai::{Dynamic, Engine, FnPtr, FuncArgs, Scope, AST, INT};
+use thiserror::Error;
+
+use cubizm_block::definition::Block;
+use cubizm_chunks::{
+    BlockChanged, BlockInteract, BlockTick, BlockTickSet, Chunk, ChunkShape, Chunks,
+};
+use cubizm_core::{world_to_chunk, world_to_local};
+
+pub use host::*;
+pub use script::*;
+
+mod host;
+mod script;
+
+/// Sent by scripts through `send_event`, for the game to react to
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ScriptEvent {
+    /// World position the sending handler ran at
+    pub origin: IVec3,
+    pub name: String,
+}
+
+/// Runs the script command `name` at `origin`
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct RunScriptCommand {
+    pub name: String,
+    pub arguments: Vec<String>,
+    pub origin: IVec3,
+}
+
+/// The lines a [RunScriptCommand] printed, or why it failed
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ScriptCommandOutput {
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum ScriptError {
+    #[error(transparent)]
+    Parse(#[from] rhai::ParseError),
+    #[error(transparent)]
+    Eval(#[from] Box<rhai::EvalAltResult>),
+    #[error("Script is not compiled")]
+    NotCompiled,
+}
+
+/// A compiled [RhaiScript] and the handlers its top level registered
+#[derive(Debug)]
+struct CompiledScript {
+    ast: AST,
+    handlers: ScriptHandlers,
+    /// Whether the script calls `raycast`, and needs the blocks within
+    /// [SCRIPT_RAYCAST_RANGE] copied
+    raycasting: bool,
+}
+
+/// The compiled [RhaiScript]s and the engine running them with the functions of
+/// [register_host]. Scripts run sandboxed: they can only reach the world through those
+/// functions, can't `eval` code and are stopped after [RhaiSettings::max_operations]
+#[derive(Resource)]
+pub struct ScriptRuntime {
+    engine: Engine,
+    state: SharedHost,
+    scripts: HashMap<AssetId<RhaiScript>, CompiledScript>,
+}
+
+impl ScriptRuntime {
+    pub fn new(max_operations: u64) -> Self {
+        let state = Arc::new(Mutex::new(HostState::default()));
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(max_operations)
+            .set_max_call_levels(32)
+            .disable_symbol("eval");
+        register_host(&mut engine, &state);
+        Self {
+            engine,
+            state,
+            scripts: HashMap::new(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, HostState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Compiles the script and runs its top level, replacing the handlers it registered
+    /// before
+    pub fn compile(
+        &mut self,
+        id: AssetId<RhaiScript>,
+        script: &RhaiScript,
+    ) -> Result<(), ScriptError> {
+        let ast = self.engine.compile(&script.source)?;
+        self.state().handlers = ScriptHandlers::default();
+        let result = self.engine.run_ast_with_scope(&mut Scope::new(), &ast);
+        let handlers = std::mem::take(&mut self.state().handlers);
+        result?;
+        self.remove(id);
+        for (name, _) in &handlers.commands {
+            if self.command(name).is_some() {
+                warn!("Script command {name} is added by several scripts, using {id:?}");
+            }
+        }
+        self.scripts.insert(
+            id,
+            CompiledScript {
+                raycasting: script.source.contains("raycast"),
+                ast,
+                handlers,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: AssetId<RhaiScript>) {
+        self.scripts.remove(&id);
+    }
+
+    /// How far from its origin the [ScriptHost] of the script has to copy blocks
+    pub fn reach(&self, id: AssetId<RhaiScript>) -> i32 {
+        match self
+            .scripts
+            .get(&id)
+            .is_some_and(|script| script.raycasting)
+        {
+            true => SCRIPT_RAYCAST_RANGE,
+            false => SCRIPT_REACH,
+        }
+    }
+
+    /// The `on_use` handlers of the block named `block`, with their script
+    pub fn use_handlers(&self, block: &str) -> Vec<(AssetId<RhaiScript>, FnPtr)> {
+        self.handlers(block, |handlers| &handlers.uses)
+    }
+
+    /// The `on_tick` handlers of the block named `block`, with their script
+    pub fn tick_handlers(&self, block: &str) -> Vec<(AssetId<RhaiScript>, FnPtr)> {
+        self.handlers(block, |handlers| &handlers.ticks)
+    }
+
+    /// Whether any script ticks the block named `block`
+    pub fn ticks(&self, block: &str) -> bool {
+        !self.tick_handlers(block).is_empty()
+    }
+
+    fn handlers(
+        &self,
+        name: &str,
+        kind: impl Fn(&ScriptHandlers) -> &Vec<(String, FnPtr)>,
+    ) -> Vec<(AssetId<RhaiScript>, FnPtr)> {
+        self.scripts
+            .iter()
+            .flat_map(|(id, script)| {
+                kind(&script.handlers)
+                    .iter()
+                    .filter(|(handled, _)| handled == name)
+                    .map(|(_, handler)| (*id, handler.clone()))
+            })
+            .collect()
+    }
+
+    /// The handler of the script command `name`, with its script
+    pub fn command(&self, name: &str) -> Option<(AssetId<RhaiScript>, FnPtr)> {
+        self.handlers(name, |handlers| &handlers.commands).pop()
+    }
+
+    /// Names of every script command, sorted
+    pub fn command_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .scripts
+            .values()
+            .flat_map(|script| &script.handlers.commands)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Runs `handler` of the script with `arguments`. Returns the `host` with the edits the
+    /// script made
+    pub fn call(
+        &self,
+        script: AssetId<RhaiScript>,
+        handler: &FnPtr,
+        host: ScriptHost,
+        arguments: impl FuncArgs,
+    ) -> Result<ScriptHost, ScriptError> {
+        let ast = &self
+            .scripts
+            .get(&script)
+            .ok_or(ScriptError::NotCompiled)?
+            .ast;
+        self.state().host = Some(host);
+        let result = handler.call::<Dynamic>(&self.engine, ast, arguments);
+        let host = self.state().host.take();
+        // Handlers can return anything, it is ignored
+        result.map(drop)?;
+        host.ok_or(ScriptError::NotCompiled)
+    }
+}
+
+/// The position of the chunk holding the block at world `position`, and the block's index in
+/// it
+fn chunk_index(position: IVec3) -> (IVec3, usize) {
+    let local = world_to_local(position);
+    (
+        world_to_chunk(position),
+        ChunkShape::linearize(local.to_array()) as usize,
+    )
+}
+
+/// The block at world `position`, `None` if its chunk isn't loaded
+pub fn block_at<'a>(
+    chunks: &Chunks,
+    assets: &'a Assets<Chunk>,
+    position: IVec3,
+) -> Option<&'a Handle<Block>> {
+    let (chunk, index) = chunk_index(position);
+    let chunk = assets.get(&chunks.chunks.get(&chunk)?.chunk)?;
+    chunk.blocks.get(index)
+}
+
+/// Block handlers waiting to run, as the edits of scripts send [BlockChanged] themselves
+#[derive(Resource, Debug, Default)]
+struct PendingHandlers(Vec<(IVec3, AssetId<RhaiScript>, FnPtr)>);
+
+/// The scripts in the folder of [RhaiSettings::path], kept loaded
+#[derive(Resource, Debug)]
+pub struct ScriptsFolder(pub Handle<LoadedFolder>);
+
+/// Lets other plugins, like a console, run the script commands
+#[derive(SystemParam)]
+pub struct ScriptCommands<'w> {
+    runtime: Option<Res<'w, ScriptRuntime>>,
+    requests: EventWriter<'w, RunScriptCommand>,
+}
+
+impl ScriptCommands<'_> {
+    /// Runs the typed command `line` at `origin` if a script adds it, its output is sent as a
+    /// [ScriptCommandOutput]. `false` if no script does
+    pub fn run(&mut self, line: &str, origin: IVec3) -> bool {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return false;
+        };
+        let runtime = self.runtime.as_ref();
+        if runtime.and_then(|runtime| runtime.command(name)).is_none() {
+            return false;
+        }
+        self.requests.send(RunScriptCommand {
+            name: name.to_string(),
+            arguments: words.map(str::to_string).collect(),
+            origin,
+        });
+        true
+    }
+
+    /// Names of every script command, sorted
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.runtime
+            .iter()
+            .flat_map(|runtime| runtime.command_names())
+    }
+}
+
+fn load_scripts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<RhaiSettings>,
+) {
+    commands.insert_resource(ScriptsFolder(
+        asset_server.load_folder(settings.path.clone()),
+    ));
+}
+
+fn update_script_blocks(
+    mut events: EventReader<AssetEvent<Block>>,
+    blocks: Res<Assets<Block>>,
+    asset_server: Res<AssetServer>,
+    mut names: ResMut<ScriptBlocks>,
+) {
+    if events.read().count() > 0 {
+        *names = ScriptBlocks::from_assets(&blocks, &asset_server);
+    }
+}
+
+fn compile_scripts(
+    mut events: EventReader<AssetEvent<RhaiScript>>,
+    scripts: Res<Assets<RhaiScript>>,
+    mut runtime: ResMut<ScriptRuntime>,
+) {
+    for event in events.read() {
+        match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                let Some(script) = scripts.get(*id) else {
+                    continue;
+                };
+                if let Err(err) = runtime.compile(*id, script) {
+                    warn!("Failed to compile script {id:?}: {err}");
+                }
+            }
+            AssetEvent::Removed { id } => runtime.remove(*id),
+            _ => {}
+        }
+    }
+}
+
+/// Queues the `on_use` handlers of the blocks that got a [BlockInteract]
+fn collect_uses(
+    mut interactions: EventReader<BlockInteract>,
+    runtime: Res<ScriptRuntime>,
+    mut pending: ResMut<PendingHandlers>,
+) {
+    for interaction in interactions.read() {
+        pending.0.extend(
+            runtime
+                .use_handlers(&block_name(&interaction.block))
+                .into_iter()
+                .map(|(script, handler)| (interaction.position, script, handler)),
+        );
+    }
+}
+
+/// Queues the `on_tick` handlers of the blocks that got a [BlockTick]
+fn collect_ticks(
+    mut ticks: EventReader<BlockTick>,
+    runtime: Res<ScriptRuntime>,
+    mut pending: ResMut<PendingHandlers>,
+) {
+    for tick in ticks.read() {
+        pending.0.extend(
+            runtime
+                .tick_handlers(&block_name(&tick.block))
+                .into_iter()
+                .map(|(script, handler)| (tick.position, script, handler)),
+        );
+    }
+}
+
+/// Applies the edits and events of a script once it returned, returning what it printed
+fn apply_host(
+    host: ScriptHost,
+    chunks: &mut Chunks,
+    assets_chunks: &mut ResMut<Assets<Chunk>>,
+    changes: &mut EventWriter<BlockChanged>,
+    events: &mut EventWriter<ScriptEvent>,
+) -> Vec<String> {
+    for (position, block) in host.edits {
+        if let Err(err) = chunks.set_block(position, block, assets_chunks, changes) {
+            warn!("Script failed to set block at {position}: {err}");
+        }
+    }
+    events.send_batch(host.events.into_iter().map(|name| ScriptEvent {
+        origin: host.origin,
+        name,
+    }));
+    host.log
+}
+
+fn run_block_handlers(
+    runtime: Res<ScriptRuntime>,
+    names: Res<ScriptBlocks>,
+    mut pending: ResMut<PendingHandlers>,
+    mut chunks: ResMut<Chunks>,
+    mut assets_chunks: ResMut<Assets<Chunk>>,
+    mut changes: EventWriter<BlockChanged>,
+    mut events: EventWriter<ScriptEvent>,
+) {
+    for (position, script, handler) in std::mem::take(&mut pending.0) {
+        let host = ScriptHost::new(position, runtime.reach(script), &names, |position| {
+            block_at(&chunks, &assets_chunks, position).cloned()
+        });
+        let arguments = (
+            INT::from(position.x),
+            INT::from(position.y),
+            INT::from(position.z),
+        );
+        let host = match runtime.call(script, &handler, host, arguments) {
+            Ok(host) => host,
+            Err(err) => {
+                warn!("Script handler at {position} failed: {err}");
+                continue;
+            }
+        };
+        apply_host(
+            host,
+            &mut chunks,
+            &mut assets_chunks,
+            &mut changes,
+            &mut events,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_script_commands(
+    mut requests: EventReader<RunScriptCommand>,
+    runtime: Res<ScriptRuntime>,
+    names: Res<ScriptBlocks>,
+    mut outputs: EventWriter<ScriptCommandOutput>,
+    mut chunks: ResMut<Chunks>,
+    mut assets_chunks: ResMut<Assets<Chunk>>,
+    mut changes: EventWriter<BlockChanged>,
+    mut events: EventWriter<ScriptEvent>,
+) {
+    for request in requests.read() {
+        let Some((script, handler)) = runtime.command(&request.name) else {
+            continue;
+        };
+        let host = ScriptHost::new(request.origin, runtime.reach(script), &names, |position| {
+            block_at(&chunks, &assets_chunks, position).cloned()
+        });
+        let origin = request.origin;
+        let arguments = (
+            arguments(&request.arguments),
+            INT::from(origin.x),
+            INT::from(origin.y),
+            INT::from(origin.z),
+        );
+        let lines = match runtime.call(script, &handler, host, arguments) {
+            Ok(host) => apply_host(
+                host,
+                &mut chunks,
+                &mut assets_chunks,
+                &mut changes,
+                &mut events,
+            ),
+            Err(err) => vec![err.to_string()],
+        };
+        outputs.send(ScriptCommandOutput {
+            name: request.name.clone(),
+            lines,
+        });
+    }
+}
+
+/// Configuration for [RhaiPlugin]
+#[derive(Resource, Debug, Clone)]
+pub struct RhaiSettings {
+    /// Asset folder of the `.rhai` scripts, run as they load and whenever they change
+    pub path: String,
+    /// Operations a handler may run before it is stopped, so a broken script can't hang the
+    /// game
+    pub max_operations: u64,
+}
+
+impl Default for RhaiSettings {
+    fn default() -> Self {
+        Self {
+            path: "scripts".into(),
+            max_operations: 1_000_000,
+        }
+    }
+}
+
+/// Runs the `.rhai` scripts of [RhaiSettings::path], whose handlers run when blocks are used
+/// or ticked and as script commands, see [register_host]. Scripts are recompiled whenever
+/// they change, so behaviour can be changed while the game runs
+#[derive(Default)]
+pub struct RhaiPlugin {
+    pub settings: RhaiSettings,
+}
+
+impl Plugin for RhaiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScriptRuntime::new(self.settings.max_operations))
+            .insert_resource(self.settings.clone())
+            .init_asset::<RhaiScript>()
+            .init_asset_loader::<RhaiScriptLoader>()
+            .init_resource::<ScriptBlocks>()
+            .init_resource::<PendingHandlers>()
+            .add_event::<ScriptEvent>()
+            .add_event::<RunScriptCommand>()
+            .add_event::<ScriptCommandOutput>()
+            .add_systems(Startup, load_scripts)
+            .add_systems(
+                Update,
+                (
+                    update_script_blocks,
+                    compile_scripts,
+                    (
+                        collect_uses,
+                        collect_ticks,
+                        run_block_handlers,
+                        run_script_commands,
+                    )
+                        .chain()
+                        .after(BlockTickSet)
+                        .run_if(resource_exists::<Chunks>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compiled(source: &str) -> ScriptRuntime {
+        let mut runtime = ScriptRuntime::new(10_000);
+        let script = RhaiScript {
+            source: source.to_string(),
+        };
+        runtime.compile(AssetId::invalid(), &script).unwrap();
+        runtime
+    }
+
+    #[test]
+    fn runs_registered_commands() {
+        let runtime = compiled(r#"command("echo", |arguments, x, y, z| print(arguments[0]));"#);
+        assert_eq!(runtime.command_names(), vec!["echo"]);
+
+        let (script, handler) = runtime.command("echo").unwrap();
+        let host = ScriptHost::new(IVec3::ZERO, SCRIPT_REACH, &ScriptBlocks::default(), |_| {
+            None
+        });
+        let arguments = (
+            arguments(&["hello".to_string()]),
+            0 as INT,
+            0 as INT,
+            0 as INT,
+        );
+        let host = runtime.call(script, &handler, host, arguments).unwrap();
+        assert_eq!(host.log, vec!["hello"]);
+    }
+
+    #[test]
+    fn stops_runaway_handlers() {
+        let runtime = compiled(r#"on_tick("blocks/info/dirt.block", |x, y, z| loop {});"#);
+        let (script, handler) = runtime.tick_handlers("blocks/info/dirt.block").remove(0);
+        let host = ScriptHost::new(IVec3::ZERO, SCRIPT_REACH, &ScriptBlocks::default(), |_| {
+            None
+        });
+        let arguments = (0 as INT, 0 as INT, 0 as INT);
+        assert!(runtime.call(script, &handler, host, arguments).is_err());
+    }
+
+    #[test]
+    fn rays_step_through_every_block() {
+        let blocks: Vec<IVec3> = ray_blocks(Vec3::new(0.5, 0.5, 0.5), Vec3::X, 2.).collect();
+        assert_eq!(blocks, vec![IVec3::ZERO, IVec3::X, IVec3::new(2, 0, 0)]);
+        assert_eq!(ray_blocks(Vec3::ZERO, Vec3::ZERO, 2.).count(), 0);
+    }
+}
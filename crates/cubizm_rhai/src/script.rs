@@ -0,0 +1,49 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use thiserror::Error;
+
+/// The source of a `.rhai` script, compiled and run by [ScriptRuntime](crate::ScriptRuntime)
+/// when it loads or changes
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct RhaiScript {
+    pub source: String,
+}
+
+#[derive(Debug, Error)]
+pub enum RhaiScriptLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Utf8(#[from] std::string::FromUtf8Error),
+}
+
+#[derive(Default)]
+pub struct RhaiScriptLoader;
+
+impl AssetLoader for RhaiScriptLoader {
+    type Asset = RhaiScript;
+    type Settings = ();
+    type Error = RhaiScriptLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _: &'a Self::Settings,
+        _: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(RhaiScript {
+                source: String::from_utf8(bytes)?,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rhai"]
+    }
+}
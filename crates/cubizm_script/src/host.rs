@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use bevy::{prelude::*, utils::HashMap};
+use wasmi::{Caller, Extern, Linker};
+
+use cubizm_block::definition::Block;
+use cubizm_block::{block_key, BlockRegistry, BASE_NAMESPACE};
+
+/// How far in blocks from the scripted block a script can read and set blocks
+pub const SCRIPT_REACH: i32 = 2;
+
+/// Module scripts import the host functions from
+pub const HOST_MODULE: &str = "cubizm";
+
+/// Blocks as scripts see them, an `i32` id for every block in the [BlockRegistry], ordered by
+/// name so they stay the same between runs with the same blocks
+#[derive(Resource, Debug, Default)]
+pub struct ScriptBlockIds {
+    handles: Arc<Vec<Handle<Block>>>,
+    ids: HashMap<AssetId<Block>, i32>,
+    names: Arc<HashMap<String, i32>>,
+}
+
+impl ScriptBlockIds {
+    pub fn from_registry(registry: &BlockRegistry) -> Self {
+        let mut blocks: Vec<(&str, &Handle<Block>)> = registry.iter().collect();
+        blocks.sort_by_key(|(name, _)| *name);
+        Self {
+            handles: Arc::new(blocks.iter().map(|(_, block)| (*block).clone()).collect()),
+            ids: (0..)
+                .zip(blocks.iter())
+                .map(|(id, (_, block))| (block.id(), id))
+                .collect(),
+            names: Arc::new(
+                (0..)
+                    .zip(blocks.iter())
+                    .map(|(id, (name, _))| (name.to_string(), id))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn id(&self, block: &Handle<Block>) -> Option<i32> {
+        self.ids.get(&block.id()).copied()
+    }
+
+    pub fn block(&self, id: i32) -> Option<&Handle<Block>> {
+        usize::try_from(id).ok().and_then(|id| self.handles.get(id))
+    }
+}
+
+/// What a script can see of the world while one of its callbacks runs. Scripts only get
+/// copies of the blocks around them, their edits are applied once the callback returns
+#[derive(Debug)]
+pub struct ScriptHost {
+    /// World position of the scripted block
+    pub origin: IVec3,
+    /// Ids of the loaded blocks within [SCRIPT_REACH] of `origin`, with the script's edits
+    blocks: HashMap<IVec3, i32>,
+    names: Arc<HashMap<String, i32>>,
+    /// Blocks the script set, in order
+    pub edits: Vec<(IVec3, Handle<Block>)>,
+    /// Ticks the script scheduled, with their delay in game ticks
+    pub ticks: Vec<(IVec3, u64)>,
+    handles: Arc<Vec<Handle<Block>>>,
+}
+
+impl ScriptHost {
+    /// `get_block` looks up the blocks around `origin` by world position, `None` if unloaded
+    pub fn new(
+        origin: IVec3,
+        ids: &ScriptBlockIds,
+        get_block: impl Fn(IVec3) -> Option<Handle<Block>>,
+    ) -> Self {
+        let reach = -SCRIPT_REACH..=SCRIPT_REACH;
+        let mut blocks = HashMap::new();
+        for x in reach.clone() {
+            for y in reach.clone() {
+                for z in reach.clone() {
+                    let position = origin + IVec3::new(x, y, z);
+                    if let Some(id) = get_block(position).and_then(|block| ids.id(&block)) {
+                        blocks.insert(position, id);
+                    }
+                }
+            }
+        }
+        Self {
+            origin,
+            blocks,
+            names: ids.names.clone(),
+            edits: Vec::new(),
+            ticks: Vec::new(),
+            handles: ids.handles.clone(),
+        }
+    }
+
+    fn in_reach(&self, position: IVec3) -> bool {
+        (position - self.origin).abs().max_element() <= SCRIPT_REACH
+    }
+}
+
+/// `len` bytes of the script's memory from `ptr` as a string
+fn read_string(caller: &Caller<'_, ScriptHost>, ptr: i32, len: i32) -> Option<String> {
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    let mut bytes = vec![0; usize::try_from(len).ok()?];
+    memory
+        .read(caller, usize::try_from(ptr).ok()?, &mut bytes)
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Defines the host functions in [HOST_MODULE]. Positions are world positions, and functions
+/// that can fail return `-1` or `0` instead of trapping:
+/// - `get_block(x, y, z) -> i32`: id of the block, `-1` if unloaded or out of reach
+/// - `set_block(x, y, z, id) -> i32`: `1` if the block will be set
+/// - `schedule_tick(x, y, z, delay: i64) -> i32`: `1` if the block will tick in `delay` game
+///   ticks
+/// - `block_id(ptr, len) -> i32`: id of the block named by the UTF-8 string in the script's
+///   memory, base blocks can leave out the namespace
+/// - `log(ptr, len)`: logs the UTF-8 string in the script's memory
+pub(crate) fn link_host(linker: &mut Linker<ScriptHost>) -> Result<(), wasmi::Error> {
+    linker.func_wrap(
+        HOST_MODULE,
+        "get_block",
+        |caller: Caller<'_, ScriptHost>, x: i32, y: i32, z: i32| -> i32 {
+            let host = caller.data();
+            host.blocks.get(&IVec3::new(x, y, z)).copied().unwrap_or(-1)
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "set_block",
+        |mut caller: Caller<'_, ScriptHost>, x: i32, y: i32, z: i32, id: i32| -> i32 {
+            let host = caller.data_mut();
+            let position = IVec3::new(x, y, z);
+            let Some(block) = usize::try_from(id)
+                .ok()
+                .and_then(|id| host.handles.get(id))
+                .cloned()
+            else {
+                return 0;
+            };
+            if !host.in_reach(position) || !host.blocks.contains_key(&position) {
+                return 0;
+            }
+            host.blocks.insert(position, id);
+            host.edits.push((position, block));
+            1
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "schedule_tick",
+        |mut caller: Caller<'_, ScriptHost>, x: i32, y: i32, z: i32, delay: i64| -> i32 {
+            let host = caller.data_mut();
+            let position = IVec3::new(x, y, z);
+            if !host.in_reach(position) {
+                return 0;
+            }
+            host.ticks.push((position, delay.max(0) as u64));
+            1
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "block_id",
+        |caller: Caller<'_, ScriptHost>, ptr: i32, len: i32| -> i32 {
+            let Some(name) = read_string(&caller, ptr, len) else {
+                return -1;
+            };
+            let names = &caller.data().names;
+            names
+                .get(&name)
+                .or_else(|| names.get(&block_key(BASE_NAMESPACE, &name)))
+                .copied()
+                .unwrap_or(-1)
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "log",
+        |caller: Caller<'_, ScriptHost>, ptr: i32, len: i32| {
+            if let Some(message) = read_string(&caller, ptr, len) {
+                info!("Block script at {}: {message}", caller.data().origin);
+            }
+        },
+    )?;
+    Ok(())
+}
@@ -0,0 +1,250 @@
+use bevy::{prelude::*, utils::HashMap};
+use thiserror::Error;
+use wasmi::{Config, Engine, Linker, Module, Store};
+
+use cubizm_block::definition::Block;
+use cubizm_block::{BlockRegistry, BlockScript};
+use cubizm_chunks::{
+    BlockChanged, BlockInteract, BlockTick, BlockTickSet, BlockTicks, Chunks, VoxelWorld,
+};
+
+pub use host::*;
+
+mod host;
+
+/// The callbacks a [BlockScript] can export, each called with the world position of its block
+/// as three `i32`s. Scripts leave out the ones they don't need
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptCallback {
+    /// `on_place`, after the block was set
+    Place,
+    /// `on_break`, after the block was replaced by another
+    Break,
+    /// `on_tick`, on every [BlockTick] of the block
+    Tick,
+    /// `on_interact`, on every [BlockInteract] with the block
+    Interact,
+}
+
+impl ScriptCallback {
+    pub fn export_name(self) -> &'static str {
+        match self {
+            Self::Place => "on_place",
+            Self::Break => "on_break",
+            Self::Tick => "on_tick",
+            Self::Interact => "on_interact",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ScriptError {
+    #[error(transparent)]
+    Wasm(#[from] wasmi::Error),
+    #[error(transparent)]
+    Trap(#[from] wasmi::core::Trap),
+    #[error("Script is not compiled")]
+    NotCompiled,
+    #[error("Failed to fuel the script: {0}")]
+    Fuel(String),
+}
+
+/// The compiled [BlockScript]s and the host functions they are linked against, see
+/// [link_host]
+#[derive(Resource)]
+pub struct ScriptRuntime {
+    engine: Engine,
+    linker: Linker<ScriptHost>,
+    modules: HashMap<AssetId<BlockScript>, Module>,
+    /// See [ScriptPlugin::fuel]
+    fuel: u64,
+}
+
+impl ScriptRuntime {
+    pub fn new(fuel: u64) -> Result<Self, ScriptError> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let mut linker = Linker::new(&engine);
+        link_host(&mut linker)?;
+        Ok(Self {
+            engine,
+            linker,
+            modules: HashMap::new(),
+            fuel,
+        })
+    }
+
+    pub fn compile(
+        &mut self,
+        id: AssetId<BlockScript>,
+        script: &BlockScript,
+    ) -> Result<(), ScriptError> {
+        let module = Module::new(&self.engine, &script.bytes[..])?;
+        self.modules.insert(id, module);
+        Ok(())
+    }
+
+    pub fn is_compiled(&self, id: AssetId<BlockScript>) -> bool {
+        self.modules.contains_key(&id)
+    }
+
+    /// Runs `callback` of the script in a fresh instance, so scripts keep no state between
+    /// calls. Returns the `host` with the edits the script made
+    pub fn call(
+        &self,
+        script: AssetId<BlockScript>,
+        callback: ScriptCallback,
+        host: ScriptHost,
+    ) -> Result<ScriptHost, ScriptError> {
+        let module = self.modules.get(&script).ok_or(ScriptError::NotCompiled)?;
+        let origin = host.origin;
+        let mut store = Store::new(&self.engine, host);
+        store
+            .add_fuel(self.fuel)
+            .map_err(|err| ScriptError::Fuel(err.to_string()))?;
+        let instance = self
+            .linker
+            .instantiate(&mut store, module)?
+            .start(&mut store)?;
+        if let Ok(function) =
+            instance.get_typed_func::<(i32, i32, i32), ()>(&store, callback.export_name())
+        {
+            function.call(&mut store, (origin.x, origin.y, origin.z))?;
+        }
+        Ok(store.into_data())
+    }
+}
+
+/// Callbacks waiting to run, collected apart from [run_block_scripts] as the edits of the
+/// scripts send [BlockChanged] themselves
+#[derive(Resource, Debug, Default)]
+struct PendingCallbacks(Vec<(IVec3, Handle<Block>, ScriptCallback)>);
+
+fn update_block_ids(registry: Option<Res<BlockRegistry>>, mut ids: ResMut<ScriptBlockIds>) {
+    if let Some(registry) = registry.filter(|registry| registry.is_changed()) {
+        *ids = ScriptBlockIds::from_registry(&registry);
+    }
+}
+
+fn compile_scripts(
+    mut events: EventReader<AssetEvent<BlockScript>>,
+    scripts: Res<Assets<BlockScript>>,
+    mut runtime: ResMut<ScriptRuntime>,
+) {
+    for event in events.read() {
+        match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                let Some(script) = scripts.get(*id) else {
+                    continue;
+                };
+                if let Err(err) = runtime.compile(*id, script) {
+                    warn!("Failed to compile block script {id:?}: {err}");
+                }
+            }
+            AssetEvent::Removed { id } => {
+                runtime.modules.remove(id);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_callbacks(
+    mut changed: EventReader<BlockChanged>,
+    mut ticks: EventReader<BlockTick>,
+    mut interactions: EventReader<BlockInteract>,
+    mut pending: ResMut<PendingCallbacks>,
+) {
+    for event in changed.read() {
+        if event.old == event.new {
+            continue;
+        }
+        pending
+            .0
+            .push((event.world_pos, event.old.clone(), ScriptCallback::Break));
+        pending
+            .0
+            .push((event.world_pos, event.new.clone(), ScriptCallback::Place));
+    }
+    pending.0.extend(
+        ticks
+            .read()
+            .map(|tick| (tick.position, tick.block.clone(), ScriptCallback::Tick)),
+    );
+    pending.0.extend(interactions.read().map(|interaction| {
+        (
+            interaction.position,
+            interaction.block.clone(),
+            ScriptCallback::Interact,
+        )
+    }));
+}
+
+fn run_block_scripts(
+    blocks: Res<Assets<Block>>,
+    runtime: Res<ScriptRuntime>,
+    ids: Res<ScriptBlockIds>,
+    mut pending: ResMut<PendingCallbacks>,
+    mut ticks: ResMut<BlockTicks>,
+    mut world: VoxelWorld,
+) {
+    for (position, block, callback) in std::mem::take(&mut pending.0) {
+        let Some(script) = blocks.get(&block).and_then(Block::script) else {
+            continue;
+        };
+        let host = ScriptHost::new(position, &ids, |position| world.get_block(position));
+        let host = match runtime.call(script.id(), callback, host) {
+            Ok(host) => host,
+            Err(err) => {
+                warn!(
+                    "Block script at {position} failed in {}: {err}",
+                    callback.export_name()
+                );
+                continue;
+            }
+        };
+        for (position, block) in host.edits {
+            if let Err(err) = world.set_block(position, block) {
+                warn!("Block script failed to set block at {position}: {err}");
+            }
+        }
+        for (position, delay) in host.ticks {
+            ticks.schedule(position, delay);
+        }
+    }
+}
+
+/// Runs the WASM [BlockScript]s of blocks when they are placed, broken, ticked or
+/// interacted with, see [ScriptCallback]. Scripts reach the world through the host functions
+/// of [link_host]
+pub struct ScriptPlugin {
+    /// Instructions a callback may run before it is stopped, so a broken script can't hang
+    /// the game
+    pub fuel: u64,
+}
+
+impl Default for ScriptPlugin {
+    fn default() -> Self {
+        Self { fuel: 1_000_000 }
+    }
+}
+
+impl Plugin for ScriptPlugin {
+    fn build(&self, app: &mut App) {
+        let runtime = ScriptRuntime::new(self.fuel).expect("Failed to link the script host");
+        app.insert_resource(runtime)
+            .init_resource::<ScriptBlockIds>()
+            .init_resource::<PendingCallbacks>()
+            .add_systems(
+                Update,
+                (
+                    update_block_ids,
+                    compile_scripts,
+                    collect_callbacks.after(BlockTickSet),
+                    run_block_scripts.run_if(resource_exists::<Chunks>),
+                )
+                    .chain(),
+            );
+    }
+}
@@ -1,13 +1,32 @@
 use bevy::asset::ron;
 
 use block_mesh::VoxelVisibility::Opaque;
-use cubizm_block::definition::{SerializedBlock, SerializedVoxelBlock};
+use cubizm_block::definition::{
+    BiomeTint, RenderLayer, SerializedBlock, SerializedBlockSounds, SerializedVoxelBlock,
+    DEFAULT_HARDNESS,
+};
+use cubizm_block::BlockShape;
 
 fn main() {
     let block = SerializedBlock::SerializedVoxel(SerializedVoxelBlock {
         name: "Test".to_string(),
         texture: Some("blocks/textures/test.jpg".to_string()),
         visibility: Opaque,
+        sounds: SerializedBlockSounds::default(),
+        emissive: None,
+        render_layer: RenderLayer::Opaque,
+        hardness: DEFAULT_HARDNESS,
+        gravity: false,
+        fluid: None,
+        ticks: false,
+        script: None,
+        properties: Vec::new(),
+        shape: BlockShape::Full,
+        model: None,
+        tint: BiomeTint::None,
+        normal_map: None,
+        metallic_roughness_map: None,
+        emissive_map: None,
     });
     std::fs::write(
         "./assets/blocks/info/test.block",
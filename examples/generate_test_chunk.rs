@@ -6,9 +6,9 @@ use cubizm_chunks::{ChunkShape, SerializedChunk, CHUNK_SIZE};
 
 fn main() {
     let mut chunk = SerializedChunk::default();
-    for x in 1..CHUNK_SIZE + 1 {
-        for y in 1..CHUNK_SIZE + 1 {
-            for z in 1..CHUNK_SIZE + 1 {
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
                 chunk.blocks[ChunkShape::linearize([x, y, z]) as usize] =
                     "blocks/info/dirt.block".to_string();
             }
@@ -0,0 +1,30 @@
+use bevy::app::AppExit;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+
+use cubizm_block::BlockPlugin;
+use cubizm_chunks::{Chunks, ChunksPlugin};
+use cubizm_core::{AppState, Cubizm};
+
+/// Loads the blocks and the default world without a window or renderer, as a dedicated server
+/// would, then exits once the chunks are loaded
+fn main() {
+    App::new()
+        .add_plugins((MinimalPlugins, AssetPlugin::default(), LogPlugin::default()))
+        .add_plugins(BlockPlugin {
+            headless: true,
+            ..default()
+        })
+        .add_plugins(ChunksPlugin {
+            headless: true,
+            ..default()
+        })
+        .add_plugins(Cubizm)
+        .add_systems(OnEnter(AppState::ChunksLoaded), exit_when_loaded)
+        .run();
+}
+
+fn exit_when_loaded(chunks: Res<Chunks>, mut exit: EventWriter<AppExit>) {
+    info!("Loaded {} chunks", chunks.chunks.len());
+    exit.send(AppExit);
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+use cubizm_chunks::{import_region, AnvilMapping};
+
+/// Imports Minecraft region files into a cubizm chunk directory:
+/// `cargo run --example import_anvil --features anvil -- <mapping.ron> <output directory> <region.mca>...`
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let (Some(mapping), Some(directory)) = (args.next(), args.next()) else {
+        eprintln!("Usage: import_anvil <mapping.ron> <output directory> <region.mca>...");
+        std::process::exit(1);
+    };
+    let mapping = match AnvilMapping::load(&mapping) {
+        Ok(mapping) => mapping,
+        Err(err) => {
+            eprintln!("Failed to read mapping {mapping}: {err}");
+            std::process::exit(1);
+        }
+    };
+    let directory = PathBuf::from(directory);
+    for region in args {
+        match import_region(&region, &mapping, &directory) {
+            Ok(import) => {
+                println!("Imported {} chunks from {region}", import.chunks.len());
+                for block in import.unmapped {
+                    println!("  {block} is not mapped, used {}", mapping.fallback);
+                }
+            }
+            Err(err) => eprintln!("Failed to import {region}: {err}"),
+        }
+    }
+}
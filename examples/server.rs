@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+
+use cubizm_block::BlockPlugin;
+use cubizm_chunks::ChunksPlugin;
+use cubizm_core::Cubizm;
+use cubizm_net::{NetServer, NetServerPlugin, TcpServerTransport};
+
+/// Dedicated server serving the default world over TCP, on the address given as the first
+/// argument or `0.0.0.0:25570`. With `--admins` every client can run server commands
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let admins = args.iter().any(|arg| arg == "--admins");
+    args.retain(|arg| arg != "--admins");
+    let address = args
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| "0.0.0.0:25570".to_string());
+    let transport = TcpServerTransport::bind(&address).expect("Failed to bind the server");
+
+    App::new()
+        .add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1.0 / 60.0,
+            ))),
+            AssetPlugin::default(),
+            LogPlugin::default(),
+        ))
+        .add_plugins(BlockPlugin {
+            headless: true,
+            ..default()
+        })
+        .add_plugins(ChunksPlugin {
+            headless: true,
+            ..default()
+        })
+        .add_plugins((Cubizm, NetServerPlugin))
+        .insert_resource(NetServer::new(transport).with_admins_by_default(admins))
+        .run();
+}
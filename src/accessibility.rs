@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Whether an action stays active while its button is held or flips on each press
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ButtonMode {
+    #[default]
+    Hold,
+    Toggle,
+}
+
+/// Player facing accessibility options, see [AccessibilityPlugin]
+#[derive(Resource, Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AccessibilitySettings {
+    /// Outline colour of the targeted block
+    pub highlight_color: Color,
+    /// Outline width of the targeted block in pixels
+    pub highlight_thickness: f32,
+    /// Multiplier on the size of every UI node
+    pub ui_scale: f32,
+    /// Disables camera bob and shake effects
+    pub reduced_motion: bool,
+    pub sprint: ButtonMode,
+    pub sneak: ButtonMode,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            // Yellow from the Okabe-Ito palette, distinguishable under all common colour
+            // vision deficiencies and against most terrain
+            highlight_color: Color::rgb_u8(240, 228, 66),
+            highlight_thickness: 2.,
+            ui_scale: 1.,
+            reduced_motion: false,
+            sprint: ButtonMode::Hold,
+            sneak: ButtonMode::Hold,
+        }
+    }
+}
+
+fn apply_ui_scale(settings: Res<AccessibilitySettings>, mut ui_scale: ResMut<UiScale>) {
+    if settings.is_changed() {
+        ui_scale.0 = settings.ui_scale;
+    }
+}
+
+/// Provides [AccessibilitySettings] and applies the ones not owned by another plugin
+pub struct AccessibilityPlugin;
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AccessibilitySettings>()
+            .add_systems(Update, apply_ui_scale);
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use cubizm_core::GameTime;
+
+use super::AmbientConditions;
+
+/// Coarse time of day used to pick ambient loops
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeOfDay {
+    #[default]
+    Day,
+    Night,
+}
+
+/// Night while the sun is below the horizon
+impl From<&GameTime> for TimeOfDay {
+    fn from(time: &GameTime) -> Self {
+        match time.daylight() > 0. {
+            true => Self::Day,
+            false => Self::Night,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedAmbientLoop {
+    pub track: String,
+    pub biome: Option<String>,
+    pub time_of_day: Option<TimeOfDay>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SerializedAudioSettings {
+    pub music_volume: f32,
+    pub ambient_volume: f32,
+    pub crossfade_seconds: f32,
+    pub playlist: Vec<String>,
+    pub ambient: Vec<SerializedAmbientLoop>,
+}
+
+/// An ambient track, optionally restricted to a biome and/or time of day
+#[derive(Clone, Debug)]
+pub struct AmbientLoop {
+    pub track: Handle<AudioSource>,
+    pub biome: Option<String>,
+    pub time_of_day: Option<TimeOfDay>,
+}
+
+#[derive(Clone, Debug, Asset, TypePath)]
+pub struct AudioSettings {
+    pub music_volume: f32,
+    pub ambient_volume: f32,
+    pub crossfade_seconds: f32,
+    pub playlist: Vec<Handle<AudioSource>>,
+    pub ambient: Vec<AmbientLoop>,
+}
+
+impl AmbientLoop {
+    /// How specifically this loop matches the conditions, `None` if it does not apply at all
+    fn score(&self, conditions: &AmbientConditions) -> Option<u8> {
+        let biome = match &self.biome {
+            Some(biome) if conditions.biome.as_ref() == Some(biome) => 2,
+            Some(_) => return None,
+            None => 0,
+        };
+        let time_of_day = match self.time_of_day {
+            Some(time_of_day) if time_of_day == conditions.time_of_day => 1,
+            Some(_) => return None,
+            None => 0,
+        };
+        Some(biome + time_of_day)
+    }
+}
+
+impl AudioSettings {
+    /// Picks the most specific ambient loop for the given conditions
+    pub fn ambient_for(&self, conditions: &AmbientConditions) -> Option<&Handle<AudioSource>> {
+        self.ambient
+            .iter()
+            .filter_map(|ambient| ambient.score(conditions).map(|score| (score, ambient)))
+            .max_by_key(|(score, _)| *score)
+            .map(|(_, ambient)| &ambient.track)
+    }
+}
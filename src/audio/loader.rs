@@ -0,0 +1,65 @@
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext},
+    utils::BoxedFuture,
+};
+use thiserror::Error;
+
+use super::definition::{AmbientLoop, AudioSettings, SerializedAudioSettings};
+
+#[derive(Default)]
+pub struct AudioSettingsLoader;
+
+#[derive(Debug, Error)]
+pub enum AudioSettingsLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for AudioSettingsLoader {
+    type Asset = AudioSettings;
+    type Settings = ();
+    type Error = AudioSettingsLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let ron: SerializedAudioSettings = ron::de::from_bytes(&bytes)?;
+
+            let playlist = ron
+                .playlist
+                .iter()
+                .map(|path| load_context.load(path))
+                .collect();
+
+            let ambient = ron
+                .ambient
+                .into_iter()
+                .map(|ambient| AmbientLoop {
+                    track: load_context.load(ambient.track),
+                    biome: ambient.biome,
+                    time_of_day: ambient.time_of_day,
+                })
+                .collect();
+
+            Ok(AudioSettings {
+                music_volume: ron.music_volume,
+                ambient_volume: ron.ambient_volume,
+                crossfade_seconds: ron.crossfade_seconds,
+                playlist,
+                ambient,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["audio"]
+    }
+}
@@ -0,0 +1,275 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+
+use cubizm_chunks::BiomeLookup;
+use cubizm_core::{point_to_block, GameTime};
+use cubizm_player::Player;
+
+pub use definition::*;
+use loader::AudioSettingsLoader;
+
+mod definition;
+mod loader;
+
+/// What the player is currently surrounded by, used to pick the ambient loop.
+/// Follows the player's biome and the [GameTime], tracks crossfade on change.
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct AmbientConditions {
+    pub biome: Option<String>,
+    pub time_of_day: TimeOfDay,
+}
+
+/// Player volume preferences, multiplied into the volumes from [AudioSettings]
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct AudioVolumes {
+    pub music: f32,
+    pub ambient: f32,
+}
+
+impl Default for AudioVolumes {
+    fn default() -> Self {
+        Self {
+            music: 1.,
+            ambient: 1.,
+        }
+    }
+}
+
+/// Crossfades the current music track into the next one in the playlist
+#[derive(Event, Debug, Default)]
+pub struct SkipTrack;
+
+#[derive(Resource, Default)]
+struct AudioSettingsHandle(Handle<AudioSettings>);
+
+#[derive(Resource, Default)]
+struct Playlist {
+    next: usize,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    Music,
+    Ambient,
+}
+
+/// Moves the volume of a playing track towards `target`, despawning it once silent if `despawn` is set
+#[derive(Component, Debug)]
+struct Fade {
+    target: f32,
+    despawn: bool,
+}
+
+impl Fade {
+    fn to(target: f32) -> Self {
+        Self {
+            target,
+            despawn: false,
+        }
+    }
+
+    fn out() -> Self {
+        Self {
+            target: 0.,
+            despawn: true,
+        }
+    }
+}
+
+fn load_audio_settings(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AudioSettingsHandle(
+        asset_server.load("audio/settings.audio"),
+    ));
+}
+
+fn spawn_track(
+    commands: &mut Commands,
+    source: Handle<AudioSource>,
+    channel: Channel,
+    settings: PlaybackSettings,
+    volume: f32,
+) {
+    commands.spawn((
+        AudioBundle {
+            source,
+            settings: settings.with_volume(Volume::ZERO),
+        },
+        channel,
+        Fade::to(volume),
+    ));
+}
+
+fn update_ambient_conditions(
+    player: Query<&Transform, With<Player>>,
+    biomes: BiomeLookup,
+    time: Option<Res<GameTime>>,
+    mut conditions: ResMut<AmbientConditions>,
+) {
+    let biome = player
+        .get_single()
+        .ok()
+        .and_then(|transform| biomes.biome(point_to_block(transform.translation).xz()))
+        .map(|biome| biome.name.clone());
+    let time_of_day = time.map_or(conditions.time_of_day, |time| TimeOfDay::from(&*time));
+    conditions.set_if_neq(AmbientConditions { biome, time_of_day });
+}
+
+fn update_ambient(
+    mut commands: Commands,
+    conditions: Res<AmbientConditions>,
+    settings_handle: Res<AudioSettingsHandle>,
+    settings: Res<Assets<AudioSettings>>,
+    mut events: EventReader<AssetEvent<AudioSettings>>,
+    tracks: Query<(Entity, &Handle<AudioSource>, &Channel, &Fade)>,
+) {
+    let settings_changed = events.read().fold(false, |changed, event| {
+        changed
+            || event.is_loaded_with_dependencies(&settings_handle.0)
+            || event.is_modified(&settings_handle.0)
+    });
+    if !settings_changed && !conditions.is_changed() {
+        return;
+    }
+    let Some(settings) = settings.get(&settings_handle.0) else {
+        return;
+    };
+
+    let wanted = settings.ambient_for(&conditions);
+    let mut playing = false;
+    for (entity, track, channel, fade) in tracks.iter() {
+        if *channel != Channel::Ambient || fade.despawn {
+            continue;
+        }
+        if Some(track) == wanted {
+            playing = true;
+            commands
+                .entity(entity)
+                .insert(Fade::to(settings.ambient_volume));
+        } else {
+            commands.entity(entity).insert(Fade::out());
+        }
+    }
+
+    if let (false, Some(track)) = (playing, wanted) {
+        spawn_track(
+            &mut commands,
+            track.clone(),
+            Channel::Ambient,
+            PlaybackSettings::LOOP,
+            settings.ambient_volume,
+        );
+    }
+}
+
+fn advance_playlist(
+    mut commands: Commands,
+    mut playlist: ResMut<Playlist>,
+    mut skip: EventReader<SkipTrack>,
+    settings_handle: Res<AudioSettingsHandle>,
+    settings: Res<Assets<AudioSettings>>,
+    tracks: Query<(Entity, &Channel, &Fade, Option<&AudioSink>)>,
+) {
+    let Some(settings) = settings.get(&settings_handle.0) else {
+        return;
+    };
+    let skip = skip.read().count() > 0;
+
+    let mut playing = false;
+    for (entity, channel, fade, sink) in tracks.iter() {
+        if *channel != Channel::Music || fade.despawn {
+            continue;
+        }
+        match sink {
+            Some(sink) if sink.empty() => commands.entity(entity).despawn(),
+            _ if skip => {
+                commands.entity(entity).insert(Fade::out());
+            }
+            _ => {
+                playing = true;
+                if fade.target != settings.music_volume {
+                    commands
+                        .entity(entity)
+                        .insert(Fade::to(settings.music_volume));
+                }
+            }
+        }
+    }
+
+    if playing || settings.playlist.is_empty() {
+        return;
+    }
+
+    let track = settings.playlist[playlist.next % settings.playlist.len()].clone();
+    playlist.next = (playlist.next + 1) % settings.playlist.len();
+    spawn_track(
+        &mut commands,
+        track,
+        Channel::Music,
+        PlaybackSettings::ONCE,
+        settings.music_volume,
+    );
+}
+
+fn fade_tracks(
+    mut commands: Commands,
+    time: Res<Time>,
+    global_volume: Res<GlobalVolume>,
+    volumes: Res<AudioVolumes>,
+    settings_handle: Res<AudioSettingsHandle>,
+    settings: Res<Assets<AudioSettings>>,
+    tracks: Query<(Entity, &AudioSink, &Channel, &Fade)>,
+) {
+    let crossfade = settings
+        .get(&settings_handle.0)
+        .map_or(1., |settings| settings.crossfade_seconds);
+    let step = if crossfade > 0. {
+        time.delta_seconds() / crossfade
+    } else {
+        f32::INFINITY
+    };
+
+    for (entity, sink, channel, fade) in tracks.iter() {
+        let channel_volume = match channel {
+            Channel::Music => volumes.music,
+            Channel::Ambient => volumes.ambient,
+        };
+        let target = fade.target * channel_volume * global_volume.volume.get();
+        let volume = sink.volume();
+        let volume = if volume < target {
+            (volume + step).min(target)
+        } else {
+            (volume - step).max(target)
+        };
+        sink.set_volume(volume);
+
+        if fade.despawn && volume <= 0. {
+            sink.stop();
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Plays the music playlist and the ambient loop matching [AmbientConditions],
+/// configured by the `audio/settings.audio` asset
+pub struct AmbientAudioPlugin;
+impl Plugin for AmbientAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<AudioSettings>()
+            .init_asset_loader::<AudioSettingsLoader>()
+            .init_resource::<AmbientConditions>()
+            .init_resource::<AudioVolumes>()
+            .init_resource::<Playlist>()
+            .add_event::<SkipTrack>()
+            .add_systems(Startup, load_audio_settings)
+            .add_systems(
+                Update,
+                (
+                    update_ambient_conditions,
+                    update_ambient,
+                    advance_playlist,
+                    fade_tracks,
+                )
+                    .chain(),
+            );
+    }
+}
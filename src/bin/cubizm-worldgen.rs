@@ -0,0 +1,152 @@
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+use bevy::app::AppExit;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+
+use cubizm_block::{BlockPlugin, BlockRegistry};
+use cubizm_chunks::{
+    chunk_file_name, write_chunk, ActiveWorld, Biome, ChunksPlugin, ChunksPluginSettings,
+    GeneratorSettings, Schematic, StructurePass, WorldBiomes, WorldGeneration, WorldHeightmap,
+    WorldManifest,
+};
+use cubizm_core::{point_to_block, world_to_chunk, AppState, Cubizm};
+
+const USAGE: &str =
+    "Usage: cubizm-worldgen --seed <seed> --radius <chunks> --output <directory> [--world <path>]";
+
+/// What to generate, from the command line
+#[derive(Resource, Debug, Clone)]
+struct WorldgenArgs {
+    seed: u64,
+    /// Chunks generated around the spawn chunk horizontally
+    radius: u32,
+    output: PathBuf,
+    /// Asset path of the [WorldManifest] whose generator is used
+    world: String,
+}
+
+impl WorldgenArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let (mut seed, mut radius, mut output) = (None, None, None);
+        let mut world = ChunksPluginSettings::default().world_path;
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+            match flag.as_str() {
+                "--seed" => seed = Some(value.parse().map_err(|_| "Invalid seed")?),
+                "--radius" => radius = Some(value.parse().map_err(|_| "Invalid radius")?),
+                "--output" => output = Some(PathBuf::from(value)),
+                "--world" => world = value,
+                _ => return Err(format!("Unknown argument {flag}")),
+            }
+        }
+        Ok(Self {
+            seed: seed.ok_or("Missing --seed")?,
+            radius: radius.ok_or("Missing --radius")?,
+            output: output.ok_or("Missing --output")?,
+            world,
+        })
+    }
+}
+
+/// Generates every chunk within a radius of a world's spawn ahead of time and writes them as
+/// `.chunkb` files, without a window or renderer
+fn main() {
+    let args = match WorldgenArgs::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            std::process::exit(1);
+        }
+    };
+    App::new()
+        .add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            ImagePlugin::default(),
+            LogPlugin::default(),
+        ))
+        .add_plugins(BlockPlugin {
+            headless: true,
+            ..default()
+        })
+        .add_plugins(ChunksPlugin {
+            headless: true,
+            settings: ChunksPluginSettings {
+                world_path: args.world.clone(),
+                ..default()
+            },
+            ..default()
+        })
+        .add_plugins(Cubizm)
+        .insert_resource(args)
+        .add_systems(OnEnter(AppState::ChunksLoaded), generate_world)
+        .run();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_world(
+    args: Res<WorldgenArgs>,
+    world: Res<ActiveWorld>,
+    manifests: Res<Assets<WorldManifest>>,
+    registry: Res<BlockRegistry>,
+    biomes: Res<WorldBiomes>,
+    biome_assets: Res<Assets<Biome>>,
+    heightmap: Res<WorldHeightmap>,
+    images: Res<Assets<Image>>,
+    structures: Res<StructurePass>,
+    schematics: Res<Assets<Schematic>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    exit.send(AppExit);
+    let Some(manifest) = manifests.get(&world.0) else {
+        error!("The world manifest {} failed to load", args.world);
+        return;
+    };
+    let settings = GeneratorSettings {
+        radius: args.radius,
+        ..manifest.generator.clone()
+    };
+    let generation = WorldGeneration {
+        seed: args.seed,
+        settings: &settings,
+        registry: &registry,
+        biomes: settings.kind.biome_map(args.seed, &biomes.0, &biome_assets),
+        heightmap: heightmap.0.as_ref().and_then(|image| images.get(image)),
+        schematics: &schematics,
+    };
+    let spawn_chunk = world_to_chunk(point_to_block(manifest.spawn));
+    let positions = settings.chunk_positions(spawn_chunk);
+    info!("Generating {} chunks of {}", positions.len(), manifest.name);
+    let mut structures = StructurePass::new(structures.schematics().to_vec());
+    let chunks = generation.generate(&positions, &mut structures);
+
+    // Serializing is as slow as generating, spread it over the threads as well
+    let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let batch = chunks.len().div_ceil(threads).max(1);
+    let output = &args.output;
+    let written: usize = std::thread::scope(|scope| {
+        let workers: Vec<_> = chunks
+            .chunks(batch)
+            .map(|batch| {
+                scope.spawn(move || {
+                    let mut written = 0;
+                    for chunk in batch {
+                        let path = output.join(chunk_file_name(chunk.position));
+                        match write_chunk(chunk, &path) {
+                            Ok(()) => written += 1,
+                            Err(err) => error!("Failed to write {}: {err}", path.display()),
+                        }
+                    }
+                    written
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or_default())
+            .sum()
+    });
+    info!("Wrote {written} chunks to {}", output.display());
+}
@@ -0,0 +1,94 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+
+use cubizm_block::definition::Block;
+use cubizm_chunks::BlockChanged;
+use cubizm_player::Player;
+
+/// Configuration for [BlockSoundsPlugin]
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct BlockSoundSettings {
+    pub volume: f32,
+    /// Most sounds started in a frame, so large edits like `fill` don't play thousands at once
+    pub max_per_frame: usize,
+}
+
+impl Default for BlockSoundSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.,
+            max_per_frame: 8,
+        }
+    }
+}
+
+/// Hears the block sounds from the player's camera
+fn follow_player_with_listener(
+    mut commands: Commands,
+    players: Query<Entity, Added<Player>>,
+    mut removed: RemovedComponents<Player>,
+) {
+    for entity in removed.read() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<SpatialListener>();
+        }
+    }
+    for entity in players.iter() {
+        commands.entity(entity).insert(SpatialListener::new(0.3));
+    }
+}
+
+/// Plays the break sound of the replaced block and the place sound of the new one at every
+/// [BlockChanged]
+fn play_block_sounds(
+    mut commands: Commands,
+    settings: Res<BlockSoundSettings>,
+    mut changes: EventReader<BlockChanged>,
+    blocks: Res<Assets<Block>>,
+) {
+    let sounds = changes
+        .read()
+        .filter(|change| change.old != change.new)
+        .flat_map(|change| {
+            let destroy = blocks
+                .get(&change.old)
+                .and_then(|block| block.sounds().destroy.clone());
+            let place = blocks
+                .get(&change.new)
+                .and_then(|block| block.sounds().place.clone());
+            [destroy, place]
+                .into_iter()
+                .flatten()
+                .map(move |sound| (change.world_pos, sound))
+        })
+        .take(settings.max_per_frame)
+        .collect::<Vec<_>>();
+    for (block, source) in sounds {
+        // Blocks span `block..block + 1`
+        let position = block.as_vec3() + Vec3::splat(0.5);
+        commands.spawn((
+            AudioBundle {
+                source,
+                settings: PlaybackSettings::DESPAWN
+                    .with_spatial(true)
+                    .with_volume(Volume::new(settings.volume)),
+            },
+            SpatialBundle::from_transform(Transform::from_translation(position)),
+        ));
+    }
+}
+
+/// Plays the [BlockSounds](cubizm_block::definition::BlockSounds) of placed and broken blocks
+/// where they are, quieter the further they are from the player
+#[derive(Default)]
+pub struct BlockSoundsPlugin {
+    pub settings: BlockSoundSettings,
+}
+
+impl Plugin for BlockSoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings)
+            .add_event::<BlockChanged>()
+            .add_systems(Update, (follow_player_with_listener, play_block_sounds));
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::VecDeque;
+
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+use cubizm_block::definition::Block;
+use cubizm_block::BlockRegistry;
+use cubizm_chunks::{ActiveWorld, BlockAabb, ChunkGizmos, Chunks, VoxelWorld, WorldManifest};
+#[cfg(feature = "rhai")]
+use cubizm_core::point_to_block;
+use cubizm_core::{split_command, CommandError, WorldCommand, MAX_FILL_VOLUME};
+#[cfg(feature = "rhai")]
+use cubizm_player::Player;
+use cubizm_player::{PlayerInput, PlayerSet};
+#[cfg(feature = "rhai")]
+use cubizm_rhai::{ScriptCommandOutput, ScriptCommands};
+
+use crate::input::{Action, ActionInput, PlayerInputSet};
+use crate::localization::Localizer;
+use crate::teleport::Teleport;
+
+/// Lines of output kept in the console, older ones are dropped
+const MAX_LOG_LINES: usize = 12;
+
+/// State of the developer console, see [DeveloperConsolePlugin]
+#[derive(Resource, Debug, Default)]
+pub struct DeveloperConsole {
+    pub open: bool,
+    /// The line being typed
+    input: String,
+    log: VecDeque<String>,
+    /// Submitted lines waiting for the world to load
+    submitted: Vec<String>,
+}
+
+impl DeveloperConsole {
+    /// Adds a line to the output of the console
+    pub fn print(&mut self, line: impl Into<String>) {
+        if self.log.len() == MAX_LOG_LINES {
+            self.log.pop_front();
+        }
+        self.log.push_back(line.into());
+    }
+
+    /// Runs `line` as if it was typed into the console
+    pub fn submit(&mut self, line: impl Into<String>) {
+        self.submitted.push(line.into());
+    }
+}
+
+/// A line typed into the [DeveloperConsole]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    Help,
+    /// `setblock`, `fill` and `tp`, shared with the commands of the server
+    World(WorldCommand),
+    /// `seed` prints the seed of the active world
+    Seed,
+    /// `reload-atlas` reads every block and its texture from disk again, rebuilding the
+    /// [BlockAtlas](cubizm_block::BlockAtlas) once they load
+    ReloadAtlas,
+    /// `chunk-borders` toggles [ChunkGizmos::borders]
+    ChunkBorders,
+    /// `chunk-padding` toggles [ChunkGizmos::padding]
+    ChunkPadding,
+}
+
+impl ConsoleCommand {
+    /// Parses a console line, a leading `/` is allowed as in chat. `None` for empty lines
+    pub fn parse(line: &str) -> Option<Result<Self, CommandError>> {
+        let (name, arguments) = split_command(line)?;
+        if let Some(command) = WorldCommand::parse(name, &arguments) {
+            return Some(command.map(Self::World));
+        }
+        Some(match (name, arguments.as_slice()) {
+            ("help", _) => Ok(Self::Help),
+            ("seed", []) => Ok(Self::Seed),
+            ("seed", _) => Err(CommandError::Usage("seed")),
+            ("reload-atlas", []) => Ok(Self::ReloadAtlas),
+            ("reload-atlas", _) => Err(CommandError::Usage("reload-atlas")),
+            ("chunk-borders", []) => Ok(Self::ChunkBorders),
+            ("chunk-borders", _) => Err(CommandError::Usage("chunk-borders")),
+            ("chunk-padding", []) => Ok(Self::ChunkPadding),
+            ("chunk-padding", _) => Err(CommandError::Usage("chunk-padding")),
+            (name, _) => Err(CommandError::Unknown(name.to_string())),
+        })
+    }
+}
+
+/// The [CommandError] in the active locale
+fn localize_error(localizer: &Localizer, err: &CommandError) -> String {
+    match err {
+        CommandError::Unknown(command) => {
+            localizer.format("console.error.unknown", &[("command", command)])
+        }
+        CommandError::Usage(usage) => localizer.format("console.error.usage", &[("usage", usage)]),
+        CommandError::UnknownBlock(block) => {
+            localizer.format("console.error.unknown_block", &[("block", block)])
+        }
+        CommandError::FillTooLarge(volume) => localizer.format(
+            "console.error.fill_too_large",
+            &[("volume", volume), ("max", &MAX_FILL_VOLUME)],
+        ),
+        CommandError::NotAdmin => err.to_string(),
+    }
+}
+
+#[derive(Component, Debug)]
+struct ConsoleText;
+
+fn setup_console(mut commands: Commands) {
+    let text = TextBundle::from_section(
+        "",
+        TextStyle {
+            font_size: 18.,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        bottom: Val::Px(8.),
+        left: Val::Px(8.),
+        right: Val::Px(8.),
+        ..default()
+    })
+    .with_background_color(Color::rgba(0., 0., 0., 0.6));
+    commands.spawn((
+        TextBundle {
+            visibility: Visibility::Hidden,
+            ..text
+        },
+        ConsoleText,
+    ));
+}
+
+/// Opens and closes the console and edits the line being typed, submitting it on enter
+fn edit_console(
+    input: ActionInput,
+    mut console: ResMut<DeveloperConsole>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut keys: EventReader<KeyboardInput>,
+) {
+    let toggled = input.just_pressed(Action::ToggleConsole);
+    if toggled {
+        console.open = !console.open;
+    }
+    let pressed: Vec<KeyCode> = keys
+        .read()
+        .filter(|key| key.state == ButtonState::Pressed)
+        .map(|key| key.key_code)
+        .collect();
+    // The key opening the console types a character as well
+    if !console.open || toggled {
+        characters.clear();
+        return;
+    }
+    for character in characters.read() {
+        console
+            .input
+            .extend(character.char.chars().filter(|c| !c.is_control()));
+    }
+    for key in pressed {
+        match key {
+            KeyCode::Backspace => {
+                console.input.pop();
+            }
+            KeyCode::Escape => console.open = false,
+            KeyCode::Enter | KeyCode::NumpadEnter => {
+                let line = std::mem::take(&mut console.input);
+                console.print(format!("> {line}"));
+                console.submit(line);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keeps the player still while typing, and swallows the keys closing the console
+fn clear_player_input(console: Res<DeveloperConsole>, mut player: ResMut<PlayerInput>) {
+    if console.open || console.is_changed() {
+        *player = PlayerInput::default();
+    }
+}
+
+/// Runs the submitted lines against the loaded world
+#[allow(clippy::too_many_arguments)]
+fn run_console_commands(
+    mut console: ResMut<DeveloperConsole>,
+    mut world: VoxelWorld,
+    registry: Option<Res<BlockRegistry>>,
+    active_world: Option<Res<ActiveWorld>>,
+    manifests: Res<Assets<WorldManifest>>,
+    asset_server: Res<AssetServer>,
+    blocks: Res<Assets<Block>>,
+    mut teleports: EventWriter<Teleport>,
+    mut gizmos: ResMut<ChunkGizmos>,
+    localizer: Localizer,
+    #[cfg(feature = "rhai")] mut scripts: ScriptCommands,
+    #[cfg(feature = "rhai")] player: Query<&Transform, With<Player>>,
+) {
+    if console.submitted.is_empty() {
+        return;
+    }
+    for line in std::mem::take(&mut console.submitted) {
+        let Some(command) = ConsoleCommand::parse(&line) else {
+            continue;
+        };
+        // Commands added by scripts run where the player stands
+        #[cfg(feature = "rhai")]
+        if matches!(command, Err(CommandError::Unknown(_))) {
+            let origin = player.get_single().map_or(IVec3::ZERO, |transform| {
+                point_to_block(transform.translation)
+            });
+            if scripts.run(line.trim().trim_start_matches('/'), origin) {
+                continue;
+            }
+        }
+        let block = |name: &str| {
+            registry
+                .as_ref()
+                .and_then(|registry| registry.find(name))
+                .cloned()
+                .ok_or_else(|| CommandError::UnknownBlock(name.to_string()))
+        };
+        let output = command.and_then(|command| match command {
+            ConsoleCommand::Help => Ok(localizer.get("console.help").to_string()),
+            ConsoleCommand::World(WorldCommand::SetBlock {
+                position,
+                block: name,
+            }) => Ok(match world.set_block(position, block(&name)?) {
+                Ok(()) => localizer.format(
+                    "console.set_block",
+                    &[("position", &position), ("block", &name)],
+                ),
+                Err(err) => localizer.format(
+                    "console.set_block.failed",
+                    &[("position", &position), ("error", &err)],
+                ),
+            }),
+            ConsoleCommand::World(WorldCommand::Fill {
+                min,
+                max,
+                block: name,
+            }) => {
+                let aabb = BlockAabb::new(min, max);
+                if aabb.volume() > MAX_FILL_VOLUME {
+                    return Err(CommandError::FillTooLarge(aabb.volume()));
+                }
+                let set = world.fill(aabb, block(&name)?);
+                Ok(localizer.format("console.fill", &[("count", &set), ("block", &name)]))
+            }
+            ConsoleCommand::World(WorldCommand::Teleport(destination)) => {
+                teleports.send(Teleport { destination });
+                Ok(localizer.format("console.teleport", &[("destination", &destination)]))
+            }
+            ConsoleCommand::Seed => Ok(active_world
+                .as_ref()
+                .and_then(|active| manifests.get(&active.0))
+                .map_or(localizer.get("console.no_world").to_string(), |manifest| {
+                    localizer.format(
+                        "console.seed",
+                        &[("world", &manifest.name), ("seed", &manifest.seed)],
+                    )
+                })),
+            ConsoleCommand::ReloadAtlas => {
+                let mut reloaded = 0;
+                for (_, handle) in registry.iter().flat_map(|registry| registry.iter()) {
+                    let texture = blocks.get(handle).and_then(|block| block.voxel_texture());
+                    let paths = [handle.path(), texture.as_ref().and_then(|t| t.path())];
+                    for path in paths.into_iter().flatten() {
+                        asset_server.reload(path.clone_owned());
+                        reloaded += 1;
+                    }
+                }
+                Ok(localizer.format("console.reload_atlas", &[("count", &reloaded)]))
+            }
+            ConsoleCommand::ChunkBorders => {
+                gizmos.borders = !gizmos.borders;
+                Ok(localizer.format(
+                    "console.chunk_borders",
+                    &[("state", &localizer.get(on_off(gizmos.borders)))],
+                ))
+            }
+            ConsoleCommand::ChunkPadding => {
+                gizmos.padding = !gizmos.padding;
+                Ok(localizer.format(
+                    "console.chunk_padding",
+                    &[("state", &localizer.get(on_off(gizmos.padding)))],
+                ))
+            }
+        });
+        console.print(output.unwrap_or_else(|err| localize_error(&localizer, &err)));
+    }
+}
+
+/// Localization key of a toggle's state
+fn on_off(enabled: bool) -> &'static str {
+    match enabled {
+        true => "console.on",
+        false => "console.off",
+    }
+}
+
+fn update_console_text(
+    console: Res<DeveloperConsole>,
+    mut text: Query<(&mut Text, &mut Visibility), With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    for (mut text, mut visibility) in text.iter_mut() {
+        *visibility = match console.open {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+        let mut value: String = console.log.iter().map(|line| format!("{line}\n")).collect();
+        value.push_str(&format!("> {}_", console.input));
+        text.sections[0].value = value;
+    }
+}
+
+/// Prints what the script commands run from the console logged
+#[cfg(feature = "rhai")]
+fn print_script_output(
+    mut console: ResMut<DeveloperConsole>,
+    mut outputs: EventReader<ScriptCommandOutput>,
+) {
+    for output in outputs.read() {
+        for line in &output.lines {
+            console.print(format!("{}: {line}", output.name));
+        }
+    }
+}
+
+/// Toggleable console for poking at the world while playing, e.g. `setblock 1 2 3 stone` or
+/// `tp 0 40 0`. Type `help` for every [ConsoleCommand]
+pub struct DeveloperConsolePlugin;
+impl Plugin for DeveloperConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DeveloperConsole>()
+            .add_event::<Teleport>()
+            .add_systems(Startup, setup_console)
+            .add_systems(
+                Update,
+                (
+                    edit_console,
+                    clear_player_input.after(PlayerInputSet).before(PlayerSet),
+                    run_console_commands.run_if(resource_exists::<Chunks>),
+                    update_console_text,
+                )
+                    .chain(),
+            );
+        #[cfg(feature = "rhai")]
+        app.add_systems(
+            Update,
+            print_script_output
+                .after(run_console_commands)
+                .before(update_console_text),
+        );
+    }
+}
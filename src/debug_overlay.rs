@@ -0,0 +1,164 @@
+use bevy::diagnostic::{DiagnosticPath, DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+
+use cubizm_block::{definition::Block, BlockRegistry};
+use cubizm_chunks::{Chunk, ChunkDiagnosticsPlugin, Chunks};
+use cubizm_core::point_to_chunk;
+
+use crate::input::{Action, ActionInput};
+use crate::localization::{LocalizedText, Localizer};
+
+/// Furthest block the overlay names as targeted, in blocks from the camera
+const TARGET_DISTANCE: f32 = 16.;
+
+/// Localization keys of the overlay's lines, each followed by its value
+const LABELS: [&str; 6] = [
+    "debug.fps",
+    "debug.position",
+    "debug.chunk",
+    "debug.target",
+    "debug.loaded_chunks",
+    "debug.vertices",
+];
+
+/// Configuration for [DebugOverlayPlugin]
+#[derive(Resource, Debug, Clone, Default)]
+pub struct DebugOverlaySettings {
+    pub visible: bool,
+}
+
+#[derive(Component, Debug)]
+struct DebugOverlay;
+
+fn setup_debug_overlay(mut commands: Commands, settings: Res<DebugOverlaySettings>) {
+    let style = TextStyle {
+        font_size: 18.,
+        color: Color::WHITE,
+        ..default()
+    };
+    let sections = (0..LABELS.len()).flat_map(|line| {
+        let separator = match line + 1 < LABELS.len() {
+            true => "\n",
+            false => "",
+        };
+        [
+            TextSection::from_style(style.clone()),
+            TextSection::from_style(style.clone()),
+            TextSection::new(separator, style.clone()),
+        ]
+    });
+    let text = TextBundle::from_sections(sections)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            right: Val::Px(8.),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0., 0., 0., 0.4));
+    commands.spawn((
+        TextBundle {
+            visibility: visibility(settings.visible),
+            ..text
+        },
+        DebugOverlay,
+        LocalizedText::new(
+            LABELS
+                .iter()
+                .enumerate()
+                .map(|(line, key)| (line * 3, *key)),
+        ),
+    ));
+}
+
+fn visibility(visible: bool) -> Visibility {
+    match visible {
+        true => Visibility::Inherited,
+        false => Visibility::Hidden,
+    }
+}
+
+fn toggle_debug_overlay(
+    input: ActionInput,
+    mut settings: ResMut<DebugOverlaySettings>,
+    mut overlay: Query<&mut Visibility, With<DebugOverlay>>,
+) {
+    if input.just_pressed(Action::ToggleDebugOverlay) {
+        settings.visible = !settings.visible;
+    }
+    if !settings.is_changed() {
+        return;
+    }
+    for mut overlay_visibility in overlay.iter_mut() {
+        *overlay_visibility = visibility(settings.visible);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_debug_overlay(
+    settings: Res<DebugOverlaySettings>,
+    localizer: Localizer,
+    diagnostics: Res<DiagnosticsStore>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    chunks: Option<Res<Chunks>>,
+    assets_chunks: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    registry: Option<Res<BlockRegistry>>,
+    mut overlay: Query<&mut Text, With<DebugOverlay>>,
+) {
+    if !settings.visible {
+        return;
+    }
+    let Some((_, camera)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let position = camera.translation();
+    let chunk = point_to_chunk(position);
+    let target = chunks
+        .as_ref()
+        .and_then(|chunks| {
+            chunks.raycast(
+                position,
+                camera.forward(),
+                TARGET_DISTANCE,
+                &assets_chunks,
+                &blocks,
+            )
+        })
+        .map(|hit| {
+            let name = registry
+                .as_ref()
+                .and_then(|registry| registry.name(&hit.block_handle))
+                .unwrap_or("?");
+            format!("{name} ({} {} {})", hit.block.x, hit.block.y, hit.block.z)
+        })
+        .unwrap_or_else(|| localizer.get("debug.target.none").to_string());
+    let format = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{value:.0}"));
+    let diagnostic = |path: &DiagnosticPath| diagnostics.get(path);
+    let values = [
+        format(diagnostic(&FrameTimeDiagnosticsPlugin::FPS).and_then(|fps| fps.smoothed())),
+        format!("{:.1} / {:.1} / {:.1}", position.x, position.y, position.z),
+        format!("{} {} {}", chunk.x, chunk.y, chunk.z),
+        target,
+        format(diagnostic(&ChunkDiagnosticsPlugin::LOADED_CHUNKS).and_then(|d| d.value())),
+        format(diagnostic(&ChunkDiagnosticsPlugin::MESH_VERTICES).and_then(|d| d.value())),
+    ];
+    for mut text in overlay.iter_mut() {
+        for (line, value) in values.iter().enumerate() {
+            text.sections[line * 3 + 1].value.clone_from(value);
+        }
+    }
+}
+
+/// Toggleable overlay for debugging the voxel systems, showing the frame rate, the camera's
+/// position and chunk, the block it looks at, and the loaded chunks and their vertices
+pub struct DebugOverlayPlugin;
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        app.init_resource::<DebugOverlaySettings>()
+            .add_systems(Startup, setup_debug_overlay)
+            .add_systems(Update, (toggle_debug_overlay, update_debug_overlay).chain());
+    }
+}
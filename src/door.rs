@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use cubizm_block::BlockRegistry;
+use cubizm_chunks::{BlockInteract, Chunks, VoxelWorld};
+
+/// Blocks [DoorPlugin] opens and closes
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct DoorSettings {
+    /// The closed and open block of every door, by registry name, see [BlockRegistry::find].
+    /// Both are tile entities, so the door looks open by showing the open block's mesh
+    pub doors: Vec<(String, String)>,
+}
+
+impl Default for DoorSettings {
+    fn default() -> Self {
+        Self {
+            doors: vec![
+                ("door".to_string(), "door_open".to_string()),
+                ("trapdoor".to_string(), "trapdoor_open".to_string()),
+            ],
+        }
+    }
+}
+
+/// Sent when a door opens or closes
+#[derive(Event, Debug, Clone)]
+pub struct DoorToggled {
+    pub position: IVec3,
+    pub open: bool,
+}
+
+fn toggle_doors(
+    mut interactions: EventReader<BlockInteract>,
+    settings: Res<DoorSettings>,
+    registry: Res<BlockRegistry>,
+    mut world: VoxelWorld,
+    mut toggled: EventWriter<DoorToggled>,
+) {
+    for interaction in interactions.read() {
+        let toggle = settings.doors.iter().find_map(|(closed, open)| {
+            let (closed, open) = (registry.find(closed)?, registry.find(open)?);
+            if interaction.block == *closed {
+                Some((open, true))
+            } else if interaction.block == *open {
+                Some((closed, false))
+            } else {
+                None
+            }
+        });
+        let Some((block, open)) = toggle else {
+            continue;
+        };
+        // Something else may have replaced the door since
+        if world.get_block(interaction.position).as_ref() != Some(&interaction.block) {
+            continue;
+        }
+        match world.set_block(interaction.position, block.clone()) {
+            Ok(()) => {
+                toggled.send(DoorToggled {
+                    position: interaction.position,
+                    open,
+                });
+            }
+            Err(err) => warn!("Failed to toggle door at {}: {err}", interaction.position),
+        }
+    }
+}
+
+/// Opens and closes doors and trapdoors when the player interacts with them, a reference for
+/// handling [BlockInteract]. A door is a pair of tile entity blocks swapped for each other
+#[derive(Default)]
+pub struct DoorPlugin {
+    pub settings: DoorSettings,
+}
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .add_event::<DoorToggled>()
+            .add_systems(
+                Update,
+                toggle_doors
+                    .run_if(resource_exists::<Chunks>)
+                    .run_if(resource_exists::<BlockRegistry>),
+            );
+    }
+}
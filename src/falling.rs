@@ -0,0 +1,259 @@
+use bevy::asset::ron;
+use bevy::ecs::world::{EntityRef, EntityWorldMut};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use cubizm_block::definition::Block;
+use cubizm_block::{block_key, BlockRegistry, BASE_NAMESPACE};
+use cubizm_chunks::{
+    BlockChanged, Chunks, PersistenceError, Persistent, PersistentEntities, VoxelWorld,
+};
+
+/// Kind falling blocks are saved as, see [PersistentEntities]
+const FALLING_BLOCK_KIND: &str = "falling_block";
+
+/// Configuration for [FallingBlocksPlugin]
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct FallingBlockSettings {
+    /// Downwards acceleration of falling blocks in blocks per second squared
+    pub gravity: f32,
+    /// Fastest falling blocks get in blocks per second
+    pub terminal_velocity: f32,
+}
+
+impl Default for FallingBlockSettings {
+    fn default() -> Self {
+        Self {
+            gravity: 20.0,
+            terminal_velocity: 40.0,
+        }
+    }
+}
+
+/// A [gravity](Block::gravity) block taken out of the voxel grid while it falls, placed back
+/// where it lands
+#[derive(Component, Debug, Clone)]
+pub struct FallingBlock {
+    pub block: Handle<Block>,
+    /// Blocks per second, negative while falling
+    pub velocity: f32,
+}
+
+/// [FallingBlock] as it is saved with the chunk it is falling through
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedFallingBlock {
+    /// Asset path of the block
+    block: String,
+    velocity: f32,
+}
+
+fn save_falling_block(entity: EntityRef) -> Result<String, PersistenceError> {
+    let falling = entity
+        .get::<FallingBlock>()
+        .ok_or(PersistenceError::MissingComponent("FallingBlock"))?;
+    let block = falling
+        .block
+        .path()
+        .ok_or(PersistenceError::MissingComponent("block asset path"))?;
+    Ok(ron::ser::to_string(&SavedFallingBlock {
+        block: block.to_string(),
+        velocity: falling.velocity,
+    })?)
+}
+
+fn spawn_falling_block(entity: &mut EntityWorldMut, data: &str) -> Result<(), PersistenceError> {
+    let saved: SavedFallingBlock = ron::de::from_str(data)?;
+    let block = entity.world_scope(|world| world.resource::<AssetServer>().load(saved.block));
+    entity.insert(FallingBlock {
+        block,
+        velocity: saved.velocity,
+    });
+    Ok(())
+}
+
+/// Sent when a [FallingBlock] lands and becomes part of the voxel grid again
+#[derive(Event, Debug, Clone)]
+pub struct BlockLanded {
+    pub position: IVec3,
+    pub block: Handle<Block>,
+}
+
+/// Makes [gravity](Block::gravity) blocks fall once the block beneath them is air. A falling
+/// block is an entity until it lands, so the chunks are only remeshed when it starts and stops.
+/// Blocks still falling are saved with the chunk they are in
+#[derive(Default)]
+pub struct FallingBlocksPlugin {
+    pub settings: FallingBlockSettings,
+}
+
+impl Plugin for FallingBlocksPlugin {
+    fn build(&self, app: &mut App) {
+        app.world
+            .get_resource_or_insert_with(PersistentEntities::default)
+            .register_with(FALLING_BLOCK_KIND, save_falling_block, spawn_falling_block);
+        app.insert_resource(self.settings)
+            .init_resource::<UnsupportedBlocks>()
+            .add_event::<BlockLanded>()
+            .add_systems(Startup, create_falling_block_mesh)
+            .add_systems(
+                Update,
+                (
+                    find_unsupported_blocks,
+                    start_falling,
+                    draw_falling_blocks,
+                    fall,
+                )
+                    .chain()
+                    .run_if(resource_exists::<Chunks>),
+            );
+    }
+}
+
+/// Mesh shared by every [FallingBlock]
+#[derive(Resource, Debug)]
+struct FallingBlockMesh(Handle<Mesh>);
+
+/// Positions of blocks that may have lost the block beneath them this frame
+#[derive(Resource, Debug, Default)]
+struct UnsupportedBlocks(Vec<IVec3>);
+
+fn create_falling_block_mesh(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(FallingBlockMesh(meshes.add(Cuboid::from_size(Vec3::ONE))));
+}
+
+/// A gravity block falls if it was placed over air or the block beneath it became air, so
+/// every changed block and the one above it are checked. Blocks stacked on a falling block
+/// follow it the frame after, once its own change is seen
+fn find_unsupported_blocks(
+    mut changed: EventReader<BlockChanged>,
+    mut unsupported: ResMut<UnsupportedBlocks>,
+) {
+    unsupported.0.extend(
+        changed
+            .read()
+            .flat_map(|event| [event.world_pos, event.world_pos + IVec3::Y]),
+    );
+}
+
+fn start_falling(
+    mut commands: Commands,
+    registry: Res<BlockRegistry>,
+    blocks: Res<Assets<Block>>,
+    mut unsupported: ResMut<UnsupportedBlocks>,
+    mut world: VoxelWorld,
+) {
+    let positions = std::mem::take(&mut unsupported.0);
+    let Some(air) = registry.get(&block_key(BASE_NAMESPACE, "Air")) else {
+        return;
+    };
+    for position in positions {
+        let Some(block) = world.get_block(position) else {
+            continue;
+        };
+        if !blocks.get(&block).is_some_and(Block::gravity) {
+            continue;
+        }
+        if world.get_block(position - IVec3::Y).as_ref() != Some(air) {
+            continue;
+        }
+        if let Err(err) = world.set_block(position, air.clone()) {
+            warn!("Failed to drop block at {position}: {err}");
+            continue;
+        }
+        commands.spawn((
+            FallingBlock {
+                block,
+                velocity: 0.0,
+            },
+            Persistent(FALLING_BLOCK_KIND.to_string()),
+            // Blocks span `position..position + 1`
+            SpatialBundle::from_transform(Transform::from_translation(
+                position.as_vec3() + Vec3::splat(0.5),
+            )),
+        ));
+    }
+}
+
+/// Gives new falling blocks, including those spawned from a save, the texture of their block
+fn draw_falling_blocks(
+    mut commands: Commands,
+    falling: Query<(Entity, &FallingBlock), Added<FallingBlock>>,
+    blocks: Res<Assets<Block>>,
+    mesh: Res<FallingBlockMesh>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, block) in falling.iter() {
+        let texture = blocks.get(&block.block).and_then(Block::voxel_texture);
+        commands.entity(entity).insert((
+            mesh.0.clone(),
+            materials.add(StandardMaterial {
+                base_color_texture: texture,
+                ..default()
+            }),
+        ));
+    }
+}
+
+/// Where a block falling from `from` to `to`, heights of its bottom, lands. Checks every
+/// block it passes top down so fast blocks don't fall through floors. `Err` while a chunk on
+/// the way is not loaded
+fn landing(
+    world: &VoxelWorld,
+    air: &Handle<Block>,
+    column: IVec2,
+    from: f32,
+    to: f32,
+) -> Result<Option<IVec3>, ()> {
+    for y in (to.floor() as i32..from.ceil() as i32).rev() {
+        let position = IVec3::new(column.x, y, column.y);
+        match world.get_block(position) {
+            None => return Err(()),
+            Some(block) if block == *air => {}
+            Some(_) => return Ok(Some(position + IVec3::Y)),
+        }
+    }
+    Ok(None)
+}
+
+fn fall(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<FallingBlockSettings>,
+    registry: Res<BlockRegistry>,
+    mut world: VoxelWorld,
+    mut falling: Query<(Entity, &mut FallingBlock, &mut Transform)>,
+    mut landed: EventWriter<BlockLanded>,
+) {
+    let Some(air) = registry.get(&block_key(BASE_NAMESPACE, "Air")) else {
+        return;
+    };
+    for (entity, mut block, mut transform) in falling.iter_mut() {
+        block.velocity = (block.velocity - settings.gravity * time.delta_seconds())
+            .max(-settings.terminal_velocity);
+        let column = transform.translation.xz().floor().as_ivec2();
+        let bottom = transform.translation.y - 0.5;
+        let next_bottom = bottom + block.velocity * time.delta_seconds();
+        let position = match landing(&world, air, column, bottom, next_bottom) {
+            Ok(Some(position)) => position,
+            Ok(None) => {
+                transform.translation.y = next_bottom + 0.5;
+                continue;
+            }
+            // Waits in the air for the chunk beneath to load instead of falling out of the world
+            Err(()) => {
+                block.velocity = 0.0;
+                continue;
+            }
+        };
+        commands.entity(entity).despawn_recursive();
+        match world.set_block(position, block.block.clone()) {
+            Ok(()) => {
+                landed.send(BlockLanded {
+                    position,
+                    block: block.block.clone(),
+                });
+            }
+            Err(err) => warn!("Failed to land block at {position}: {err}"),
+        }
+    }
+}
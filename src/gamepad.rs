@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use cubizm_player::{PlayerInput, PlayerSet};
+
+use crate::input::{ActionInput, PlayerInputSet, StickAction};
+
+/// Tuning for gamepad look, see [GamepadPlugin]
+#[derive(Resource, Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GamepadSettings {
+    /// Turn rate at full stick deflection, in degrees per second
+    pub look_speed: f32,
+    /// Exponent applied to stick deflection before scaling, values above 1 give
+    /// finer control near the centre of the stick
+    pub look_curve: f32,
+    pub invert_look_y: bool,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            look_speed: 180.,
+            look_curve: 2.,
+            invert_look_y: false,
+        }
+    }
+}
+
+fn apply_curve(stick: Vec2, exponent: f32) -> Vec2 {
+    let deflection = stick.length().min(1.);
+    stick.normalize_or_zero() * deflection.powf(exponent)
+}
+
+fn gamepad_move(input: ActionInput, mut player: ResMut<PlayerInput>) {
+    // Gamepad buttons for ascend and descend are read along with the keyboard
+    player.movement += input.stick(StickAction::Move).clamp_length_max(1.);
+}
+
+fn gamepad_look(
+    input: ActionInput,
+    time: Res<Time>,
+    settings: Res<GamepadSettings>,
+    mut player: ResMut<PlayerInput>,
+) {
+    let stick = apply_curve(input.stick(StickAction::Look), settings.look_curve);
+    if stick == Vec2::ZERO {
+        return;
+    }
+    let turn = stick * settings.look_speed.to_radians() * time.delta_seconds();
+    let pitch_turn = match settings.invert_look_y {
+        true => -turn.y,
+        false => turn.y,
+    };
+    player.look += Vec2::new(turn.x, pitch_turn);
+}
+
+/// Lets the player move and look around with a gamepad, alongside the keyboard and mouse.
+/// Which sticks drive [StickAction]s and which buttons break, place and pick blocks is set in
+/// the [InputMap](crate::input::InputMap), by default the triggers break and place and the
+/// d-pad changes the held block
+pub struct GamepadPlugin;
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GamepadSettings>()
+            .init_resource::<PlayerInput>()
+            .add_systems(
+                Update,
+                (gamepad_move, gamepad_look)
+                    .in_set(PlayerInputSet)
+                    .before(PlayerSet),
+            );
+    }
+}
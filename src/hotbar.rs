@@ -0,0 +1,488 @@
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use block_mesh::{Voxel, VoxelVisibility};
+
+use cubizm_block::definition::Block;
+use cubizm_block::texture_atlas::BlockAtlas;
+use cubizm_block::BlockRegistry;
+
+use crate::accessibility::AccessibilitySettings;
+use crate::input::{Action, ActionInput};
+use crate::localization::LocalizedText;
+
+pub const HOTBAR_SLOTS: usize = 9;
+
+const SLOT_SIZE: f32 = 48.;
+const ICON_SIZE: f32 = 40.;
+const SLOT_COLOR: Color = Color::rgba(0., 0., 0., 0.5);
+const SLOT_BORDER_COLOR: Color = Color::rgba(0.2, 0.2, 0.2, 0.8);
+
+/// Blocks the player can switch between, filled from the [BlockRegistry] once it is loaded
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct Hotbar {
+    pub slots: [Option<Handle<Block>>; HOTBAR_SLOTS],
+    /// Index of the slot in use
+    pub selected: usize,
+}
+
+impl Hotbar {
+    pub fn selected_block(&self) -> Option<&Handle<Block>> {
+        self.slots.get(self.selected)?.as_ref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    /// Moves the selection by `offset` slots, wrapping around at either end
+    pub fn scroll(&mut self, offset: i32) {
+        self.selected = (self.selected as i32 + offset).rem_euclid(HOTBAR_SLOTS as i32) as usize;
+    }
+}
+
+/// The block in the selected [Hotbar] slot, the one placing puts down
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct SelectedBlock(pub Option<Handle<Block>>);
+
+/// Whether the screen listing every registered block is open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, States)]
+pub enum BlockPickerState {
+    #[default]
+    Closed,
+    Open,
+}
+
+/// The [BlockAtlas] layout of each page as an asset, needed to show block textures in the UI
+#[derive(Resource, Debug)]
+struct BlockIcons {
+    pages: Vec<(Handle<Image>, Handle<TextureAtlasLayout>)>,
+}
+
+#[derive(Component, Debug)]
+struct HotbarSlot(usize);
+
+/// Shows the block of the [HotbarSlot] it is a child of
+#[derive(Component, Debug)]
+struct BlockIcon;
+
+#[derive(Component, Debug)]
+struct BlockPicker;
+
+/// A button of the [BlockPicker] putting its block into the selected hotbar slot
+#[derive(Component, Debug)]
+struct PickerEntry(Handle<Block>);
+
+fn spawn_hotbar(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(4.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for slot in 0..HOTBAR_SLOTS {
+                parent
+                    .spawn((
+                        HotbarSlot(slot),
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(SLOT_SIZE),
+                                height: Val::Px(SLOT_SIZE),
+                                border: UiRect::all(Val::Px(2.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: SLOT_COLOR.into(),
+                            border_color: SLOT_BORDER_COLOR.into(),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|slot| {
+                        slot.spawn((BlockIcon, icon_bundle()));
+                    });
+            }
+        });
+}
+
+fn icon_bundle() -> AtlasImageBundle {
+    AtlasImageBundle {
+        style: Style {
+            width: Val::Px(ICON_SIZE),
+            height: Val::Px(ICON_SIZE),
+            ..default()
+        },
+        visibility: Visibility::Hidden,
+        ..default()
+    }
+}
+
+/// Whether `block` can be put in the hotbar, air and blocks still loading can't
+fn is_placeable(blocks: &Assets<Block>, block: &Handle<Block>) -> bool {
+    blocks
+        .get(block)
+        .is_some_and(|block| block.get_visibility() != VoxelVisibility::Empty)
+}
+
+/// Registered blocks the player can pick, sorted by name
+fn placeable_blocks<'a>(
+    registry: &'a BlockRegistry,
+    blocks: &'a Assets<Block>,
+) -> Vec<(&'a str, &'a Handle<Block>)> {
+    let mut placeable: Vec<_> = registry
+        .iter()
+        .filter(|(_, block)| is_placeable(blocks, block))
+        .collect();
+    placeable.sort_by_key(|(name, _)| *name);
+    placeable
+}
+
+fn fill_hotbar(
+    registry: Res<BlockRegistry>,
+    blocks: Res<Assets<Block>>,
+    mut hotbar: ResMut<Hotbar>,
+) {
+    if !registry.is_changed() || !hotbar.is_empty() {
+        return;
+    }
+    for (slot, (_, block)) in hotbar
+        .slots
+        .iter_mut()
+        .zip(placeable_blocks(&registry, &blocks))
+    {
+        *slot = Some(block.clone());
+    }
+}
+
+fn select_hotbar_slot(
+    input: ActionInput,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut wheel: EventReader<MouseWheel>,
+    mut hotbar: ResMut<Hotbar>,
+) {
+    const DIGITS: [KeyCode; HOTBAR_SLOTS] = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ];
+    if let Some(slot) = DIGITS.iter().position(|key| keys.just_pressed(*key)) {
+        hotbar.selected = slot;
+    }
+    // Scrolling down moves right, like the wheel scrolls a page
+    let scrolled: f32 = wheel.read().map(|event| event.y).sum();
+    let mut offset = if scrolled > 0. {
+        -1
+    } else if scrolled < 0. {
+        1
+    } else {
+        0
+    };
+    offset += input.just_pressed(Action::HotbarNext) as i32;
+    offset -= input.just_pressed(Action::HotbarPrevious) as i32;
+    if offset != 0 {
+        hotbar.scroll(offset);
+    }
+}
+
+/// Swaps the block in the selected slot for its neighbour among the placeable blocks on
+/// [Action::NextBlock] and [Action::PreviousBlock]
+fn cycle_selected_block(
+    input: ActionInput,
+    registry: Res<BlockRegistry>,
+    blocks: Res<Assets<Block>>,
+    mut hotbar: ResMut<Hotbar>,
+) {
+    let offset = input.just_pressed(Action::NextBlock) as i32
+        - input.just_pressed(Action::PreviousBlock) as i32;
+    if offset == 0 {
+        return;
+    }
+    let placeable = placeable_blocks(&registry, &blocks);
+    if placeable.is_empty() {
+        return;
+    }
+    // An empty slot or one with an unregistered block starts before the first block
+    let current = hotbar
+        .selected_block()
+        .and_then(|selected| placeable.iter().position(|(_, block)| *block == selected));
+    let next = match current {
+        Some(index) => (index as i32 + offset).rem_euclid(placeable.len() as i32) as usize,
+        None if offset > 0 => 0,
+        None => placeable.len() - 1,
+    };
+    let selected = hotbar.selected;
+    hotbar.slots[selected] = Some(placeable[next].1.clone());
+}
+
+fn update_selected_block(hotbar: Res<Hotbar>, mut selected: ResMut<SelectedBlock>) {
+    if hotbar.is_changed() {
+        selected.set_if_neq(SelectedBlock(hotbar.selected_block().cloned()));
+    }
+}
+
+fn update_block_icons(
+    mut commands: Commands,
+    atlas: Res<BlockAtlas>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    if atlas.is_changed() {
+        let pages = atlas
+            .pages()
+            .iter()
+            .enumerate()
+            .map(|(page, image)| (image.clone(), layouts.add(atlas.page_layout(page))))
+            .collect();
+        commands.insert_resource(BlockIcons { pages });
+    }
+}
+
+/// Shows the texture of `block` on an icon, hiding it for blocks without one
+fn set_icon(
+    icons: &BlockIcons,
+    atlas: &BlockAtlas,
+    block: Option<&Block>,
+    image: &mut UiImage,
+    texture_atlas: &mut TextureAtlas,
+    visibility: &mut Visibility,
+) {
+    let icon = block
+        .and_then(|block| atlas.texture_index(block))
+        .and_then(|index| Some((icons.pages.get(atlas.texture_page(index))?, index)));
+    let Some(((page_image, layout), index)) = icon else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    image.texture = page_image.clone();
+    *texture_atlas = TextureAtlas {
+        layout: layout.clone(),
+        index,
+    };
+    *visibility = Visibility::Inherited;
+}
+
+fn update_hotbar_ui(
+    hotbar: Res<Hotbar>,
+    accessibility: Res<AccessibilitySettings>,
+    icons: Option<Res<BlockIcons>>,
+    atlas: Option<Res<BlockAtlas>>,
+    blocks: Res<Assets<Block>>,
+    mut slots: Query<(&HotbarSlot, &mut BorderColor, &Children)>,
+    mut slot_icons: Query<(&mut UiImage, &mut TextureAtlas, &mut Visibility), With<BlockIcon>>,
+) {
+    let (Some(icons), Some(atlas)) = (icons, atlas) else {
+        return;
+    };
+    if !hotbar.is_changed() && !accessibility.is_changed() && !icons.is_changed() {
+        return;
+    }
+    for (slot, mut border, children) in slots.iter_mut() {
+        border.0 = match slot.0 == hotbar.selected {
+            true => accessibility.highlight_color,
+            false => SLOT_BORDER_COLOR,
+        };
+        let block = hotbar.slots[slot.0]
+            .as_ref()
+            .and_then(|block| blocks.get(block));
+        for child in children.iter() {
+            if let Ok((mut image, mut texture_atlas, mut visibility)) = slot_icons.get_mut(*child) {
+                set_icon(
+                    &icons,
+                    &atlas,
+                    block,
+                    &mut image,
+                    &mut texture_atlas,
+                    &mut visibility,
+                );
+            }
+        }
+    }
+}
+
+fn toggle_block_picker(
+    input: ActionInput,
+    state: Res<State<BlockPickerState>>,
+    mut next_state: ResMut<NextState<BlockPickerState>>,
+) {
+    if !input.just_pressed(Action::ToggleBlockPicker) {
+        return;
+    }
+    next_state.set(match state.get() {
+        BlockPickerState::Closed => BlockPickerState::Open,
+        BlockPickerState::Open => BlockPickerState::Closed,
+    });
+}
+
+fn set_cursor_grab(windows: &mut Query<&mut Window, With<PrimaryWindow>>, grab: bool) {
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor.grab_mode = match grab {
+            true => CursorGrabMode::Confined,
+            false => CursorGrabMode::None,
+        };
+        window.cursor.visible = !grab;
+    }
+}
+
+fn open_block_picker(
+    mut commands: Commands,
+    registry: Option<Res<BlockRegistry>>,
+    blocks: Res<Assets<Block>>,
+    icons: Option<Res<BlockIcons>>,
+    atlas: Option<Res<BlockAtlas>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    set_cursor_grab(&mut windows, false);
+    let entries = registry
+        .as_ref()
+        .map(|registry| placeable_blocks(registry, &blocks))
+        .unwrap_or_default();
+    commands
+        .spawn((
+            BlockPicker,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 24.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                LocalizedText::new([(0, "picker.title")]),
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        max_width: Val::Percent(60.),
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
+                        column_gap: Val::Px(4.),
+                        row_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|grid| {
+                    for (_, block) in entries {
+                        let mut icon = icon_bundle();
+                        if let (Some(icons), Some(atlas)) = (&icons, &atlas) {
+                            set_icon(
+                                icons,
+                                atlas,
+                                blocks.get(block),
+                                &mut icon.image,
+                                &mut icon.texture_atlas,
+                                &mut icon.visibility,
+                            );
+                        }
+                        grid.spawn((
+                            PickerEntry(block.clone()),
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(SLOT_SIZE),
+                                    height: Val::Px(SLOT_SIZE),
+                                    border: UiRect::all(Val::Px(2.)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color: SLOT_COLOR.into(),
+                                border_color: SLOT_BORDER_COLOR.into(),
+                                ..default()
+                            },
+                        ))
+                        .with_children(|entry| {
+                            entry.spawn(icon);
+                        });
+                    }
+                });
+        });
+}
+
+fn close_block_picker(
+    mut commands: Commands,
+    pickers: Query<Entity, With<BlockPicker>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    set_cursor_grab(&mut windows, true);
+    for picker in pickers.iter() {
+        commands.entity(picker).despawn_recursive();
+    }
+}
+
+fn pick_block(
+    accessibility: Res<AccessibilitySettings>,
+    mut entries: Query<(&PickerEntry, &Interaction, &mut BorderColor), Changed<Interaction>>,
+    mut hotbar: ResMut<Hotbar>,
+) {
+    for (entry, interaction, mut border) in entries.iter_mut() {
+        border.0 = match interaction {
+            Interaction::None => SLOT_BORDER_COLOR,
+            _ => accessibility.highlight_color,
+        };
+        if *interaction == Interaction::Pressed {
+            let selected = hotbar.selected;
+            hotbar.slots[selected] = Some(entry.0.clone());
+        }
+    }
+}
+
+/// Shows a [Hotbar] at the bottom of the screen, selected with the number keys, the mouse
+/// wheel or [Action::HotbarNext] and [Action::HotbarPrevious], and a picker screen listing
+/// every registered block behind [Action::ToggleBlockPicker]. Without a mouse the selected
+/// slot's block is changed with [Action::NextBlock] and [Action::PreviousBlock]
+pub struct HotbarPlugin;
+impl Plugin for HotbarPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hotbar>()
+            .init_resource::<SelectedBlock>()
+            .init_state::<BlockPickerState>()
+            .add_systems(Startup, spawn_hotbar)
+            .add_systems(
+                Update,
+                (
+                    fill_hotbar.run_if(resource_exists::<BlockRegistry>),
+                    update_block_icons.run_if(resource_exists::<BlockAtlas>),
+                    toggle_block_picker,
+                    select_hotbar_slot.run_if(in_state(BlockPickerState::Closed)),
+                    cycle_selected_block
+                        .run_if(in_state(BlockPickerState::Closed))
+                        .run_if(resource_exists::<BlockRegistry>),
+                    pick_block.run_if(in_state(BlockPickerState::Open)),
+                    update_selected_block,
+                    update_hotbar_ui,
+                )
+                    .chain(),
+            )
+            .add_systems(OnEnter(BlockPickerState::Open), open_block_picker)
+            .add_systems(OnExit(BlockPickerState::Open), close_block_picker);
+    }
+}
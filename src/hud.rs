@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+
+use cubizm_chunks::BiomeLookup;
+use cubizm_core::{point_to_block, point_to_chunk};
+use cubizm_player::Player;
+
+use crate::input::{Action, ActionInput};
+use crate::localization::{LocalizedText, Localizer};
+
+/// Configuration for [CoordinatesHudPlugin]
+#[derive(Resource, Debug, Clone, Default)]
+pub struct CoordinatesHudSettings {
+    pub visible: bool,
+}
+
+#[derive(Component, Debug)]
+struct CoordinatesHud;
+
+fn setup_coordinates_hud(mut commands: Commands, settings: Res<CoordinatesHudSettings>) {
+    let style = TextStyle {
+        font_size: 18.,
+        color: Color::WHITE,
+        ..default()
+    };
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::from_style(style.clone()),
+            TextSection::from_style(style.clone()),
+            TextSection::new("\n", style.clone()),
+            TextSection::from_style(style.clone()),
+            TextSection::from_style(style.clone()),
+            TextSection::new("\n", style.clone()),
+            TextSection::from_style(style.clone()),
+            TextSection::from_style(style.clone()),
+            TextSection::new("\n", style.clone()),
+            TextSection::from_style(style.clone()),
+            TextSection::from_style(style),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            left: Val::Px(8.),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0., 0., 0., 0.4)),
+        CoordinatesHud,
+        LocalizedText::new([
+            (0, "hud.position"),
+            (3, "hud.chunk"),
+            (6, "hud.facing"),
+            (9, "hud.biome"),
+        ]),
+        visibility(settings.visible),
+    ));
+}
+
+fn visibility(visible: bool) -> Visibility {
+    match visible {
+        true => Visibility::Inherited,
+        false => Visibility::Hidden,
+    }
+}
+
+fn toggle_coordinates_hud(
+    input: ActionInput,
+    mut settings: ResMut<CoordinatesHudSettings>,
+    mut hud: Query<&mut Visibility, With<CoordinatesHud>>,
+) {
+    if input.just_pressed(Action::ToggleCoordinatesHud) {
+        settings.visible = !settings.visible;
+    }
+    if !settings.is_changed() {
+        return;
+    }
+    for mut hud_visibility in hud.iter_mut() {
+        *hud_visibility = visibility(settings.visible);
+    }
+}
+
+/// Localization key of the cardinal direction of a horizontal heading, with -z as north
+fn cardinal(forward: Vec3) -> &'static str {
+    let yaw = forward.x.atan2(-forward.z).to_degrees().rem_euclid(360.);
+    match ((yaw + 22.5) / 45.) as u32 % 8 {
+        0 => "hud.facing.north",
+        1 => "hud.facing.north_east",
+        2 => "hud.facing.east",
+        3 => "hud.facing.south_east",
+        4 => "hud.facing.south",
+        5 => "hud.facing.south_west",
+        6 => "hud.facing.west",
+        _ => "hud.facing.north_west",
+    }
+}
+
+fn update_coordinates_hud(
+    settings: Res<CoordinatesHudSettings>,
+    localizer: Localizer,
+    biomes: BiomeLookup,
+    player: Query<&Transform, With<Player>>,
+    mut hud: Query<&mut Text, With<CoordinatesHud>>,
+) {
+    if !settings.visible {
+        return;
+    }
+    let Ok(transform) = player.get_single() else {
+        return;
+    };
+    let position = transform.translation;
+    let chunk = point_to_chunk(position);
+    let forward = transform.forward();
+    let biome = biomes.biome(point_to_block(position).xz());
+
+    for mut text in hud.iter_mut() {
+        text.sections[1].value =
+            format!("{:.1} / {:.1} / {:.1}", position.x, position.y, position.z);
+        text.sections[4].value = format!("{} {} {}", chunk.x, chunk.y, chunk.z);
+        text.sections[7].value = localizer.get(cardinal(*forward)).to_string();
+        text.sections[10].value = match biome {
+            Some(biome) => biome.name.clone(),
+            None => localizer.get("hud.biome.none").to_string(),
+        };
+    }
+}
+
+/// Toggleable overlay showing the player's position, chunk, facing direction and biome
+pub struct CoordinatesHudPlugin;
+impl Plugin for CoordinatesHudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CoordinatesHudSettings>()
+            .add_systems(Startup, setup_coordinates_hud)
+            .add_systems(
+                Update,
+                (toggle_coordinates_hud, update_coordinates_hud).chain(),
+            );
+    }
+}
@@ -0,0 +1,333 @@
+use std::hash::Hash;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use cubizm_player::{PlayerInput, PlayerSet};
+
+/// Something the player can do, independent of the buttons bound to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    /// Up while flying
+    MoveAscend,
+    /// Down while flying
+    MoveDescend,
+    /// Leaves the ground while walking, usually bound with [Action::MoveAscend]
+    Jump,
+    Sprint,
+    Sneak,
+    ToggleGrabCursor,
+    ToggleFlying,
+    BreakBlock,
+    PlaceBlock,
+    HotbarNext,
+    HotbarPrevious,
+    /// Swaps the block in the selected hotbar slot for the next registered one, picking
+    /// blocks without the mouse driven picker
+    NextBlock,
+    PreviousBlock,
+    ToggleBlockPicker,
+    ToggleCoordinatesHud,
+    TogglePhotoMode,
+    CapturePhoto,
+    ToggleConsole,
+    ToggleDebugOverlay,
+    ToggleWireframe,
+}
+
+/// A physical button that can trigger an [Action]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Matches the button on any connected gamepad
+    Gamepad(GamepadButtonType),
+}
+
+/// Something the player steers with an analog stick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum StickAction {
+    Move,
+    Look,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl Stick {
+    fn axes(self) -> (GamepadAxisType, GamepadAxisType) {
+        match self {
+            Stick::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            Stick::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        }
+    }
+}
+
+/// Maps every [Action] to the buttons that trigger it, any of which will do,
+/// and every [StickAction] to the gamepad stick that drives it
+#[derive(Resource, Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct InputMap {
+    buttons: HashMap<Action, Vec<Binding>>,
+    sticks: HashMap<StickAction, Stick>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::*;
+        let buttons = HashMap::from([
+            (Action::MoveForward, vec![Key(KeyCode::KeyW)]),
+            (Action::MoveBackward, vec![Key(KeyCode::KeyS)]),
+            (Action::MoveLeft, vec![Key(KeyCode::KeyA)]),
+            (Action::MoveRight, vec![Key(KeyCode::KeyD)]),
+            (
+                Action::MoveAscend,
+                vec![Key(KeyCode::Space), Gamepad(GamepadButtonType::South)],
+            ),
+            (
+                Action::MoveDescend,
+                vec![Key(KeyCode::ShiftLeft), Gamepad(GamepadButtonType::East)],
+            ),
+            (
+                Action::Jump,
+                vec![Key(KeyCode::Space), Gamepad(GamepadButtonType::South)],
+            ),
+            (
+                Action::Sprint,
+                vec![
+                    Key(KeyCode::ControlLeft),
+                    Gamepad(GamepadButtonType::LeftThumb),
+                ],
+            ),
+            (
+                Action::Sneak,
+                vec![Key(KeyCode::KeyC), Gamepad(GamepadButtonType::RightThumb)],
+            ),
+            (
+                Action::ToggleGrabCursor,
+                vec![Key(KeyCode::Escape), Gamepad(GamepadButtonType::Start)],
+            ),
+            (
+                Action::ToggleFlying,
+                vec![Key(KeyCode::KeyF), Gamepad(GamepadButtonType::North)],
+            ),
+            (
+                Action::BreakBlock,
+                vec![
+                    Mouse(MouseButton::Left),
+                    Gamepad(GamepadButtonType::RightTrigger2),
+                ],
+            ),
+            (
+                Action::PlaceBlock,
+                vec![
+                    Mouse(MouseButton::Right),
+                    Gamepad(GamepadButtonType::LeftTrigger2),
+                ],
+            ),
+            (
+                Action::HotbarNext,
+                vec![Gamepad(GamepadButtonType::RightTrigger)],
+            ),
+            (
+                Action::HotbarPrevious,
+                vec![Gamepad(GamepadButtonType::LeftTrigger)],
+            ),
+            (
+                Action::NextBlock,
+                vec![Gamepad(GamepadButtonType::DPadRight)],
+            ),
+            (
+                Action::PreviousBlock,
+                vec![Gamepad(GamepadButtonType::DPadLeft)],
+            ),
+            (Action::ToggleBlockPicker, vec![Key(KeyCode::KeyE)]),
+            (Action::ToggleCoordinatesHud, vec![Key(KeyCode::F3)]),
+            (
+                Action::TogglePhotoMode,
+                vec![Key(KeyCode::F4), Gamepad(GamepadButtonType::Select)],
+            ),
+            (
+                Action::CapturePhoto,
+                vec![Key(KeyCode::F2), Gamepad(GamepadButtonType::West)],
+            ),
+            (Action::ToggleConsole, vec![Key(KeyCode::Backquote)]),
+            (Action::ToggleDebugOverlay, vec![Key(KeyCode::F6)]),
+            (Action::ToggleWireframe, vec![Key(KeyCode::F7)]),
+        ]);
+        let sticks = HashMap::from([
+            (StickAction::Move, Stick::Left),
+            (StickAction::Look, Stick::Right),
+        ]);
+        Self { buttons, sticks }
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.buttons.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds `binding` to `action`, keeping the bindings it already has
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.buttons.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: Action) {
+        self.buttons.remove(&action);
+    }
+
+    pub fn stick(&self, action: StickAction) -> Option<Stick> {
+        self.sticks.get(&action).copied()
+    }
+
+    pub fn bind_stick(&mut self, action: StickAction, stick: Stick) {
+        self.sticks.insert(action, stick);
+    }
+
+    /// The first keyboard binding of `action`, for consumers that only understand keys
+    pub fn key(&self, action: Action) -> Option<KeyCode> {
+        self.bindings(action)
+            .iter()
+            .find_map(|binding| match binding {
+                Binding::Key(key) => Some(*key),
+                _ => None,
+            })
+    }
+}
+
+/// Reads [Action]s through the [InputMap] instead of raw buttons
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    map: Res<'w, InputMap>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl ActionInput<'_> {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.matches(action, ButtonCheck::Pressed)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.matches(action, ButtonCheck::JustPressed)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.matches(action, ButtonCheck::JustReleased)
+    }
+
+    /// Like [ActionInput::pressed] but ignoring keyboard and mouse bindings, for systems
+    /// that complement a controller already reading those
+    pub fn gamepad_pressed(&self, action: Action) -> bool {
+        self.map.bindings(action).iter().any(|binding| {
+            matches!(binding, Binding::Gamepad(_)) && self.test(*binding, ButtonCheck::Pressed)
+        })
+    }
+
+    /// Deflection of the stick bound to `action`, taken from whichever connected gamepad
+    /// pushes it furthest. Each component is in `-1..=1` with `+y` pointing up
+    pub fn stick(&self, action: StickAction) -> Vec2 {
+        let Some((x_axis, y_axis)) = self.map.stick(action).map(Stick::axes) else {
+            return Vec2::ZERO;
+        };
+        self.gamepads
+            .iter()
+            .map(|gamepad| {
+                Vec2::new(
+                    self.gamepad_axes
+                        .get(GamepadAxis::new(gamepad, x_axis))
+                        .unwrap_or_default(),
+                    self.gamepad_axes
+                        .get(GamepadAxis::new(gamepad, y_axis))
+                        .unwrap_or_default(),
+                )
+            })
+            .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap_or_default()
+    }
+
+    fn matches(&self, action: Action, check: ButtonCheck) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| self.test(*binding, check))
+    }
+
+    fn test(&self, binding: Binding, check: ButtonCheck) -> bool {
+        match binding {
+            Binding::Key(key) => check.test(&self.keys, key),
+            Binding::Mouse(button) => check.test(&self.mouse, button),
+            Binding::Gamepad(button) => self.gamepads.iter().any(|gamepad| {
+                check.test(&self.gamepad_buttons, GamepadButton::new(gamepad, button))
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ButtonCheck {
+    Pressed,
+    JustPressed,
+    JustReleased,
+}
+
+impl ButtonCheck {
+    fn test<T: Copy + Eq + Hash + Send + Sync + 'static>(
+        self,
+        input: &ButtonInput<T>,
+        button: T,
+    ) -> bool {
+        match self {
+            ButtonCheck::Pressed => input.pressed(button),
+            ButtonCheck::JustPressed => input.just_pressed(button),
+            ButtonCheck::JustReleased => input.just_released(button),
+        }
+    }
+}
+
+/// Systems writing the [PlayerInput] of the frame, before [PlayerSet] reads it
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerInputSet;
+
+/// Feeds the player controller from the movement actions, see [PlayerInput]
+fn write_player_input(input: ActionInput, mut player: ResMut<PlayerInput>) {
+    let axis = |positive, negative| {
+        input.pressed(positive) as i32 as f32 - input.pressed(negative) as i32 as f32
+    };
+    player.movement += Vec2::new(
+        axis(Action::MoveRight, Action::MoveLeft),
+        axis(Action::MoveForward, Action::MoveBackward),
+    );
+    player.vertical += axis(Action::MoveAscend, Action::MoveDescend);
+    player.jump |= input.pressed(Action::Jump);
+    player.toggle_flying |= input.just_pressed(Action::ToggleFlying);
+    player.toggle_grab_cursor |= input.just_pressed(Action::ToggleGrabCursor);
+}
+
+/// Provides the [InputMap] and drives the player controller with it. The map is read from the
+/// `input` of the [GameSettings](crate::settings::GameSettings) asset, systems read
+/// [Action]s through [ActionInput] rather than raw buttons
+pub struct InputMapPlugin;
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>()
+            .init_resource::<PlayerInput>()
+            .configure_sets(Update, PlayerInputSet.before(PlayerSet))
+            .add_systems(Update, write_player_input.in_set(PlayerInputSet));
+    }
+}
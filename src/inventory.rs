@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+
+use cubizm_block::BlockRegistry;
+use cubizm_chunks::hash_unit;
+use cubizm_inventory::{AddItems, Inventory, InventorySet, ItemStack, LootTable, LootTables};
+use cubizm_player::Player;
+
+use crate::mining::BlockBroken;
+
+/// Configuration for [PlayerInventoryPlugin]
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct PlayerInventorySettings {
+    /// Slots of the inventory every [Player] is given
+    pub size: usize,
+    /// Placing blocks doesn't need them in the inventory
+    pub creative: bool,
+}
+
+impl Default for PlayerInventorySettings {
+    fn default() -> Self {
+        Self {
+            size: 36,
+            creative: true,
+        }
+    }
+}
+
+fn give_player_inventory(
+    mut commands: Commands,
+    settings: Res<PlayerInventorySettings>,
+    players: Query<Entity, (Added<Player>, Without<Inventory>)>,
+) {
+    for player in players.iter() {
+        commands
+            .entity(player)
+            .insert(Inventory::new(settings.size));
+    }
+}
+
+/// Adds what broken blocks drop to the player's inventory: the rolls of their [LootTable], or
+/// the block itself without one
+fn collect_broken_blocks(
+    mut broken: EventReader<BlockBroken>,
+    registry: Res<BlockRegistry>,
+    loot_tables: Option<Res<LootTables>>,
+    tables: Res<Assets<LootTable>>,
+    players: Query<Entity, (With<Player>, With<Inventory>)>,
+    mut add: EventWriter<AddItems>,
+    mut rolls: Local<u64>,
+) {
+    let Some(player) = players.iter().next() else {
+        broken.clear();
+        return;
+    };
+    for event in broken.read() {
+        let Some(name) = registry.name(&event.block) else {
+            continue;
+        };
+        let table = loot_tables
+            .as_ref()
+            .and_then(|loot_tables| loot_tables.get(name))
+            .and_then(|handle| tables.get(handle));
+        let stacks = match table {
+            Some(table) => table.roll(|| {
+                *rolls += 1;
+                hash_unit((event.position.to_array(), *rolls))
+            }),
+            None => vec![ItemStack::new(name, 1)],
+        };
+        add.send_batch(stacks.into_iter().map(|stack| AddItems {
+            inventory: player,
+            stack,
+        }));
+    }
+}
+
+/// Gives the [Player] an [Inventory] that the drops of blocks they break are added to, and that
+/// placing takes them from unless [PlayerInventorySettings::creative] is set
+#[derive(Default)]
+pub struct PlayerInventoryPlugin {
+    pub settings: PlayerInventorySettings,
+}
+
+impl Plugin for PlayerInventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings).add_systems(
+            Update,
+            (
+                give_player_inventory,
+                collect_broken_blocks
+                    .run_if(resource_exists::<BlockRegistry>)
+                    .before(InventorySet),
+            ),
+        );
+    }
+}
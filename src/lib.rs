@@ -3,15 +3,105 @@ use bevy::prelude::*;
 
 use cubizm_block::BlockPlugin;
 use cubizm_chunks::ChunksPlugin;
-use cubizm_core::Cubizm;
+use cubizm_core::{Cubizm, DayNightPlugin, DistanceFogPlugin};
+use cubizm_inventory::{InventoryPlugin, ItemPlugin};
+
+use accessibility::AccessibilityPlugin;
+use audio::AmbientAudioPlugin;
+use block_sounds::BlockSoundsPlugin;
+use console::DeveloperConsolePlugin;
+use debug_overlay::DebugOverlayPlugin;
+use door::DoorPlugin;
+use falling::FallingBlocksPlugin;
+use gamepad::GamepadPlugin;
+use hotbar::HotbarPlugin;
+use hud::CoordinatesHudPlugin;
+use input::InputMapPlugin;
+use inventory::PlayerInventoryPlugin;
+use localization::LocalizationPlugin;
+use mining::MiningPlugin;
+use movement::MovementPlugin;
+use photo_mode::PhotoModePlugin;
+use placement::PlacementPlugin;
+use portal::PortalPlugin;
+use settings::SettingsPlugin;
+use sky::SkyPlugin;
+use spawn::SpawnPlugin;
+use target::BlockTargetPlugin;
+use teleport::TeleportPlugin;
+
+pub mod accessibility;
+pub mod audio;
+pub mod block_sounds;
+pub mod console;
+pub mod debug_overlay;
+pub mod door;
+pub mod falling;
+pub mod gamepad;
+pub mod hotbar;
+pub mod hud;
+pub mod input;
+pub mod inventory;
+pub mod localization;
+pub mod mining;
+pub mod movement;
+pub mod photo_mode;
+pub mod placement;
+pub mod portal;
+pub mod prelude;
+#[cfg(feature = "rhai")]
+pub mod scripting;
+pub mod settings;
+pub mod sky;
+pub mod spawn;
+pub mod target;
+pub mod teleport;
 
 pub struct CubizmGameDefault;
 
 impl PluginGroup for CubizmGameDefault {
     fn build(self) -> PluginGroupBuilder {
-        PluginGroupBuilder::start::<Self>()
-            .add(BlockPlugin)
-            .add(ChunksPlugin)
+        let group = PluginGroupBuilder::start::<Self>()
+            .add(BlockPlugin::default())
+            .add(ChunksPlugin::default())
+            .add(ItemPlugin::default())
             .add(Cubizm)
+            .add(DayNightPlugin::default())
+            .add(SkyPlugin::default())
+            .add(DistanceFogPlugin::default())
+            .add(InputMapPlugin)
+            .add(GamepadPlugin)
+            .add(MovementPlugin)
+            .add(BlockTargetPlugin::default())
+            .add(MiningPlugin::default())
+            .add(PlacementPlugin)
+            .add(FallingBlocksPlugin::default())
+            .add(DoorPlugin::default())
+            .add(HotbarPlugin)
+            .add(ItemPlugin::default())
+            .add(InventoryPlugin)
+            .add(PlayerInventoryPlugin::default())
+            .add(AccessibilityPlugin)
+            .add(LocalizationPlugin)
+            .add(AmbientAudioPlugin)
+            .add(BlockSoundsPlugin::default())
+            .add(PhotoModePlugin)
+            .add(CoordinatesHudPlugin)
+            .add(DebugOverlayPlugin)
+            .add(TeleportPlugin)
+            .add(PortalPlugin::default())
+            .add(SpawnPlugin)
+            .add(DeveloperConsolePlugin)
+            .add(SettingsPlugin);
+        #[cfg(feature = "rhai")]
+        let group = group.add(scripting::ScriptingPlugin);
+        #[cfg(feature = "raymarch")]
+        let group = group.add(cubizm_chunks::RaymarchPlugin);
+        // Colliders only, add bevy_rapier3d's RapierPhysicsPlugin to simulate them
+        #[cfg(feature = "rapier")]
+        let group = group.add(cubizm_physics::PhysicsPlugin);
+        #[cfg(feature = "scripting")]
+        let group = group.add(cubizm_script::ScriptPlugin::default());
+        group
     }
 }
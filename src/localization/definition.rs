@@ -0,0 +1,30 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
+
+/// UI strings of one language, keyed by identifiers such as `hud.position`
+#[derive(Debug, Clone, Default, Asset, TypePath, Deserialize)]
+#[serde(transparent)]
+pub struct Locale {
+    strings: HashMap<String, String>,
+}
+
+impl Locale {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+}
+
+/// Locales to look strings up in, most specific first. `pt-BR` falls back to `pt`, then to
+/// `fallback`
+pub fn locale_chain(locale: &str, fallback: &str) -> Vec<String> {
+    let mut chain = vec![locale.to_string()];
+    let mut tag = locale;
+    while let Some((parent, _)) = tag.rsplit_once('-') {
+        chain.push(parent.to_string());
+        tag = parent;
+    }
+    if !chain.iter().any(|locale| locale == fallback) {
+        chain.push(fallback.to_string());
+    }
+    chain
+}
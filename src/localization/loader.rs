@@ -0,0 +1,41 @@
+use bevy::{
+    asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext},
+    utils::BoxedFuture,
+};
+use thiserror::Error;
+
+use super::definition::Locale;
+
+#[derive(Default)]
+pub struct LocaleLoader;
+
+#[derive(Debug, Error)]
+pub enum LocaleLoaderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    RonSpannedError(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for LocaleLoader {
+    type Asset = Locale;
+    type Settings = ();
+    type Error = LocaleLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _: &'a Self::Settings,
+        _: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["locale"]
+    }
+}
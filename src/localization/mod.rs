@@ -0,0 +1,145 @@
+use std::fmt;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+pub use definition::*;
+use loader::LocaleLoader;
+
+mod definition;
+mod loader;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// The active UI language. Strings are looked up in `locale/<locale>.locale`, then in each
+/// less specific locale and finally in the fallback locale
+#[derive(Resource, Debug)]
+pub struct Localization {
+    locale: String,
+    fallback: String,
+    chain: Vec<Handle<Locale>>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Self {
+            locale: DEFAULT_LOCALE.to_string(),
+            fallback: DEFAULT_LOCALE.to_string(),
+            chain: Vec::new(),
+        }
+    }
+}
+
+impl Localization {
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Switches the UI language, every [LocalizedText] is updated once the locale files load
+    pub fn set_locale(&mut self, locale: impl Into<String>) {
+        let locale = locale.into();
+        if locale != self.locale {
+            self.locale = locale;
+            self.chain.clear();
+        }
+    }
+
+    /// Sets the locale used for strings missing from the active one
+    pub fn set_fallback(&mut self, fallback: impl Into<String>) {
+        let fallback = fallback.into();
+        if fallback != self.fallback {
+            self.fallback = fallback;
+            self.chain.clear();
+        }
+    }
+}
+
+/// Looks up UI strings in the active [Localization]
+#[derive(SystemParam)]
+pub struct Localizer<'w> {
+    localization: Res<'w, Localization>,
+    locales: Res<'w, Assets<Locale>>,
+}
+
+impl Localizer<'_> {
+    /// The string for `key` in the first locale of the fallback chain that has it, or `key`
+    /// itself if none do
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.localization
+            .chain
+            .iter()
+            .filter_map(|handle| self.locales.get(handle))
+            .find_map(|locale| locale.get(key))
+            .unwrap_or(key)
+    }
+
+    /// The string for `key` with each `{name}` in it replaced by the argument `name`
+    pub fn format(&self, key: &str, arguments: &[(&str, &dyn fmt::Display)]) -> String {
+        arguments
+            .iter()
+            .fold(self.get(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), &value.to_string())
+            })
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.localization.is_changed()
+    }
+}
+
+/// Replaces text sections with localized strings, keeping them up to date as the locale changes
+#[derive(Component, Debug, Clone, Default)]
+pub struct LocalizedText {
+    /// Section index and the key of the string it shows
+    pub sections: Vec<(usize, String)>,
+}
+
+impl LocalizedText {
+    pub fn new<K: Into<String>>(sections: impl IntoIterator<Item = (usize, K)>) -> Self {
+        Self {
+            sections: sections
+                .into_iter()
+                .map(|(section, key)| (section, key.into()))
+                .collect(),
+        }
+    }
+}
+
+fn load_locales(mut localization: ResMut<Localization>, asset_server: Res<AssetServer>) {
+    if !localization.chain.is_empty() {
+        return;
+    }
+    localization.chain = locale_chain(&localization.locale, &localization.fallback)
+        .into_iter()
+        .map(|locale| asset_server.load(format!("locale/{locale}.locale")))
+        .collect();
+}
+
+fn update_localized_text(
+    mut events: EventReader<AssetEvent<Locale>>,
+    localizer: Localizer,
+    mut texts: Query<(Ref<LocalizedText>, &mut Text)>,
+) {
+    let reload = localizer.is_changed() | (events.read().count() > 0);
+    for (localized, mut text) in texts.iter_mut() {
+        if !reload && !localized.is_changed() {
+            continue;
+        }
+        for (section, key) in localized.sections.iter() {
+            if let Some(section) = text.sections.get_mut(*section) {
+                section.value = localizer.get(key).to_string();
+            }
+        }
+    }
+}
+
+/// Loads [Locale] files for the active [Localization] and fills in [LocalizedText]
+pub struct LocalizationPlugin;
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Locale>()
+            .init_asset_loader::<LocaleLoader>()
+            .init_resource::<Localization>()
+            .add_systems(Update, (load_locales, update_localized_text).chain());
+    }
+}
@@ -1,14 +1,17 @@
-use bevy::pbr::wireframe::{WireframeConfig, WireframePlugin};
+use bevy::pbr::wireframe::WireframePlugin;
 use bevy::prelude::*;
 use bevy::render::settings::{RenderCreation, WgpuFeatures, WgpuSettings};
 use bevy::render::RenderPlugin;
 
-use bevy_flycam::PlayerPlugin;
-use cubizm_game::CubizmGameDefault;
+use cubizm_core::mods::ModsPlugin;
+use cubizm_game::prelude::*;
+use cubizm_player::PlayerPlugin;
 
 fn main() {
     let mut app = App::new();
 
+    // Registers an asset source per mod, which has to happen before `DefaultPlugins`
+    app.add_plugins(ModsPlugin::default());
     app.add_plugins((
         DefaultPlugins.set(RenderPlugin {
             render_creation: RenderCreation::Automatic(WgpuSettings {
@@ -21,16 +24,7 @@ fn main() {
         // You need to add this plugin to enable wireframe rendering
         WireframePlugin,
         CubizmGameDefault,
-        PlayerPlugin,
-    ))
-    .insert_resource(WireframeConfig {
-        // The global wireframe config enables drawing of wireframes on every mesh,
-        // except those with `NoWireframe`. Meshes with `Wireframe` will always have a wireframe,
-        // regardless of the global configuration.
-        global: true,
-        // Controls the default color of all wireframes. Used as the default color for global wireframes.
-        // Can be changed per mesh using the `WireframeColor` component.
-        default_color: Color::WHITE,
-    });
+        PlayerPlugin::default(),
+    ));
     app.run();
 }
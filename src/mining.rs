@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+use bevy::{
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+
+use cubizm_block::definition::{Block, DEFAULT_HARDNESS};
+use cubizm_block::{block_key, BlockRegistry, BASE_NAMESPACE};
+use cubizm_chunks::{hash_unit, Chunks, VoxelWorld};
+
+use crate::hotbar::BlockPickerState;
+use crate::input::{Action, ActionInput};
+use crate::target::{BlockTargetSet, TargetedBlock};
+
+/// Number of crack images the overlay steps through while a block breaks
+pub const CRACK_STAGES: usize = 10;
+/// Width and height in pixels of the crack images
+const CRACK_SIZE: u32 = 16;
+
+/// Configuration for [MiningPlugin]
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct MiningSettings {
+    /// Multiplies how fast blocks break, `2.0` breaks them in half their
+    /// [hardness](Block::hardness)
+    pub speed: f32,
+    /// Time between breaking blocks of no hardness while [Action::BreakBlock] is held, a fresh
+    /// press breaks one right away
+    pub instant_break_interval: Duration,
+}
+
+impl Default for MiningSettings {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            instant_break_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+/// The block the player is breaking
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct Mining {
+    /// World position of the block, `None` while not mining
+    pub target: Option<IVec3>,
+    /// From 0 to 1, the block breaks at 1
+    pub progress: f32,
+}
+
+/// Sent when the player breaks a block
+#[derive(Event, Debug, Clone)]
+pub struct BlockBroken {
+    pub position: IVec3,
+    /// The block that was broken
+    pub block: Handle<Block>,
+}
+
+/// Cube drawn over the block being broken, textured with its crack stage
+#[derive(Component, Debug)]
+struct CrackOverlay;
+
+/// One material per crack stage
+#[derive(Resource, Debug)]
+struct CrackMaterials(Vec<Handle<StandardMaterial>>);
+
+/// Breaks the [TargetedBlock] while [Action::BreakBlock] is held, taking longer the
+/// harder the block is and drawing cracks over it meanwhile
+#[derive(Default)]
+pub struct MiningPlugin {
+    pub settings: MiningSettings,
+}
+
+impl Plugin for MiningPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings)
+            .init_resource::<Mining>()
+            .add_event::<BlockBroken>()
+            .add_systems(Startup, spawn_crack_overlay)
+            .add_systems(
+                Update,
+                (
+                    mine_block
+                        .run_if(resource_exists::<Chunks>)
+                        .run_if(in_state(BlockPickerState::Closed))
+                        .after(BlockTargetSet),
+                    update_crack_overlay,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Pixels of the crack images in the order they crack, from a few random walks out of the
+/// centre
+fn crack_pixels() -> Vec<UVec2> {
+    const WALKS: u32 = 5;
+    const STEPS: u32 = 14;
+    let mut pixels = Vec::new();
+    for step in 0..STEPS {
+        for walk in 0..WALKS {
+            // Each walk heads out in its own direction and wobbles along the way
+            let angle = (walk as f32 + hash_unit(("crack", walk)) * 0.5) / WALKS as f32
+                * std::f32::consts::TAU;
+            let wobble = (hash_unit(("crack", walk, step)) - 0.5) * 0.8;
+            let direction = Vec2::from_angle(angle + wobble);
+            let pixel = (Vec2::splat(CRACK_SIZE as f32 / 2.0) + direction * step as f32 * 0.6)
+                .as_uvec2()
+                .min(UVec2::splat(CRACK_SIZE - 1));
+            if !pixels.contains(&pixel) {
+                pixels.push(pixel);
+            }
+        }
+    }
+    pixels
+}
+
+fn crack_image(pixels: &[UVec2]) -> Image {
+    let mut data = vec![0; (CRACK_SIZE * CRACK_SIZE * 4) as usize];
+    for pixel in pixels {
+        let index = ((pixel.y * CRACK_SIZE + pixel.x) * 4) as usize;
+        data[index..index + 4].copy_from_slice(&[20, 20, 20, 200]);
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: CRACK_SIZE,
+            height: CRACK_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+fn spawn_crack_overlay(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let pixels = crack_pixels();
+    let stages = (1..=CRACK_STAGES)
+        .map(|stage| {
+            let image = crack_image(&pixels[..pixels.len() * stage / CRACK_STAGES]);
+            materials.add(StandardMaterial {
+                base_color_texture: Some(images.add(image)),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                // Keeps the overlay from fighting with the block's faces
+                depth_bias: 10.0,
+                ..default()
+            })
+        })
+        .collect::<Vec<_>>();
+    commands.spawn((
+        CrackOverlay,
+        PbrBundle {
+            mesh: meshes.add(Cuboid::from_size(Vec3::splat(1.002))),
+            material: stages[0].clone(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        NotShadowCaster,
+        NotShadowReceiver,
+    ));
+    commands.insert_resource(CrackMaterials(stages));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn mine_block(
+    input: ActionInput,
+    time: Res<Time>,
+    settings: Res<MiningSettings>,
+    registry: Res<BlockRegistry>,
+    blocks: Res<Assets<Block>>,
+    target: Res<TargetedBlock>,
+    mut world: VoxelWorld,
+    mut mining: ResMut<Mining>,
+    mut broken: EventWriter<BlockBroken>,
+    mut last_break: Local<Option<Duration>>,
+) {
+    if !input.pressed(Action::BreakBlock) {
+        mining.set_if_neq(Mining::default());
+        return;
+    }
+    let Some(hit) = target.0.clone() else {
+        mining.set_if_neq(Mining::default());
+        return;
+    };
+    if mining.target != Some(hit.block) {
+        mining.target = Some(hit.block);
+        mining.progress = 0.0;
+    }
+    let hardness = blocks
+        .get(&hit.block_handle)
+        .map_or(DEFAULT_HARDNESS, Block::hardness);
+    if hardness < 0.0 {
+        return;
+    }
+    mining.progress = if hardness == 0.0 {
+        // Held down these would break one a frame, wait between them unless pressed again
+        let waited =
+            last_break.is_none_or(|at| time.elapsed() - at >= settings.instant_break_interval);
+        if !waited && !input.just_pressed(Action::BreakBlock) {
+            return;
+        }
+        1.0
+    } else {
+        mining.progress + time.delta_seconds() * settings.speed / hardness
+    };
+    if mining.progress < 1.0 {
+        return;
+    }
+    let Some(air) = registry.get(&block_key(BASE_NAMESPACE, "Air")) else {
+        return;
+    };
+    match world.set_block(hit.block, air.clone()) {
+        Ok(()) => {
+            *last_break = Some(time.elapsed());
+            broken.send(BlockBroken {
+                position: hit.block,
+                block: hit.block_handle,
+            });
+        }
+        Err(err) => warn!("Failed to break block at {}: {err}", hit.block),
+    }
+    mining.set_if_neq(Mining::default());
+}
+
+fn update_crack_overlay(
+    mining: Res<Mining>,
+    stages: Option<Res<CrackMaterials>>,
+    mut overlay: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut Handle<StandardMaterial>,
+        ),
+        With<CrackOverlay>,
+    >,
+) {
+    if !mining.is_changed() {
+        return;
+    }
+    let Some(stages) = stages else {
+        return;
+    };
+    for (mut transform, mut visibility, mut material) in overlay.iter_mut() {
+        let Some(target) = mining.target.filter(|_| mining.progress > 0.0) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let stage = ((mining.progress * CRACK_STAGES as f32) as usize).min(CRACK_STAGES - 1);
+        // Blocks span `position..position + 1`
+        transform.translation = target.as_vec3() + Vec3::splat(0.5);
+        *material = stages.0[stage].clone();
+        *visibility = Visibility::Inherited;
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use cubizm_player::{PlayerSet, PlayerSettings};
+
+use crate::accessibility::{AccessibilitySettings, ButtonMode};
+use crate::input::{Action, ActionInput};
+
+const SPRINT_MULTIPLIER: f32 = 2.;
+const SNEAK_MULTIPLIER: f32 = 0.25;
+
+/// Movement states the player can hold or toggle, see [ButtonMode]
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MovementModifiers {
+    pub sprinting: bool,
+    pub sneaking: bool,
+}
+
+impl MovementModifiers {
+    pub fn speed_multiplier(&self) -> f32 {
+        match (self.sprinting, self.sneaking) {
+            (_, true) => SNEAK_MULTIPLIER,
+            (true, false) => SPRINT_MULTIPLIER,
+            (false, false) => 1.,
+        }
+    }
+}
+
+fn modifier_active(input: &ActionInput, action: Action, mode: ButtonMode, active: bool) -> bool {
+    match mode {
+        ButtonMode::Hold => input.pressed(action),
+        ButtonMode::Toggle => active != input.just_pressed(action),
+    }
+}
+
+fn update_movement_modifiers(
+    input: ActionInput,
+    accessibility: Res<AccessibilitySettings>,
+    mut modifiers: ResMut<MovementModifiers>,
+) {
+    let updated = MovementModifiers {
+        sprinting: modifier_active(
+            &input,
+            Action::Sprint,
+            accessibility.sprint,
+            modifiers.sprinting,
+        ),
+        sneaking: modifier_active(
+            &input,
+            Action::Sneak,
+            accessibility.sneak,
+            modifiers.sneaking,
+        ),
+    };
+    modifiers.set_if_neq(updated);
+}
+
+fn apply_movement_speed(modifiers: Res<MovementModifiers>, mut player: ResMut<PlayerSettings>) {
+    if modifiers.is_changed() {
+        player.speed_multiplier = modifiers.speed_multiplier();
+    }
+}
+
+/// Sprint and sneak for the player
+pub struct MovementPlugin;
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MovementModifiers>()
+            .init_resource::<PlayerSettings>()
+            .add_systems(
+                Update,
+                (update_movement_modifiers, apply_movement_speed)
+                    .chain()
+                    .before(PlayerSet),
+            );
+    }
+}
@@ -0,0 +1,217 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::window::PrimaryWindow;
+use image::imageops::{self, FilterType};
+use image::RgbImage;
+
+use cubizm_player::{CharacterController, Player};
+
+use crate::input::{Action, ActionInput};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, States)]
+pub enum PhotoModeState {
+    #[default]
+    Off,
+    On,
+}
+
+/// Run condition, whether photo mode is on. Always `false` without [PhotoModePlugin]
+pub fn photo_mode_active(state: Option<Res<State<PhotoModeState>>>) -> bool {
+    state.is_some_and(|state| *state.get() == PhotoModeState::On)
+}
+
+/// Configuration for [PhotoModePlugin]
+#[derive(Resource, Debug, Clone)]
+pub struct PhotoModeSettings {
+    /// Size of the saved image, cropped to its aspect ratio. `None` keeps the window resolution
+    pub resolution: Option<UVec2>,
+    pub hide_player: bool,
+    pub directory: PathBuf,
+}
+
+impl Default for PhotoModeSettings {
+    fn default() -> Self {
+        Self {
+            resolution: None,
+            hide_player: true,
+            directory: PathBuf::from("screenshots"),
+        }
+    }
+}
+
+/// Marks the player's visible body, hidden in photo mode if [PhotoModeSettings::hide_player] is set
+#[derive(Component, Debug, Default)]
+pub struct PlayerBody;
+
+/// The free camera spawned while photo mode is active
+#[derive(Component, Debug)]
+struct PhotoCamera;
+
+/// The camera photo mode detached from, restored on exit
+#[derive(Component, Debug)]
+struct DetachedCamera;
+
+/// Visibility an entity had before photo mode hid it
+#[derive(Component, Debug)]
+struct HiddenForPhoto(Visibility);
+
+fn toggle_photo_mode(
+    input: ActionInput,
+    state: Res<State<PhotoModeState>>,
+    mut next_state: ResMut<NextState<PhotoModeState>>,
+) {
+    if !input.just_pressed(Action::TogglePhotoMode) {
+        return;
+    }
+    next_state.set(match state.get() {
+        PhotoModeState::Off => PhotoModeState::On,
+        PhotoModeState::On => PhotoModeState::Off,
+    });
+}
+
+#[allow(clippy::type_complexity)]
+fn enter_photo_mode(
+    mut commands: Commands,
+    settings: Res<PhotoModeSettings>,
+    mut cameras: Query<(Entity, &mut Camera, &Transform, &Projection), With<Player>>,
+    mut hud: Query<(Entity, &mut Visibility), (With<Node>, Without<Parent>)>,
+    mut player: Query<(Entity, &mut Visibility), (With<PlayerBody>, Without<Node>)>,
+) {
+    for (entity, mut camera, transform, projection) in cameras.iter_mut() {
+        camera.is_active = false;
+        commands
+            .entity(entity)
+            .remove::<Player>()
+            .insert(DetachedCamera);
+        commands.spawn((
+            Camera3dBundle {
+                transform: *transform,
+                projection: projection.clone(),
+                ..default()
+            },
+            Player,
+            CharacterController::flying(),
+            PhotoCamera,
+        ));
+    }
+
+    let mut hide = |entity: Entity, mut visibility: Mut<Visibility>| {
+        commands.entity(entity).insert(HiddenForPhoto(*visibility));
+        *visibility = Visibility::Hidden;
+    };
+    for (entity, visibility) in hud.iter_mut() {
+        hide(entity, visibility);
+    }
+    if settings.hide_player {
+        for (entity, visibility) in player.iter_mut() {
+            hide(entity, visibility);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn exit_photo_mode(
+    mut commands: Commands,
+    photo_cameras: Query<Entity, With<PhotoCamera>>,
+    mut detached: Query<(Entity, &mut Camera), (With<DetachedCamera>, Without<PhotoCamera>)>,
+    mut hidden: Query<(Entity, &mut Visibility, &HiddenForPhoto)>,
+) {
+    for entity in photo_cameras.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (entity, mut camera) in detached.iter_mut() {
+        camera.is_active = true;
+        commands
+            .entity(entity)
+            .remove::<DetachedCamera>()
+            .insert(Player);
+    }
+    for (entity, mut visibility, HiddenForPhoto(previous)) in hidden.iter_mut() {
+        *visibility = *previous;
+        commands.entity(entity).remove::<HiddenForPhoto>();
+    }
+}
+
+fn capture_photo(
+    input: ActionInput,
+    settings: Res<PhotoModeSettings>,
+    window: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    if !input.just_pressed(Action::CapturePhoto) {
+        return;
+    }
+    let Ok(window) = window.get_single() else {
+        warn!("Primary window not found for `capture_photo`!");
+        return;
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = settings.directory.join(format!("photo-{timestamp}.png"));
+    let resolution = settings.resolution;
+    if let Err(err) = screenshot_manager
+        .take_screenshot(window, move |image| save_photo(image, resolution, &path))
+    {
+        warn!("{err}");
+    }
+}
+
+/// Scales `image` to cover `size` and crops the overflow evenly off both sides, keeping the
+/// aspect ratio of the window instead of stretching it
+fn fit_photo(image: &RgbImage, size: UVec2) -> RgbImage {
+    let size = size.max(UVec2::ONE);
+    let scale = (size.x as f32 / image.width() as f32).max(size.y as f32 / image.height() as f32);
+    let scaled = (Vec2::new(image.width() as f32, image.height() as f32) * scale)
+        .ceil()
+        .as_uvec2()
+        .max(size);
+    let scaled = imageops::resize(image, scaled.x, scaled.y, FilterType::Lanczos3);
+    let offset = (UVec2::new(scaled.width(), scaled.height()) - size) / 2;
+    imageops::crop_imm(&scaled, offset.x, offset.y, size.x, size.y).to_image()
+}
+
+fn save_photo(image: Image, resolution: Option<UVec2>, path: &Path) {
+    let image = match image.try_into_dynamic() {
+        // Drop the alpha channel, it holds brightness values when HDR is enabled
+        Ok(image) => image.to_rgb8(),
+        Err(err) => {
+            error!("Cannot save photo, screen format cannot be understood: {err}");
+            return;
+        }
+    };
+    let image = match resolution {
+        Some(size) if size != UVec2::new(image.width(), image.height()) => fit_photo(&image, size),
+        _ => image,
+    };
+
+    if let Some(directory) = path.parent() {
+        if let Err(err) = std::fs::create_dir_all(directory) {
+            error!("Cannot create {}: {err}", directory.display());
+            return;
+        }
+    }
+    match image.save(path) {
+        Ok(()) => info!("Photo saved to {}", path.display()),
+        Err(err) => error!("Cannot save photo: {err}"),
+    }
+}
+
+/// Detaches a free camera, hides the HUD and player and captures screenshots to
+/// [PhotoModeSettings::directory]
+pub struct PhotoModePlugin;
+impl Plugin for PhotoModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhotoModeSettings>()
+            .init_state::<PhotoModeState>()
+            .add_systems(Update, toggle_photo_mode)
+            .add_systems(Update, capture_photo.run_if(in_state(PhotoModeState::On)))
+            .add_systems(OnEnter(PhotoModeState::On), enter_photo_mode)
+            .add_systems(OnExit(PhotoModeState::On), exit_photo_mode);
+    }
+}
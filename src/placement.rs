@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use cubizm_block::definition::Block;
+use cubizm_block::{BlockRegistry, BlockState};
+use cubizm_chunks::{BlockInteract, Chunks, VoxelWorld};
+use cubizm_inventory::Inventory;
+use cubizm_player::{Player, PlayerSettings};
+
+use crate::hotbar::{BlockPickerState, SelectedBlock};
+use crate::input::{Action, ActionInput};
+use crate::inventory::PlayerInventorySettings;
+use crate::target::{BlockTargetSet, TargetedBlock};
+
+/// Sent when the player places a block
+#[derive(Event, Debug, Clone)]
+pub struct BlockPlaced {
+    pub position: IVec3,
+    pub block: Handle<Block>,
+}
+
+/// Whether the player's collision box overlaps the block at `position`
+fn overlaps_player(position: IVec3, eye: Vec3, settings: &PlayerSettings) -> bool {
+    let min = eye
+        - Vec3::new(
+            settings.body_size.x / 2.,
+            settings.eye_height,
+            settings.body_size.z / 2.,
+        );
+    let max = min + settings.body_size;
+    // Blocks span `position..position + 1`
+    let block = position.as_vec3();
+    min.cmplt(block + Vec3::ONE).all() && max.cmpgt(block).all()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn place_block(
+    input: ActionInput,
+    selected: Res<SelectedBlock>,
+    target: Res<TargetedBlock>,
+    settings: Res<PlayerSettings>,
+    inventory_settings: Res<PlayerInventorySettings>,
+    registry: Res<BlockRegistry>,
+    blocks: Res<Assets<Block>>,
+    mut players: Query<(&Camera, &GlobalTransform, Option<&mut Inventory>), With<Player>>,
+    mut world: VoxelWorld,
+    mut placed: EventWriter<BlockPlaced>,
+    mut interactions: EventWriter<BlockInteract>,
+) {
+    if !input.just_pressed(Action::PlaceBlock) {
+        return;
+    }
+    let Some(hit) = &target.0 else {
+        return;
+    };
+    // Tile entities are used instead, sneaking places against them
+    if !input.pressed(Action::Sneak)
+        && blocks
+            .get(&hit.block_handle)
+            .is_some_and(|block| !block.is_voxel())
+    {
+        interactions.send(BlockInteract {
+            position: hit.block,
+            block: hit.block_handle.clone(),
+        });
+        return;
+    }
+    let Some(block) = &selected.0 else {
+        return;
+    };
+    let Some((_, eye, mut inventory)) = players.iter_mut().find(|(camera, _, _)| camera.is_active)
+    else {
+        return;
+    };
+    if overlaps_player(hit.place, eye.translation(), &settings) {
+        return;
+    }
+    // Outside creative mode the block comes out of the inventory
+    let item = match inventory_settings.creative {
+        true => None,
+        false => {
+            let Some(name) = registry.name(block) else {
+                return;
+            };
+            match inventory.as_mut() {
+                Some(inventory) if inventory.count(name) > 0 => Some(name),
+                _ => return,
+            }
+        }
+    };
+    match world.set_block(hit.place, block.clone()) {
+        Ok(()) => {
+            // Turn the block to face the player or lie along the face it was placed against
+            if let Some(definition) = blocks.get(block) {
+                let state = BlockState::placed(definition.properties(), hit.normal, eye.forward());
+                if state != BlockState::default() {
+                    if let Err(err) = world.set_block_state(hit.place, state) {
+                        warn!("Failed to turn block at {}: {err}", hit.place);
+                    }
+                }
+            }
+            if let (Some(item), Some(inventory)) = (item, inventory.as_mut()) {
+                inventory.remove(item, 1);
+            }
+            placed.send(BlockPlaced {
+                position: hit.place,
+                block: block.clone(),
+            });
+        }
+        Err(err) => warn!("Failed to place block at {}: {err}", hit.place),
+    }
+}
+
+/// Puts the [SelectedBlock] against the face of the [TargetedBlock] on [Action::PlaceBlock],
+/// unless the player stands in the way or, outside
+/// [creative mode](PlayerInventorySettings::creative), has none of it in their [Inventory].
+/// Targeted tile entities get a [BlockInteract] instead unless the player sneaks
+pub struct PlacementPlugin;
+impl Plugin for PlacementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BlockPlaced>().add_systems(
+            Update,
+            place_block
+                .run_if(resource_exists::<Chunks>)
+                .run_if(resource_exists::<BlockRegistry>)
+                .run_if(in_state(BlockPickerState::Closed))
+                .after(BlockTargetSet),
+        );
+    }
+}
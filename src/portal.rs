@@ -0,0 +1,134 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use cubizm_block::BlockRegistry;
+use cubizm_chunks::{ActiveWorld, Chunk, Chunks, WorldId, WorldManifest, Worlds};
+use cubizm_core::{point_to_block, GameTime};
+use cubizm_player::{Player, PlayerSettings};
+
+use crate::teleport::{TeleportHold, WorldTeleport};
+
+/// Blocks [PortalPlugin] takes the player through
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct PortalSettings {
+    /// Registry name of the portal block, see [BlockRegistry::find]. Portal blocks lead where
+    /// the [PortalLink](cubizm_chunks::PortalLink) of the active world's [WorldManifest]
+    /// whose corners hold them does, those outside of every link lead nowhere
+    pub block: String,
+}
+
+impl Default for PortalSettings {
+    fn default() -> Self {
+        Self {
+            block: "portal".to_string(),
+        }
+    }
+}
+
+/// The [GameTime] of every world that isn't active, put back when switching to it, so time
+/// stands still in the worlds the player left. A world entered for the first time starts at
+/// the time of the world left
+#[derive(Resource, Debug, Default)]
+pub struct WorldTimes {
+    parked: HashMap<WorldId, GameTime>,
+}
+
+impl WorldTimes {
+    /// Keeps `time` as the time of `from` and gives the time of `to`, `None` the first time
+    pub fn switch(&mut self, from: WorldId, to: &WorldId, time: &GameTime) -> Option<GameTime> {
+        self.parked.insert(from, time.clone());
+        self.parked.remove(to)
+    }
+
+    pub fn get(&self, world: &WorldId) -> Option<&GameTime> {
+        self.parked.get(world)
+    }
+}
+
+/// Sends the player through the portal they stepped into. Portals only lead on once the
+/// player left the last one, so arriving in a portal does not lead straight back
+#[allow(clippy::too_many_arguments)]
+fn enter_portals(
+    settings: Res<PortalSettings>,
+    player_settings: Res<PlayerSettings>,
+    registry: Res<BlockRegistry>,
+    chunks: Res<Chunks>,
+    assets_chunks: Res<Assets<Chunk>>,
+    active: Res<ActiveWorld>,
+    manifests: Res<Assets<WorldManifest>>,
+    player: Query<&Transform, (With<Player>, Without<TeleportHold>)>,
+    mut teleports: EventWriter<WorldTeleport>,
+    mut in_portal: Local<bool>,
+) {
+    let (Some(portal), Some(manifest)) = (registry.find(&settings.block), manifests.get(&active.0))
+    else {
+        return;
+    };
+    for transform in player.iter() {
+        let feet = transform.translation - Vec3::Y * player_settings.eye_height;
+        let blocks = [feet, transform.translation].map(point_to_block);
+        let portals: Vec<IVec3> = blocks
+            .into_iter()
+            .filter(|block| chunks.get_block(*block, &assets_chunks).ok().as_ref() == Some(portal))
+            .collect();
+        let entered = !portals.is_empty() && !*in_portal;
+        *in_portal = !portals.is_empty();
+        if !entered {
+            continue;
+        }
+        if let Some(link) = portals.iter().find_map(|block| manifest.portal_at(*block)) {
+            teleports.send(WorldTeleport {
+                world: link.world.clone(),
+                destination: link.destination,
+            });
+        }
+    }
+}
+
+/// Parks the [GameTime] of the world switched away from in [WorldTimes] and restores that of
+/// the world switched to
+fn switch_world_time(
+    worlds: Res<Worlds>,
+    mut times: ResMut<WorldTimes>,
+    mut time: ResMut<GameTime>,
+    mut current: Local<Option<WorldId>>,
+) {
+    let active = worlds.active();
+    let Some(previous) = current.replace(active.clone()) else {
+        return;
+    };
+    if previous == *active {
+        return;
+    }
+    if let Some(restored) = times.switch(previous, active, &time) {
+        *time = restored;
+    }
+}
+
+/// Takes the player to another world of [Worlds] when they step into the portal blocks of a
+/// [PortalLink](cubizm_chunks::PortalLink), see [PortalSettings], and keeps the [GameTime] of
+/// every world apart, see [WorldTimes]
+#[derive(Default)]
+pub struct PortalPlugin {
+    pub settings: PortalSettings,
+}
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .init_resource::<WorldTimes>()
+            .add_event::<WorldTeleport>()
+            .add_systems(
+                Update,
+                (
+                    enter_portals
+                        .run_if(resource_exists::<Chunks>)
+                        .run_if(resource_exists::<BlockRegistry>)
+                        .run_if(resource_exists::<ActiveWorld>)
+                        .run_if(resource_exists::<PlayerSettings>),
+                    switch_world_time
+                        .run_if(resource_exists::<Worlds>)
+                        .run_if(resource_exists::<GameTime>),
+                ),
+            );
+    }
+}
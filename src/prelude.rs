@@ -0,0 +1,15 @@
+//! The types games built on cubizm use the most, re-exported from the crate that defines each,
+//! so there is one path to import them from
+pub use crate::settings::CubizmSettings;
+pub use crate::CubizmGameDefault;
+
+pub use cubizm_block::{
+    definition::Block, BlockAtlas, BlockPlugin, BlockPluginSettings, BlockRegistry, BlockSampling,
+    BlockState, BlockTextureMode,
+};
+pub use cubizm_chunks::{
+    BlockChanged, BlockInteract, ChunkVertexFormat, Chunks, ChunksPlugin, ChunksPluginSettings,
+    MeshingMode, SwitchWorld, VoxelWorld, WorldId, Worlds,
+};
+pub use cubizm_core::{AppState, Cubizm, GameTime, LoadingStateApp, RenderDistance};
+pub use cubizm_inventory::{InventoryPlugin, ItemPlugin};
@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+
+use cubizm_block::definition::Block;
+use cubizm_chunks::BlockInteract;
+use cubizm_rhai::{block_name, RhaiPlugin, ScriptRuntime};
+
+use crate::input::{Action, ActionInput};
+use crate::target::{BlockTargetSet, TargetedBlock};
+
+/// Sends [BlockInteract] for the voxel block the player looks at when they press
+/// [Action::PlaceBlock] and a script uses it, tile entities already get one from placing
+fn use_scripted_blocks(
+    input: ActionInput,
+    target: Res<TargetedBlock>,
+    blocks: Res<Assets<Block>>,
+    runtime: Res<ScriptRuntime>,
+    mut interactions: EventWriter<BlockInteract>,
+) {
+    if !input.just_pressed(Action::PlaceBlock) {
+        return;
+    }
+    let Some(hit) = &target.0 else {
+        return;
+    };
+    let voxel = blocks.get(&hit.block_handle).is_some_and(Block::is_voxel);
+    if voxel
+        && !runtime
+            .use_handlers(&block_name(&hit.block_handle))
+            .is_empty()
+    {
+        interactions.send(BlockInteract {
+            position: hit.block,
+            block: hit.block_handle.clone(),
+        });
+    }
+}
+
+/// Runs the rhai scripts with [RhaiPlugin] and lets the player use the blocks they handle
+pub struct ScriptingPlugin;
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RhaiPlugin::default())
+            .add_systems(Update, use_scripted_blocks.after(BlockTargetSet));
+    }
+}
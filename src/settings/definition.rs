@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::accessibility::AccessibilitySettings;
+use crate::gamepad::GamepadSettings;
+use crate::input::InputMap;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Volumes {
+    pub master: f32,
+    pub music: f32,
+    pub ambient: f32,
+}
+
+/// Player facing tunables, loaded from `settings.ron` and re-applied whenever the file changes
+#[derive(Debug, Clone, Asset, TypePath, Deserialize, Serialize)]
+pub struct GameSettings {
+    /// In chunks
+    pub render_distance: u32,
+    /// In chunks, terrain past [GameSettings::render_distance] is drawn as low detail
+    /// impostors up to here
+    pub far_terrain_distance: u32,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    /// Multiplier on the default mouse look speed
+    pub mouse_sensitivity: f32,
+    /// Borderless on the current monitor
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub input: InputMap,
+    pub gamepad: GamepadSettings,
+    pub volumes: Volumes,
+    /// Draws the edges of every mesh
+    pub wireframe: bool,
+    pub accessibility: AccessibilitySettings,
+    /// UI language such as `en` or `pt-BR`, see [Localization](crate::localization::Localization)
+    pub locale: String,
+}
+
+/// The [GameSettings] the player changes while playing, kept in sync with `settings.ron`:
+/// edits to the file show up here and changes to the resource are applied right away and
+/// saved back to the file
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct CubizmSettings {
+    /// In chunks
+    pub render_distance: u32,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    /// Multiplier on the default mouse look speed
+    pub mouse_sensitivity: f32,
+    /// Borderless on the current monitor
+    pub fullscreen: bool,
+    pub vsync: bool,
+    /// Draws the edges of every mesh
+    pub wireframe: bool,
+}
+
+impl Default for CubizmSettings {
+    fn default() -> Self {
+        Self {
+            render_distance: 8,
+            fov: 45.,
+            mouse_sensitivity: 1.,
+            fullscreen: false,
+            vsync: true,
+            wireframe: true,
+        }
+    }
+}
+
+impl From<&GameSettings> for CubizmSettings {
+    fn from(settings: &GameSettings) -> Self {
+        Self {
+            render_distance: settings.render_distance,
+            fov: settings.fov,
+            mouse_sensitivity: settings.mouse_sensitivity,
+            fullscreen: settings.fullscreen,
+            vsync: settings.vsync,
+            wireframe: settings.wireframe,
+        }
+    }
+}
+
+impl GameSettings {
+    /// Overwrites the settings the player changes while playing
+    pub fn set_cubizm_settings(&mut self, settings: &CubizmSettings) {
+        self.render_distance = settings.render_distance;
+        self.fov = settings.fov;
+        self.mouse_sensitivity = settings.mouse_sensitivity;
+        self.fullscreen = settings.fullscreen;
+        self.vsync = settings.vsync;
+        self.wireframe = settings.wireframe;
+    }
+}
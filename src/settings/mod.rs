@@ -0,0 +1,187 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::ron;
+use bevy::audio::Volume;
+use bevy::pbr::wireframe::WireframeConfig;
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode};
+
+use cubizm_chunks::{FarTerrainDistance, RenderDistance};
+use cubizm_player::PlayerSettings;
+
+use crate::accessibility::AccessibilitySettings;
+use crate::audio::AudioVolumes;
+use crate::gamepad::GamepadSettings;
+use crate::input::{Action, ActionInput, InputMap};
+use crate::localization::Localization;
+
+pub use definition::*;
+use loader::GameSettingsLoader;
+
+mod definition;
+mod loader;
+
+#[derive(Resource, Default)]
+pub struct GameSettingsHandle(Handle<GameSettings>);
+
+impl GameSettingsHandle {
+    pub fn clone_handle(&self) -> Handle<GameSettings> {
+        Handle::clone(&self.0)
+    }
+}
+
+fn load_settings(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(GameSettingsHandle(
+        asset_server.load::<GameSettings>("settings.ron"),
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_settings(
+    mut events: EventReader<AssetEvent<GameSettings>>,
+    settings_handle: Res<GameSettingsHandle>,
+    settings: Res<Assets<GameSettings>>,
+    mut cubizm_settings: ResMut<CubizmSettings>,
+    mut far_terrain_distance: ResMut<FarTerrainDistance>,
+    mut input_map: ResMut<InputMap>,
+    mut gamepad: ResMut<GamepadSettings>,
+    mut accessibility: ResMut<AccessibilitySettings>,
+    mut localization: ResMut<Localization>,
+    mut global_volume: ResMut<GlobalVolume>,
+    mut volumes: ResMut<AudioVolumes>,
+) {
+    let changed = events.read().fold(false, |changed, event| {
+        changed
+            || event.is_loaded_with_dependencies(&settings_handle.0)
+            || event.is_modified(&settings_handle.0)
+    });
+    if !changed {
+        return;
+    }
+    let Some(settings) = settings.get(&settings_handle.0) else {
+        return;
+    };
+
+    cubizm_settings.set_if_neq(CubizmSettings::from(settings));
+    far_terrain_distance.0 = settings.far_terrain_distance;
+
+    input_map.set_if_neq(settings.input.clone());
+    *gamepad = settings.gamepad.clone();
+    accessibility.set_if_neq(settings.accessibility.clone());
+    if localization.locale() != settings.locale {
+        localization.set_locale(settings.locale.clone());
+    }
+
+    global_volume.volume = Volume::new(settings.volumes.master);
+    *volumes = AudioVolumes {
+        music: settings.volumes.music,
+        ambient: settings.volumes.ambient,
+    };
+}
+
+/// Applies [CubizmSettings] to the camera, window, controls and chunks whenever they change,
+/// and once they are added. Cameras spawned later are picked up too
+fn apply_cubizm_settings(
+    settings: Res<CubizmSettings>,
+    mut render_distance: ResMut<RenderDistance>,
+    mut player: ResMut<PlayerSettings>,
+    wireframe: Option<ResMut<WireframeConfig>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut projections: Query<&mut Projection, With<Camera3d>>,
+    added_cameras: Query<(), Added<Camera3d>>,
+) {
+    if !settings.is_changed() && added_cameras.is_empty() {
+        return;
+    }
+
+    render_distance.set_if_neq(RenderDistance(settings.render_distance));
+    player.sensitivity = PlayerSettings::default().sensitivity * settings.mouse_sensitivity;
+
+    if let Some(mut wireframe) = wireframe {
+        wireframe.global = settings.wireframe;
+    }
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        let mode = if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        };
+        let present_mode = if settings.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        };
+        // Only touch the window when needed, every change reconfigures it
+        if window.mode != mode {
+            window.mode = mode;
+        }
+        if window.present_mode != present_mode {
+            window.present_mode = present_mode;
+        }
+    }
+
+    for mut projection in projections.iter_mut() {
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = settings.fov.to_radians();
+        }
+    }
+}
+
+fn toggle_wireframe(input: ActionInput, mut settings: ResMut<CubizmSettings>) {
+    if input.just_pressed(Action::ToggleWireframe) {
+        settings.wireframe = !settings.wireframe;
+    }
+}
+
+/// Writes [CubizmSettings] changed in game back to `settings.ron`, leaving the ones that were
+/// just read from it alone
+fn save_cubizm_settings(
+    cubizm_settings: Res<CubizmSettings>,
+    settings_handle: Res<GameSettingsHandle>,
+    mut settings: ResMut<Assets<GameSettings>>,
+) {
+    if !cubizm_settings.is_changed() {
+        return;
+    }
+    let Some(current) = settings.get(&settings_handle.0) else {
+        return;
+    };
+    if CubizmSettings::from(current) == *cubizm_settings {
+        return;
+    }
+    let Some(settings) = settings.get_mut(&settings_handle.0) else {
+        return;
+    };
+    settings.set_cubizm_settings(&cubizm_settings);
+    let path = FileAssetReader::get_base_path().join("assets/settings.ron");
+    let saved = ron::ser::to_string_pretty(&*settings, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|text| std::fs::write(path, text).map_err(|err| err.to_string()));
+    if let Err(err) = saved {
+        warn!("Failed to save settings: {err}");
+    }
+}
+
+/// Loads [GameSettings] from `settings.ron` and applies them live to the camera, window,
+/// controls, audio and chunk render distance. Changes made in game through [CubizmSettings]
+/// are saved back to the file
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<GameSettings>()
+            .init_asset_loader::<GameSettingsLoader>()
+            .init_resource::<PlayerSettings>()
+            .init_resource::<CubizmSettings>()
+            .add_systems(Startup, load_settings)
+            .add_systems(
+                Update,
+                (
+                    apply_settings,
+                    toggle_wireframe,
+                    apply_cubizm_settings,
+                    save_cubizm_settings,
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,295 @@
+use bevy::{
+    asset::load_internal_asset,
+    core_pipeline::Skybox,
+    pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+            TextureViewDescriptor, TextureViewDimension,
+        },
+        view::NoFrustumCulling,
+    },
+};
+
+use cubizm_core::{GameTime, Sun};
+
+const SKY_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0x2f8c_71d4_a03e_4b96_9e52_c61b_7d08_f3a5);
+
+/// What is drawn behind the world
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkyMode {
+    /// A dome fading from the [SkyColors::horizon] to the [SkyColors::zenith] with the sun on it
+    Gradient,
+    /// A [Skybox] on every 3D camera
+    Cubemap {
+        /// Asset path of an image with the six faces of the cubemap stacked vertically, in the
+        /// order +x, -x, +y, -y, +z, -z
+        image: String,
+        /// [Skybox::brightness] at noon
+        day_brightness: f32,
+        /// [Skybox::brightness] at night
+        night_brightness: f32,
+    },
+}
+
+/// A pair of sky colours at the top of the sky and at the horizon
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkyColors {
+    pub zenith: Color,
+    pub horizon: Color,
+}
+
+/// Configuration for [SkyPlugin]. The mode is only read at startup, the colours can be changed
+/// at any time
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct SkySettings {
+    pub mode: SkyMode,
+    pub day: SkyColors,
+    pub night: SkyColors,
+    /// Colour the horizon glows with while the sun rises and sets
+    pub sunset: Color,
+    pub sun: Color,
+}
+
+impl Default for SkySettings {
+    fn default() -> Self {
+        Self {
+            mode: SkyMode::Gradient,
+            day: SkyColors {
+                zenith: Color::rgb(0.25, 0.5, 0.95),
+                horizon: Color::rgb(0.7, 0.85, 1.0),
+            },
+            night: SkyColors {
+                zenith: Color::rgb(0.005, 0.008, 0.03),
+                horizon: Color::rgb(0.04, 0.05, 0.1),
+            },
+            sunset: Color::rgb(1.0, 0.5, 0.25),
+            sun: Color::rgb(1.0, 0.95, 0.8),
+        }
+    }
+}
+
+/// Material of the sky dome in [SkyMode::Gradient], its colours follow the [GameTime]
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone, Default)]
+pub struct SkyMaterial {
+    #[uniform(0)]
+    pub zenith: Color,
+    #[uniform(0)]
+    pub horizon: Color,
+    /// Towards the sun, `w` is unused
+    #[uniform(0)]
+    pub sun_direction: Vec4,
+    /// The alpha fades the sun out below the horizon
+    #[uniform(0)]
+    pub sun_color: Color,
+}
+
+impl Material for SkyMaterial {
+    fn vertex_shader() -> ShaderRef {
+        SKY_SHADER.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SKY_SHADER.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.vertex.buffers =
+            vec![layout.get_layout(&[Mesh::ATTRIBUTE_POSITION.at_shader_location(0)])?];
+        // The camera is inside the dome
+        descriptor.primitive.cull_mode = None;
+        if let Some(depth_stencil) = descriptor.depth_stencil.as_mut() {
+            depth_stencil.depth_write_enabled = false;
+        }
+        Ok(())
+    }
+}
+
+/// The sky dome, kept around the active camera
+#[derive(Component, Debug)]
+pub struct SkyDome;
+
+/// The cubemap of [SkyMode::Cubemap], and whether it was turned into a cube texture yet
+#[derive(Resource, Debug)]
+struct SkyCubemap {
+    image: Handle<Image>,
+    ready: bool,
+}
+
+/// Draws a sky behind the world that changes with the [GameTime] of
+/// [DayNightPlugin](cubizm_core::DayNightPlugin), replacing the flat [ClearColor]
+#[derive(Default)]
+pub struct SkyPlugin {
+    pub settings: SkySettings,
+}
+
+impl Plugin for SkyPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, SKY_SHADER, "sky.wgsl", Shader::from_wgsl);
+        app.add_plugins(MaterialPlugin::<SkyMaterial> {
+            // The prepass would draw the dome at its real depth, in front of the world
+            prepass_enabled: false,
+            ..default()
+        })
+        .insert_resource(self.settings.clone())
+        .add_systems(Startup, spawn_sky)
+        .add_systems(
+            Update,
+            (
+                follow_camera,
+                update_sky_colors,
+                (prepare_cubemap, update_skyboxes).chain(),
+            ),
+        );
+    }
+}
+
+fn spawn_sky(
+    mut commands: Commands,
+    settings: Res<SkySettings>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<SkyMaterial>>,
+) {
+    match &settings.mode {
+        SkyMode::Gradient => {
+            commands.spawn((
+                SkyDome,
+                MaterialMeshBundle {
+                    mesh: meshes.add(Sphere::new(10.0).mesh().uv(32, 16)),
+                    material: materials.add(SkyMaterial::default()),
+                    ..default()
+                },
+                NoFrustumCulling,
+                NotShadowCaster,
+                NotShadowReceiver,
+            ));
+        }
+        SkyMode::Cubemap { image, .. } => {
+            commands.insert_resource(SkyCubemap {
+                image: asset_server.load(image.clone()),
+                ready: false,
+            });
+        }
+    }
+}
+
+fn follow_camera(
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut domes: Query<&mut Transform, With<SkyDome>>,
+) {
+    let Some((_, camera)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    for mut transform in domes.iter_mut() {
+        transform.translation = camera.translation();
+    }
+}
+
+fn mix(from: Color, to: Color, amount: f32) -> Color {
+    let from = Vec4::from(from.as_linear_rgba_f32());
+    let to = Vec4::from(to.as_linear_rgba_f32());
+    let mixed = from.lerp(to, amount.clamp(0.0, 1.0));
+    Color::rgba_linear(mixed.x, mixed.y, mixed.z, mixed.w)
+}
+
+fn update_sky_colors(
+    game_time: Res<GameTime>,
+    settings: Res<SkySettings>,
+    mut clear_color: ResMut<ClearColor>,
+    suns: Query<&Transform, With<Sun>>,
+    domes: Query<&Handle<SkyMaterial>, With<SkyDome>>,
+    mut materials: ResMut<Assets<SkyMaterial>>,
+) {
+    if !game_time.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let daylight = game_time.daylight();
+    let sun_height = game_time.sun_angle().sin();
+    // Strongest with the sun on the horizon, gone once it is a quarter of the way up
+    let sunset = (1.0 - sun_height.abs() * 4.0).max(0.0);
+    let zenith = mix(settings.night.zenith, settings.day.zenith, daylight);
+    let horizon = mix(
+        mix(settings.night.horizon, settings.day.horizon, daylight),
+        settings.sunset,
+        sunset * 0.8,
+    );
+    clear_color.0 = horizon;
+
+    // Lights shine along their forward axis, away from the sun
+    let sun_direction = suns
+        .iter()
+        .next()
+        .map_or(Vec3::Y, |transform| transform.rotation * Vec3::Z);
+    let sun_color = settings
+        .sun
+        .with_a(((sun_height + 0.05) * 20.0).clamp(0.0, 1.0));
+    for handle in domes.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.zenith = zenith;
+            material.horizon = horizon;
+            material.sun_direction = sun_direction.extend(0.0);
+            material.sun_color = sun_color;
+        }
+    }
+}
+
+fn prepare_cubemap(cubemap: Option<ResMut<SkyCubemap>>, mut images: ResMut<Assets<Image>>) {
+    let Some(mut cubemap) = cubemap else {
+        return;
+    };
+    if cubemap.ready {
+        return;
+    }
+    let Some(image) = images.get_mut(&cubemap.image) else {
+        return;
+    };
+    // Images load as a single 2D texture, the six faces become the layers of a cube
+    if image.texture_descriptor.array_layer_count() == 1 {
+        image.reinterpret_stacked_2d_as_array(image.height() / image.width());
+        image.texture_view_descriptor = Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::Cube),
+            ..default()
+        });
+    }
+    cubemap.ready = true;
+}
+
+fn update_skyboxes(
+    mut commands: Commands,
+    cubemap: Option<Res<SkyCubemap>>,
+    settings: Res<SkySettings>,
+    game_time: Res<GameTime>,
+    cameras: Query<Entity, (With<Camera3d>, Without<Skybox>)>,
+    mut skyboxes: Query<&mut Skybox>,
+) {
+    let Some(cubemap) = cubemap.filter(|cubemap| cubemap.ready) else {
+        return;
+    };
+    let SkyMode::Cubemap {
+        day_brightness,
+        night_brightness,
+        ..
+    } = settings.mode
+    else {
+        return;
+    };
+    let brightness = night_brightness + (day_brightness - night_brightness) * game_time.daylight();
+    for entity in cameras.iter() {
+        commands.entity(entity).insert(Skybox {
+            image: cubemap.image.clone(),
+            brightness,
+        });
+    }
+    for mut skybox in skyboxes.iter_mut() {
+        skybox.brightness = brightness;
+    }
+}
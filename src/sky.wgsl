@@ -0,0 +1,48 @@
+#import bevy_pbr::{
+    mesh_functions,
+    mesh_view_bindings::view,
+    view_transformations::position_world_to_clip,
+}
+
+struct SkyUniform {
+    zenith: vec4<f32>,
+    horizon: vec4<f32>,
+    sun_direction: vec4<f32>,
+    sun_color: vec4<f32>,
+}
+
+@group(2) @binding(0) var<uniform> sky: SkyUniform;
+
+// Cosine of the angle from the centre of the sun to its edge
+const SUN_SIZE: f32 = 0.9992;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+};
+
+struct SkyVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> SkyVertexOutput {
+    var out: SkyVertexOutput;
+    let model = mesh_functions::get_model_matrix(vertex.instance_index);
+    let world_position = mesh_functions::mesh_position_local_to_world(model, vec4(vertex.position, 1.0));
+    out.world_position = world_position.xyz;
+    out.position = position_world_to_clip(world_position.xyz);
+    // Depth is reversed, pushing the dome onto the far plane keeps it behind everything
+    out.position.z = 0.0;
+    return out;
+}
+
+@fragment
+fn fragment(in: SkyVertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(in.world_position - view.world_position);
+    var color = mix(sky.horizon, sky.zenith, smoothstep(0.0, 0.6, max(direction.y, 0.0)));
+    let sun = smoothstep(SUN_SIZE - 0.0004, SUN_SIZE, dot(direction, sky.sun_direction.xyz));
+    color = mix(color, sky.sun_color, sun * sky.sun_color.a);
+    return vec4(color.rgb, 1.0);
+}
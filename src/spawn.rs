@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use cubizm_block::definition::Block;
+use cubizm_chunks::{ActiveWorld, Chunk, Chunks, WorldManifest};
+use cubizm_core::AppState;
+use cubizm_player::{Player, PlayerSettings};
+
+use crate::teleport::Teleport;
+
+/// Sent once the player was placed at the spawn of the world
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerSpawned {
+    /// Where the player's eyes are
+    pub position: Vec3,
+}
+
+/// The spawn of the active world moved onto the highest block of its column, so the player
+/// doesn't start inside the terrain. The manifest spawn as is when the column holds no blocks
+fn resolve_spawn(
+    world: &WorldManifest,
+    chunks: &Chunks,
+    chunk_assets: &Assets<Chunk>,
+    blocks: &Assets<Block>,
+    eye_height: f32,
+) -> Vec3 {
+    let column = world.spawn.xz().floor().as_ivec2();
+    match chunks.highest_block(column, chunk_assets, blocks) {
+        Some(top) => top.as_vec3() + Vec3::new(0.5, 1. + eye_height, 0.5),
+        None => world.spawn,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_player(
+    active_world: Option<Res<ActiveWorld>>,
+    manifests: Res<Assets<WorldManifest>>,
+    chunks: Option<Res<Chunks>>,
+    chunk_assets: Res<Assets<Chunk>>,
+    blocks: Res<Assets<Block>>,
+    settings: Option<Res<PlayerSettings>>,
+    player: Query<(), With<Player>>,
+    mut teleports: EventWriter<Teleport>,
+    mut spawned: EventWriter<PlayerSpawned>,
+) {
+    let world = active_world.and_then(|world| manifests.get(&world.0));
+    let (Some(world), Some(chunks)) = (world, chunks) else {
+        warn!("No world loaded, leaving the player where it is");
+        return;
+    };
+    if player.is_empty() {
+        return;
+    }
+    let eye_height = settings.map_or(PlayerSettings::default().eye_height, |settings| {
+        settings.eye_height
+    });
+    let position = resolve_spawn(world, &chunks, &chunk_assets, &blocks, eye_height);
+    info!("Spawning the player at {position}");
+    // Holds the player until the ground is meshed
+    teleports.send(Teleport {
+        destination: position,
+    });
+    spawned.send(PlayerSpawned { position });
+}
+
+/// Places the player on the ground at the spawn column of the [WorldManifest] once the world
+/// has loaded. Needs [TeleportPlugin](crate::teleport::TeleportPlugin)
+pub struct SpawnPlugin;
+
+impl Plugin for SpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerSpawned>()
+            .add_systems(OnEnter(AppState::Finished), spawn_player);
+    }
+}
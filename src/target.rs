@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+
+use cubizm_chunks::{Chunks, RaycastHit, VoxelWorld};
+use cubizm_player::Player;
+
+use crate::accessibility::AccessibilitySettings;
+use crate::photo_mode::photo_mode_active;
+
+/// Configuration for [BlockTargetPlugin]
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct BlockTargetSettings {
+    /// How far away in blocks the player can reach blocks
+    pub reach: f32,
+    /// Outlines the targeted block in the [AccessibilitySettings::highlight_color]
+    pub outline: bool,
+}
+
+impl Default for BlockTargetSettings {
+    fn default() -> Self {
+        Self {
+            reach: 5.0,
+            outline: true,
+        }
+    }
+}
+
+/// The block the player is looking at within [BlockTargetSettings::reach], the one breaking
+/// and placing act on
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct TargetedBlock(pub Option<RaycastHit>);
+
+/// Systems updating the [TargetedBlock], run those reading it after them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockTargetSet;
+
+/// Gizmos of the target outline, apart from the others so its line width can follow the
+/// [AccessibilitySettings::highlight_thickness]
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct TargetOutlineGizmos;
+
+/// Raycasts from the player's camera every frame to find the [TargetedBlock] and outlines it
+#[derive(Default)]
+pub struct BlockTargetPlugin {
+    pub settings: BlockTargetSettings,
+}
+
+impl Plugin for BlockTargetPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings)
+            .init_resource::<TargetedBlock>()
+            .init_gizmo_group::<TargetOutlineGizmos>()
+            .add_systems(
+                Update,
+                (
+                    // The photo mode camera is a flying `Player` too, it must not break or
+                    // place blocks
+                    update_targeted_block
+                        .run_if(resource_exists::<Chunks>.and_then(not(photo_mode_active)))
+                        .in_set(BlockTargetSet),
+                    clear_targeted_block
+                        .run_if(photo_mode_active)
+                        .in_set(BlockTargetSet),
+                    apply_outline_thickness,
+                    draw_target_outline.after(BlockTargetSet),
+                ),
+            );
+    }
+}
+
+fn update_targeted_block(
+    settings: Res<BlockTargetSettings>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Player>>,
+    world: VoxelWorld,
+    mut target: ResMut<TargetedBlock>,
+) {
+    let hit = cameras
+        .iter()
+        .find(|(camera, _)| camera.is_active)
+        .and_then(|(_, camera)| {
+            world.raycast(camera.translation(), camera.forward(), settings.reach)
+        });
+    target.set_if_neq(TargetedBlock(hit));
+}
+
+fn clear_targeted_block(mut target: ResMut<TargetedBlock>) {
+    target.set_if_neq(TargetedBlock(None));
+}
+
+fn apply_outline_thickness(
+    accessibility: Res<AccessibilitySettings>,
+    mut config_store: ResMut<GizmoConfigStore>,
+) {
+    if accessibility.is_changed() {
+        let (config, _) = config_store.config_mut::<TargetOutlineGizmos>();
+        config.line_width = accessibility.highlight_thickness;
+    }
+}
+
+fn draw_target_outline(
+    settings: Res<BlockTargetSettings>,
+    accessibility: Res<AccessibilitySettings>,
+    target: Res<TargetedBlock>,
+    mut gizmos: Gizmos<TargetOutlineGizmos>,
+) {
+    if !settings.outline {
+        return;
+    }
+    let Some(hit) = &target.0 else {
+        return;
+    };
+    // Blocks span `position..position + 1`, slightly larger so the faces don't hide the lines
+    let bounds = Transform::from_translation(hit.block.as_vec3() + Vec3::splat(0.5))
+        .with_scale(Vec3::splat(1.005));
+    gizmos.cuboid(bounds, accessibility.highlight_color);
+}
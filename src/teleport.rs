@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use cubizm_chunks::{
+    ActiveWorld, Chunks, StreamChunks, SwitchWorld, WorldId, WorldManifest, WorldSaver, Worlds,
+};
+use cubizm_core::point_to_chunk;
+use cubizm_net::ServerTeleport;
+use cubizm_player::{CharacterController, Player};
+
+/// Longest the player is held after a teleport, in case the ground never shows up
+const HOLD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Moves the player to `destination` in the active world, streaming in the chunks around it.
+/// The player is held in place until the ground there is meshed, turns out empty or never
+/// loads, or [HOLD_TIMEOUT] runs out
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Teleport {
+    pub destination: Vec3,
+}
+
+/// Moves the player to `destination` in another world of [Worlds], switching to it and
+/// streaming in the chunks around `destination`. The player is held like for a [Teleport]
+/// until the world is loaded
+#[derive(Event, Debug, Clone)]
+pub struct WorldTeleport {
+    pub world: WorldId,
+    pub destination: Vec3,
+}
+
+/// The player is waiting for the terrain at `destination` in `world` to be ready
+#[derive(Component, Debug, Clone)]
+pub struct TeleportHold {
+    pub world: WorldId,
+    pub destination: Vec3,
+    timeout: Timer,
+}
+
+/// Moves the player to the last destination of a [Teleport] or [WorldTeleport] and streams in
+/// the chunks around every destination
+fn teleport(
+    mut commands: Commands,
+    mut teleports: EventReader<Teleport>,
+    mut world_teleports: EventReader<WorldTeleport>,
+    worlds: Option<Res<Worlds>>,
+    mut switches: EventWriter<SwitchWorld>,
+    mut streams: EventWriter<StreamChunks>,
+    mut player: Query<(Entity, &mut Transform), With<Player>>,
+) {
+    let active = worlds.map_or_else(WorldId::default, |worlds| worlds.active().clone());
+    let mut last = None;
+    for Teleport { destination } in teleports.read().copied() {
+        last = Some((active.clone(), destination));
+    }
+    for WorldTeleport { world, destination } in world_teleports.read().cloned() {
+        switches.send(SwitchWorld(world.clone()));
+        last = Some((world, destination));
+    }
+    let Some((world, destination)) = last else {
+        return;
+    };
+    streams.send(StreamChunks {
+        world: world.clone(),
+        center: destination,
+    });
+    for (entity, mut transform) in player.iter_mut() {
+        transform.translation = destination;
+        commands.entity(entity).insert(TeleportHold {
+            world: world.clone(),
+            destination,
+            timeout: Timer::new(HOLD_TIMEOUT, TimerMode::Once),
+        });
+    }
+}
+
+/// Teleports the player where the server sent it with `/tp`
+fn forward_server_teleports(
+    mut server_teleports: EventReader<ServerTeleport>,
+    mut teleports: EventWriter<Teleport>,
+) {
+    for ServerTeleport { destination } in server_teleports.read().copied() {
+        teleports.send(Teleport { destination });
+    }
+}
+
+/// The chunk holding the block the player will stand on at `position`
+fn ground_chunk(position: Vec3) -> IVec3 {
+    point_to_chunk(position - Vec3::Y)
+}
+
+/// Whether the chunk at `ground` will load, streamed in around `destination` like
+/// [StreamChunks] does: the generator fills it and it wasn't saved. `true` until the manifest
+/// is loaded
+fn ground_is_streamed(
+    ground: IVec3,
+    destination: Vec3,
+    manifest: Option<&WorldManifest>,
+    saver: Option<&WorldSaver>,
+) -> bool {
+    let Some(manifest) = manifest else {
+        return true;
+    };
+    manifest
+        .generator
+        .chunk_positions(point_to_chunk(destination))
+        .contains(&ground)
+        && !saver.is_some_and(|saver| saver.has_file(ground))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn hold_player(
+    mut commands: Commands,
+    time: Res<Time>,
+    worlds: Option<Res<Worlds>>,
+    chunks: Option<Res<Chunks>>,
+    active: Option<Res<ActiveWorld>>,
+    manifests: Res<Assets<WorldManifest>>,
+    saver: Option<Res<WorldSaver>>,
+    meshes: Res<Assets<Mesh>>,
+    mut player: Query<(
+        Entity,
+        &mut Transform,
+        &mut TeleportHold,
+        Option<&mut CharacterController>,
+    )>,
+) {
+    for (entity, mut transform, mut hold, controller) in player.iter_mut() {
+        let ground = ground_chunk(hold.destination);
+        // Only judged once the world switched to is the one loaded
+        let arrived = worlds
+            .as_ref()
+            .is_none_or(|worlds| *worlds.active() == hold.world);
+        let ready = chunks.as_ref().filter(|_| arrived).is_some_and(|chunks| {
+            match chunks.chunks.get(&ground) {
+                // An empty chunk has nothing to stand on and no mesh to wait for
+                Some(chunk) => {
+                    meshes.contains(&chunk.mesh_handle)
+                        || !chunks.occupancy().is_chunk_occupied(ground)
+                }
+                None => {
+                    let manifest = active.as_ref().and_then(|active| manifests.get(&active.0));
+                    !ground_is_streamed(ground, hold.destination, manifest, saver.as_deref())
+                }
+            }
+        });
+        let timed_out = hold.timeout.tick(time.delta()).finished();
+        // Undo any movement while the ground is still missing
+        transform.translation = hold.destination;
+        if let Some(mut controller) = controller {
+            controller.velocity = Vec3::ZERO;
+        }
+        if ready || timed_out {
+            if timed_out && !ready {
+                warn!(
+                    "Ground at {} did not load in time, releasing the player",
+                    hold.destination
+                );
+            }
+            commands.entity(entity).remove::<TeleportHold>();
+        }
+    }
+}
+
+/// Handles [Teleport] and [WorldTeleport] events for the player, and [ServerTeleport]s when
+/// playing on a server
+pub struct TeleportPlugin;
+impl Plugin for TeleportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Teleport>()
+            .add_event::<WorldTeleport>()
+            .add_event::<ServerTeleport>()
+            .add_event::<SwitchWorld>()
+            .add_event::<StreamChunks>()
+            .add_systems(Update, (forward_server_teleports, teleport).chain())
+            .add_systems(
+                PostUpdate,
+                hold_player.before(TransformSystem::TransformPropagate),
+            );
+    }
+}